@@ -1,5 +1,5 @@
 //! Model definitions for [`TemplateLookup`](super::TemplateLookup) manifests on disk.
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 use serde::Deserialize;
 
@@ -13,7 +13,7 @@ pub struct StoreManifest {
 
     /// Values that must match the attributes from the lookup request to select this store.
     #[serde(default)]
-    pub matchers: HashMap<String, Value>,
+    pub matchers: BTreeMap<String, Value>,
 
     /// ID of the store that must match the lookup request to select this store.
     pub store: String,
@@ -31,7 +31,7 @@ pub struct StoresManifest {
 pub struct VersionManifest {
     /// Values that must match the attributes from the lookup request to select this version.
     #[serde(default)]
-    pub matchers: HashMap<String, Value>,
+    pub matchers: BTreeMap<String, Value>,
 
     /// Options to load templates selected by this rule.
     pub template: VersionTemplate,
@@ -1,5 +1,5 @@
 //! Template lookup logic
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::path::Path;
 
 use anyhow::Context;
@@ -15,21 +15,85 @@ use super::TemplateContext;
 use super::TemplateLoadOptions;
 use crate::platform::templates::TemplateFactory;
 
-/// Checks if the attributes match the matchers.
-fn attributes_match(
+/// Explain why the attributes do not match the matchers, if they don't.
+///
+/// Matchers are checked in order of attribute name so the same mismatch is reported
+/// when more than one attribute does not match.
+fn explain_attributes(
     attributes: &serde_json::Map<String, serde_json::Value>,
-    matchers: &HashMap<String, Value>,
-) -> bool {
-    for (name, value) in matchers {
-        let is_match = attributes
-            .get(name)
-            .map(|attribute| value == attribute)
-            .unwrap_or(false);
+    matchers: &BTreeMap<String, Value>,
+) -> Option<AttributeMismatch> {
+    for (name, expected) in matchers {
+        let actual = attributes.get(name);
+        let is_match = actual.map(|actual| expected == actual).unwrap_or(false);
         if !is_match {
-            return false;
+            return Some(AttributeMismatch {
+                actual: actual.cloned(),
+                attribute: name.clone(),
+                expected: expected.into(),
+            });
         }
     }
-    true
+    None
+}
+
+/// Details of the first attribute that failed to match a rule.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AttributeMismatch {
+    /// Value of the attribute in the lookup request, if the attribute is set.
+    pub actual: Option<serde_json::Value>,
+
+    /// Name of the attribute that did not match.
+    pub attribute: String,
+
+    /// Value the rule requires the attribute to have.
+    pub expected: serde_json::Value,
+}
+
+impl std::fmt::Display for AttributeMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.actual {
+            None => write!(
+                f,
+                "attribute '{}' is not set but {} is expected",
+                self.attribute, self.expected,
+            ),
+            Some(actual) => write!(
+                f,
+                "attribute '{}' is {} but {} is expected",
+                self.attribute, actual, self.expected,
+            ),
+        }
+    }
+}
+
+/// Explanation of the outcome of a [`TemplateLookup::lookup`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum LookupExplanation {
+    /// A template is selected by the rules at the given positions.
+    Matched {
+        /// Position of the selected rule in the store rules.
+        store_rule: usize,
+
+        /// Position of the selected rule in the version rules of the selected store.
+        version_rule: usize,
+    },
+
+    /// No store rule matched the lookup request.
+    NoStore {
+        /// Attribute mismatches for store rules with the requested store ID, in rule order.
+        mismatches: Vec<AttributeMismatch>,
+    },
+
+    /// A store rule matched but none of its version rules matched the lookup request.
+    NoVersion {
+        /// Attribute mismatches for version rules with matching version requirements,
+        /// in rule order.
+        mismatches: Vec<AttributeMismatch>,
+
+        /// Position of the selected rule in the store rules.
+        store_rule: usize,
+    },
 }
 
 /// Errors looking up templates, loading lookup manifests, etc ...
@@ -59,7 +123,7 @@ impl LookupError {
 /// Rule to select the store to lookup the version from.
 pub struct StoreRule {
     /// Values that must match the attributes from the lookup request to select this store.
-    pub matchers: HashMap<String, Value>,
+    pub matchers: BTreeMap<String, Value>,
 
     /// ID of the store that must match the lookup request to select this store.
     pub store: String,
@@ -225,31 +289,70 @@ impl<T: TemplateFactory> TemplateLookup<T> {
     /// - The value of a rule property MUST match the value of the corresponding attribute EXACTLY.
     /// - Any request attribute that is NOT also a rule property is ignored.
     pub async fn lookup(&self, context: &TemplateContext) -> Result<Option<T::Template>> {
-        // Parse store version into a semver usable version.
-        let version = semver::Version::parse(&context.store_version)?;
-
-        // Lookup a store rule.
-        let store_rule = self.stores.iter().find(|rule| {
-            rule.store == context.store && attributes_match(&context.attributes, &rule.matchers)
-        });
-        let store_rule = match store_rule {
-            None => return Ok(None),
-            Some(rule) => rule,
-        };
-
-        // Lookup a version rule.
-        let version_rule = store_rule.versions.iter().find(|rule| {
-            rule.version.matches(&version) && attributes_match(&context.attributes, &rule.matchers)
-        });
-        let version_rule = match version_rule {
-            None => return Ok(None),
-            Some(rule) => rule,
+        // Select rules with the same logic used to explain lookups.
+        let (store_rule, version_rule) = match self.explain(context)? {
+            LookupExplanation::Matched {
+                store_rule,
+                version_rule,
+            } => (store_rule, version_rule),
+            _ => return Ok(None),
         };
+        let version_rule = &self.stores[store_rule].versions[version_rule];
 
         // Load the template based on the rule.
         let template = self.factory.load(&version_rule.template).await?;
         Ok(Some(template))
     }
+
+    /// Explain which rules a [`TemplateLookup::lookup`] would select, without loading templates.
+    ///
+    /// When no rule is selected the explanation lists why rules that could have
+    /// been selected failed to match the request attributes.
+    /// This is intended to troubleshoot lookup rules with debug logs or dry-run requests.
+    pub fn explain(&self, context: &TemplateContext) -> Result<LookupExplanation> {
+        let version = semver::Version::parse(&context.store_version)?;
+
+        // Lookup a store rule, collecting mismatches for rules of the same store.
+        let mut mismatches = Vec::new();
+        let mut store_rule = None;
+        for (index, rule) in self.stores.iter().enumerate() {
+            if rule.store != context.store {
+                continue;
+            }
+            match explain_attributes(&context.attributes, &rule.matchers) {
+                None => {
+                    store_rule = Some((index, rule));
+                    break;
+                }
+                Some(mismatch) => mismatches.push(mismatch),
+            }
+        }
+        let (store_index, store_rule) = match store_rule {
+            None => return Ok(LookupExplanation::NoStore { mismatches }),
+            Some(found) => found,
+        };
+
+        // Lookup a version rule, collecting mismatches for rules matching the version.
+        let mut mismatches = Vec::new();
+        for (index, rule) in store_rule.versions.iter().enumerate() {
+            if !rule.version.matches(&version) {
+                continue;
+            }
+            match explain_attributes(&context.attributes, &rule.matchers) {
+                None => {
+                    return Ok(LookupExplanation::Matched {
+                        store_rule: store_index,
+                        version_rule: index,
+                    })
+                }
+                Some(mismatch) => mismatches.push(mismatch),
+            }
+        }
+        Ok(LookupExplanation::NoVersion {
+            mismatches,
+            store_rule: store_index,
+        })
+    }
 }
 
 impl<T: TemplateFactory> Extend<StoreRule> for TemplateLookup<T> {
@@ -259,7 +362,7 @@ impl<T: TemplateFactory> Extend<StoreRule> for TemplateLookup<T> {
 }
 
 /// Subset of [`serde_json::Value`] types allowed in matchers.
-#[derive(Debug, Default, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
 pub enum Value {
     /// Represents a JSON boolean.
     Bool(bool),
@@ -287,6 +390,17 @@ impl PartialEq<serde_json::Value> for Value {
     }
 }
 
+impl From<&Value> for serde_json::Value {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Bool(value) => serde_json::Value::Bool(*value),
+            Value::Null => serde_json::Value::Null,
+            Value::Number(value) => serde_json::Value::Number(value.clone()),
+            Value::String(value) => serde_json::Value::String(value.clone()),
+        }
+    }
+}
+
 impl From<bool> for Value {
     fn from(value: bool) -> Self {
         Value::Bool(value)
@@ -314,7 +428,7 @@ impl From<&'static str> for Value {
 /// Rule to select the template version to load.
 pub struct VersionRule {
     /// Values that must match the attributes from the lookup request to select this version.
-    pub matchers: HashMap<String, Value>,
+    pub matchers: BTreeMap<String, Value>,
 
     /// Options to load templates selected by this rule.
     pub template: TemplateLoadOptions,
@@ -1,7 +1,11 @@
 use anyhow::Result;
 
+use super::AttributeMismatch;
+use super::LookupExplanation;
+use super::StoreRule;
 use super::TemplateLoadOptions;
 use super::TemplateLookup;
+use super::VersionRule;
 use crate::platform::templates::TemplateFactory;
 
 struct RuleFactory();
@@ -76,8 +80,78 @@ async fn lookup_template() {
     );
 }
 
+#[test]
+fn explain_lookup() {
+    let version = |matchers: &[(&str, &str)]| VersionRule {
+        matchers: matchers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string().into()))
+            .collect(),
+        template: TemplateLoadOptions {
+            options: Default::default(),
+            template: "test".into(),
+        },
+        version: "^1.2".parse().unwrap(),
+    };
+    let store = StoreRule {
+        matchers: Default::default(),
+        store: "postgres".into(),
+        versions: vec![version(&[("mode", "sharded")]), version(&[])],
+    };
+    let templates = TemplateLookup {
+        factory: RuleFactory(),
+        stores: vec![store],
+    };
+    let mut context = crate::platform::templates::TemplateContext {
+        attributes: Default::default(),
+        cluster_id: "WHO_CARES".into(),
+        store: "postgres".into(),
+        store_version: "1.2.3".into(),
+    };
+
+    // The second version rule matches after the first is rejected.
+    let explanation = templates.explain(&context).unwrap();
+    assert_eq!(
+        explanation,
+        LookupExplanation::Matched {
+            store_rule: 0,
+            version_rule: 1,
+        },
+    );
+
+    // Without a fallback rule the mismatched attribute is reported.
+    let mut templates = templates;
+    templates.stores[0].versions.pop();
+    context
+        .attributes
+        .insert("mode".into(), "replica-set".into());
+    let explanation = templates.explain(&context).unwrap();
+    let mismatch = AttributeMismatch {
+        actual: Some("replica-set".into()),
+        attribute: "mode".into(),
+        expected: "sharded".into(),
+    };
+    assert_eq!(
+        explanation,
+        LookupExplanation::NoVersion {
+            mismatches: vec![mismatch],
+            store_rule: 0,
+        },
+    );
+}
+
 mod attributes_match {
-    use super::super::attributes_match;
+    use std::collections::BTreeMap;
+
+    use super::super::explain_attributes;
+    use super::super::Value;
+
+    fn attributes_match(
+        attributes: &serde_json::Map<String, serde_json::Value>,
+        matchers: &BTreeMap<String, Value>,
+    ) -> bool {
+        explain_attributes(attributes, matchers).is_none()
+    }
 
     #[test]
     fn no_attrs_no_matchers() {
@@ -91,7 +165,7 @@ mod attributes_match {
     fn no_attrs_with_matchers() {
         let attributes = serde_json::Map::new();
         let matchers = {
-            let mut matchers = std::collections::HashMap::default();
+            let mut matchers = BTreeMap::default();
             matchers.insert("mode".into(), "none".into());
             matchers
         };
@@ -119,7 +193,7 @@ mod attributes_match {
             attrs
         };
         let matchers = {
-            let mut matchers = std::collections::HashMap::default();
+            let mut matchers = BTreeMap::default();
             matchers.insert("mode".into(), "none".into());
             matchers
         };
@@ -135,11 +209,64 @@ mod attributes_match {
             attrs
         };
         let matchers = {
-            let mut matchers = std::collections::HashMap::default();
+            let mut matchers = BTreeMap::default();
             matchers.insert("mode".into(), "none".into());
             matchers
         };
         let did_match = attributes_match(&attributes, &matchers);
         assert_eq!(did_match, true);
     }
+
+    #[test]
+    fn explain_first_mismatch() {
+        let attributes = {
+            let mut attrs = serde_json::Map::new();
+            attrs.insert("mode".into(), "some".into());
+            attrs.insert("role".into(), "shard".into());
+            attrs
+        };
+        let matchers = {
+            let mut matchers = BTreeMap::default();
+            matchers.insert("role".into(), "config".into());
+            matchers.insert("mode".into(), "none".into());
+            matchers
+        };
+        let mismatch = explain_attributes(&attributes, &matchers).unwrap();
+        assert_eq!(mismatch.attribute, "mode");
+        assert_eq!(mismatch.actual, Some("some".into()));
+        assert_eq!(mismatch.expected, "none");
+        assert_eq!(
+            mismatch.to_string(),
+            r#"attribute 'mode' is "some" but "none" is expected"#,
+        );
+    }
+
+    #[test]
+    fn explain_missing_attribute() {
+        let attributes = serde_json::Map::new();
+        let matchers = {
+            let mut matchers = BTreeMap::default();
+            matchers.insert("mode".into(), "none".into());
+            matchers
+        };
+        let mismatch = explain_attributes(&attributes, &matchers).unwrap();
+        assert_eq!(mismatch.attribute, "mode");
+        assert_eq!(mismatch.actual, None);
+        assert_eq!(mismatch.expected, "none");
+    }
+
+    #[test]
+    fn explain_match() {
+        let attributes = {
+            let mut attrs = serde_json::Map::new();
+            attrs.insert("mode".into(), "none".into());
+            attrs
+        };
+        let matchers = {
+            let mut matchers = BTreeMap::default();
+            matchers.insert("mode".into(), "none".into());
+            matchers
+        };
+        assert_eq!(explain_attributes(&attributes, &matchers), None);
+    }
 }
@@ -7,6 +7,8 @@ use serde_json::Value;
 
 mod lookup;
 
+pub use self::lookup::AttributeMismatch;
+pub use self::lookup::LookupExplanation;
 pub use self::lookup::TemplateLookup;
 
 /// Cluster node context to render templates with.
@@ -1,41 +1,190 @@
 //! Action API endpoints.
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::sync::Arc;
+
 use actix_web::dev::AppService;
 use actix_web::dev::HttpServiceFactory;
 use actix_web::web::Data;
+use actix_web::web::Json;
 use actix_web::web::Path;
+use actix_web::web::Query;
 use actix_web::HttpResponse;
 use actix_web::Responder;
+use tokio::sync::Notify;
 
+use crate::agent::framework::actions::ActionNotFound;
 use crate::agent::framework::actions::ActionsRegistry;
+use crate::agent::framework::actions::NodeInfoLookup;
+use crate::agent::framework::actions::ScheduleLimits;
 use crate::agent::framework::store;
+use crate::agent::framework::store::persist::InsertActions;
+use crate::agent::framework::store::persist::InsertActionsOutcome;
+use crate::agent::framework::store::persist::PatchActionMetadata;
+use crate::agent::framework::store::persist::PatchActionMetadataOutcome;
+use crate::agent::framework::store::StoreError;
 use crate::agent::framework::Injector;
+use crate::agent::framework::NodeInfo;
 use crate::agent::models::ActionExecution;
 use crate::agent::models::ActionExecutionRequest;
 use crate::agent::models::ActionExecutionResponse;
+use crate::agent::models::NodeStatus;
 use crate::context::Context;
 use crate::utils::actix::error::Error;
 use crate::utils::actix::error::Result;
+use crate::utils::validate::Validate;
+use crate::utils::validate::ValidationErrors;
 
 /// Register actions API endpoints as an [`actix_web`] service.
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct ActionsService {
     /// Catalogue of known action handlers.
     actions: ActionsRegistry,
 
+    /// Lookup current node information to check schedule conditions.
+    node_info: Option<Arc<dyn NodeInfoLookup>>,
+
+    /// Rate limits on scheduling actions, shared across the process.
+    schedule_limits: ScheduleLimits,
+
+    /// Notify the actions executor when new actions are scheduled.
+    scheduled: Arc<Notify>,
+
     /// Interface to the agent persisted store.
     store: store::Store,
 }
 
+impl std::fmt::Debug for ActionsService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ActionsService")
+            .field("actions", &self.actions)
+            .field("node_info", &self.node_info.as_ref().map(|_| "<NodeInfo>"))
+            .field("schedule_limits", &self.schedule_limits)
+            .field("scheduled", &self.scheduled)
+            .field("store", &self.store)
+            .finish()
+    }
+}
+
 impl ActionsService {
     /// Initialise an [`ActionsService`] with dependencies from the given [`Injector`].
     pub fn with_injector(injector: &Injector) -> ActionsService {
         ActionsService {
             actions: injector.actions.clone(),
+            node_info: None,
+            schedule_limits: injector.schedule_limits.clone(),
+            scheduled: Arc::clone(&injector.actions_scheduled),
             store: injector.store.clone(),
         }
     }
+
+    /// Schedule a batch of action requests atomically: either all of them are stored or none is.
+    ///
+    /// IDs are rejected if they are repeated within the batch or if they are already used
+    /// by a stored action. Stored IDs are checked in the same store transaction that
+    /// inserts the batch so concurrent requests can't claim an ID between check and write.
+    /// Requests without an ID are never rejected since unique IDs are generated for them.
+    ///
+    /// All offending IDs are reported with a `400 Bad Request` response.
+    /// Returns the IDs of the scheduled actions, in the same order as the batch.
+    pub async fn schedule_batch(
+        &self,
+        context: &Context,
+        batch: Vec<ActionExecutionRequest>,
+    ) -> Result<Vec<uuid::Uuid>> {
+        // Reject IDs repeated within the batch before anything is written.
+        let mut errors = ValidationErrors::new();
+        let mut seen = HashSet::new();
+        for (index, request) in batch.iter().enumerate() {
+            if let Some(id) = request.id {
+                if !seen.insert(id) {
+                    let path = format!("{}.id", index);
+                    errors.add(path, format!("action ID {} is duplicated in the batch", id));
+                }
+            }
+        }
+        errors.into_result()?;
+
+        // Insert the batch, rejecting it if any ID is already in use.
+        let actions: Vec<ActionExecution> = batch.into_iter().map(ActionExecution::from).collect();
+        let ids: Vec<uuid::Uuid> = actions.iter().map(|action| action.id).collect();
+        let op = InsertActions { actions };
+        let outcome = self
+            .store
+            .persist(context, op)
+            .await
+            .map_err(Error::from_anyhow_typed::<StoreError>)?;
+        match outcome {
+            InsertActionsOutcome::Inserted => (),
+            InsertActionsOutcome::IdsInUse(in_use) => {
+                let mut errors = ValidationErrors::new();
+                for (index, id) in ids.iter().enumerate() {
+                    if in_use.contains(id) {
+                        let path = format!("{}.id", index);
+                        errors.add(path, format!("action ID {} is already in use", id));
+                    }
+                }
+                errors.into_result()?;
+            }
+        }
+        self.scheduled.notify_one();
+        Ok(ids)
+    }
+
+    /// Use the given [`NodeInfo`] implementation to check schedule conditions.
+    pub fn node_info<I>(mut self, node_info: I) -> Self
+    where
+        I: NodeInfo,
+    {
+        self.node_info = Some(Arc::new(node_info));
+        self
+    }
+}
+
+/// The action metadata was not updated because the action is finished.
+#[derive(Debug, thiserror::Error)]
+#[error("action {0} is finished and can no longer be updated")]
+pub struct ActionPatchFinished(pub uuid::Uuid);
+
+/// The action was not scheduled because the node is not in the requested status.
+#[derive(Debug, thiserror::Error)]
+#[error("action not scheduled: node status is {actual:?} but {expected:?} was required")]
+pub struct ActionScheduleNodeStatusMismatch {
+    /// Node status the action was requested to be scheduled in.
+    pub expected: NodeStatus,
+
+    /// Current node status.
+    pub actual: NodeStatus,
+}
+
+/// Query parameters to page through action lists.
+///
+/// Unknown parameters are rejected so typos in requests are reported instead of silently ignored.
+#[derive(Clone, Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ActionsListParams {
+    /// Maximum number of actions to return, capped at [`ACTIONS_LIST_MAX_LIMIT`].
+    ///
+    /// [`ACTIONS_LIST_MAX_LIMIT`]: crate::agent::framework::store::query::ACTIONS_LIST_MAX_LIMIT
+    #[serde(default = "ActionsListParams::default_limit")]
+    pub limit: u32,
+
+    /// Number of actions to skip before the first returned action.
+    #[serde(default)]
+    pub offset: u32,
+}
+
+impl ActionsListParams {
+    fn default_limit() -> u32 {
+        store::query::ACTIONS_LIST_LIMIT
+    }
 }
 
+/// Node information is needed to check a schedule condition but is not available.
+#[derive(Debug, thiserror::Error)]
+#[error("node information is not available to check schedule conditions")]
+pub struct ActionScheduleNoNodeInfo;
+
 impl HttpServiceFactory for ActionsService {
     fn register(self, config: &mut AppService) {
         let service = self.clone();
@@ -59,6 +208,11 @@ impl HttpServiceFactory for ActionsService {
                     .guard(actix_web::guard::Get())
                     .to(lookup),
             )
+            .service(
+                actix_web::web::resource("/{action_id}")
+                    .guard(actix_web::guard::Patch())
+                    .to(patch),
+            )
             .service(
                 actix_web::web::resource("")
                     .guard(actix_web::guard::Post())
@@ -69,9 +223,20 @@ impl HttpServiceFactory for ActionsService {
 }
 
 /// Query already finished agent actions.
-pub async fn finished(service: Data<ActionsService>, context: Context) -> Result<impl Responder> {
-    let query = store::query::ActionsFinished {};
-    let response = service.store.query(&context, query).await?;
+pub async fn finished(
+    service: Data<ActionsService>,
+    context: Context,
+    params: Query<ActionsListParams>,
+) -> Result<impl Responder> {
+    let query = store::query::ActionsFinished {
+        limit: params.limit,
+        offset: params.offset,
+    };
+    let response = service
+        .store
+        .query(&context, query)
+        .await
+        .map_err(Error::from_anyhow_typed::<StoreError>)?;
     Ok(HttpResponse::Ok().json(response))
 }
 
@@ -81,7 +246,11 @@ pub async fn lookup(
     id: Path<uuid::Uuid>,
 ) -> Result<impl Responder> {
     let query = store::query::Action::new(id.into_inner());
-    let response = service.store.query(&context, query).await?;
+    let response = service
+        .store
+        .query(&context, query)
+        .await
+        .map_err(Error::from_anyhow_typed::<StoreError>)?;
     let response = match response {
         None => HttpResponse::NotFound().finish(),
         Some(response) => HttpResponse::Ok().json(response),
@@ -89,10 +258,50 @@ pub async fn lookup(
     Ok(response)
 }
 
+/// Merge metadata into an action that is not yet finished.
+pub async fn patch(
+    service: Data<ActionsService>,
+    context: Context,
+    id: Path<uuid::Uuid>,
+    metadata: Json<BTreeMap<String, String>>,
+) -> Result<impl Responder> {
+    let id = id.into_inner();
+    let op = PatchActionMetadata {
+        id,
+        metadata: metadata.into_inner(),
+    };
+    let response = service
+        .store
+        .persist(&context, op)
+        .await
+        .map_err(Error::from_anyhow_typed::<StoreError>)?;
+    let response = match response {
+        PatchActionMetadataOutcome::Finished => {
+            let error = ActionPatchFinished(id);
+            let error = Error::with_status(actix_web::http::StatusCode::CONFLICT, error);
+            return Err(error);
+        }
+        PatchActionMetadataOutcome::NotFound => HttpResponse::NotFound().finish(),
+        PatchActionMetadataOutcome::Patched(action) => HttpResponse::Ok().json(action),
+    };
+    Ok(response)
+}
+
 /// Query currently running and queued agent actions.
-pub async fn queue(service: Data<ActionsService>, context: Context) -> Result<impl Responder> {
-    let query = store::query::ActionsQueue {};
-    let response = service.store.query(&context, query).await?;
+pub async fn queue(
+    service: Data<ActionsService>,
+    context: Context,
+    params: Query<ActionsListParams>,
+) -> Result<impl Responder> {
+    let query = store::query::ActionsQueue {
+        limit: params.limit,
+        offset: params.offset,
+    };
+    let response = service
+        .store
+        .query(&context, query)
+        .await
+        .map_err(Error::from_anyhow_typed::<StoreError>)?;
     Ok(HttpResponse::Ok().json(response))
 }
 
@@ -103,24 +312,46 @@ pub async fn schedule(
     action: actix_web::web::Json<ActionExecutionRequest>,
 ) -> Result<impl Responder> {
     // Validate request parameters.
+    action.validate()?;
     //  -> Check action kind is known.
     service
         .actions
         .lookup(&action.kind)
-        .map_err(|error| Error::with_status(actix_web::http::StatusCode::BAD_REQUEST, error))?;
-    //  -> Check created time is in UTC.
-    if let Some(created_time) = &action.created_time {
-        if !created_time.offset().is_utc() {
-            let error = anyhow::anyhow!("The provided created_time MUST be in UTC");
-            let error = Error::with_status(actix_web::http::StatusCode::BAD_REQUEST, error);
+        .map_err(Error::from_anyhow_typed::<ActionNotFound>)?;
+    //  -> Check the node is in the requested status.
+    if let Some(expected) = &action.if_node_status {
+        let node_info = service
+            .node_info
+            .as_ref()
+            .ok_or(ActionScheduleNoNodeInfo)
+            .map_err(anyhow::Error::from)?;
+        let node = node_info.lookup_node(&context).await?;
+        if &node.node_status != expected {
+            let error = ActionScheduleNodeStatusMismatch {
+                expected: expected.clone(),
+                actual: node.node_status,
+            };
+            let error = Error::with_status(actix_web::http::StatusCode::PRECONDITION_FAILED, error);
             return Err(error);
         }
     }
+    //  -> Check the action kind is within its scheduling rate limits.
+    service
+        .schedule_limits
+        .acquire(&action.kind)
+        .map_err(|error| {
+            Error::with_status(actix_web::http::StatusCode::TOO_MANY_REQUESTS, error)
+        })?;
 
     // Store the action in the DB.
     let action = ActionExecution::from(action.into_inner());
     let id = action.id;
-    service.store.persist(&context, action).await?;
+    service
+        .store
+        .persist(&context, action)
+        .await
+        .map_err(Error::from_anyhow_typed::<StoreError>)?;
+    service.scheduled.notify_one();
     Ok(HttpResponse::Ok().json(ActionExecutionResponse { id }))
 }
 
@@ -131,18 +362,73 @@ mod tests {
     use actix_web::test::read_body_json;
     use actix_web::test::TestRequest;
 
+    use anyhow::Result;
+
     use super::ActionsService;
+    use crate::agent::framework::actions::ScheduleLimits;
     use crate::agent::framework::tests::actix_app;
     use crate::agent::framework::Injector;
+    use crate::agent::framework::NodeInfo;
+    use crate::agent::framework::ScheduleRateLimit;
     use crate::agent::models::ActionExecution;
     use crate::agent::models::ActionExecutionList;
     use crate::agent::models::ActionExecutionRequest;
     use crate::agent::models::ActionExecutionResponse;
+    use crate::agent::models::AgentVersion;
+    use crate::agent::models::Node;
+    use crate::agent::models::NodeStatus;
+    use crate::agent::models::ShardsInfo;
+    use crate::agent::models::StoreExtras;
+    use crate::agent::models::StoreVersion;
+    use crate::context::Context;
 
     fn actions_service(injector: &Injector) -> ActionsService {
         ActionsService::with_injector(injector)
     }
 
+    #[derive(Clone)]
+    pub struct FakeNodeInfo(NodeStatus);
+    #[async_trait::async_trait]
+    impl NodeInfo for FakeNodeInfo {
+        async fn node_info(&self, _: &Context) -> Result<Node> {
+            Ok(Node {
+                agent_version: AgentVersion {
+                    checkout: "commit".into(),
+                    number: "1.2.3".into(),
+                    taint: "none".into(),
+                },
+                attributes: Default::default(),
+                node_id: "id-test-node".into(),
+                node_status: self.0.clone(),
+                store_id: "test.mock".into(),
+                store_version: StoreVersion {
+                    checkout: None,
+                    number: "3.2.1".into(),
+                    extra: None,
+                },
+            })
+        }
+
+        async fn shards(&self, _: &Context) -> Result<ShardsInfo> {
+            anyhow::bail!(anyhow::anyhow!("shards are not needed by API tests"))
+        }
+
+        async fn store_info(&self, _: &Context) -> Result<StoreExtras> {
+            anyhow::bail!(anyhow::anyhow!("store info is not needed by API tests"))
+        }
+    }
+
+    fn status_request(status: NodeStatus) -> ActionExecutionRequest {
+        ActionExecutionRequest {
+            args: Default::default(),
+            created_time: None,
+            id: None,
+            if_node_status: Some(status),
+            kind: super::store::fixtures::ACTION_KIND.to_string(),
+            metadata: Default::default(),
+        }
+    }
+
     #[tokio::test]
     async fn finished_actions() {
         let injector = Injector::fixture().await;
@@ -163,6 +449,35 @@ mod tests {
         assert_eq!(body.actions.len(), 1);
     }
 
+    #[tokio::test]
+    async fn finished_actions_paged() {
+        let injector = Injector::fixture().await;
+        let service = actions_service(&injector);
+        let app = actix_app().service(service);
+        let app = init_service(app).await;
+
+        let context = super::Context::fixture();
+        let mut ids = Vec::new();
+        for _ in 0..3 {
+            let id = uuid::Uuid::new_v4();
+            let mut action = super::store::fixtures::action(id);
+            action.finished_time = Some(time::OffsetDateTime::now_utc());
+            action.scheduled_time += time::Duration::seconds(ids.len() as i64);
+            injector.store.persist(&context, action).await.unwrap();
+            ids.push(id);
+        }
+
+        let request = TestRequest::get()
+            .uri("/actions/finished?limit=1&offset=1")
+            .to_request();
+        let response = call_service(&app, request).await;
+
+        assert_eq!(response.status(), actix_web::http::StatusCode::OK);
+        let body: ActionExecutionList = read_body_json(response).await;
+        assert_eq!(body.actions.len(), 1);
+        assert_eq!(body.actions[0].id, ids[1]);
+    }
+
     #[tokio::test]
     async fn lookup_action() {
         let injector = Injector::fixture().await;
@@ -205,6 +520,75 @@ mod tests {
         assert_eq!(response.status(), actix_web::http::StatusCode::NOT_FOUND);
     }
 
+    #[tokio::test]
+    async fn patch_action_metadata() {
+        let injector = Injector::fixture().await;
+        let id = uuid::Uuid::new_v4();
+        let mut action = super::store::fixtures::action(id);
+        action.metadata.insert("owner".into(), "tests".into());
+        let context = super::Context::fixture();
+        injector.store.persist(&context, action).await.unwrap();
+
+        let service = actions_service(&injector);
+        let app = actix_app().service(service);
+        let app = init_service(app).await;
+
+        let request = TestRequest::patch()
+            .uri(&format!("/action/{}", id))
+            .set_json(serde_json::json!({"link": "https://example.com/ticket/1"}))
+            .to_request();
+        let response = call_service(&app, request).await;
+
+        assert_eq!(response.status(), actix_web::http::StatusCode::OK);
+        let body: ActionExecution = read_body_json(response).await;
+        assert_eq!(body.metadata["link"], "https://example.com/ticket/1");
+        assert_eq!(body.metadata["owner"], "tests");
+
+        let query = super::store::query::Action::new(id);
+        let stored = injector.store.query(&context, query).await.unwrap();
+        assert_eq!(stored.unwrap().metadata, body.metadata);
+    }
+
+    #[tokio::test]
+    async fn patch_action_metadata_finished() {
+        let injector = Injector::fixture().await;
+        let id = uuid::Uuid::new_v4();
+        let mut action = super::store::fixtures::action(id);
+        action.finished_time = Some(time::OffsetDateTime::now_utc());
+        let context = super::Context::fixture();
+        injector.store.persist(&context, action).await.unwrap();
+
+        let service = actions_service(&injector);
+        let app = actix_app().service(service);
+        let app = init_service(app).await;
+
+        let request = TestRequest::patch()
+            .uri(&format!("/action/{}", id))
+            .set_json(serde_json::json!({"link": "https://example.com/ticket/1"}))
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::CONFLICT);
+
+        let query = super::store::query::Action::new(id);
+        let stored = injector.store.query(&context, query).await.unwrap();
+        assert!(stored.unwrap().metadata.is_empty());
+    }
+
+    #[tokio::test]
+    async fn patch_action_metadata_not_found() {
+        let injector = Injector::fixture().await;
+        let service = actions_service(&injector);
+        let app = actix_app().service(service);
+        let app = init_service(app).await;
+
+        let request = TestRequest::patch()
+            .uri(&format!("/action/{}", uuid::Uuid::new_v4()))
+            .set_json(serde_json::json!({"link": "https://example.com/ticket/1"}))
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::NOT_FOUND);
+    }
+
     #[tokio::test]
     async fn queued_actions() {
         let injector = Injector::fixture().await;
@@ -224,6 +608,44 @@ mod tests {
         assert_eq!(body.actions.len(), 1);
     }
 
+    #[tokio::test]
+    async fn queued_actions_paged() {
+        let injector = Injector::fixture().await;
+        let service = actions_service(&injector);
+        let app = actix_app().service(service);
+        let app = init_service(app).await;
+
+        let context = super::Context::fixture();
+        for _ in 0..3 {
+            let action = super::store::fixtures::action(uuid::Uuid::new_v4());
+            injector.store.persist(&context, action).await.unwrap();
+        }
+
+        let request = TestRequest::get()
+            .uri("/actions/queue?offset=2")
+            .to_request();
+        let response = call_service(&app, request).await;
+
+        assert_eq!(response.status(), actix_web::http::StatusCode::OK);
+        let body: ActionExecutionList = read_body_json(response).await;
+        assert_eq!(body.actions.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn queued_actions_unknown_param() {
+        let injector = Injector::fixture().await;
+        let service = actions_service(&injector);
+        let app = actix_app().service(service);
+        let app = init_service(app).await;
+
+        let request = TestRequest::get()
+            .uri("/actions/queue?ofset=2")
+            .to_request();
+        let response = call_service(&app, request).await;
+
+        assert_eq!(response.status(), actix_web::http::StatusCode::BAD_REQUEST);
+    }
+
     #[tokio::test]
     async fn schedule_action() {
         let injector = Injector::fixture().await;
@@ -236,6 +658,7 @@ mod tests {
             args: Default::default(),
             created_time: None,
             id: Some(id),
+            if_node_status: None,
             kind: super::store::fixtures::ACTION_KIND.to_string(),
             metadata: Default::default(),
         };
@@ -250,6 +673,33 @@ mod tests {
         assert_eq!(body.id, id);
     }
 
+    #[tokio::test]
+    async fn schedule_action_store_quiesced() {
+        let injector = Injector::fixture().await;
+        injector.store.quiesce().await;
+        let service = actions_service(&injector);
+        let app = actix_app().service(service);
+        let app = init_service(app).await;
+
+        let request = ActionExecutionRequest {
+            args: Default::default(),
+            created_time: None,
+            id: None,
+            if_node_status: None,
+            kind: super::store::fixtures::ACTION_KIND.to_string(),
+            metadata: Default::default(),
+        };
+        let request = TestRequest::post()
+            .uri("/action")
+            .set_json(request)
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(
+            response.status(),
+            actix_web::http::StatusCode::SERVICE_UNAVAILABLE,
+        );
+    }
+
     #[tokio::test]
     async fn schedule_action_created_in_utc() {
         let injector = Injector::fixture().await;
@@ -263,6 +713,7 @@ mod tests {
             args: Default::default(),
             created_time: Some(created_time),
             id: None,
+            if_node_status: None,
             kind: super::store::fixtures::ACTION_KIND.to_string(),
             metadata: Default::default(),
         };
@@ -273,6 +724,8 @@ mod tests {
         let response = call_service(&app, request).await;
 
         assert_eq!(response.status(), actix_web::http::StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = read_body_json(response).await;
+        assert_eq!(body["error_details"][0]["path"], "created_time");
     }
 
     #[tokio::test]
@@ -286,6 +739,7 @@ mod tests {
             args: Default::default(),
             created_time: None,
             id: None,
+            if_node_status: None,
             kind: "not.a/real.action".to_string(),
             metadata: Default::default(),
         };
@@ -297,4 +751,264 @@ mod tests {
 
         assert_eq!(response.status(), actix_web::http::StatusCode::BAD_REQUEST);
     }
+
+    #[tokio::test]
+    async fn schedule_action_unknown_field() {
+        let injector = Injector::fixture().await;
+        let service = actions_service(&injector);
+        let app = actix_app().service(service);
+        let app = init_service(app).await;
+
+        let request = serde_json::json!({
+            "kind": super::store::fixtures::ACTION_KIND,
+            "metdata": {"source": "test"},
+        });
+        let request = TestRequest::post()
+            .uri("/action")
+            .set_json(request)
+            .to_request();
+        let response = call_service(&app, request).await;
+
+        assert_eq!(response.status(), actix_web::http::StatusCode::BAD_REQUEST);
+        let context = super::Context::fixture();
+        let query = super::store::query::ActionsQueue {
+            limit: 10,
+            offset: 0,
+        };
+        let queue = injector.store.query(&context, query).await.unwrap();
+        assert!(queue.actions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn schedule_action_rate_limited() {
+        let mut injector = Injector::fixture().await;
+        let limit = ScheduleRateLimit {
+            burst: 2,
+            refill_per_sec: 0.0,
+        };
+        injector
+            .config
+            .actions
+            .schedule_limits
+            .insert(super::store::fixtures::ACTION_KIND.to_string(), limit);
+        injector.schedule_limits = ScheduleLimits::from_config(&injector.config.actions);
+        let service = actions_service(&injector);
+        let app = actix_app().service(service);
+        let app = init_service(app).await;
+
+        let schedule = || {
+            let request = ActionExecutionRequest {
+                args: Default::default(),
+                created_time: None,
+                id: None,
+                if_node_status: None,
+                kind: super::store::fixtures::ACTION_KIND.to_string(),
+                metadata: Default::default(),
+            };
+            TestRequest::post()
+                .uri("/action")
+                .set_json(request)
+                .to_request()
+        };
+
+        // Requests within the limit are accepted.
+        for _ in 0..2 {
+            let response = call_service(&app, schedule()).await;
+            assert_eq!(response.status(), actix_web::http::StatusCode::OK);
+        }
+
+        // Requests beyond the limit are rejected.
+        let response = call_service(&app, schedule()).await;
+        assert_eq!(
+            response.status(),
+            actix_web::http::StatusCode::TOO_MANY_REQUESTS
+        );
+        let body: serde_json::Value = read_body_json(response).await;
+        let message = body["error_msg"].as_str().unwrap();
+        assert!(message.contains("scheduling rate limit exceeded"));
+    }
+
+    #[tokio::test]
+    async fn schedule_action_rate_limit_other_kinds() {
+        let mut injector = Injector::fixture().await;
+        let limit = ScheduleRateLimit {
+            burst: 0,
+            refill_per_sec: 0.0,
+        };
+        injector
+            .config
+            .actions
+            .schedule_limits
+            .insert("agent.replicante.io/test.fail".to_string(), limit);
+        injector.schedule_limits = ScheduleLimits::from_config(&injector.config.actions);
+        let service = actions_service(&injector);
+        let app = actix_app().service(service);
+        let app = init_service(app).await;
+
+        let request = ActionExecutionRequest {
+            args: Default::default(),
+            created_time: None,
+            id: None,
+            if_node_status: None,
+            kind: super::store::fixtures::ACTION_KIND.to_string(),
+            metadata: Default::default(),
+        };
+        let request = TestRequest::post()
+            .uri("/action")
+            .set_json(request)
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn schedule_action_if_node_status_matches() {
+        let injector = Injector::fixture().await;
+        let service = actions_service(&injector).node_info(FakeNodeInfo(NodeStatus::Healthy));
+        let app = actix_app().service(service);
+        let app = init_service(app).await;
+
+        let request = TestRequest::post()
+            .uri("/action")
+            .set_json(status_request(NodeStatus::Healthy))
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn schedule_action_if_node_status_mismatch() {
+        let injector = Injector::fixture().await;
+        let service = actions_service(&injector).node_info(FakeNodeInfo(NodeStatus::Unhealthy));
+        let app = actix_app().service(service);
+        let app = init_service(app).await;
+
+        let request = TestRequest::post()
+            .uri("/action")
+            .set_json(status_request(NodeStatus::Healthy))
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(
+            response.status(),
+            actix_web::http::StatusCode::PRECONDITION_FAILED,
+        );
+        let body: serde_json::Value = read_body_json(response).await;
+        assert_eq!(body["error"], true);
+        assert_eq!(
+            body["error_msg"],
+            "action not scheduled: node status is Unhealthy but Healthy was required",
+        );
+
+        let context = Context::fixture();
+        let query = super::store::query::ActionsQueue::default();
+        let queue = injector.store.query(&context, query).await.unwrap();
+        assert!(queue.actions.is_empty());
+    }
+
+    fn batch_request(id: Option<uuid::Uuid>) -> ActionExecutionRequest {
+        ActionExecutionRequest {
+            args: Default::default(),
+            created_time: None,
+            id,
+            if_node_status: None,
+            kind: super::store::fixtures::ACTION_KIND.to_string(),
+            metadata: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn schedule_batch_clean() {
+        let injector = Injector::fixture().await;
+        let service = actions_service(&injector);
+        let context = Context::fixture();
+        let id = uuid::Uuid::new_v4();
+        let batch = vec![
+            batch_request(Some(id)),
+            batch_request(None),
+            batch_request(None),
+            batch_request(Some(uuid::Uuid::new_v4())),
+        ];
+        let ids = service.schedule_batch(&context, batch).await.unwrap();
+        assert_eq!(ids.len(), 4);
+        assert_eq!(ids[0], id);
+
+        let query = super::store::query::ActionsQueue::default();
+        let queue = injector.store.query(&context, query).await.unwrap();
+        assert_eq!(queue.actions.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn schedule_batch_duplicate_in_batch() {
+        let injector = Injector::fixture().await;
+        let service = actions_service(&injector);
+        let context = Context::fixture();
+        let id = uuid::Uuid::new_v4();
+        let batch = vec![
+            batch_request(Some(id)),
+            batch_request(Some(uuid::Uuid::new_v4())),
+            batch_request(Some(id)),
+        ];
+        let error = service.schedule_batch(&context, batch).await.unwrap_err();
+        let response = actix_web::ResponseError::error_response(&error);
+        assert_eq!(response.status(), actix_web::http::StatusCode::BAD_REQUEST);
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let details = body["error_details"].as_array().unwrap();
+        assert_eq!(details.len(), 1);
+        assert_eq!(details[0]["path"], "2.id");
+        assert_eq!(
+            details[0]["message"],
+            format!("action ID {} is duplicated in the batch", id),
+        );
+
+        let query = super::store::query::ActionsQueue::default();
+        let queue = injector.store.query(&context, query).await.unwrap();
+        assert!(queue.actions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn schedule_batch_id_in_use() {
+        let injector = Injector::fixture().await;
+        let service = actions_service(&injector);
+        let context = Context::fixture();
+        let id = uuid::Uuid::new_v4();
+        let action = super::store::fixtures::action(id);
+        injector.store.persist(&context, action).await.unwrap();
+
+        let batch = vec![batch_request(None), batch_request(Some(id))];
+        let error = service.schedule_batch(&context, batch).await.unwrap_err();
+        let response = actix_web::ResponseError::error_response(&error);
+        assert_eq!(response.status(), actix_web::http::StatusCode::BAD_REQUEST);
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let details = body["error_details"].as_array().unwrap();
+        assert_eq!(details.len(), 1);
+        assert_eq!(details[0]["path"], "1.id");
+        assert_eq!(
+            details[0]["message"],
+            format!("action ID {} is already in use", id)
+        );
+
+        // The request without an ID in the batch was not scheduled either.
+        let query = super::store::query::ActionsQueue::default();
+        let queue = injector.store.query(&context, query).await.unwrap();
+        assert_eq!(queue.actions.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn schedule_batches_concurrently_with_the_same_id() {
+        let injector = Injector::fixture().await;
+        let service = actions_service(&injector);
+        let context = Context::fixture();
+        let id = uuid::Uuid::new_v4();
+        let (first, second) = tokio::join!(
+            service.schedule_batch(&context, vec![batch_request(Some(id))]),
+            service.schedule_batch(&context, vec![batch_request(Some(id))]),
+        );
+        assert!(first.is_ok() != second.is_ok());
+    }
 }
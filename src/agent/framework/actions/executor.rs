@@ -1,17 +1,29 @@
 //! Execute running and queued actions, progressing them until a final state.
 use std::future::Future;
+use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::Error;
 use anyhow::Result;
 use opentelemetry_api::trace::FutureExt;
+use tokio::sync::Notify;
 
 use crate::agent::framework::actions::ActionHandlerChangeValue;
+use crate::agent::framework::actions::ActionMetadata;
+use crate::agent::framework::actions::ActionPreconditionFailed;
+use crate::agent::framework::actions::ActionPreconditionNoNodeInfo;
+use crate::agent::framework::actions::ActionPreconditionOutcome;
+use crate::agent::framework::actions::ActionStateStore;
 use crate::agent::framework::actions::ActionsRegistry;
+use crate::agent::framework::actions::NodeInfoLookup;
+use crate::agent::framework::actions::ResourceLocks;
 use crate::agent::framework::metrics::action;
+use crate::agent::framework::store::persist::DeferAction;
+use crate::agent::framework::store::persist::UpdateActionProgress;
 use crate::agent::framework::store::query::ActionNextToExecute;
 use crate::agent::framework::store::Store;
 use crate::agent::framework::Injector;
+use crate::agent::framework::NodeInfo;
 use crate::agent::models::ActionExecution;
 use crate::agent::models::ActionExecutionPhase;
 use crate::context::Context;
@@ -22,44 +34,36 @@ use crate::utils::trace::TraceFutureErrExt;
 pub struct ActionsExecutor {
     context: Context,
     interval: Duration,
+    interval_max: Duration,
+    node_info: Option<Arc<dyn NodeInfoLookup>>,
     registry: ActionsRegistry,
+    resources: ResourceLocks,
+    scheduled: Arc<Notify>,
     store: Store,
 }
 
 impl ActionsExecutor {
     /// Loop executing agent until the process is shut down.
+    ///
+    /// While no actions are pending the pause between cycles doubles after each cycle,
+    /// up to the configured maximum, to reduce load on the store when the agent is idle.
+    /// The pause is reset as soon as an action is found or a new action is scheduled.
     pub async fn task<S>(self, shutdown: S) -> Result<()>
     where
         S: Future<Output = ()>,
     {
         tokio::pin!(shutdown);
         slog::debug!(self.context.logger, "Starting actions execution");
-        let tracer = crate::agent::framework::trace::tracer();
+        let mut backoff = IdleBackoff::new(self.interval, self.interval_max);
 
         loop {
-            // Create a root span to trace activities of this loop.
-            let context = crate::utils::trace::root(&tracer, "action.executor");
-            let _timer = action::EXECUTE_LOOPS_DURATION.start_timer();
+            let busy = self.cycle().await;
+            let pause = backoff.next(busy);
 
-            // Look for next action to execute and invoke its handler.
-            let action = self
-                .store
-                .query(&self.context, ActionNextToExecute {})
-                .trace_on_err_with_status()
-                .with_context(context)
-                .await;
-            if let Err(error) = self.task_loop(action).await {
-                action::EXECUTE_LOOPS_ERROR.inc();
-                slog::error!(
-                    self.context.logger,
-                    "Actions execution loop did not complete successfully";
-                    ErrorAttributes::from(&error)
-                );
-            }
-
-            // Sleep until the next cycle or shutdown.
+            // Sleep until the next cycle, an action is scheduled, or shutdown.
             tokio::select! {
-                _ = tokio::time::sleep(self.interval) => {},
+                _ = tokio::time::sleep(pause) => {},
+                _ = self.scheduled.notified() => backoff.reset(),
                 _ = &mut shutdown => {
                     slog::debug!(self.context.logger, "Gracefully shutting down actions executor");
                     return Ok(());
@@ -68,6 +72,33 @@ impl ActionsExecutor {
         }
     }
 
+    /// Look for the next action to execute and invoke its handler.
+    ///
+    /// Returns `false` only if the store reported no actions are pending.
+    async fn cycle(&self) -> bool {
+        // Create a root span to trace activities of this loop.
+        let tracer = crate::agent::framework::trace::tracer();
+        let context = crate::utils::trace::root(&tracer, "action.executor");
+        let _timer = action::EXECUTE_LOOPS_DURATION.start_timer();
+
+        let action = self
+            .store
+            .query(&self.context, ActionNextToExecute {})
+            .trace_on_err_with_status()
+            .with_context(context)
+            .await;
+        let busy = !matches!(action, Ok(None));
+        if let Err(error) = self.task_loop(action).await {
+            action::EXECUTE_LOOPS_ERROR.inc();
+            slog::error!(
+                self.context.logger,
+                "Actions execution loop did not complete successfully";
+                ErrorAttributes::from(&error)
+            );
+        }
+        busy
+    }
+
     /// Initialise an [`ActionsExecutor`] with dependencies from the given [`Injector`].
     pub fn with_injector(injector: &Injector) -> Self {
         let context = injector
@@ -76,13 +107,27 @@ impl ActionsExecutor {
             .log_values(slog::o!("component" => "actions-executor"))
             .build();
         let interval = injector.config.actions.execute_interval;
+        let interval_max = injector.config.actions.execute_interval_max;
         ActionsExecutor {
             context,
-            interval: Duration::from_secs(interval),
+            interval: interval.into(),
+            interval_max: interval_max.into(),
+            node_info: None,
             registry: injector.actions.clone(),
+            resources: injector.actions_resources.clone(),
+            scheduled: Arc::clone(&injector.actions_scheduled),
             store: injector.store.clone(),
         }
     }
+
+    /// Use the given [`NodeInfo`] implementation to check action pre-conditions.
+    pub fn node_info<I>(mut self, node_info: I) -> Self
+    where
+        I: NodeInfo,
+    {
+        self.node_info = Some(Arc::new(node_info));
+        self
+    }
 }
 
 impl ActionsExecutor {
@@ -99,7 +144,44 @@ impl ActionsExecutor {
             Err(error) => return self.fail_action(action, error).await,
             Ok(metadata) => metadata,
         };
-        let mut changes = match metadata.handler.invoke(&self.context, &action).await {
+
+        // Hold exclusive access to the resources the action needs until it is updated.
+        let _resources = self.resources.lock(&metadata.resources).await;
+        match self.check_precondition(metadata, &action).await {
+            Err(error) => return self.fail_action(action, error).await,
+            Ok(ActionPreconditionOutcome::Met) => (),
+            Ok(ActionPreconditionOutcome::Fail(reason)) => {
+                let kind = action.kind.clone();
+                let error = ActionPreconditionFailed { kind, reason };
+                return self.fail_action(action, error.into()).await;
+            }
+            Ok(ActionPreconditionOutcome::Requeue(reason)) => {
+                slog::info!(
+                    self.context.logger,
+                    "Action pre-condition not met yet, will check again later";
+                    "action_id" => %action.id,
+                    "action_kind" => &action.kind,
+                    "reason" => reason,
+                );
+                // Defer the action so actions queued behind it are not blocked.
+                let defer = DeferAction {
+                    id: action.id,
+                    until: time::OffsetDateTime::now_utc() + self.interval,
+                };
+                return self.store.persist(&self.context, defer).await;
+            }
+        };
+        // Record when the handler is first invoked to measure how long actions run for.
+        // The start time must be persisted even if the handler reports no changes.
+        let mut action = action;
+        let mut save = false;
+        if action.started_time.is_none() {
+            action.started_time = Some(time::OffsetDateTime::now_utc());
+            save = true;
+        }
+        let state = ActionStateStore::new(self.store.clone(), action.id);
+        let context = self.context.derive().value(state).build();
+        let mut changes = match metadata.handler.invoke(&context, &action).await {
             Err(error) => return self.fail_action(action, error).await,
             Ok(changes) => changes,
         };
@@ -111,8 +193,6 @@ impl ActionsExecutor {
         let changes = changes;
 
         // Update the ActionExecution record based on the invocation result.
-        let mut action = action;
-        let mut save = false;
         if changes.phase != action.state.phase {
             action.phase_to(changes.phase);
             save = true;
@@ -149,7 +229,29 @@ impl ActionsExecutor {
         if !save {
             return Ok(());
         }
-        self.store.persist(&self.context, action).await
+        action::observe_finished(&action);
+        let progress = UpdateActionProgress { action };
+        self.store.persist(&self.context, progress).await
+    }
+
+    /// Check the action pre-condition, if the action defines one.
+    async fn check_precondition(
+        &self,
+        metadata: &ActionMetadata,
+        action: &ActionExecution,
+    ) -> Result<ActionPreconditionOutcome> {
+        let precondition = match &metadata.precondition {
+            None => return Ok(ActionPreconditionOutcome::Met),
+            Some(precondition) => precondition,
+        };
+        let node_info = self
+            .node_info
+            .as_ref()
+            .ok_or_else(|| ActionPreconditionNoNodeInfo {
+                kind: action.kind.clone(),
+            })?;
+        let node = node_info.lookup_node(&self.context).await?;
+        precondition.check(&self.context, action, &node).await
     }
 
     /// Fail the action due to an error during handling or invocation.
@@ -157,7 +259,43 @@ impl ActionsExecutor {
         action::FAILED.inc();
         action.state.error = Some(crate::utils::error::into_json(error));
         action.finish(ActionExecutionPhase::Failed);
-        self.store.persist(&self.context, action).await
+        action::observe_finished(&action);
+        let progress = UpdateActionProgress { action };
+        self.store.persist(&self.context, progress).await
+    }
+}
+
+/// Grow the pause between execution cycles while the executor is idle.
+#[derive(Debug)]
+struct IdleBackoff {
+    current: Duration,
+    interval: Duration,
+    max: Duration,
+}
+
+impl IdleBackoff {
+    fn new(interval: Duration, max: Duration) -> IdleBackoff {
+        IdleBackoff {
+            current: interval,
+            interval,
+            max: max.max(interval),
+        }
+    }
+
+    /// Pause to wait before the next cycle, based on the outcome of the last cycle.
+    fn next(&mut self, busy: bool) -> Duration {
+        if busy {
+            self.reset();
+            return self.current;
+        }
+        let pause = self.current;
+        self.current = self.current.saturating_mul(2).min(self.max);
+        pause
+    }
+
+    /// Return to the base interval between cycles.
+    fn reset(&mut self) {
+        self.current = self.interval;
     }
 }
 
@@ -165,22 +303,42 @@ impl ActionsExecutor {
 mod tests {
     use anyhow::Result;
 
+    use std::time::Duration;
+
     use super::ActionsExecutor;
+    use super::IdleBackoff;
     use crate::agent::framework::actions::ActionHandler;
     use crate::agent::framework::actions::ActionHandlerChanges as Changes;
     use crate::agent::framework::actions::ActionMetadata;
+    use crate::agent::framework::actions::ActionPrecondition;
+    use crate::agent::framework::actions::ActionPreconditionOutcome;
+    use crate::agent::framework::actions::ActionStateStore;
     use crate::agent::framework::actions::ActionsRegistry;
+    use crate::agent::framework::metrics::action;
     use crate::agent::framework::store::fixtures;
     use crate::agent::framework::store::query::Action;
+    use crate::agent::framework::store::query::ActionState;
     use crate::agent::framework::Injector;
+    use crate::agent::framework::NodeInfo;
     use crate::agent::models::ActionExecution;
     use crate::agent::models::ActionExecutionPhase;
+    use crate::agent::models::AgentVersion;
+    use crate::agent::models::Node;
+    use crate::agent::models::NodeStatus;
+    use crate::agent::models::ShardsInfo;
+    use crate::agent::models::StoreExtras;
+    use crate::agent::models::StoreVersion;
     use crate::context::Context;
+    use crate::utils::config::HumanDuration;
 
     const ACTION_KIND_DONE: &str = "agent.replicante.io/test.done";
     const ACTION_KIND_FAIL: &str = "agent.replicante.io/test.fail";
     const ACTION_KIND_NO_CHANGE: &str = "agent.replicante.io/test.no.change";
+    const ACTION_KIND_PRE_FAIL: &str = "agent.replicante.io/test.pre.fail";
+    const ACTION_KIND_PRE_MET: &str = "agent.replicante.io/test.pre.met";
+    const ACTION_KIND_PRE_REQUEUE: &str = "agent.replicante.io/test.pre.requeue";
     const ACTION_KIND_RESET: &str = "agent.replicante.io/test.reset";
+    const ACTION_KIND_STEPS: &str = "agent.replicante.io/test.steps";
     const ACTION_KIND_UPDATE: &str = "agent.replicante.io/test.update";
 
     #[derive(Debug)]
@@ -222,6 +380,22 @@ mod tests {
         }
     }
 
+    /// Complete after two invocations, tracking progress in the action state.
+    #[derive(Debug)]
+    pub struct StepsAction;
+    #[async_trait::async_trait]
+    impl ActionHandler for StepsAction {
+        async fn invoke(&self, context: &Context, _: &ActionExecution) -> Result<Changes> {
+            let state = context.require::<ActionStateStore>();
+            let step: u32 = state.get(context, "step").await?.unwrap_or(0) + 1;
+            if step >= 2 {
+                return Ok(Changes::to(ActionExecutionPhase::Done));
+            }
+            state.set(context, "step", &step).await?;
+            Ok(Changes::to(ActionExecutionPhase::Running))
+        }
+    }
+
     #[derive(Debug)]
     pub struct UpdateAction;
     #[async_trait::async_trait]
@@ -234,6 +408,73 @@ mod tests {
         }
     }
 
+    #[derive(Clone)]
+    pub struct FakeNodeInfo;
+    #[async_trait::async_trait]
+    impl NodeInfo for FakeNodeInfo {
+        async fn node_info(&self, _: &Context) -> Result<Node> {
+            Ok(Node {
+                agent_version: AgentVersion {
+                    checkout: "commit".into(),
+                    number: "1.2.3".into(),
+                    taint: "none".into(),
+                },
+                attributes: Default::default(),
+                node_id: "id-test-node".into(),
+                node_status: NodeStatus::NotInCluster,
+                store_id: "test.mock".into(),
+                store_version: StoreVersion {
+                    checkout: None,
+                    number: "3.2.1".into(),
+                    extra: None,
+                },
+            })
+        }
+
+        async fn shards(&self, _: &Context) -> Result<ShardsInfo> {
+            anyhow::bail!(anyhow::anyhow!("shards are not needed by executor tests"))
+        }
+
+        async fn store_info(&self, _: &Context) -> Result<StoreExtras> {
+            anyhow::bail!(anyhow::anyhow!(
+                "store info is not needed by executor tests"
+            ))
+        }
+    }
+
+    #[derive(Debug)]
+    pub struct RequireStatus(NodeStatus);
+    #[async_trait::async_trait]
+    impl ActionPrecondition for RequireStatus {
+        async fn check(
+            &self,
+            _: &Context,
+            _: &ActionExecution,
+            node: &Node,
+        ) -> Result<ActionPreconditionOutcome> {
+            if node.node_status == self.0 {
+                return Ok(ActionPreconditionOutcome::Met);
+            }
+            let reason = format!("node status is not {:?}", self.0);
+            Ok(ActionPreconditionOutcome::Fail(reason))
+        }
+    }
+
+    #[derive(Debug)]
+    pub struct RequeueCheck;
+    #[async_trait::async_trait]
+    impl ActionPrecondition for RequeueCheck {
+        async fn check(
+            &self,
+            _: &Context,
+            _: &ActionExecution,
+            _: &Node,
+        ) -> Result<ActionPreconditionOutcome> {
+            let reason = "node is not ready yet".to_string();
+            Ok(ActionPreconditionOutcome::Requeue(reason))
+        }
+    }
+
     struct Fixtures {
         action: ActionExecution,
         context: Context,
@@ -273,11 +514,27 @@ mod tests {
                     ActionMetadata::build_internal(ACTION_KIND_NO_CHANGE, LoopAction).finish(),
                 )
                 .register(ActionMetadata::build_internal(ACTION_KIND_RESET, ResetAction).finish())
+                .register(ActionMetadata::build_internal(ACTION_KIND_STEPS, StepsAction).finish())
                 .register(ActionMetadata::build_internal(ACTION_KIND_UPDATE, UpdateAction).finish())
+                .register(
+                    ActionMetadata::build_internal(ACTION_KIND_PRE_FAIL, DoneAction)
+                        .precondition(RequireStatus(NodeStatus::Healthy))
+                        .finish(),
+                )
+                .register(
+                    ActionMetadata::build_internal(ACTION_KIND_PRE_MET, DoneAction)
+                        .precondition(RequireStatus(NodeStatus::NotInCluster))
+                        .finish(),
+                )
+                .register(
+                    ActionMetadata::build_internal(ACTION_KIND_PRE_REQUEUE, DoneAction)
+                        .precondition(RequeueCheck)
+                        .finish(),
+                )
                 .finish();
             injector.actions = actions;
 
-            let executor = ActionsExecutor::with_injector(&injector);
+            let executor = ActionsExecutor::with_injector(&injector).node_info(FakeNodeInfo);
             Fixtures {
                 action,
                 context,
@@ -295,6 +552,19 @@ mod tests {
                 .await
                 .unwrap()
         }
+
+        /// Get a key from the state of the action to assert on handler progress.
+        async fn state_from_store(&self, key: &str) -> Option<serde_json::Value> {
+            let query = ActionState {
+                action_id: self.action.id,
+                key: key.to_string(),
+            };
+            self.injector
+                .store
+                .query(&self.context, query)
+                .await
+                .unwrap()
+        }
     }
 
     #[tokio::test]
@@ -343,6 +613,22 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn invoke_no_changes_records_started_time() {
+        let fixtures = Fixtures::with_action_config(|mut action| {
+            action.kind = ACTION_KIND_NO_CHANGE.to_string();
+            action.state.phase = ActionExecutionPhase::Running;
+            action
+        })
+        .await;
+        let action = Ok(Some(fixtures.action.clone()));
+        fixtures.executor.task_loop(action).await.unwrap();
+
+        let action = fixtures.action_from_store().await.unwrap();
+        assert_eq!(action.state.phase, ActionExecutionPhase::Running);
+        assert!(action.started_time.is_some());
+    }
+
     #[tokio::test]
     async fn invoke_no_changes_new() {
         let fixtures = Fixtures::with_action_config(|mut action| {
@@ -393,6 +679,27 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn invoke_observes_finish_latency() {
+        let fixtures = Fixtures::with_action_config(|mut action| {
+            action.kind = ACTION_KIND_DONE.to_string();
+            action
+        })
+        .await;
+        let created_before = action::CREATE_TO_FINISH_DURATION.get_sample_count();
+        let scheduled_before = action::SCHEDULE_TO_FINISH_DURATION.get_sample_count();
+        let run_before = action::RUN_DURATION.get_sample_count();
+        let action = Ok(Some(fixtures.action.clone()));
+        fixtures.executor.task_loop(action).await.unwrap();
+
+        let action = fixtures.action_from_store().await.unwrap();
+        assert_eq!(action.state.phase, ActionExecutionPhase::Done);
+        assert!(action.started_time.is_some());
+        assert!(action::CREATE_TO_FINISH_DURATION.get_sample_count() > created_before);
+        assert!(action::SCHEDULE_TO_FINISH_DURATION.get_sample_count() > scheduled_before);
+        assert!(action::RUN_DURATION.get_sample_count() > run_before);
+    }
+
     #[tokio::test]
     async fn invoke_update_state() {
         let fixtures = Fixtures::with_action_config(|mut action| {
@@ -452,6 +759,163 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn precondition_failed() {
+        let fixtures = Fixtures::with_action_config(|mut action| {
+            action.kind = ACTION_KIND_PRE_FAIL.to_string();
+            action
+        })
+        .await;
+        let action = Ok(Some(fixtures.action.clone()));
+        fixtures.executor.task_loop(action).await.unwrap();
+
+        let action = fixtures.action_from_store().await.unwrap();
+        assert_eq!(action.state.phase, ActionExecutionPhase::Failed);
+        let error = action.state.error.expect("structured error details");
+        assert_eq!(
+            error,
+            serde_json::json!({
+                "error_msg": "pre-condition for action agent.replicante.io/test.pre.fail not met: node status is not Healthy",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn precondition_met() {
+        let fixtures = Fixtures::with_action_config(|mut action| {
+            action.kind = ACTION_KIND_PRE_MET.to_string();
+            action
+        })
+        .await;
+        let action = Ok(Some(fixtures.action.clone()));
+        fixtures.executor.task_loop(action).await.unwrap();
+
+        let action = fixtures.action_from_store().await.unwrap();
+        assert_eq!(action.state.phase, ActionExecutionPhase::Done);
+    }
+
+    #[tokio::test]
+    async fn precondition_requeue() {
+        let fixtures = Fixtures::with_action_config(|mut action| {
+            action.kind = ACTION_KIND_PRE_REQUEUE.to_string();
+            action
+        })
+        .await;
+        let action = Ok(Some(fixtures.action.clone()));
+        fixtures.executor.task_loop(action).await.unwrap();
+
+        let action = fixtures.action_from_store().await.unwrap();
+        assert_eq!(action, fixtures.action);
+    }
+
+    #[tokio::test]
+    async fn precondition_requeue_does_not_block_queue() {
+        let fixtures = Fixtures::with_action_config(|mut action| {
+            action.kind = ACTION_KIND_PRE_REQUEUE.to_string();
+            action
+        })
+        .await;
+        let mut next = fixtures::action(uuid::Uuid::new_v4());
+        next.kind = ACTION_KIND_DONE.to_string();
+        next.scheduled_time = fixtures.action.scheduled_time + time::Duration::seconds(1);
+        fixtures
+            .injector
+            .store
+            .persist(&fixtures.context, next.clone())
+            .await
+            .unwrap();
+
+        // The first cycle requeues the head of the queue, the second runs the next action.
+        assert!(fixtures.executor.cycle().await);
+        assert!(fixtures.executor.cycle().await);
+
+        let requeued = fixtures.action_from_store().await.unwrap();
+        assert_eq!(requeued.state.phase, fixtures.action.state.phase);
+        let query = Action::new(next.id);
+        let next = fixtures
+            .injector
+            .store
+            .query(&fixtures.context, query)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(next.state.phase, ActionExecutionPhase::Done);
+    }
+
+    #[tokio::test]
+    async fn idle_backoff_grows_and_resets() {
+        let mut injector = Injector::fixture().await;
+        injector.config.actions.execute_interval = HumanDuration::from_secs(1);
+        injector.config.actions.execute_interval_max = HumanDuration::from_secs(5);
+        let executor = ActionsExecutor::with_injector(&injector);
+        let mut backoff = IdleBackoff::new(executor.interval, executor.interval_max);
+
+        let mut pauses = Vec::new();
+        for _ in 0..5 {
+            let busy = executor.cycle().await;
+            pauses.push(backoff.next(busy).as_secs());
+        }
+        assert_eq!(pauses, [1, 2, 4, 5, 5]);
+
+        let context = Context::fixture();
+        let action = fixtures::action(uuid::Uuid::new_v4());
+        injector.store.persist(&context, action).await.unwrap();
+        let busy = executor.cycle().await;
+        assert!(busy);
+        assert_eq!(backoff.next(busy), Duration::from_secs(1));
+        assert_eq!(backoff.next(false), Duration::from_secs(1));
+        assert_eq!(backoff.next(false), Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn state_written_by_handler() {
+        let fixtures = Fixtures::with_action_config(|mut action| {
+            action.kind = ACTION_KIND_STEPS.to_string();
+            action
+        })
+        .await;
+        let action = Ok(Some(fixtures.action.clone()));
+        fixtures.executor.task_loop(action).await.unwrap();
+
+        let action = fixtures.action_from_store().await.unwrap();
+        assert_eq!(action.state.phase, ActionExecutionPhase::Running);
+        let step = fixtures.state_from_store("step").await;
+        assert_eq!(step, Some(serde_json::json!(1)));
+    }
+
+    #[tokio::test]
+    async fn state_read_on_later_loop() {
+        let fixtures = Fixtures::with_action_config(|mut action| {
+            action.kind = ACTION_KIND_STEPS.to_string();
+            action
+        })
+        .await;
+        let action = Ok(Some(fixtures.action.clone()));
+        fixtures.executor.task_loop(action).await.unwrap();
+
+        // The second loop only completes the action if it reads the first step.
+        let action = Ok(fixtures.action_from_store().await);
+        fixtures.executor.task_loop(action).await.unwrap();
+        let action = fixtures.action_from_store().await.unwrap();
+        assert_eq!(action.state.phase, ActionExecutionPhase::Done);
+    }
+
+    #[tokio::test]
+    async fn state_cleared_on_finish() {
+        let fixtures = Fixtures::with_action_config(|mut action| {
+            action.kind = ACTION_KIND_STEPS.to_string();
+            action
+        })
+        .await;
+        let action = Ok(Some(fixtures.action.clone()));
+        fixtures.executor.task_loop(action).await.unwrap();
+        assert!(fixtures.state_from_store("step").await.is_some());
+
+        let action = Ok(fixtures.action_from_store().await);
+        fixtures.executor.task_loop(action).await.unwrap();
+        assert_eq!(fixtures.state_from_store("step").await, None);
+    }
+
     #[tokio::test]
     async fn skip_on_no_action() {
         let fixtures = Fixtures::default().await;
@@ -0,0 +1,139 @@
+//! Token bucket rate limits on scheduling actions, by kind.
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::agent::framework::ActionsConfig;
+use crate::agent::framework::ScheduleRateLimit;
+
+/// Error returned when an action kind has exceeded its scheduling rate limit.
+#[derive(Debug, thiserror::Error)]
+#[error("scheduling rate limit exceeded for actions of kind '{kind}'")]
+pub struct ScheduleLimitExceeded {
+    /// Kind of the action that could not be scheduled.
+    pub kind: String,
+}
+
+/// Process-wide state of scheduling rate limits for all configured action kinds.
+#[derive(Clone, Debug)]
+pub struct ScheduleLimits {
+    buckets: Arc<Mutex<HashMap<String, TokenBucket>>>,
+}
+
+impl ScheduleLimits {
+    /// Initialise scheduling rate limits from the actions configuration.
+    pub fn from_config(conf: &ActionsConfig) -> ScheduleLimits {
+        let buckets = conf
+            .schedule_limits
+            .iter()
+            .map(|(kind, limit)| (kind.clone(), TokenBucket::new(limit)))
+            .collect();
+        ScheduleLimits {
+            buckets: Arc::new(Mutex::new(buckets)),
+        }
+    }
+
+    /// Consume a token to schedule an action of the given kind, if one is available.
+    ///
+    /// Action kinds without a configured limit are always allowed.
+    pub fn acquire(&self, kind: &str) -> Result<(), ScheduleLimitExceeded> {
+        let mut buckets = self
+            .buckets
+            .lock()
+            .expect("ScheduleLimits buckets mutex poisoned");
+        let bucket = match buckets.get_mut(kind) {
+            None => return Ok(()),
+            Some(bucket) => bucket,
+        };
+        if bucket.take(Instant::now()) {
+            return Ok(());
+        }
+        Err(ScheduleLimitExceeded {
+            kind: kind.to_string(),
+        })
+    }
+}
+
+/// Token bucket tracking the scheduling allowance of a single action kind.
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    last_refill: Instant,
+    refill_per_sec: f64,
+    tokens: f64,
+}
+
+impl TokenBucket {
+    fn new(limit: &ScheduleRateLimit) -> TokenBucket {
+        let capacity = f64::from(limit.burst);
+        TokenBucket {
+            capacity,
+            last_refill: Instant::now(),
+            refill_per_sec: limit.refill_per_sec.max(0.0),
+            tokens: capacity,
+        }
+    }
+
+    /// Refill the bucket based on elapsed time and take a token if available.
+    fn take(&mut self, now: Instant) -> bool {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+        if self.tokens < 1.0 {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use super::TokenBucket;
+    use crate::agent::framework::ScheduleRateLimit;
+
+    #[test]
+    fn bucket_empties_after_burst() {
+        let limit = ScheduleRateLimit {
+            burst: 2,
+            refill_per_sec: 0.0,
+        };
+        let mut bucket = TokenBucket::new(&limit);
+        let now = Instant::now();
+        assert!(bucket.take(now));
+        assert!(bucket.take(now));
+        assert!(!bucket.take(now));
+    }
+
+    #[test]
+    fn bucket_refills_over_time() {
+        let limit = ScheduleRateLimit {
+            burst: 1,
+            refill_per_sec: 2.0,
+        };
+        let mut bucket = TokenBucket::new(&limit);
+        let now = Instant::now();
+        assert!(bucket.take(now));
+        assert!(!bucket.take(now));
+        assert!(bucket.take(now + Duration::from_millis(500)));
+        assert!(!bucket.take(now + Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn bucket_refill_is_capped() {
+        let limit = ScheduleRateLimit {
+            burst: 1,
+            refill_per_sec: 10.0,
+        };
+        let mut bucket = TokenBucket::new(&limit);
+        let now = Instant::now() + Duration::from_secs(60);
+        assert!(bucket.take(now));
+        assert!(!bucket.take(now));
+    }
+}
@@ -6,18 +6,35 @@
 mod api;
 mod executor;
 mod handler;
+mod limits;
+mod precondition;
 mod registry;
+mod resources;
+mod state;
 
 pub mod wellknown;
 
 pub(in crate::agent::framework) use executor::ActionsExecutor;
 pub(in crate::agent::framework) use handler::ActionHandlerChangeValue;
+pub(in crate::agent::framework) use precondition::NodeInfoLookup;
 
+pub use api::ActionPatchFinished;
+pub use api::ActionScheduleNoNodeInfo;
+pub use api::ActionScheduleNodeStatusMismatch;
 pub use api::ActionsService;
 pub use handler::ActionHandler;
 pub use handler::ActionHandlerChanges;
+pub use limits::ScheduleLimitExceeded;
+pub use limits::ScheduleLimits;
+pub use precondition::ActionPrecondition;
+pub use precondition::ActionPreconditionFailed;
+pub use precondition::ActionPreconditionNoNodeInfo;
+pub use precondition::ActionPreconditionOutcome;
 pub use registry::ActionMetadata;
 pub use registry::ActionMetadataBuilder;
 pub use registry::ActionNotFound;
 pub use registry::ActionsRegistry;
 pub use registry::ActionsRegistryBuilder;
+pub use resources::ResourceGuard;
+pub use resources::ResourceLocks;
+pub use state::ActionStateStore;
@@ -0,0 +1,82 @@
+//! Pre-conditions checked before action handlers are invoked.
+use anyhow::Result;
+
+use crate::agent::framework::NodeInfo;
+use crate::agent::models::ActionExecution;
+use crate::agent::models::Node;
+use crate::context::Context;
+
+/// Check the node is in a state suitable for an action to execute.
+///
+/// Pre-conditions are evaluated by the actions executor before each invocation
+/// of the [`ActionHandler`] for the action.
+///
+/// [`ActionHandler`]: super::ActionHandler
+#[async_trait::async_trait]
+pub trait ActionPrecondition: std::fmt::Debug + Send + Sync {
+    /// Check if the action can be executed on the node in its current state.
+    ///
+    /// ## Errors
+    ///
+    /// If the check fails for any reason the [`ActionExecution`] is failed
+    /// the same as when an [`ActionHandler`] invocation fails.
+    ///
+    /// [`ActionHandler`]: super::ActionHandler
+    async fn check(
+        &self,
+        context: &Context,
+        action: &ActionExecution,
+        node: &Node,
+    ) -> Result<ActionPreconditionOutcome>;
+}
+
+/// Result of an [`ActionPrecondition`] check.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ActionPreconditionOutcome {
+    /// The pre-condition is met and the action handler can be invoked.
+    Met,
+
+    /// The pre-condition is not met and the action should fail, with the reason given.
+    Fail(String),
+
+    /// The pre-condition is not met yet and the action should be checked again later.
+    Requeue(String),
+}
+
+/// The action failed because its pre-condition was not met.
+#[derive(Debug, thiserror::Error)]
+#[error("pre-condition for action {kind} not met: {reason}")]
+pub struct ActionPreconditionFailed {
+    /// The kind of the action that failed its pre-condition.
+    pub kind: String,
+
+    /// The reason returned by the pre-condition check.
+    pub reason: String,
+}
+
+/// Node information is needed to check a pre-condition but is not available.
+#[derive(Debug, thiserror::Error)]
+#[error("node information is not available to check pre-condition for action {kind}")]
+pub struct ActionPreconditionNoNodeInfo {
+    /// The kind of the action with a pre-condition to check.
+    pub kind: String,
+}
+
+/// Type erased access to the [`NodeInfo`] implementation for the agent.
+#[async_trait::async_trait]
+pub(in crate::agent::framework) trait NodeInfoLookup:
+    Send + Sync
+{
+    /// Lookup information about the node.
+    async fn lookup_node(&self, context: &Context) -> Result<Node>;
+}
+
+#[async_trait::async_trait]
+impl<I> NodeInfoLookup for I
+where
+    I: NodeInfo,
+{
+    async fn lookup_node(&self, context: &Context) -> Result<Node> {
+        self.node_info(context).await
+    }
+}
@@ -2,9 +2,15 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use actix_web::http::StatusCode;
 use anyhow::Result;
 
 use super::ActionHandler;
+use super::ActionPrecondition;
+use crate::agent::models::ActionExecutionRequest;
+use crate::utils::actix::error::IntoStatusCode;
+use crate::utils::validate::Validate;
+use crate::utils::validate::ValidationErrors;
 
 /// List of restricted action kind domains which can only be used by the SDK itself.
 const REPLICANTE_DOMAINS: [&str; 1] = ["replicante.io"];
@@ -19,6 +25,12 @@ pub struct ActionMetadata {
 
     /// [`ActionHandler`] to invoke for [`ActionExecution`] with matching `kind`.
     pub(in crate::agent::framework) handler: Box<dyn ActionHandler>,
+
+    /// Optional [`ActionPrecondition`] to check before the handler is invoked.
+    pub(in crate::agent::framework) precondition: Option<Box<dyn ActionPrecondition>>,
+
+    /// Labels of node resources the action needs exclusive access to while it is handled.
+    pub(in crate::agent::framework) resources: Vec<String>,
 }
 
 impl ActionMetadata {
@@ -64,7 +76,12 @@ impl ActionMetadata {
     {
         let kind = kind.into();
         let handler = Box::new(handler);
-        ActionMetadataBuilder { kind, handler }
+        ActionMetadataBuilder {
+            kind,
+            handler,
+            precondition: None,
+            resources: Vec::new(),
+        }
     }
 }
 
@@ -72,16 +89,49 @@ impl ActionMetadata {
 pub struct ActionMetadataBuilder {
     kind: String,
     handler: Box<dyn ActionHandler>,
+    precondition: Option<Box<dyn ActionPrecondition>>,
+    resources: Vec<String>,
 }
 
 impl ActionMetadataBuilder {
+    /// Require exclusive access to a node resource while the action is handled.
+    ///
+    /// Actions sharing a resource label (for example `disk` or `cpu`) are never handled
+    /// at the same time, even when the agent could otherwise execute them concurrently.
+    /// The method can be called multiple times to require access to several resources.
+    ///
+    /// NOTE:
+    ///   The actions executor currently handles one action at a time, so resource locks
+    ///   never contend yet. Declaring resources now ensures actions are serialised
+    ///   correctly once actions are executed concurrently.
+    pub fn exclusive_resource<S>(mut self, resource: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.resources.push(resource.into());
+        self
+    }
+
     /// Complete the [`ActionMetadata`] build process.
-    pub fn finish(self) -> ActionMetadata {
+    pub fn finish(mut self) -> ActionMetadata {
+        self.resources.sort();
+        self.resources.dedup();
         ActionMetadata {
             kind: self.kind,
             handler: self.handler,
+            precondition: self.precondition,
+            resources: self.resources,
         }
     }
+
+    /// Check the given [`ActionPrecondition`] before each invocation of the action handler.
+    pub fn precondition<P>(mut self, precondition: P) -> Self
+    where
+        P: ActionPrecondition + 'static,
+    {
+        self.precondition = Some(Box::new(precondition));
+        self
+    }
 }
 
 /// Collection of [`ActionMetadata`] records known to the agent.
@@ -109,6 +159,23 @@ impl ActionsRegistry {
             .ok_or(ActionNotFound { kind })
             .map_err(anyhow::Error::from)
     }
+
+    /// Validate an [`ActionExecutionRequest`], including that its kind is registered.
+    ///
+    /// Serde can't check action kinds against the registry while requests are decoded,
+    /// so requests decoded from sources other than the agent API can be checked
+    /// with this method once decoded.
+    pub fn validate_request(
+        &self,
+        request: &ActionExecutionRequest,
+    ) -> std::result::Result<(), ValidationErrors> {
+        let mut errors = request.validate().err().unwrap_or_default();
+        let kind = &request.kind;
+        if !kind.trim().is_empty() && !self.entries.contains_key(kind) {
+            errors.add("kind", format!("action kind '{}' is not registered", kind));
+        }
+        errors.into_result()
+    }
 }
 
 /// Build an [`ActionsRegistry`] instance.
@@ -127,7 +194,7 @@ impl ActionsRegistryBuilder {
     pub fn register(mut self, metadata: ActionMetadata) -> Self {
         if self.entries.contains_key(&metadata.kind) {
             panic!(
-                "action {} cannot be registered more then once",
+                "action {} cannot be registered more than once",
                 metadata.kind,
             );
         }
@@ -146,6 +213,16 @@ pub struct ActionNotFound {
     pub kind: String,
 }
 
+/// Respond with `400 Bad Request` when requested action kinds are not registered.
+///
+/// Action kinds are provided by clients when actions are scheduled,
+/// so unknown kinds are an issue with the request.
+impl IntoStatusCode for ActionNotFound {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
@@ -155,6 +232,7 @@ mod tests {
     use super::ActionMetadata;
     use super::ActionsRegistry;
     use crate::agent::models::ActionExecution;
+    use crate::agent::models::ActionExecutionRequest;
     use crate::context::Context;
 
     #[derive(Debug)]
@@ -187,6 +265,16 @@ mod tests {
         assert_eq!(metadata.kind, kind);
     }
 
+    #[test]
+    fn metadata_exclusive_resources() {
+        let metadata = ActionMetadata::build("test", TestNoop {})
+            .exclusive_resource("disk")
+            .exclusive_resource("cpu")
+            .exclusive_resource("disk")
+            .finish();
+        assert_eq!(metadata.resources, ["cpu", "disk"]);
+    }
+
     #[test]
     fn lookup_action() {
         let handler = TestNoop {};
@@ -203,8 +291,39 @@ mod tests {
         assert_eq!(error.kind, "test");
     }
 
+    fn request(kind: &str) -> ActionExecutionRequest {
+        ActionExecutionRequest {
+            args: Default::default(),
+            created_time: None,
+            id: None,
+            if_node_status: None,
+            kind: kind.into(),
+            metadata: Default::default(),
+        }
+    }
+
+    #[test]
+    fn validate_request_known_kind() {
+        let handler = TestNoop {};
+        let metadata = ActionMetadata::build("test", handler).finish();
+        let registry = ActionsRegistry::build().register(metadata).finish();
+        registry.validate_request(&request("test")).unwrap();
+    }
+
+    #[test]
+    fn validate_request_unknown_kind() {
+        let registry = ActionsRegistry::build().finish();
+        let errors = registry.validate_request(&request("test")).unwrap_err();
+        let errors: Vec<(&str, &str)> = errors
+            .errors()
+            .iter()
+            .map(|error| (error.path.as_str(), error.message.as_str()))
+            .collect();
+        assert_eq!(errors, [("kind", "action kind 'test' is not registered")]);
+    }
+
     #[test]
-    #[should_panic(expected = "action test cannot be registered more then once")]
+    #[should_panic(expected = "action test cannot be registered more than once")]
     fn register_action_twice() {
         let handler = TestNoop {};
         let metadata = ActionMetadata::build("test", handler).finish();
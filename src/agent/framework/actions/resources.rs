@@ -0,0 +1,107 @@
+//! Exclusive access to node resources shared by action handlers.
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use tokio::sync::Mutex as AsyncMutex;
+use tokio::sync::OwnedMutexGuard;
+
+/// Process-wide locks on node resources, by resource label.
+///
+/// Actions declaring the same resource label (see [`ActionMetadataBuilder::exclusive_resource`])
+/// are executed one at a time while actions with no labels in common can run concurrently.
+///
+/// Locks only make a difference when actions are executed concurrently:
+/// the actions executor currently handles one action at a time so they never contend.
+///
+/// [`ActionMetadataBuilder::exclusive_resource`]: super::ActionMetadataBuilder::exclusive_resource
+#[derive(Clone, Debug, Default)]
+pub struct ResourceLocks {
+    locks: Arc<Mutex<HashMap<String, Arc<AsyncMutex<()>>>>>,
+}
+
+impl ResourceLocks {
+    /// Wait for exclusive access to all the given resources.
+    ///
+    /// Resources are locked in sorted order to prevent deadlocks between callers
+    /// waiting on overlapping sets of resources.
+    /// Access is released when the returned [`ResourceGuard`] is dropped.
+    pub async fn lock(&self, resources: &[String]) -> ResourceGuard {
+        let mut resources: Vec<&String> = resources.iter().collect();
+        resources.sort();
+        resources.dedup();
+
+        let mut guards = Vec::with_capacity(resources.len());
+        for resource in resources {
+            let lock = self.resource(resource);
+            guards.push(lock.lock_owned().await);
+        }
+        ResourceGuard { _guards: guards }
+    }
+
+    /// Lookup the lock for a resource, creating it if needed.
+    fn resource(&self, resource: &str) -> Arc<AsyncMutex<()>> {
+        let mut locks = self
+            .locks
+            .lock()
+            .expect("ResourceLocks locks mutex poisoned");
+        let lock = locks.entry(resource.to_string()).or_default();
+        Arc::clone(lock)
+    }
+}
+
+/// Exclusive access to a set of node resources, released on drop.
+#[derive(Debug)]
+pub struct ResourceGuard {
+    _guards: Vec<OwnedMutexGuard<()>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use tokio::sync::Barrier;
+
+    use super::ResourceLocks;
+
+    #[tokio::test]
+    async fn shared_resource_serialises() {
+        let locks = ResourceLocks::default();
+        let first = locks.lock(&["disk".into()]).await;
+
+        let waiting = locks.clone();
+        let second = tokio::spawn(async move {
+            let _guard = waiting.lock(&["cpu".into(), "disk".into()]).await;
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!second.is_finished());
+
+        drop(first);
+        tokio::time::timeout(Duration::from_secs(1), second)
+            .await
+            .expect("second lock to be acquired once the first is released")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn different_resources_run_concurrently() {
+        let locks = ResourceLocks::default();
+        let barrier = Arc::new(Barrier::new(2));
+        let tasks = ["cpu", "disk"].map(|resource| {
+            let barrier = Arc::clone(&barrier);
+            let locks = locks.clone();
+            tokio::spawn(async move {
+                let _guard = locks.lock(&[resource.to_string()]).await;
+                // Both tasks must hold their locks at the same time to pass the barrier.
+                barrier.wait().await;
+            })
+        });
+        for task in tasks {
+            tokio::time::timeout(Duration::from_secs(1), task)
+                .await
+                .expect("tasks with different resources to run concurrently")
+                .unwrap();
+        }
+    }
+}
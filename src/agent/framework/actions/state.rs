@@ -0,0 +1,74 @@
+//! Handler managed state persisted across [`ActionExecution`] invocations.
+//!
+//! [`ActionExecution`]: crate::agent::models::ActionExecution
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::agent::framework::store::persist::DeleteActionState;
+use crate::agent::framework::store::persist::SetActionState;
+use crate::agent::framework::store::query::ActionState;
+use crate::agent::framework::store::Store;
+use crate::context::Context;
+
+/// Key-value store scoped to a single action, for handlers to track multi-step progress.
+///
+/// Before invoking an [`ActionHandler`] the executor attaches an [`ActionStateStore`]
+/// for the action being executed to the invocation [`Context`].
+/// Handlers can retrieve it with `context.require::<ActionStateStore>()` and use it to
+/// record progress so later invocations can resume where earlier ones left off.
+///
+/// State is stored alongside the action and is removed once the action finishes.
+///
+/// [`ActionHandler`]: super::ActionHandler
+#[derive(Clone, Debug)]
+pub struct ActionStateStore {
+    action_id: uuid::Uuid,
+    store: Store,
+}
+
+impl ActionStateStore {
+    /// Delete a key from the action state, if it is set.
+    pub async fn delete(&self, context: &Context, key: &str) -> Result<()> {
+        let op = DeleteActionState {
+            action_id: self.action_id,
+            key: key.to_string(),
+        };
+        self.store.persist(context, op).await
+    }
+
+    /// Lookup a key from the action state and decode it into the requested type.
+    pub async fn get<T>(&self, context: &Context, key: &str) -> Result<Option<T>>
+    where
+        T: DeserializeOwned,
+    {
+        let op = ActionState {
+            action_id: self.action_id,
+            key: key.to_string(),
+        };
+        let value = match self.store.query(context, op).await? {
+            None => return Ok(None),
+            Some(value) => value,
+        };
+        let value = serde_json::from_value(value)?;
+        Ok(Some(value))
+    }
+
+    /// Create an [`ActionStateStore`] for the given action.
+    pub(in crate::agent::framework) fn new(store: Store, action_id: uuid::Uuid) -> Self {
+        ActionStateStore { action_id, store }
+    }
+
+    /// Create or update a key in the action state.
+    pub async fn set<T>(&self, context: &Context, key: &str, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        let op = SetActionState {
+            action_id: self.action_id,
+            key: key.to_string(),
+            value: serde_json::to_value(value)?,
+        };
+        self.store.persist(context, op).await
+    }
+}
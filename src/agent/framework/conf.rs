@@ -1,23 +1,47 @@
 //! Overall configuration for Agents.
+use std::collections::BTreeMap;
+use std::num::NonZeroUsize;
+
+use anyhow::Context;
+use anyhow::Result;
 use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use serde::Serialize;
+use serde_yaml::Mapping;
+use serde_yaml::Value;
 
+use crate::agent::framework::store::StoreEncoding;
 use crate::runtime::actix_web::ServerConfig;
+use crate::runtime::shutdown::ShutdownConfig;
 use crate::runtime::shutdown::DEFAULT_SHUTDOWN_GRACE_TIMEOUT;
 use crate::runtime::telemetry::TelemetryConfig;
 use crate::runtime::tokio_conf::TokioRuntimeConf;
+use crate::utils::config::HumanDuration;
+use crate::utils::config::HumanDurationSecondsError;
 
 /// Tune actions handling configuration.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ActionsConfig {
     /// Number of days a finished actions is kept by the store clean process.
     #[serde(default = "ActionsConfig::default_clean_age")]
     pub clean_age: u32,
 
-    /// Seconds to pause between action execution cycles.
+    /// Pause between action execution cycles.
     #[serde(default = "ActionsConfig::default_execute_interval")]
-    pub execute_interval: u64,
+    pub execute_interval: HumanDuration,
+
+    /// Maximum pause between action execution cycles while no action is pending.
+    ///
+    /// While idle the pause between cycles doubles, starting from `execute_interval`,
+    /// until this maximum is reached.
+    #[serde(default = "ActionsConfig::default_execute_interval_max")]
+    pub execute_interval_max: HumanDuration,
+
+    /// Limit the rate at which actions can be scheduled, by action kind.
+    ///
+    /// Action kinds without a limit can be scheduled without restrictions.
+    #[serde(default)]
+    pub schedule_limits: BTreeMap<String, ScheduleRateLimit>,
 }
 
 impl Default for ActionsConfig {
@@ -25,6 +49,8 @@ impl Default for ActionsConfig {
         ActionsConfig {
             clean_age: Self::default_clean_age(),
             execute_interval: Self::default_execute_interval(),
+            execute_interval_max: Self::default_execute_interval_max(),
+            schedule_limits: Default::default(),
         }
     }
 }
@@ -34,11 +60,25 @@ impl ActionsConfig {
         14
     }
 
-    fn default_execute_interval() -> u64 {
-        10
+    fn default_execute_interval() -> HumanDuration {
+        HumanDuration::from_secs(10)
+    }
+
+    fn default_execute_interval_max() -> HumanDuration {
+        HumanDuration::from_secs(60)
     }
 }
 
+/// Token bucket limit on the rate at which actions of a kind can be scheduled.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleRateLimit {
+    /// Maximum number of actions that can be scheduled in a burst.
+    pub burst: u32,
+
+    /// Number of actions, per second, added back to the allowed burst.
+    pub refill_per_sec: f64,
+}
+
 /// Container for the complete agent configuration.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct AgentConf<C>
@@ -68,9 +108,9 @@ where
     #[serde(default)]
     pub runtime: RuntimeConf,
 
-    /// Path to the persistence store for the agent.
-    #[serde(default = "AgentConf::<C>::default_store_path")]
-    pub store_path: String,
+    /// Agent persistence store configuration.
+    #[serde(default)]
+    pub store: StoreConfig,
 
     /// Telemetry configuration for the agent.
     #[serde(default)]
@@ -88,7 +128,7 @@ where
             http: Default::default(),
             node_id: None,
             runtime: Default::default(),
-            store_path: AgentConf::<C>::default_store_path(),
+            store: Default::default(),
             telemetry: Default::default(),
         }
     }
@@ -106,7 +146,7 @@ where
             http: self.http.clone(),
             node_id: self.node_id.clone(),
             runtime: self.runtime.clone(),
-            store_path: self.store_path.clone(),
+            store: self.store.clone(),
             telemetry: self.telemetry.clone(),
         }
     }
@@ -116,11 +156,281 @@ impl<C> AgentConf<C>
 where
     C: Clone + std::fmt::Debug + Serialize + DeserializeOwned,
 {
-    fn default_store_path() -> String {
+    /// Load the agent configuration from a YAML document.
+    ///
+    /// In [`ConfLoadMode::Strict`] mode keys in the document that are not part of
+    /// the configuration are rejected so typos are reported instead of silently ignored.
+    pub fn from_yaml(data: &str, mode: ConfLoadMode) -> Result<AgentConf<C>> {
+        from_yaml::<_, C, AgentConf<Mapping>>(data, mode, |conf| conf.custom)
+    }
+}
+
+/// Unified configuration for the entire agent process, organised into sections.
+///
+/// Compared to [`AgentConf`] options are grouped by the process component they configure,
+/// so a single file can drive the whole process.
+/// Use [`Agent::configure_process`](super::Agent::configure_process) to run an agent with it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProcessConfig<C>
+where
+    C: Clone + std::fmt::Debug + Serialize + DeserializeOwned,
+{
+    /// Tune actions handling configuration.
+    #[serde(default)]
+    pub actions: ActionsConfig,
+
+    /// Agent configuration specific to the implementation.
+    #[serde(flatten, deserialize_with = "C::deserialize")]
+    pub custom: C,
+
+    /// ID of the node as defined by the platform the node runs on.
+    #[serde(default)]
+    pub node_id: Option<String>,
+
+    /// Tokio Runtime configuration.
+    #[serde(default)]
+    pub runtime: TokioRuntimeConf,
+
+    /// HTTP Server configuration.
+    #[serde(default)]
+    pub server: ServerConfig,
+
+    /// Process shutdown configuration.
+    ///
+    /// The grace timeout must be a whole number of seconds.
+    #[serde(default)]
+    pub shutdown: ShutdownConfig,
+
+    /// Agent persistence store configuration.
+    #[serde(default)]
+    pub store: StoreConfig,
+
+    /// Telemetry configuration for the agent.
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+}
+
+impl<C> Default for ProcessConfig<C>
+where
+    C: Clone + std::fmt::Debug + Default + Serialize + DeserializeOwned,
+{
+    fn default() -> Self {
+        ProcessConfig {
+            actions: Default::default(),
+            custom: Default::default(),
+            node_id: None,
+            runtime: Default::default(),
+            server: Default::default(),
+            shutdown: Default::default(),
+            store: Default::default(),
+            telemetry: Default::default(),
+        }
+    }
+}
+
+impl<C> ProcessConfig<C>
+where
+    C: Clone + std::fmt::Debug + Serialize + DeserializeOwned,
+{
+    /// Load the process configuration from a YAML document.
+    ///
+    /// Unknown keys are handled according to `mode` as described in [`AgentConf::from_yaml`].
+    pub fn from_yaml(data: &str, mode: ConfLoadMode) -> Result<ProcessConfig<C>> {
+        from_yaml::<_, C, ProcessConfig<Mapping>>(data, mode, |conf| conf.custom)
+    }
+}
+
+/// Convert the unified process configuration into the agent configuration.
+///
+/// Fails if the shutdown grace timeout is not a whole number of seconds.
+impl<C> TryFrom<ProcessConfig<C>> for AgentConf<C>
+where
+    C: Clone + std::fmt::Debug + Serialize + DeserializeOwned,
+{
+    type Error = HumanDurationSecondsError;
+
+    fn try_from(value: ProcessConfig<C>) -> Result<Self, Self::Error> {
+        let shutdown_grace_sec = value.shutdown.grace_timeout.whole_secs()?;
+        Ok(AgentConf {
+            actions: value.actions,
+            custom: value.custom,
+            http: value.server,
+            node_id: value.node_id,
+            runtime: RuntimeConf {
+                shutdown_grace_sec,
+                tokio: value.runtime,
+            },
+            store: value.store,
+            telemetry: value.telemetry,
+        })
+    }
+}
+
+/// Agent persistence store configuration.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct StoreConfig {
+    /// Encoding of structured data written to the persistence store.
+    ///
+    /// Data already in the store remains readable when the encoding is changed.
+    #[serde(default)]
+    pub encoding: StoreEncoding,
+
+    /// Periodic maintenance of the persistence store to keep its size bounded.
+    #[serde(default)]
+    pub maintenance: StoreMaintenanceConfig,
+
+    /// Path to the persistence store for the agent.
+    #[serde(default = "StoreConfig::default_path")]
+    pub path: String,
+
+    /// Create missing parent directories of the store path when the agent starts.
+    #[serde(default)]
+    pub path_create: bool,
+
+    /// Send store queries to a pool of read-only connections of the given size.
+    ///
+    /// When not set all store operations share a single connection.
+    #[serde(default)]
+    pub read_pool: Option<NonZeroUsize>,
+
+    /// Limit pending store writes with a queue of the given capacity.
+    ///
+    /// Once the queue is full components wait for space before writing to the store.
+    /// When not set store writes are performed directly by the components that need them.
+    #[serde(default)]
+    pub write_queue: Option<NonZeroUsize>,
+}
+
+impl Default for StoreConfig {
+    fn default() -> Self {
+        StoreConfig {
+            encoding: Default::default(),
+            maintenance: Default::default(),
+            path: StoreConfig::default_path(),
+            path_create: false,
+            read_pool: None,
+            write_queue: None,
+        }
+    }
+}
+
+impl StoreConfig {
+    fn default_path() -> String {
         "agent.db".into()
     }
 }
 
+/// Periodic maintenance of the agent persistence store.
+///
+/// Deleting records leaves free pages in the store and a large write-ahead log
+/// (when one is in use) so the store file may keep growing without maintenance.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct StoreMaintenanceConfig {
+    /// Enable periodic maintenance of the store.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Interval between store maintenance runs, which must be greater than zero.
+    #[serde(default = "StoreMaintenanceConfig::default_interval")]
+    pub interval: HumanDuration,
+
+    /// Also rebuild the store file to release free pages.
+    ///
+    /// Rebuilding the store blocks all other store operations while it runs
+    /// so it is skipped when actions are queued or running.
+    #[serde(default)]
+    pub vacuum: bool,
+}
+
+impl Default for StoreMaintenanceConfig {
+    fn default() -> Self {
+        StoreMaintenanceConfig {
+            enabled: false,
+            interval: StoreMaintenanceConfig::default_interval(),
+            vacuum: false,
+        }
+    }
+}
+
+impl StoreMaintenanceConfig {
+    fn default_interval() -> HumanDuration {
+        HumanDuration::from_secs(60 * 60)
+    }
+}
+
+/// Errors loading the agent configuration.
+#[derive(Debug, thiserror::Error)]
+pub enum AgentConfError {
+    /// Unable to decode the agent configuration.
+    #[error("unable to decode the agent configuration")]
+    Decode,
+
+    /// The configuration contains a key that is not a known configuration option.
+    #[error("unknown configuration key '{key}'")]
+    UnknownField {
+        /// Full path to the unknown key.
+        key: String,
+    },
+}
+
+/// How to handle unknown keys when loading configuration.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ConfLoadMode {
+    /// Unknown keys are ignored.
+    #[default]
+    Lenient,
+
+    /// Unknown keys are rejected with an error naming the offending key.
+    Strict,
+}
+
+/// Load a configuration container from a YAML document, checking for unknown keys if needed.
+///
+/// Custom configuration is flattened into the containers so options not known to the framework
+/// are collected by decoding the document into a container with a `Mapping` in place of
+/// the custom configuration (`U`), from which `custom` extracts them.
+/// The collected options are then checked against the custom configuration type (`C`).
+fn from_yaml<T, C, U>(data: &str, mode: ConfLoadMode, custom: fn(U) -> Mapping) -> Result<T>
+where
+    T: DeserializeOwned,
+    C: DeserializeOwned,
+    U: DeserializeOwned,
+{
+    let conf: T = serde_yaml::from_str(data).context(AgentConfError::Decode)?;
+    if mode == ConfLoadMode::Lenient {
+        return Ok(conf);
+    }
+
+    let mut unknown = None;
+    let document = serde_yaml::Deserializer::from_str(data);
+    let framework: U = serde_ignored::deserialize(document, |path| {
+        unknown.get_or_insert_with(|| path.to_string());
+    })
+    .context(AgentConfError::Decode)?;
+    let custom = custom(framework);
+    if unknown.is_none() && !custom.is_empty() {
+        let first = custom.keys().next().map(|key| match key.as_str() {
+            Some(key) => key.to_string(),
+            None => serde_yaml::to_string(key)
+                .unwrap_or_default()
+                .trim()
+                .to_string(),
+        });
+        let decoded: Result<C, _> = serde_ignored::deserialize(Value::Mapping(custom), |path| {
+            unknown.get_or_insert_with(|| path.to_string());
+        });
+        // Types that don't decode from mappings, such as `()`, ignore all options when flattened.
+        if decoded.is_err() {
+            unknown = first;
+        }
+    }
+
+    match unknown {
+        None => Ok(conf),
+        Some(key) => anyhow::bail!(AgentConfError::UnknownField { key }),
+    }
+}
+
 /// Programmatic options for the agent process.
 pub struct AgentOptions {
     /// Prefix for web request metrics names.
@@ -153,3 +463,172 @@ impl Default for RuntimeConf {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde::Serialize;
+
+    use super::AgentConf;
+    use super::AgentConfError;
+    use super::ConfLoadMode;
+    use super::ProcessConfig;
+    use crate::utils::config::HumanDuration;
+
+    #[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+    struct CustomConf {
+        #[serde(default)]
+        compress: bool,
+    }
+
+    const TYPO: &str = r#"
+compress: true
+complress: true
+"#;
+
+    const NESTED_TYPO: &str = r#"
+actions:
+  clean_age: 7
+  execute_intervl: 5
+"#;
+
+    const VALID: &str = r#"
+actions:
+  clean_age: 7
+  schedule_limits:
+    agent.replicante.io/test.ping:
+      burst: 2
+      refill_per_sec: 0.5
+compress: true
+node_id: node-1
+runtime:
+  shutdown_grace_sec: 30
+  workers: 4
+telemetry:
+  logs:
+    level: debug
+  otel:
+    sampling:
+      mode: !Ratio 0.5
+"#;
+
+    #[test]
+    fn lenient_ignores_unknown_keys() {
+        let conf = AgentConf::<CustomConf>::from_yaml(TYPO, ConfLoadMode::Lenient).unwrap();
+        assert!(conf.custom.compress);
+    }
+
+    #[test]
+    fn strict_rejects_unknown_keys() {
+        let error = AgentConf::<CustomConf>::from_yaml(TYPO, ConfLoadMode::Strict).unwrap_err();
+        match error.downcast_ref::<AgentConfError>() {
+            Some(AgentConfError::UnknownField { key }) => assert_eq!(key, "complress"),
+            _ => panic!("unexpected error: {:?}", error),
+        }
+    }
+
+    #[test]
+    fn strict_rejects_unknown_keys_without_custom_conf() {
+        let error = AgentConf::<()>::from_yaml(TYPO, ConfLoadMode::Strict).unwrap_err();
+        assert_eq!(error.to_string(), "unknown configuration key 'compress'");
+    }
+
+    #[test]
+    fn strict_rejects_nested_unknown_keys() {
+        let error =
+            AgentConf::<CustomConf>::from_yaml(NESTED_TYPO, ConfLoadMode::Strict).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "unknown configuration key 'actions.execute_intervl'",
+        );
+    }
+
+    #[test]
+    fn strict_accepts_known_keys() {
+        let conf = AgentConf::<CustomConf>::from_yaml(VALID, ConfLoadMode::Strict).unwrap();
+        assert!(conf.custom.compress);
+        assert_eq!(conf.actions.clean_age, 7);
+        assert_eq!(conf.node_id.as_deref(), Some("node-1"));
+        assert_eq!(conf.runtime.shutdown_grace_sec, 30);
+    }
+
+    const ALIASES: &str = r#"
+compress: true
+telemetry:
+  logs:
+    level: DEBUG
+  otel:
+    timeout_sec: 5
+"#;
+
+    #[test]
+    fn strict_accepts_aliases() {
+        let conf = AgentConf::<CustomConf>::from_yaml(ALIASES, ConfLoadMode::Strict).unwrap();
+        let timeout = conf
+            .telemetry
+            .otel
+            .timeout
+            .map(|timeout| timeout.duration());
+        assert_eq!(timeout, Some(std::time::Duration::from_secs(5)));
+    }
+
+    const PROCESS: &str = r#"
+compress: true
+node_id: node-1
+runtime:
+  workers: 4
+server:
+  bind: 127.0.0.1:8000
+shutdown:
+  grace_timeout: 2m
+store:
+  path: /var/lib/agent/agent.db
+  path_create: true
+  read_pool: 4
+  write_queue: 16
+telemetry:
+  logs:
+    level: debug
+"#;
+
+    #[test]
+    fn process_config_round_trip() {
+        let conf = ProcessConfig::<CustomConf>::from_yaml(PROCESS, ConfLoadMode::Strict).unwrap();
+        assert!(conf.custom.compress);
+        assert_eq!(conf.server.bind, "127.0.0.1:8000");
+        assert_eq!(conf.shutdown.grace_timeout.duration().as_secs(), 120);
+        assert_eq!(conf.store.path, "/var/lib/agent/agent.db");
+        assert!(conf.store.path_create);
+        assert_eq!(conf.store.read_pool.map(|size| size.get()), Some(4));
+
+        let encoded = serde_yaml::to_string(&conf).unwrap();
+        let decoded =
+            ProcessConfig::<CustomConf>::from_yaml(&encoded, ConfLoadMode::Strict).unwrap();
+        assert_eq!(decoded, conf);
+    }
+
+    #[test]
+    fn process_config_into_agent_conf() {
+        let conf = ProcessConfig::<CustomConf>::from_yaml(PROCESS, ConfLoadMode::Strict).unwrap();
+        let conf = AgentConf::try_from(conf).unwrap();
+        assert!(conf.custom.compress);
+        assert_eq!(conf.http.bind, "127.0.0.1:8000");
+        assert_eq!(conf.runtime.shutdown_grace_sec, 120);
+        assert_eq!(conf.runtime.tokio.workers, Some(4));
+        assert_eq!(conf.store.path, "/var/lib/agent/agent.db");
+        assert!(conf.store.path_create);
+        assert_eq!(conf.store.read_pool.map(|size| size.get()), Some(4));
+        assert_eq!(conf.store.write_queue.map(|size| size.get()), Some(16));
+    }
+
+    #[test]
+    fn process_config_into_agent_conf_fractional_grace() {
+        let mut conf = ProcessConfig::<CustomConf>::default();
+        conf.shutdown.grace_timeout = HumanDuration::from_millis(1500);
+        let error = AgentConf::try_from(conf).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid duration '1500ms', expected a whole number of seconds",
+        );
+    }
+}
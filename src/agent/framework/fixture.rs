@@ -0,0 +1,162 @@
+//! Fixtures for agent implementations to write integration tests with.
+//!
+//! The [`actix_app`] fixture wires the agent API endpoints the same way
+//! a running agent process does, backed by an [`Injector::fixture`]:
+//!
+//! ```ignore
+//! let injector = Injector::fixture().await;
+//! let app = actix_web::test::init_service(actix_app(&injector, MyNodeInfo::new())).await;
+//! let request = TestRequest::get().uri("/api/unstable/info/node").to_request();
+//! let response = actix_web::test::call_service(&app, request).await;
+//! ```
+use actix_web::body::MessageBody;
+use actix_web::dev::ServiceFactory;
+use actix_web::dev::ServiceRequest;
+use actix_web::dev::ServiceResponse;
+use actix_web::web::Data;
+use actix_web::App;
+use actix_web::Error;
+
+use super::actions::ActionsService;
+use super::info;
+use super::Injector;
+use super::NodeInfo;
+use crate::utils::actix::metrics::MetricsCollector;
+use crate::utils::actix::metrics::MetricsExporter;
+
+/// Prefix of metrics names for requests to the fixture [`App`].
+const METRICS_PREFIX: &str = "agent_fixture";
+
+/// ActixWeb [`App`] serving the agent API endpoints, ready for [`init_service`].
+///
+/// The following endpoints are available:
+///
+/// - Agent API endpoints, using the given [`NodeInfo`], under `/api/unstable`.
+/// - Prometheus metrics about requests to the [`App`] at `/metrics`.
+///
+/// [`init_service`]: actix_web::test::init_service
+pub fn actix_app<I>(
+    injector: &Injector,
+    node_info: I,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Response = ServiceResponse<impl MessageBody>,
+        Config = (),
+        Error = Error,
+        InitError = (),
+    >,
+>
+where
+    I: NodeInfo,
+{
+    let registry = prometheus::Registry::new();
+    let collector = MetricsCollector::build()
+        .prefix(METRICS_PREFIX)
+        .registry(registry.clone())
+        .finish();
+    let exporter = MetricsExporter::new(registry);
+
+    let actions = ActionsService::with_injector(injector).node_info(node_info.clone());
+    let info = info::into_actix_service(node_info);
+    let api = actix_web::web::scope("/api/unstable")
+        .service(info)
+        .service(actions);
+    App::new()
+        .app_data(Data::new(injector.context.clone()))
+        .service(api)
+        .service(actix_web::web::resource("/metrics").route(actix_web::web::get().to(exporter)))
+        .wrap(collector)
+        .wrap(crate::context::ActixTransform)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::call_service;
+    use actix_web::test::init_service;
+    use actix_web::test::read_body;
+    use actix_web::test::read_body_json;
+    use actix_web::test::TestRequest;
+    use anyhow::Result;
+
+    use super::actix_app;
+    use crate::agent::framework::store::fixtures;
+    use crate::agent::framework::Injector;
+    use crate::agent::framework::NodeInfo;
+    use crate::agent::models::ActionExecutionList;
+    use crate::agent::models::AgentVersion;
+    use crate::agent::models::Node;
+    use crate::agent::models::NodeStatus;
+    use crate::agent::models::ShardsInfo;
+    use crate::agent::models::StoreExtras;
+    use crate::agent::models::StoreVersion;
+    use crate::context::Context;
+
+    #[derive(Clone)]
+    struct FakeNodeInfo;
+
+    #[async_trait::async_trait]
+    impl NodeInfo for FakeNodeInfo {
+        async fn node_info(&self, _: &Context) -> Result<Node> {
+            Ok(Node {
+                agent_version: AgentVersion {
+                    checkout: "commit".into(),
+                    number: "1.2.3".into(),
+                    taint: "none".into(),
+                },
+                attributes: Default::default(),
+                node_id: "id-test-node".into(),
+                node_status: NodeStatus::Healthy,
+                store_id: "test.mock".into(),
+                store_version: StoreVersion {
+                    checkout: None,
+                    number: "3.2.1".into(),
+                    extra: None,
+                },
+            })
+        }
+
+        async fn shards(&self, _: &Context) -> Result<ShardsInfo> {
+            anyhow::bail!(anyhow::anyhow!("shards are not needed by fixture tests"))
+        }
+
+        async fn store_info(&self, _: &Context) -> Result<StoreExtras> {
+            anyhow::bail!(anyhow::anyhow!("store info is not needed by fixture tests"))
+        }
+    }
+
+    #[tokio::test]
+    async fn end_to_end() {
+        let injector = Injector::fixture().await;
+        let action = fixtures::action(uuid::Uuid::new_v4());
+        injector
+            .store
+            .persist(&injector.context, action)
+            .await
+            .unwrap();
+        let app = init_service(actix_app(&injector, FakeNodeInfo)).await;
+
+        let request = TestRequest::get()
+            .uri("/api/unstable/info/node")
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::OK);
+        let node: Node = read_body_json(response).await;
+        assert_eq!(node.node_id, "id-test-node");
+
+        let request = TestRequest::get()
+            .uri("/api/unstable/actions/queue")
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::OK);
+        let queue: ActionExecutionList = read_body_json(response).await;
+        assert_eq!(queue.actions.len(), 1);
+
+        let request = TestRequest::get().uri("/metrics").to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::OK);
+        let metrics = read_body(response).await;
+        let metrics = String::from_utf8(metrics.to_vec()).unwrap();
+        assert!(metrics.contains("agent_fixture_request_durations"));
+    }
+}
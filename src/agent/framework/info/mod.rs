@@ -15,6 +15,7 @@ use crate::context::Context;
 mod node;
 mod shards;
 mod store_version;
+mod validate;
 
 #[cfg(test)]
 mod tests;
@@ -27,6 +28,9 @@ pub use self::store_version::StoreVersionFile;
 pub use self::store_version::StoreVersionFileError;
 pub use self::store_version::StoreVersionFixed;
 pub use self::store_version::StoreVersionStrategy;
+pub use self::validate::NodeIdentityMismatch;
+pub use self::validate::StoreVersionUnsupported;
+pub use self::validate::ValidatingNodeInfo;
 
 /// Registers an [`NodeInfo`] implementation as an [`actix_web`] service.
 #[derive(Clone, Debug)]
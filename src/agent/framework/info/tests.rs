@@ -5,6 +5,7 @@ use actix_web::test::TestRequest;
 use anyhow::Result;
 
 use crate::agent::framework::tests::actix_app;
+use crate::agent::framework::Injector;
 use crate::agent::framework::NodeInfo;
 use crate::agent::models::AgentVersion;
 use crate::agent::models::Node;
@@ -17,6 +18,9 @@ use crate::agent::models::StoreVersion;
 use crate::context::Context;
 
 use super::into_actix_service;
+use super::NodeIdentityMismatch;
+use super::StoreVersionUnsupported;
+use super::ValidatingNodeInfo;
 
 #[derive(Clone)]
 struct FakeAgent {}
@@ -118,3 +122,62 @@ async fn shards() {
         }]
     );
 }
+
+#[tokio::test]
+async fn validating_node_info_matching() {
+    let context = Context::fixture();
+    let info = ValidatingNodeInfo::new(FakeAgent::new())
+        .cluster_id("cluster-mock")
+        .node_id("id-test-node");
+    let node = info.node_info(&context).await.unwrap();
+    assert_eq!(node.node_id, "id-test-node");
+    let store = info.store_info(&context).await.unwrap();
+    assert_eq!(store.cluster_id, "cluster-mock");
+}
+
+#[tokio::test]
+async fn validating_node_info_mismatched() {
+    let context = Context::fixture();
+    let info = ValidatingNodeInfo::new(FakeAgent::new())
+        .cluster_id("cluster-other")
+        .node_id("id-other-node");
+    let error = info.node_info(&context).await.unwrap_err();
+    let error = error.downcast_ref::<NodeIdentityMismatch>().unwrap();
+    assert_eq!(error.field, "node_id");
+    assert_eq!(error.actual, "id-test-node");
+    assert_eq!(error.expected, "id-other-node");
+    let error = info.store_info(&context).await.unwrap_err();
+    let error = error.downcast_ref::<NodeIdentityMismatch>().unwrap();
+    assert_eq!(error.field, "cluster_id");
+}
+
+#[tokio::test]
+async fn validating_node_info_with_injector() {
+    let context = Context::fixture();
+    let mut injector = Injector::fixture().await;
+    injector.config.node_id = Some("id-other-node".into());
+    let info = ValidatingNodeInfo::with_injector(FakeAgent::new(), &injector);
+    let error = info.node_info(&context).await.unwrap_err();
+    let error = error.downcast_ref::<NodeIdentityMismatch>().unwrap();
+    assert_eq!(error.expected, "id-other-node");
+}
+
+#[tokio::test]
+async fn validating_node_info_store_version_supported() {
+    let context = Context::fixture();
+    let required = semver::VersionReq::parse("^3.2").unwrap();
+    let info = ValidatingNodeInfo::new(FakeAgent::new()).store_versions(required);
+    let node = info.node_info(&context).await.unwrap();
+    assert_eq!(node.store_version.number, "3.2.1");
+}
+
+#[tokio::test]
+async fn validating_node_info_store_version_unsupported() {
+    let context = Context::fixture();
+    let required = semver::VersionReq::parse(">=4.0").unwrap();
+    let info = ValidatingNodeInfo::new(FakeAgent::new()).store_versions(required);
+    let error = info.node_info(&context).await.unwrap_err();
+    let error = error.downcast_ref::<StoreVersionUnsupported>().unwrap();
+    assert_eq!(error.version, "3.2.1");
+    assert_eq!(error.required.to_string(), ">=4.0");
+}
@@ -0,0 +1,197 @@
+//! Decorate [`NodeInfo`] implementations to check returned information against the agent.
+use anyhow::Result;
+
+use super::NodeInfo;
+use crate::agent::framework::Injector;
+use crate::agent::models::Node;
+use crate::agent::models::ShardsInfo;
+use crate::agent::models::StoreExtras;
+use crate::agent::models::StoreVersion;
+use crate::context::Context;
+
+/// Information returned by a [`NodeInfo`] implementation does not match the agent identity.
+#[derive(Debug, thiserror::Error)]
+#[error("node information reports {field} '{actual}' but the agent expects '{expected}'")]
+pub struct NodeIdentityMismatch {
+    /// Value returned by the [`NodeInfo`] implementation.
+    pub actual: String,
+
+    /// Value the agent is configured with.
+    pub expected: String,
+
+    /// Name of the mismatched identity field.
+    pub field: &'static str,
+}
+
+/// The store version reported by a [`NodeInfo`] implementation is not supported by the agent.
+#[derive(Debug, thiserror::Error)]
+#[error("store version '{version}' is not supported by the agent (requires '{required}')")]
+pub struct StoreVersionUnsupported {
+    /// Version requirement the agent supports.
+    pub required: semver::VersionReq,
+
+    /// Store version reported by the [`NodeInfo`] implementation.
+    pub version: String,
+}
+
+/// Decorate a [`NodeInfo`] implementation to check the returned information is about this agent.
+///
+/// Identifiers in the returned [`Node`] and [`StoreExtras`] are compared to the identity
+/// the agent is configured with and mismatches are logged and returned as errors.
+/// This catches bugs in [`NodeInfo`] implementations before incorrect data is reported.
+///
+/// Only identifiers the agent knows about are checked:
+///
+/// - The node ID, if configured (see [`ValidatingNodeInfo::node_id`]).
+/// - The cluster ID, if configured (see [`ValidatingNodeInfo::cluster_id`]).
+///
+/// The store version can also be checked against the versions the agent supports
+/// (see [`ValidatingNodeInfo::store_versions`]) so agents refuse to operate
+/// on stores they may not handle correctly.
+#[derive(Clone, Debug)]
+pub struct ValidatingNodeInfo<I>
+where
+    I: NodeInfo,
+{
+    cluster_id: Option<String>,
+    inner: I,
+    node_id: Option<String>,
+    store_versions: Option<semver::VersionReq>,
+}
+
+impl<I> ValidatingNodeInfo<I>
+where
+    I: NodeInfo,
+{
+    /// Check information from the given [`NodeInfo`] against the agent identity.
+    ///
+    /// No identifiers are checked until expected values are set.
+    pub fn new(inner: I) -> ValidatingNodeInfo<I> {
+        ValidatingNodeInfo {
+            cluster_id: None,
+            inner,
+            node_id: None,
+            store_versions: None,
+        }
+    }
+
+    /// Check information from the given [`NodeInfo`] against the identity from an [`Injector`].
+    pub fn with_injector(inner: I, injector: &Injector) -> ValidatingNodeInfo<I> {
+        let mut validator = ValidatingNodeInfo::new(inner);
+        validator.node_id = injector.config.node_id.clone();
+        validator
+    }
+
+    /// Expect the store to report the given cluster ID.
+    pub fn cluster_id<S>(mut self, cluster_id: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.cluster_id = Some(cluster_id.into());
+        self
+    }
+
+    /// Expect the node to report the given node ID.
+    pub fn node_id<S>(mut self, node_id: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.node_id = Some(node_id.into());
+        self
+    }
+
+    /// Expect the node to report a store version matching the given requirement.
+    ///
+    /// Store versions that are not valid semantic versions are also rejected.
+    pub fn store_versions(mut self, required: semver::VersionReq) -> Self {
+        self.store_versions = Some(required);
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl<I> NodeInfo for ValidatingNodeInfo<I>
+where
+    I: NodeInfo,
+{
+    async fn node_info(&self, context: &Context) -> Result<Node> {
+        let node = self.inner.node_info(context).await?;
+        check_identity(context, "node_id", self.node_id.as_deref(), &node.node_id)?;
+        if let Some(required) = &self.store_versions {
+            check_store_version(context, required, &node.store_version)?;
+        }
+        Ok(node)
+    }
+
+    async fn shards(&self, context: &Context) -> Result<ShardsInfo> {
+        self.inner.shards(context).await
+    }
+
+    async fn store_info(&self, context: &Context) -> Result<StoreExtras> {
+        let store = self.inner.store_info(context).await?;
+        check_identity(
+            context,
+            "cluster_id",
+            self.cluster_id.as_deref(),
+            &store.cluster_id,
+        )?;
+        Ok(store)
+    }
+}
+
+/// Check a returned identifier matches the expected value, if any.
+fn check_identity(
+    context: &Context,
+    field: &'static str,
+    expected: Option<&str>,
+    actual: &str,
+) -> Result<()> {
+    let expected = match expected {
+        Some(expected) if expected != actual => expected,
+        _ => return Ok(()),
+    };
+    slog::error!(
+        context.logger, "Node information does not match the agent identity";
+        "field" => field,
+        "actual" => actual,
+        "expected" => expected,
+    );
+    let error = NodeIdentityMismatch {
+        actual: actual.to_string(),
+        expected: expected.to_string(),
+        field,
+    };
+    anyhow::bail!(error)
+}
+
+/// Check the store version satisfies the agent requirement.
+fn check_store_version(
+    context: &Context,
+    required: &semver::VersionReq,
+    version: &StoreVersion,
+) -> Result<()> {
+    let supported = match version.satisfies(required) {
+        Ok(supported) => supported,
+        Err(error) => {
+            slog::error!(
+                context.logger, "Unable to check the store version is supported by the agent";
+                "required" => %required,
+                "version" => &version.number,
+            );
+            anyhow::bail!(error)
+        }
+    };
+    if supported {
+        return Ok(());
+    }
+    slog::error!(
+        context.logger, "Store version is not supported by the agent";
+        "required" => %required,
+        "version" => &version.number,
+    );
+    let error = StoreVersionUnsupported {
+        required: required.clone(),
+        version: version.number.clone(),
+    };
+    anyhow::bail!(error)
+}
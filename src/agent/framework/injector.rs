@@ -1,9 +1,13 @@
 //! Dependency injection to enable easy access to Process Global resources.
+use std::sync::Arc;
 use std::sync::RwLock;
 
 use once_cell::sync::Lazy;
+use tokio::sync::Notify;
 
 use super::actions::ActionsRegistry;
+use super::actions::ResourceLocks;
+use super::actions::ScheduleLimits;
 use super::store::Store;
 use super::AgentConf;
 use crate::context::Context;
@@ -17,6 +21,12 @@ pub struct Injector {
     /// Registry of available action implementation for the agent.
     pub actions: ActionsRegistry,
 
+    /// Process-wide locks on node resources actions need exclusive access to.
+    pub actions_resources: ResourceLocks,
+
+    /// Notify the actions executor when new actions are scheduled.
+    pub actions_scheduled: Arc<Notify>,
+
     /// Configuration for the agent framework.
     ///
     /// This configuration is stripped of its type parameter to enable easy reference
@@ -26,6 +36,9 @@ pub struct Injector {
     /// Root context for the process.
     pub context: Context,
 
+    /// Process-wide rate limits on scheduling actions.
+    pub schedule_limits: ScheduleLimits,
+
     /// Agent persisted store.
     pub store: Store,
 }
@@ -70,19 +83,30 @@ impl Injector {
             .clone()
     }
 
-    #[cfg(test)]
+    #[cfg(any(test, feature = "test-fixture"))]
     /// Initialise an injector to be used in tests.
+    ///
+    /// The injector uses an in-memory store and registers the wellknown test actions.
     pub async fn fixture() -> Self {
         let mut actions = ActionsRegistry::build();
         for metadata in crate::agent::framework::actions::wellknown::test::all() {
             actions = actions.register(metadata);
         }
 
+        let config: crate::agent::framework::AgentConf<()> = Default::default();
+        let context = Context::fixture();
+        let schedule_limits = ScheduleLimits::from_config(&config.actions);
+        let store = Store::initialise(&context.logger, super::store::MEMORY_PATH)
+            .await
+            .expect("fixture store to be initialised");
         Self {
             actions: actions.finish(),
-            config: Default::default(),
-            context: Context::fixture(),
-            store: super::store::fixtures::store().await,
+            actions_resources: Default::default(),
+            actions_scheduled: Default::default(),
+            config,
+            context,
+            schedule_limits,
+            store,
         }
     }
 }
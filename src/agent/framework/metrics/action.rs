@@ -1,46 +1,113 @@
 //! Agent SDK metrics related to actions.
-use once_cell::sync::Lazy;
 use prometheus::Counter;
 use prometheus::Histogram;
 use prometheus::HistogramOpts;
 
-/// Number of action execution loops where an action was run.
-pub static EXECUTE_LOOPS_BUSY: Lazy<Counter> = Lazy::new(|| {
-    Counter::new(
-        "repliagent_action_loops_busy",
-        "Number of action execution loops where an action was run",
-    )
-    .expect("failed to initialise EXECUTE_LOOPS_BUSY counter")
-});
-
-/// Duration (in seconds) of an action execution loop.
-pub static EXECUTE_LOOPS_DURATION: Lazy<Histogram> = Lazy::new(|| {
-    Histogram::with_opts(
-        HistogramOpts::new(
-            "repliagent_action_loops_duration",
-            "Duration (in seconds) of an action execution loop",
+use crate::agent::models::ActionExecution;
+
+/// Buckets (in seconds) for the duration of actions from when they are scheduled to completion.
+const FINISH_DURATION_BUCKETS: [f64; 10] = [
+    1.0, 5.0, 15.0, 30.0, 60.0, 300.0, 900.0, 1800.0, 3600.0, 7200.0,
+];
+
+declare_metrics! {
+    /// Duration (in seconds) from when an action was created to when it finished.
+    ///
+    /// Actions can be created by systems other than the agent (such as Core) so this duration
+    /// includes any time the action spent before being scheduled on the agent.
+    pub static CREATE_TO_FINISH_DURATION: Histogram = {
+        Histogram::with_opts(
+            HistogramOpts::new(
+                "repliagent_action_create_to_finish_duration",
+                "Duration (in seconds) from when an action was created to when it finished",
+            )
+            .buckets(FINISH_DURATION_BUCKETS.to_vec()),
+        )
+        .expect("failed to initialise CREATE_TO_FINISH_DURATION histogram")
+    };
+
+    /// Number of action execution loops where an action was run.
+    pub static EXECUTE_LOOPS_BUSY: Counter = {
+        Counter::new(
+            "repliagent_action_loops_busy",
+            "Number of action execution loops where an action was run",
+        )
+        .expect("failed to initialise EXECUTE_LOOPS_BUSY counter")
+    };
+
+    /// Duration (in seconds) of an action execution loop.
+    pub static EXECUTE_LOOPS_DURATION: Histogram = {
+        Histogram::with_opts(
+            HistogramOpts::new(
+                "repliagent_action_loops_duration",
+                "Duration (in seconds) of an action execution loop",
+            )
+            .buckets(vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0]),
+        )
+        .expect("failed to initialise EXECUTE_LOOPS_DURATION histogram")
+    };
+
+    /// Number of action execution loops that ended in error.
+    ///
+    /// NOTE: actions can fail with the execution loop completing successfully.
+    pub static EXECUTE_LOOPS_ERROR: Counter = {
+        Counter::new(
+            "repliagent_action_loops_error",
+            "Number of action execution loops that ended in error",
+        )
+        .expect("failed to initialise EXECUTE_LOOPS_BUSY counter")
+    };
+
+    /// Number of actions that ended in the failed state.
+    pub static FAILED: Counter = {
+        Counter::new(
+            "repliagent_action_failed",
+            "Number of actions that ended in the failed state",
+        )
+        .expect("failed to initialise FAILED counter")
+    };
+
+    /// Duration (in seconds) from when the agent started running an action to when it finished.
+    ///
+    /// Actions that fail before their handler is invoked are not observed.
+    pub static RUN_DURATION: Histogram = {
+        Histogram::with_opts(
+            HistogramOpts::new(
+                "repliagent_action_run_duration",
+                "Duration (in seconds) from when the agent started running an action to when it finished",
+            )
+            .buckets(FINISH_DURATION_BUCKETS.to_vec()),
+        )
+        .expect("failed to initialise RUN_DURATION histogram")
+    };
+
+    /// Duration (in seconds) from when an action was scheduled on the agent to when it finished.
+    pub static SCHEDULE_TO_FINISH_DURATION: Histogram = {
+        Histogram::with_opts(
+            HistogramOpts::new(
+                "repliagent_action_schedule_to_finish_duration",
+                "Duration (in seconds) from when an action was scheduled on the agent to when it finished",
+            )
+            .buckets(FINISH_DURATION_BUCKETS.to_vec()),
         )
-        .buckets(vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0]),
-    )
-    .expect("failed to initialise EXECUTE_LOOPS_DURATION histogram")
-});
+        .expect("failed to initialise SCHEDULE_TO_FINISH_DURATION histogram")
+    };
+}
 
-/// Number of action execution loops that ended in error.
+/// Observe the latency of an action that reached a final phase.
 ///
-/// NOTE: actions can fail with the execution loop completing successfully.
-pub static EXECUTE_LOOPS_ERROR: Lazy<Counter> = Lazy::new(|| {
-    Counter::new(
-        "repliagent_action_loops_error",
-        "Number of action execution loops that ended in error",
-    )
-    .expect("failed to initialise EXECUTE_LOOPS_BUSY counter")
-});
-
-/// Number of actions that ended in the failed state.
-pub static FAILED: Lazy<Counter> = Lazy::new(|| {
-    Counter::new(
-        "repliagent_action_failed",
-        "Number of actions that ended in the failed state",
-    )
-    .expect("failed to initialise FAILED counter")
-});
+/// Actions that are not finished are ignored.
+pub fn observe_finished(action: &ActionExecution) {
+    let finished_time = match action.finished_time {
+        None => return,
+        Some(finished_time) => finished_time,
+    };
+    let created = (finished_time - action.created_time).as_seconds_f64();
+    CREATE_TO_FINISH_DURATION.observe(created.max(0.0));
+    let scheduled = (finished_time - action.scheduled_time).as_seconds_f64();
+    SCHEDULE_TO_FINISH_DURATION.observe(scheduled.max(0.0));
+    if let Some(started_time) = action.started_time {
+        let run = (finished_time - started_time).as_seconds_f64();
+        RUN_DURATION.observe(run.max(0.0));
+    }
+}
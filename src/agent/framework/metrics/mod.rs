@@ -1,10 +1,33 @@
 //! Definitions of all Agent SDK metrics.
+//!
+//! Metrics are declared with the `declare_metrics!` macro so every declared metric
+//! is automatically included in the collectors registered during process initialisation.
 use anyhow::Result;
+use prometheus::core::Collector;
+use prometheus::Registry;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 use crate::agent::framework::InitialiseHookArgs;
 
+/// Declare lazily initialised metrics and collect them for registration.
+///
+/// Each declaration expands to a `Lazy` static initialised with the given expression.
+/// The macro also defines a `collectors` function returning all the declared metrics.
+macro_rules! declare_metrics {
+    ($($(#[$meta:meta])* pub static $name:ident: $type:ty = $init:expr;)+) => {
+        $(
+            $(#[$meta])*
+            pub static $name: once_cell::sync::Lazy<$type> = once_cell::sync::Lazy::new(|| $init);
+        )+
+
+        /// All metrics declared in this module, for registration.
+        pub(super) fn collectors() -> Vec<Box<dyn prometheus::core::Collector>> {
+            vec![$(Box::new($name.clone())),+]
+        }
+    };
+}
+
 pub mod action;
 pub mod store;
 
@@ -13,16 +36,59 @@ pub fn initialise<C>(args: &InitialiseHookArgs<C>) -> Result<()>
 where
     C: Clone + std::fmt::Debug + PartialEq + Serialize + DeserializeOwned,
 {
-    let collectors: [Box<dyn prometheus::core::Collector>; 6] = [
-        Box::new(action::EXECUTE_LOOPS_BUSY.clone()),
-        Box::new(action::EXECUTE_LOOPS_DURATION.clone()),
-        Box::new(action::EXECUTE_LOOPS_ERROR.clone()),
-        Box::new(action::FAILED.clone()),
-        Box::new(store::OPS_DURATION.clone()),
-        Box::new(store::OPS_ERR.clone()),
-    ];
-    for collector in collectors {
-        args.telemetry.metrics.register(collector)?;
+    register(&args.telemetry.metrics)
+}
+
+/// All metrics declared by the Agent SDK.
+fn collectors() -> Vec<Box<dyn Collector>> {
+    let mut collectors = action::collectors();
+    collectors.extend(store::collectors());
+    collectors
+}
+
+/// Register all Agent SDK metrics with the given [`Registry`].
+fn register(registry: &Registry) -> Result<()> {
+    for collector in collectors() {
+        registry.register(collector)?;
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use prometheus::core::Collector;
+    use prometheus::Error;
+    use prometheus::Registry;
+
+    use super::action;
+    use super::store;
+
+    #[test]
+    fn all_declared_metrics_are_registered() {
+        let registry = Registry::new();
+        super::register(&registry).unwrap();
+
+        // List metrics declared by each module independently of the collectors to register.
+        let declared: Vec<Box<dyn Collector>> = vec![
+            Box::new(action::CREATE_TO_FINISH_DURATION.clone()),
+            Box::new(action::EXECUTE_LOOPS_BUSY.clone()),
+            Box::new(action::EXECUTE_LOOPS_DURATION.clone()),
+            Box::new(action::EXECUTE_LOOPS_ERROR.clone()),
+            Box::new(action::FAILED.clone()),
+            Box::new(action::RUN_DURATION.clone()),
+            Box::new(action::SCHEDULE_TO_FINISH_DURATION.clone()),
+            Box::new(store::ACTIONS_CLEANED.clone()),
+            Box::new(store::OPS_DURATION.clone()),
+            Box::new(store::OPS_ERR.clone()),
+        ];
+
+        // Registering a metric a second time fails only if it was registered already.
+        for collector in declared {
+            let name = collector.desc()[0].fq_name.clone();
+            match registry.register(collector) {
+                Err(Error::AlreadyReg) => (),
+                result => panic!("metric {} was not registered: {:?}", name, result),
+            }
+        }
+    }
+}
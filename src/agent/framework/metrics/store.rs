@@ -1,5 +1,4 @@
 //! Agent SDK metrics related to the agent store.
-use once_cell::sync::Lazy;
 use prometheus::Counter;
 use prometheus::CounterVec;
 use prometheus::HistogramOpts;
@@ -7,30 +6,41 @@ use prometheus::HistogramTimer;
 use prometheus::HistogramVec;
 use prometheus::Opts;
 
-/// Duration (in seconds) of an agent store operation.
-pub static OPS_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
-    HistogramVec::new(
-        HistogramOpts::new(
-            "repliagent_store_ops_duration",
-            "Duration (in seconds) of an action execution loop",
+declare_metrics! {
+    /// Number of finished actions removed from the agent store by the store cleaner.
+    pub static ACTIONS_CLEANED: Counter = {
+        Counter::new(
+            "repliagent_store_actions_cleaned",
+            "Number of finished actions removed from the agent store by the store cleaner",
         )
-        .buckets(vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0]),
-        &["op"],
-    )
-    .expect("failed to initialise OPS_DURATION histogram")
-});
+        .expect("failed to initialise ACTIONS_CLEANED counter")
+    };
 
-/// Number of agent store operations that resulted in error.
-pub static OPS_ERR: Lazy<CounterVec> = Lazy::new(|| {
-    CounterVec::new(
-        Opts::new(
-            "repliagent_store_ops_error",
-            "Number of agent store operations that resulted in error",
-        ),
-        &["op"],
-    )
-    .expect("failed to initialise OPS_ERR counter")
-});
+    /// Duration (in seconds) of an agent store operation.
+    pub static OPS_DURATION: HistogramVec = {
+        HistogramVec::new(
+            HistogramOpts::new(
+                "repliagent_store_ops_duration",
+                "Duration (in seconds) of an action execution loop",
+            )
+            .buckets(vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0]),
+            &["op"],
+        )
+        .expect("failed to initialise OPS_DURATION histogram")
+    };
+
+    /// Number of agent store operations that resulted in error.
+    pub static OPS_ERR: CounterVec = {
+        CounterVec::new(
+            Opts::new(
+                "repliagent_store_ops_error",
+                "Number of agent store operations that resulted in error",
+            ),
+            &["op"],
+        )
+        .expect("failed to initialise OPS_ERR counter")
+    };
+}
 
 /// Observe the execution of an agent store operation.
 ///
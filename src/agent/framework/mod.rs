@@ -33,6 +33,14 @@
 //! structures that collect all the information needed by both framework and agent implementation.
 //! The loaded configuration is the provided to the [`Agent::configure`] method.
 //!
+//! Alternatively [`ProcessConfig`] groups the same options into sections by process component
+//! (server, shutdown, store, telemetry, ...) and is provided to [`Agent::configure_process`].
+//!
+//! Configuration can be loaded from YAML documents with [`AgentConf::from_yaml`]
+//! or [`ProcessConfig::from_yaml`].
+//! Use [`ConfLoadMode::Strict`] to reject unknown configuration keys, such as typos,
+//! instead of silently ignoring them.
+//!
 //! Aside from the user configuration options described above the framework expects some
 //! agent specific options that implementations must provide.
 //! The [`Agent::run`] method lists all the options that, if missing, cause the process to fail.
@@ -118,13 +126,23 @@ mod proc;
 mod trace;
 
 pub mod actions;
+#[cfg(any(test, feature = "test-fixture"))]
+pub mod fixture;
 pub mod store;
 
 #[cfg(test)]
 mod tests;
 
+pub use self::conf::ActionsConfig;
 pub use self::conf::AgentConf;
+pub use self::conf::AgentConfError;
 pub use self::conf::AgentOptions;
+pub use self::conf::ConfLoadMode;
+pub use self::conf::ProcessConfig;
+pub use self::conf::ScheduleRateLimit;
+pub use self::conf::StoreConfig;
+pub use self::conf::StoreMaintenanceConfig;
+pub use self::info::NodeIdentityMismatch;
 pub use self::info::NodeInfo;
 pub use self::info::StoreVersionChain;
 pub use self::info::StoreVersionCommand;
@@ -134,6 +152,8 @@ pub use self::info::StoreVersionFile;
 pub use self::info::StoreVersionFileError;
 pub use self::info::StoreVersionFixed;
 pub use self::info::StoreVersionStrategy;
+pub use self::info::StoreVersionUnsupported;
+pub use self::info::ValidatingNodeInfo;
 pub use self::injector::Injector;
 pub use self::node_id::detect_node_id;
 pub use self::node_id::NodeIdDetectError;
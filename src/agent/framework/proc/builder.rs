@@ -12,23 +12,30 @@ use crate::agent::framework::actions::ActionsExecutor;
 use crate::agent::framework::actions::ActionsRegistry;
 use crate::agent::framework::actions::ActionsRegistryBuilder;
 use crate::agent::framework::actions::ActionsService;
+use crate::agent::framework::actions::ScheduleLimits;
 use crate::agent::framework::info;
 use crate::agent::framework::store::Store;
 use crate::agent::framework::store::StoreClean;
+use crate::agent::framework::store::StoreMaintenance;
+use crate::agent::framework::store::StorePath;
 use crate::agent::framework::AgentConf;
 use crate::agent::framework::AgentOptions;
 use crate::agent::framework::Injector;
 use crate::agent::framework::NodeInfo;
 use crate::agent::framework::NodeInfoFactory;
 use crate::agent::framework::NodeInfoFactoryArgs;
+use crate::agent::framework::ProcessConfig;
+use crate::agent::framework::ValidatingNodeInfo;
 use crate::context::ActixTransform;
 use crate::context::Context;
 use crate::runtime::actix_web::AppConfigurer;
 use crate::runtime::actix_web::AppFactory;
+use crate::runtime::shutdown::ShutdownConfig;
 use crate::runtime::shutdown::ShutdownManager;
 use crate::runtime::shutdown::ShutdownManagerBuilder;
 use crate::runtime::telemetry::initialise as telemetry_init;
 use crate::runtime::telemetry::TelemetryOptions;
+use crate::utils::error::slog::ErrorAttributes;
 
 use super::init::InitialiseHookVec;
 use super::InitialiseHook;
@@ -79,6 +86,7 @@ where
     node_info: Option<IF>,
     options: Option<AgentOptions>,
     shutdown: ShutdownManagerBuilder<()>,
+    shutdown_conf: Option<ShutdownConfig>,
     telemetry_options: Option<TelemetryOptions>,
 }
 
@@ -103,6 +111,7 @@ where
             node_info: None,
             options: None,
             shutdown,
+            shutdown_conf: None,
             telemetry_options: None,
         }
     }
@@ -110,9 +119,22 @@ where
     /// Set the agent configuration to use.
     pub fn configure(mut self, conf: AgentConf<C>) -> Self {
         self.conf = Some(conf);
+        self.shutdown_conf = None;
         self
     }
 
+    /// Set the agent configuration to use from a unified [`ProcessConfig`].
+    ///
+    /// This replaces any configuration previously set with [`Agent::configure`].
+    ///
+    /// Returns an error if the shutdown grace timeout is not a whole number of seconds.
+    pub fn configure_process(mut self, conf: ProcessConfig<C>) -> Result<Self> {
+        let shutdown_conf = conf.shutdown.clone();
+        self.conf = Some(AgentConf::try_from(conf)?);
+        self.shutdown_conf = Some(shutdown_conf);
+        Ok(self)
+    }
+
     /// Set the implementation for the node information gathering to use.
     pub fn node_info(mut self, factory: IF) -> Self {
         self.node_info = Some(factory);
@@ -148,11 +170,15 @@ where
 
     /// Finalise agent setup and run it.
     ///
+    /// Information returned by the [`NodeInfo`] implementation is checked against
+    /// the configured `node_id`, if one is set (see [`ValidatingNodeInfo`]).
+    ///
     /// # Panics
     ///
     /// This method panics if required elements are not defined:
     ///
-    /// - The agent MUST be configured with a call to [`Agent::configure`].
+    /// - The agent MUST be configured with a call to [`Agent::configure`]
+    ///   or [`Agent::configure_process`].
     /// - The agent MUST be given [`AgentOptions`] with a call to [`Agent::options`].
     /// - The agent MUST be given [`NodeInfo`] with a call to [`Agent::node_info`].
     /// - The agent MUST be given [`TelemetryOptions`] with a call to [`Agent::telemetry_options`].
@@ -174,18 +200,35 @@ where
         // Initialise the process.
         let telemetry = telemetry_init(conf.telemetry.clone(), telemetry_options).await?;
         let mut shutdown = self.shutdown;
-        shutdown
-            .logger(telemetry.logger.clone())
-            .graceful_shutdown_timeout(Duration::from_secs(conf.runtime.shutdown_grace_sec));
+        shutdown.logger(telemetry.logger.clone());
+        match self.shutdown_conf {
+            None => shutdown
+                .graceful_shutdown_timeout(Duration::from_secs(conf.runtime.shutdown_grace_sec)),
+            Some(shutdown_conf) => shutdown_conf.apply(&mut shutdown),
+        };
         slog::info!(telemetry.logger, "Process telemetry initialised");
 
         // Initialise agent globals.
         let context = Context::root(telemetry.logger.clone()).build();
-        let store = Store::initialise(&telemetry.logger, &conf.store_path).await?;
+        let store_path = StorePath::new(&conf.store.path).create_parent(conf.store.path_create);
+        let store = Store::initialise(&telemetry.logger, store_path)
+            .await?
+            .with_encoding(conf.store.encoding);
+        let store = match conf.store.read_pool {
+            None => store,
+            Some(size) => store.with_read_pool(size).await?,
+        };
+        let store = match conf.store.write_queue {
+            None => store,
+            Some(capacity) => store.with_write_queue(capacity),
+        };
         let injector = Injector {
             actions: self.actions.finish(),
+            actions_resources: Default::default(),
+            actions_scheduled: Default::default(),
             config: conf.erase_custom(),
             context,
+            schedule_limits: ScheduleLimits::from_config(&conf.actions),
             store,
         };
         Injector::initialise(injector.clone());
@@ -209,14 +252,17 @@ where
                 telemetry: &telemetry,
             })
             .await?;
+        // Check node information against the agent identity, when one is configured.
+        let node_info = ValidatingNodeInfo::with_injector(node_info, &injector);
 
         // Set up predefined agent endpoints.
         slog::debug!(telemetry.logger, "Configuring agent API endpoints");
         let mut app = self.app;
         let app_injector = injector.clone();
+        let executor_node_info = node_info.clone();
         app.with_config(move |conf| {
-            let actions = ActionsService::with_injector(&app_injector);
             let info = node_info.clone();
+            let actions = ActionsService::with_injector(&app_injector).node_info(info.clone());
             let info = info::into_actix_service(info);
             let scope = actix_web::web::scope("/api/unstable")
                 .service(info)
@@ -232,7 +278,11 @@ where
             .build();
         let factory = AppFactory::configure(app, conf.http.clone())
             .metrics(options.requests_metrics_prefix, telemetry.metrics.clone())
-            .done();
+            .request_id(true)
+            .done()?;
+        if let Some(admin) = factory.admin_server()? {
+            shutdown.watch_actix(admin, ());
+        }
         let server = HttpServer::new(move || {
             let app = factory.initialise();
             // Enable per-request contexts.
@@ -245,18 +295,43 @@ where
         shutdown.watch_actix(server.run(), ());
 
         // Spawn actions execution background task.
-        let executor = ActionsExecutor::with_injector(&injector);
+        let executor = ActionsExecutor::with_injector(&injector).node_info(executor_node_info);
         let executor = executor.task(shutdown.shutdown_notification());
-        shutdown.watch_tokio(tokio::spawn(executor));
+        shutdown.watch_future(executor);
 
         // Spawn store cleaner background task.
         let cleaner = StoreClean::with_injector(&injector);
         let cleaner = cleaner.task(shutdown.shutdown_notification());
-        shutdown.watch_tokio(tokio::spawn(cleaner));
+        shutdown.watch_future(cleaner);
+
+        // Spawn store maintenance background task, if enabled.
+        if injector.config.store.maintenance.enabled {
+            let maintenance = StoreMaintenance::with_injector(&injector)?;
+            let maintenance = maintenance.task(shutdown.shutdown_notification());
+            shutdown.watch_future(maintenance);
+        }
+
+        // Spawn metrics push background task, if enabled.
+        if let Some(push) = telemetry.metrics_push.clone() {
+            let push = push.task(shutdown.shutdown_notification());
+            shutdown.watch_future(push);
+        }
 
         // Complete shutdown setup and run the agent until an exit condition.
         let exit = shutdown.build();
-        exit.wait().await
+        let result = exit.wait().await;
+
+        // Let pending store writes complete before closing it.
+        slog::debug!(telemetry.logger, "Quiescing agent store before closing it");
+        injector.store.quiesce().await;
+        if let Err(error) = injector.store.close().await {
+            slog::warn!(
+                telemetry.logger,
+                "Unable to cleanly close the agent store";
+                ErrorAttributes::from(&error)
+            );
+        }
+        result
     }
 
     /// Set the [`TelemetryOptions`] for the agent process to use.
@@ -288,6 +363,77 @@ where
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::Agent;
+    use crate::agent::framework::ConfLoadMode;
+    use crate::agent::framework::NodeInfo;
+    use crate::agent::framework::NodeInfoFactory;
+    use crate::agent::framework::NodeInfoFactoryArgs;
+    use crate::agent::framework::ProcessConfig;
+    use crate::agent::models::Node;
+    use crate::agent::models::ShardsInfo;
+    use crate::agent::models::StoreExtras;
+    use crate::context::Context;
+
+    #[derive(Clone)]
+    struct FakeNodeInfo;
+
+    #[async_trait::async_trait]
+    impl NodeInfo for FakeNodeInfo {
+        async fn node_info(&self, _: &Context) -> Result<Node> {
+            anyhow::bail!(anyhow::anyhow!("node info is not needed by builder tests"))
+        }
+
+        async fn shards(&self, _: &Context) -> Result<ShardsInfo> {
+            anyhow::bail!(anyhow::anyhow!("shards are not needed by builder tests"))
+        }
+
+        async fn store_info(&self, _: &Context) -> Result<StoreExtras> {
+            anyhow::bail!(anyhow::anyhow!("store info is not needed by builder tests"))
+        }
+    }
+
+    struct FakeNodeInfoFactory;
+
+    #[async_trait::async_trait]
+    impl NodeInfoFactory for FakeNodeInfoFactory {
+        type Conf = ();
+        type NodeInfo = FakeNodeInfo;
+
+        async fn factory<'a>(&self, _: NodeInfoFactoryArgs<'a, ()>) -> Result<FakeNodeInfo> {
+            Ok(FakeNodeInfo)
+        }
+    }
+
+    const PROCESS: &str = r#"
+server:
+  bind: 127.0.0.1:8000
+shutdown:
+  grace_timeout: 90s
+  progress_interval: 10s
+store:
+  path: ":memory:"
+"#;
+
+    #[test]
+    fn configure_from_process_config() {
+        let conf = ProcessConfig::<()>::from_yaml(PROCESS, ConfLoadMode::Strict).unwrap();
+        let agent = Agent::<(), FakeNodeInfoFactory>::build()
+            .configure_process(conf.clone())
+            .unwrap()
+            .node_info(FakeNodeInfoFactory);
+
+        let agent_conf = agent.conf.unwrap();
+        assert_eq!(agent_conf.http.bind, "127.0.0.1:8000");
+        assert_eq!(agent_conf.runtime.shutdown_grace_sec, 90);
+        assert_eq!(agent_conf.store.path, ":memory:");
+        assert_eq!(agent.shutdown_conf, Some(conf.shutdown));
+    }
+}
+
 /* *** Agent process builder ***
 let (tokio, conf) = config::load()?;
 let runtime = tokio::Runtime::from_conf(tokio)?;
@@ -6,6 +6,7 @@ use anyhow::Result;
 use opentelemetry_api::trace::FutureExt;
 
 use super::Store;
+use crate::agent::framework::metrics;
 use crate::agent::framework::store::manage;
 use crate::agent::framework::Injector;
 use crate::context::Context;
@@ -86,11 +87,20 @@ impl StoreClean {
 }
 
 impl StoreClean {
-    /// Perform a round of cleaning duties.
-    async fn task_loop(&self) -> Result<()> {
+    /// Perform a round of cleaning duties, returning the number of actions removed.
+    async fn task_loop(&self) -> Result<usize> {
         let expire = time::OffsetDateTime::now_utc() - self.clean_age;
         let expire = manage::CleanActions::since(expire);
-        self.store.manage(&self.context, expire).await
+        let removed = self.store.manage(&self.context, expire).await?;
+        metrics::store::ACTIONS_CLEANED.inc_by(removed as f64);
+        if removed > 0 {
+            slog::info!(
+                self.context.logger,
+                "Removed finished actions from the store";
+                "removed" => removed,
+            );
+        }
+        Ok(removed)
     }
 }
 
@@ -170,7 +180,8 @@ mod tests {
         fixtures.add_action(ActionExecutionPhase::Failed, old).await;
 
         let cleaner = StoreClean::with_injector(&fixtures.injector);
-        cleaner.task_loop().await.unwrap();
+        let removed = cleaner.task_loop().await.unwrap();
+        assert_eq!(removed, 2);
 
         let actions = fixtures.count_actions().await;
         assert_eq!(actions, 2);
@@ -195,7 +206,8 @@ mod tests {
             .await;
 
         let cleaner = StoreClean::with_injector(&fixtures.injector);
-        cleaner.task_loop().await.unwrap();
+        let removed = cleaner.task_loop().await.unwrap();
+        assert_eq!(removed, 0);
 
         let actions = fixtures.count_actions().await;
         assert_eq!(actions, 5);
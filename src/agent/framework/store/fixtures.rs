@@ -24,6 +24,7 @@ pub fn action(id: Uuid) -> ActionExecution {
         kind: String::from(ACTION_KIND),
         metadata: Default::default(),
         scheduled_time: timestamp,
+        started_time: None,
         state: ActionExecutionState {
             error: None,
             payload: None,
@@ -40,3 +41,26 @@ pub async fn store() -> Store {
         .await
         .expect("store to be initialised")
 }
+
+/// Temporary directory for a test store, removed with all the store files when dropped.
+pub struct TestStore(tempfile::TempDir);
+
+impl TestStore {
+    pub fn new() -> TestStore {
+        TestStore(tempfile::tempdir().expect("temporary directory to be created"))
+    }
+
+    /// Path to the store file within the temporary directory.
+    pub fn path(&self) -> String {
+        self.0
+            .path()
+            .join("agent.db")
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    pub fn wal_size(&self) -> u64 {
+        let wal = format!("{}-wal", self.path());
+        std::fs::metadata(wal).map(|meta| meta.len()).unwrap_or(0)
+    }
+}
@@ -0,0 +1,187 @@
+//! Background task to perform [`Store`] maintenance tasks.
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::Result;
+use opentelemetry_api::trace::FutureExt;
+
+use super::Store;
+use super::StoreError;
+use crate::agent::framework::store::manage;
+use crate::agent::framework::store::query;
+use crate::agent::framework::Injector;
+use crate::context::Context;
+use crate::utils::error::slog::ErrorAttributes;
+use crate::utils::trace::TraceFutureErrExt;
+
+/// Background task to periodically maintain the agent store.
+///
+/// Maintaining the store is done to keep the size of the store files bounded
+/// after the [`StoreClean`](super::StoreClean) task removes records.
+///
+/// The following maintenance tasks are performed:
+///
+/// - The write-ahead log, if one is in use, is checkpointed and truncated.
+/// - Optionally, the store is rebuilt to release free pages when no actions are queued or running.
+pub struct StoreMaintenance {
+    context: Context,
+    interval: Duration,
+    store: Store,
+    vacuum: bool,
+}
+
+impl StoreMaintenance {
+    /// Loop performing store maintenance duties until process shutdown.
+    pub async fn task<S>(self, shutdown: S) -> Result<()>
+    where
+        S: Future<Output = ()>,
+    {
+        tokio::pin!(shutdown);
+        slog::debug!(self.context.logger, "Starting background store maintenance");
+        let tracer = crate::agent::framework::trace::tracer();
+
+        loop {
+            // Sleep until the next cycle or shutdown.
+            tokio::select! {
+                _ = tokio::time::sleep(self.interval) => {},
+                _ = &mut shutdown => {
+                    slog::debug!(self.context.logger, "Gracefully shutting down store maintenance");
+                    return Ok(());
+                }
+            }
+
+            // Create a root span to trace activities of this loop.
+            let context = crate::utils::trace::root(&tracer, "store.maintenance");
+
+            // Execute a maintenance loop.
+            let result = self
+                .task_loop()
+                .trace_on_err_with_status()
+                .with_context(context)
+                .await;
+            if let Err(error) = result {
+                slog::error!(
+                    self.context.logger,
+                    "Store maintenance loop encountered an error";
+                    ErrorAttributes::from(&error)
+                );
+            }
+        }
+    }
+
+    /// Initialise a [`StoreMaintenance`] with dependencies from the given [`Injector`].
+    ///
+    /// Returns an error if the configured maintenance interval is zero.
+    pub fn with_injector(injector: &Injector) -> Result<StoreMaintenance> {
+        let conf = &injector.config.store.maintenance;
+        if conf.interval.duration().is_zero() {
+            anyhow::bail!(StoreError::MaintenanceIntervalZero);
+        }
+        let context = injector
+            .context
+            .derive()
+            .log_values(slog::o!("component" => "store-maintenance"))
+            .build();
+        Ok(StoreMaintenance {
+            context,
+            interval: conf.interval.into(),
+            store: injector.store.clone(),
+            vacuum: conf.vacuum,
+        })
+    }
+}
+
+impl StoreMaintenance {
+    /// Perform a round of maintenance duties.
+    async fn task_loop(&self) -> Result<()> {
+        if self.vacuum {
+            self.vacuum().await?;
+        }
+
+        // Checkpoint last as rebuilding the store goes through the write-ahead log.
+        self.store.manage(&self.context, manage::Checkpoint).await
+    }
+
+    /// Rebuild the store, but only when no actions are waiting for it.
+    async fn vacuum(&self) -> Result<()> {
+        let queue = self
+            .store
+            .query(&self.context, query::ActionsQueue::default())
+            .await?;
+        if !queue.actions.is_empty() {
+            slog::debug!(
+                self.context.logger,
+                "Skipping store vacuum while actions are queued or running"
+            );
+            return Ok(());
+        }
+        self.store.manage(&self.context, manage::Vacuum).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Injector;
+    use super::Store;
+    use super::StoreError;
+    use super::StoreMaintenance;
+    use crate::agent::framework::store::fixtures;
+    use crate::agent::framework::store::fixtures::TestStore;
+    use crate::context::Context;
+    use crate::utils::config::HumanDuration;
+
+    #[tokio::test]
+    async fn checkpoint_truncates_wal() {
+        let path = TestStore::new();
+        let context = Context::fixture();
+        let store = Store::initialise(&context.logger, &path.path())
+            .await
+            .unwrap();
+        store
+            .store
+            .call(|connection| {
+                connection.query_row("PRAGMA journal_mode=WAL;", [], |_| Ok(()))?;
+                connection.execute_batch("PRAGMA wal_autocheckpoint=0;")?;
+                Ok(())
+            })
+            .await
+            .unwrap();
+
+        // Fill the write-ahead log by inserting and deleting many actions.
+        for _ in 0..100 {
+            let action = fixtures::action(uuid::Uuid::new_v4());
+            store.persist(&context, action).await.unwrap();
+        }
+        store
+            .store
+            .call(|connection| {
+                connection.execute("DELETE FROM actions;", [])?;
+                Ok(())
+            })
+            .await
+            .unwrap();
+        assert!(path.wal_size() > 0);
+
+        let mut injector = Injector::fixture().await;
+        injector.config.store.maintenance.vacuum = true;
+        injector.store = store;
+        let maintenance = StoreMaintenance::with_injector(&injector).unwrap();
+        maintenance.task_loop().await.unwrap();
+        assert_eq!(path.wal_size(), 0);
+        injector.store.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn zero_interval_rejected() {
+        let mut injector = Injector::fixture().await;
+        injector.config.store.maintenance.interval = HumanDuration::from_secs(0);
+        let error = match StoreMaintenance::with_injector(&injector) {
+            Ok(_) => panic!("zero maintenance interval accepted"),
+            Err(error) => error,
+        };
+        assert!(matches!(
+            error.downcast_ref::<StoreError>(),
+            Some(StoreError::MaintenanceIntervalZero)
+        ));
+    }
+}
@@ -12,12 +12,14 @@ pub trait ManageOp: Into<ManageOps> + SealManageOp {
 }
 
 /// Clean all actions finished prior to the given time.
+///
+/// Returns the number of actions removed from the store.
 pub struct CleanActions {
     age: time::OffsetDateTime,
 }
 impl SealManageOp for CleanActions {}
 impl ManageOp for CleanActions {
-    type Response = ();
+    type Response = usize;
 }
 impl From<CleanActions> for ManageOps {
     fn from(value: CleanActions) -> Self {
@@ -32,6 +34,30 @@ impl CleanActions {
     }
 }
 
+/// Checkpoint the store write-ahead log, if one is in use, and truncate it.
+pub struct Checkpoint;
+impl SealManageOp for Checkpoint {}
+impl ManageOp for Checkpoint {
+    type Response = ();
+}
+impl From<Checkpoint> for ManageOps {
+    fn from(_: Checkpoint) -> Self {
+        ManageOps::Checkpoint
+    }
+}
+
+/// Rebuild the store to release free pages and reduce its size.
+pub struct Vacuum;
+impl SealManageOp for Vacuum {}
+impl ManageOp for Vacuum {
+    type Response = ();
+}
+impl From<Vacuum> for ManageOps {
+    fn from(_: Vacuum) -> Self {
+        ManageOps::Vacuum
+    }
+}
+
 /// Private module to seal as many implementation details as possible.
 mod sealed {
     /// Super-trait to seal the [`ManageOp`](super::ManageOp) trait.
@@ -41,10 +67,20 @@ mod sealed {
     pub enum ManageOps {
         /// Clean all actions finished prior to the given time.
         CleanActions(time::OffsetDateTime),
+
+        /// Checkpoint the store write-ahead log, if one is in use, and truncate it.
+        Checkpoint,
+
+        /// Rebuild the store to release free pages and reduce its size.
+        Vacuum,
     }
 
     /// Enumeration of responses for all supported management operations.
     pub enum ManageResponses {
+        /// Number of records removed by a cleaning operation.
+        Cleaned(usize),
+
+        /// The operation completed without additional information.
         Success,
     }
 
@@ -53,7 +89,16 @@ mod sealed {
         fn from(value: ManageResponses) -> Self {
             match value {
                 ManageResponses::Success => (),
-                //_ => panic!("unexpected result type for the given management operation"),
+                _ => panic!("unexpected result type for the given management operation"),
+            }
+        }
+    }
+
+    impl From<ManageResponses> for usize {
+        fn from(value: ManageResponses) -> Self {
+            match value {
+                ManageResponses::Cleaned(value) => value,
+                _ => panic!("unexpected result type for the given management operation"),
             }
         }
     }
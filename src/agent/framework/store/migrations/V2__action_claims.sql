@@ -0,0 +1,4 @@
+-- Track which executor claimed an action for execution and until when.
+ALTER TABLE actions ADD COLUMN claimant TEXT DEFAULT NULL;
+-- Claim expiry is compared with the current time so use REAL like other queried on times.
+ALTER TABLE actions ADD COLUMN claim_expiry REAL DEFAULT NULL;
@@ -0,0 +1,8 @@
+-- Handler managed state for unfinished actions, stored as one record per key.
+CREATE TABLE IF NOT EXISTS actions_state(
+  action_id TEXT NOT NULL,
+  key TEXT NOT NULL,
+  -- Stored as JSON text or tagged binary data, like other structured data.
+  value TEXT NOT NULL,
+  PRIMARY KEY (action_id, key)
+);
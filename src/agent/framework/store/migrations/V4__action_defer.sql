@@ -0,0 +1,3 @@
+-- Actions that can't progress yet are skipped by the executor until this time.
+-- Compared with the current time so use REAL like other queried on times.
+ALTER TABLE actions ADD COLUMN defer_until REAL DEFAULT NULL;
@@ -0,0 +1,2 @@
+-- Time the agent started running the action, used to measure how long actions run for.
+ALTER TABLE actions ADD COLUMN started_time REAL DEFAULT NULL;
@@ -3,11 +3,42 @@
 //! Querying and updating the [`Store`] is performed using operation objects
 //! which allow the generic [`Store::query`] and [`Store::persist`] methods to perform
 //! specialised operations while preserving strict typing.
+//!
+//! By default persist operations are applied to the store directly by the caller.
+//! Alternatively a bounded write queue can be enabled with [`Store::with_write_queue`]
+//! to apply backpressure on writers under bursty load.
+//!
+//! All operations share a single connection to the store by default.
+//! Read-heavy agents can open a pool of read-only connections with [`Store::with_read_pool`]
+//! so queries run in parallel with each other and with writes.
+//!
+//! Structured data, such as action arguments, is stored as JSON text by default.
+//! A more compact binary encoding can be selected with [`Store::with_encoding`].
+//! Records carry the encoding they were written with so stores can hold
+//! a mix of encodings and switching encoding does not require data migrations.
+//!
+//! All actions can be exported to, and imported from, a portable NDJSON stream with
+//! [`Store::export_actions`] and [`Store::import_actions`] for backups and migrations.
+//!
+//! Before the store is closed during process shutdown it can be quiesced with [`Store::quiesce`]
+//! so pending writes complete and new writes are rejected instead of failing mid-way.
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+
+use actix_web::http::StatusCode;
 use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
 use slog::Logger;
+use tokio::sync::RwLock;
 use tokio_rusqlite::Connection;
 
 mod cleaner;
+mod maintenance;
+mod path;
+mod pool;
+mod portable;
+mod queue;
 mod schema;
 mod statements;
 
@@ -21,6 +52,11 @@ pub(super) mod fixtures;
 mod tests;
 
 pub use self::cleaner::StoreClean;
+pub use self::maintenance::StoreMaintenance;
+pub use self::path::StorePath;
+pub use self::portable::ActionsImport;
+pub use self::portable::PortableActionsError;
+pub use self::queue::WriteQueueError;
 
 use self::manage::ManageOp;
 use self::manage::ManageOps;
@@ -28,23 +64,78 @@ use self::manage::ManageResponses;
 use self::persist::PersistOp;
 use self::persist::PersistOps;
 use self::persist::PersistResponses;
+use self::pool::ReadPool;
 use self::query::QueryOp;
 use self::query::QueryOps;
 use self::query::QueryResponses;
+use self::queue::WriteQueue;
 use crate::context::Context;
+use crate::utils::actix::error::IntoStatusCode;
 
 /// Special path requesting the use of an in-memory store.
 pub const MEMORY_PATH: &str = ":memory:";
 
+/// Encodings for structured data written to the agent store.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum StoreEncoding {
+    /// Store structured data as JSON text.
+    #[default]
+    #[serde(rename = "json")]
+    Json,
+
+    /// Store structured data as [MessagePack](https://msgpack.org/) binary data.
+    #[serde(rename = "msgpack")]
+    MessagePack,
+}
+
+/// Errors preparing or using the agent store.
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    /// The configured store maintenance interval is zero.
+    #[error("agent store maintenance interval must be greater than zero")]
+    MaintenanceIntervalZero,
+
+    /// The store path can't be written to, for example because its parent directory is missing.
+    #[error("agent store path '{0}' is not writable")]
+    PathNotWritable(String),
+
+    /// The store was quiesced ahead of shutdown and no longer accepts writes.
+    #[error("agent store was quiesced and no longer accepts writes")]
+    Quiesced,
+}
+
+/// Respond with `503 Service Unavailable` once the store is quiesced ahead of shutdown.
+impl IntoStatusCode for StoreError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::MaintenanceIntervalZero => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::PathNotWritable(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::Quiesced => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+}
+
 /// Manage persisted data needed for Agent operations.
 #[derive(Clone, Debug)]
 pub struct Store {
+    encoding: StoreEncoding,
+    path: StorePath,
+    /// Set once the store is quiesced, held for reading by in-flight writes.
+    quiesced: Arc<RwLock<bool>>,
+    /// Read-only connections for queries, if a read pool is enabled.
+    readers: Option<ReadPool>,
     store: Connection,
+    writes: Option<WriteQueue>,
 }
 
 impl Store {
     /// Close the connection to the store and flush all pending updates.
+    ///
+    /// Use [`Store::quiesce`] first to wait for in-flight writes to complete.
     pub async fn close(&self) -> Result<()> {
+        if let Some(readers) = &self.readers {
+            readers.close().await?;
+        }
         self.store.clone().close().await?;
         Ok(())
     }
@@ -52,20 +143,26 @@ impl Store {
     /// Initialise the Agent store, including any needed schema migrations.
     ///
     /// The special [`MEMORY_PATH`] constant can be specified to create an in-memory store.
+    /// Other paths are checked to be writable before the store is opened (see [`StorePath`]).
     ///
     /// NOTE:
     ///   The use of an in-memory store is only intended for tests and experimentation
     ///   as all data will be lost as soon as the process terminates.
-    pub async fn initialise(logger: &Logger, path: &str) -> Result<Store> {
+    pub async fn initialise<P>(logger: &Logger, path: P) -> Result<Store>
+    where
+        P: Into<StorePath>,
+    {
         // Open or create the SQLite DB.
-        let store = if path == MEMORY_PATH {
+        let path = path.into();
+        let store = if path.is_memory() {
             slog::warn!(
                 logger,
                 "Using in-memory store means data will be lost once the process terminates"
             );
             Connection::open_in_memory().await
         } else {
-            Connection::open(path).await
+            path.prepare()?;
+            Connection::open(path.as_str()).await
         };
         let store = store?;
 
@@ -81,7 +178,14 @@ impl Store {
             })
             .await?;
 
-        Ok(Store { store })
+        Ok(Store {
+            encoding: StoreEncoding::default(),
+            path,
+            quiesced: Default::default(),
+            readers: None,
+            store,
+            writes: None,
+        })
     }
 
     /// Perform management actions on the store.
@@ -92,9 +196,19 @@ impl Store {
     where
         O: ManageOp,
     {
+        let quiesced = self.quiesced.read().await;
+        if *quiesced {
+            anyhow::bail!(StoreError::Quiesced);
+        }
         let op = op.into();
         let response = match op {
             ManageOps::CleanActions(age) => statements::actions::clean(&self.store, age)
+                .await
+                .map(ManageResponses::Cleaned),
+            ManageOps::Checkpoint => statements::maintenance::checkpoint(&self.store)
+                .await
+                .map(|_| ManageResponses::Success),
+            ManageOps::Vacuum => statements::maintenance::vacuum(&self.store)
                 .await
                 .map(|_| ManageResponses::Success),
         };
@@ -109,17 +223,31 @@ impl Store {
     where
         O: PersistOp,
     {
+        let quiesced = self.quiesced.read().await;
+        if *quiesced {
+            anyhow::bail!(StoreError::Quiesced);
+        }
         let op = op.into();
-        let response = match op {
-            PersistOps::ActionExecution(action) => {
-                statements::actions::persist(&self.store, action)
-                    .await
-                    .map(|_| PersistResponses::Success)
-            }
+        let response = match &self.writes {
+            None => persist_op(&self.store, op, self.encoding).await,
+            Some(writes) => writes.persist(op, self.encoding).await,
         };
         response.map(O::Response::from)
     }
 
+    /// Stop accepting writes to the store and wait for pending writes to complete.
+    ///
+    /// Once this method returns all persist and manage operations started earlier,
+    /// including any waiting in the write queue, have completed and it is safe to
+    /// [`Store::close`] the store.
+    /// Later persist and manage operations fail with [`StoreError::Quiesced`].
+    ///
+    /// Quiescing applies to all clones of the store and can safely be repeated.
+    pub async fn quiesce(&self) {
+        let mut quiesced = self.quiesced.write().await;
+        *quiesced = true;
+    }
+
     /// Query records from the agent store.
     ///
     /// The supported query operations are defined in the [`query`] module and
@@ -128,21 +256,126 @@ impl Store {
     where
         O: QueryOp,
     {
+        let store = self.reader();
         let op = op.into();
         let response = match op {
-            QueryOps::Action(id) => statements::actions::get(&self.store, id)
+            QueryOps::Action(id) => statements::actions::get(store, id)
                 .await
                 .map(QueryResponses::Action),
-            QueryOps::ActionNextToExecute => statements::actions::next_to_execute(&self.store)
+            QueryOps::ActionState(op) => statements::action_state::get(store, op)
+                .await
+                .map(QueryResponses::ActionState),
+            QueryOps::ActionNextToExecute => statements::actions::next_to_execute(store)
                 .await
                 .map(QueryResponses::Action),
-            QueryOps::ActionsFinished => statements::actions::finished(&self.store)
+            QueryOps::ActionsAll(op) => statements::actions::all(store, op)
+                .await
+                .map(QueryResponses::Actions),
+            QueryOps::ActionsCountByPhase => statements::actions::count_by_phase(store)
+                .await
+                .map(QueryResponses::ActionsCount),
+            QueryOps::ActionsFinished(op) => statements::actions::finished(store, op)
                 .await
                 .map(QueryResponses::ActionsList),
-            QueryOps::ActionsQueue => statements::actions::queue(&self.store)
+            QueryOps::ActionsQueue(op) => statements::actions::queue(store, op)
                 .await
                 .map(QueryResponses::ActionsList),
         };
         response.map(O::Response::from)
     }
+
+    /// Connection to send queries to, from the read pool if one is enabled.
+    fn reader(&self) -> &Connection {
+        match &self.readers {
+            None => &self.store,
+            Some(readers) => readers.connection(),
+        }
+    }
+
+    /// Encode structured data written to the store with the given [`StoreEncoding`].
+    ///
+    /// Records already in the store remain readable regardless of the encoding they use.
+    pub fn with_encoding(mut self, encoding: StoreEncoding) -> Store {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Send queries to a pool of `size` read-only connections instead of the writer connection.
+    ///
+    /// The store is switched to SQLite's write-ahead log (WAL) journal mode so queries
+    /// can proceed while writes are in progress.
+    /// Persist and manage operations keep using a single writer connection.
+    ///
+    /// In-memory stores can't share data across connections so they are returned unchanged.
+    pub async fn with_read_pool(mut self, size: NonZeroUsize) -> Result<Store> {
+        if self.path.is_memory() {
+            return Ok(self);
+        }
+        self.store
+            .call(|connection| {
+                connection.query_row("PRAGMA journal_mode=WAL;", [], |_| Ok(()))?;
+                Ok(())
+            })
+            .await?;
+        let readers = ReadPool::open(self.path.as_str(), size).await?;
+        self.readers = Some(readers);
+        Ok(self)
+    }
+
+    /// Bound the number of pending persist operations with a write queue.
+    ///
+    /// Store operations are already applied one at a time by the connection thread,
+    /// but the connection accepts an unlimited number of pending operations.
+    /// The write queue limits pending persist operations to `capacity`:
+    /// once full, callers wait for space to free up instead of piling more work on the store.
+    ///
+    /// Persist operations are applied directly by callers unless this method is used.
+    pub fn with_write_queue(mut self, capacity: NonZeroUsize) -> Store {
+        let writes = WriteQueue::spawn(self.store.clone(), capacity);
+        self.writes = Some(writes);
+        self
+    }
+}
+
+/// Apply a persist operation to the store.
+async fn persist_op(
+    store: &Connection,
+    op: PersistOps,
+    encoding: StoreEncoding,
+) -> Result<PersistResponses> {
+    match op {
+        PersistOps::ActionExecution(action) => {
+            statements::actions::persist(store, action, encoding)
+                .await
+                .map(|_| PersistResponses::Success)
+        }
+        PersistOps::ClaimNextAction(claim) => statements::actions::claim_next(store, claim)
+            .await
+            .map(|action| PersistResponses::Action(action.map(Box::new))),
+        PersistOps::DeferAction(op) => statements::actions::defer(store, op)
+            .await
+            .map(|_| PersistResponses::Success),
+        PersistOps::DeleteActionState(op) => statements::action_state::delete(store, op)
+            .await
+            .map(|_| PersistResponses::Success),
+        PersistOps::ImportAction(op) => statements::actions::import(store, op, encoding)
+            .await
+            .map(PersistResponses::Imported),
+        PersistOps::InsertActions(op) => statements::actions::insert_batch(store, op, encoding)
+            .await
+            .map(PersistResponses::Inserted),
+        PersistOps::PatchActionMetadata(patch) => {
+            statements::actions::patch_metadata(store, patch, encoding)
+                .await
+                .map(PersistResponses::ActionMetadataPatch)
+        }
+        PersistOps::SetActionState(op) => statements::action_state::set(store, op, encoding)
+            .await
+            .map(|_| PersistResponses::Success),
+        PersistOps::UpdateActionProgress(op) => {
+            statements::actions::update_progress(store, op, encoding)
+                .await
+                .map(|_| PersistResponses::Success)
+        }
+    }
 }
@@ -0,0 +1,163 @@
+//! Location of the agent store, checked before the store is opened.
+use std::fs::OpenOptions;
+use std::path::Path;
+
+use anyhow::Context;
+use anyhow::Result;
+
+use super::StoreError;
+use super::MEMORY_PATH;
+
+/// Path to the agent store, validated by [`Store::initialise`] before the store is opened.
+///
+/// Opening the store at a path that can't be written to fails with an opaque SQLite error.
+/// Instead, the path is checked ahead of time so a [`StoreError::PathNotWritable`]
+/// naming the path can be reported.
+///
+/// The special [`MEMORY_PATH`] is never checked as it does not refer to a file.
+///
+/// [`Store::initialise`]: super::Store::initialise
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StorePath {
+    create_parent: bool,
+    path: String,
+}
+
+impl StorePath {
+    /// Return the path as a string.
+    pub fn as_str(&self) -> &str {
+        &self.path
+    }
+
+    /// Create missing parent directories of the store path before opening it.
+    pub fn create_parent(mut self, create: bool) -> Self {
+        self.create_parent = create;
+        self
+    }
+
+    /// Check if the path requests an in-memory store.
+    pub fn is_memory(&self) -> bool {
+        self.path == MEMORY_PATH
+    }
+
+    /// Path to an in-memory store.
+    pub fn memory() -> StorePath {
+        StorePath::new(MEMORY_PATH)
+    }
+
+    /// Path to the agent store at the given location.
+    pub fn new<S>(path: S) -> StorePath
+    where
+        S: Into<String>,
+    {
+        StorePath {
+            create_parent: false,
+            path: path.into(),
+        }
+    }
+
+    /// Ensure the store path can be written to, creating parent directories if requested.
+    ///
+    /// If the store file does not exist yet it is created empty, which SQLite treats
+    /// as a new database.
+    pub fn prepare(&self) -> Result<()> {
+        if self.is_memory() {
+            return Ok(());
+        }
+
+        let path = Path::new(&self.path);
+        let error = || StoreError::PathNotWritable(self.path.clone());
+        if self.create_parent {
+            if let Some(parent) = path
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+            {
+                std::fs::create_dir_all(parent).with_context(error)?;
+            }
+        }
+        OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)
+            .with_context(error)?;
+        Ok(())
+    }
+}
+
+impl From<&str> for StorePath {
+    fn from(value: &str) -> Self {
+        StorePath::new(value)
+    }
+}
+
+impl From<String> for StorePath {
+    fn from(value: String) -> Self {
+        StorePath::new(value)
+    }
+}
+
+impl From<&String> for StorePath {
+    fn from(value: &String) -> Self {
+        StorePath::new(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StoreError;
+    use super::StorePath;
+
+    /// Path to a file in the temporary test directory.
+    fn temp_path(dir: &tempfile::TempDir, path: &str) -> String {
+        dir.path().join(path).to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn from_string_ref() {
+        let path = String::from("agent.db");
+        assert_eq!(StorePath::from(&path), StorePath::from(path.as_str()));
+    }
+
+    #[test]
+    fn memory_path() {
+        let path = StorePath::memory();
+        assert!(path.is_memory());
+        path.create_parent(true).prepare().unwrap();
+        assert!(!std::path::Path::new(super::MEMORY_PATH).exists());
+    }
+
+    #[test]
+    fn missing_parent() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = temp_path(&dir, "missing/agent.db");
+        let error = StorePath::new(path.clone()).prepare().unwrap_err();
+        match error.downcast_ref::<StoreError>() {
+            Some(StoreError::PathNotWritable(actual)) => assert_eq!(actual, &path),
+            _ => panic!("unexpected error: {:?}", error),
+        }
+        assert_eq!(
+            error.to_string(),
+            format!("agent store path '{}' is not writable", path),
+        );
+    }
+
+    #[test]
+    fn missing_parent_created() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = temp_path(&dir, "missing/agent.db");
+        StorePath::new(path.clone())
+            .create_parent(true)
+            .prepare()
+            .unwrap();
+        assert!(std::path::Path::new(&path).is_file());
+    }
+
+    #[test]
+    fn valid_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = temp_path(&dir, "agent.db");
+        StorePath::new(path.clone()).prepare().unwrap();
+        assert!(std::path::Path::new(&path).is_file());
+    }
+}
@@ -1,4 +1,7 @@
 //! Store persistence operations.
+use std::collections::BTreeMap;
+use std::time::Duration;
+
 use crate::agent::models::ActionExecution;
 
 pub(crate) use self::sealed::PersistOps;
@@ -13,8 +16,237 @@ pub trait PersistOp: Into<PersistOps> + SealPersistOp {
     type Response: From<PersistResponses>;
 }
 
+/// Atomically claim the next [`ActionExecution`] to execute, if any is available.
+///
+/// Actions are eligible to be claimed when they are not finished and either:
+///
+/// - They are not claimed by any executor.
+/// - They are already claimed by the same `claimant` (this extends the lease).
+/// - The lease of the current claim has expired.
+///
+/// Actions deferred with [`DeferAction`] are not eligible until the deferral expires.
+///
+/// Eligible actions are selected in the same order as [`ActionNextToExecute`] and
+/// marked as claimed in a single statement so two executors never claim the same action.
+///
+/// [`ActionNextToExecute`]: super::query::ActionNextToExecute
+pub struct ClaimNextAction {
+    /// Identifier of the executor claiming the action.
+    pub claimant: String,
+
+    /// Duration of the claim, after which the action can be claimed by other executors.
+    pub lease: Duration,
+}
+impl SealPersistOp for ClaimNextAction {}
+impl PersistOp for ClaimNextAction {
+    type Response = Option<ActionExecution>;
+}
+impl From<ClaimNextAction> for PersistOps {
+    fn from(value: ClaimNextAction) -> Self {
+        PersistOps::ClaimNextAction(value)
+    }
+}
+
+/// Skip an unfinished [`ActionExecution`] when looking for actions to execute until a given time.
+///
+/// Deferred actions are ignored by [`ActionNextToExecute`] and [`ClaimNextAction`]
+/// so actions queued behind them can progress in the meantime.
+/// Deferring an action that is finished or does not exist is not an error.
+///
+/// [`ActionNextToExecute`]: super::query::ActionNextToExecute
+pub struct DeferAction {
+    /// ID of the action to defer.
+    pub id: uuid::Uuid,
+
+    /// Time until which the action is skipped.
+    pub until: time::OffsetDateTime,
+}
+impl SealPersistOp for DeferAction {}
+impl PersistOp for DeferAction {
+    type Response = ();
+}
+impl From<DeferAction> for PersistOps {
+    fn from(value: DeferAction) -> Self {
+        PersistOps::DeferAction(value)
+    }
+}
+
+/// Delete a key from the handler managed state of an action.
+///
+/// Deleting a key that is not set is not an error.
+pub struct DeleteActionState {
+    /// ID of the action the state belongs to.
+    pub action_id: uuid::Uuid,
+
+    /// Key of the state to delete.
+    pub key: String,
+}
+impl SealPersistOp for DeleteActionState {}
+impl PersistOp for DeleteActionState {
+    type Response = ();
+}
+impl From<DeleteActionState> for PersistOps {
+    fn from(value: DeleteActionState) -> Self {
+        PersistOps::DeleteActionState(value)
+    }
+}
+
+/// Insert an [`ActionExecution`], or fully replace an existing one, with its handler managed state.
+///
+/// Unlike persisting an [`ActionExecution`], all stored information about the action
+/// is replaced: existing handler managed state is dropped and claims and deferrals are cleared.
+/// Used to restore actions with [`Store::import_actions`].
+///
+/// [`Store::import_actions`]: super::Store::import_actions
+pub struct ImportAction {
+    /// The action to insert or replace.
+    pub action: ActionExecution,
+
+    /// Handler managed state of the action.
+    pub state: BTreeMap<String, serde_json::Value>,
+}
+impl SealPersistOp for ImportAction {}
+impl PersistOp for ImportAction {
+    type Response = ImportActionOutcome;
+}
+impl From<ImportAction> for PersistOps {
+    fn from(value: ImportAction) -> Self {
+        PersistOps::ImportAction(value)
+    }
+}
+
+/// Result of an [`ImportAction`] operation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportActionOutcome {
+    /// No action with the same ID existed.
+    Inserted,
+
+    /// An action with the same ID but different information or state was replaced.
+    Replaced,
+
+    /// An identical action with the same ID and state was already stored.
+    Unchanged,
+}
+
+/// Atomically insert a batch of new [`ActionExecution`] records.
+///
+/// IDs are checked and actions inserted in a single transaction:
+/// if any action ID is already in use no action in the batch is inserted.
+pub struct InsertActions {
+    /// The actions to insert.
+    pub actions: Vec<ActionExecution>,
+}
+impl SealPersistOp for InsertActions {}
+impl PersistOp for InsertActions {
+    type Response = InsertActionsOutcome;
+}
+impl From<InsertActions> for PersistOps {
+    fn from(value: InsertActions) -> Self {
+        PersistOps::InsertActions(value)
+    }
+}
+
+/// Result of an [`InsertActions`] operation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InsertActionsOutcome {
+    /// Actions were not inserted because some of their IDs are already in use.
+    IdsInUse(Vec<uuid::Uuid>),
+
+    /// All actions in the batch were inserted.
+    Inserted,
+}
+
+/// Merge metadata into an unfinished [`ActionExecution`] without changing any other field.
+///
+/// Keys in `metadata` are added to the action metadata, replacing existing values.
+/// Keys not in `metadata` are left untouched.
+pub struct PatchActionMetadata {
+    /// ID of the action to update.
+    pub id: uuid::Uuid,
+
+    /// Metadata to merge into the action metadata.
+    pub metadata: BTreeMap<String, String>,
+}
+impl SealPersistOp for PatchActionMetadata {}
+impl PersistOp for PatchActionMetadata {
+    type Response = PatchActionMetadataOutcome;
+}
+impl From<PatchActionMetadata> for PersistOps {
+    fn from(value: PatchActionMetadata) -> Self {
+        PersistOps::PatchActionMetadata(value)
+    }
+}
+
+/// Result of a [`PatchActionMetadata`] operation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PatchActionMetadataOutcome {
+    /// The action is finished and was not updated.
+    Finished,
+
+    /// No action with the requested ID exists.
+    NotFound,
+
+    /// The action metadata was updated, the full updated action is included.
+    Patched(Box<ActionExecution>),
+}
+
+/// Create or update a key in the handler managed state of an action.
+///
+/// Handler managed state is removed from the store once the action finishes.
+pub struct SetActionState {
+    /// ID of the action the state belongs to.
+    pub action_id: uuid::Uuid,
+
+    /// Key of the state to create or update.
+    pub key: String,
+
+    /// Value to store for the key.
+    pub value: serde_json::Value,
+}
+impl SealPersistOp for SetActionState {}
+impl PersistOp for SetActionState {
+    type Response = ();
+}
+impl From<SetActionState> for PersistOps {
+    fn from(value: SetActionState) -> Self {
+        PersistOps::SetActionState(value)
+    }
+}
+
+/// Save the execution progress of an existing [`ActionExecution`].
+///
+/// Only the action state, phase and start and finish times are updated.
+/// Use this operation instead of persisting the whole [`ActionExecution`] when saving
+/// changes to an action loaded earlier, so metadata changed in the meantime with
+/// [`PatchActionMetadata`] is not overwritten.
+/// Updating an action that does not exist is not an error.
+pub struct UpdateActionProgress {
+    /// The action with the updated progress.
+    pub action: ActionExecution,
+}
+impl SealPersistOp for UpdateActionProgress {}
+impl PersistOp for UpdateActionProgress {
+    type Response = ();
+}
+impl From<UpdateActionProgress> for PersistOps {
+    fn from(value: UpdateActionProgress) -> Self {
+        PersistOps::UpdateActionProgress(value)
+    }
+}
+
 /// Private module to seal as many implementation details as possible.
 mod sealed {
+    use super::ClaimNextAction;
+    use super::DeferAction;
+    use super::DeleteActionState;
+    use super::ImportAction;
+    use super::ImportActionOutcome;
+    use super::InsertActions;
+    use super::InsertActionsOutcome;
+    use super::PatchActionMetadata;
+    use super::PatchActionMetadataOutcome;
+    use super::SetActionState;
+    use super::UpdateActionProgress;
     use crate::agent::models::ActionExecution;
 
     /// Super-trait to seal the [`PersistOp`](super::PersistOp) trait.
@@ -24,10 +256,46 @@ mod sealed {
     pub enum PersistOps {
         /// Create or update an [`ActionExecution`] records.
         ActionExecution(ActionExecution),
+
+        /// Atomically claim the next [`ActionExecution`] to execute.
+        ClaimNextAction(ClaimNextAction),
+
+        /// Skip an unfinished [`ActionExecution`] until a given time.
+        DeferAction(DeferAction),
+
+        /// Delete a key from the handler managed state of an action.
+        DeleteActionState(DeleteActionState),
+
+        /// Insert or fully replace an [`ActionExecution`] with its handler managed state.
+        ImportAction(ImportAction),
+
+        /// Atomically insert a batch of new [`ActionExecution`] records.
+        InsertActions(InsertActions),
+
+        /// Merge metadata into an unfinished [`ActionExecution`].
+        PatchActionMetadata(PatchActionMetadata),
+
+        /// Create or update a key in the handler managed state of an action.
+        SetActionState(SetActionState),
+
+        /// Save the execution progress of an existing [`ActionExecution`].
+        UpdateActionProgress(UpdateActionProgress),
     }
 
     /// Enumeration of possible responses for all supported persist operations.
     pub enum PersistResponses {
+        /// Result of a persist operation returning an optional [`ActionExecution`].
+        Action(Option<Box<ActionExecution>>),
+
+        /// Result of a [`PatchActionMetadata`] operation.
+        ActionMetadataPatch(PatchActionMetadataOutcome),
+
+        /// Result of an [`ImportAction`] operation.
+        Imported(ImportActionOutcome),
+
+        /// Result of an [`InsertActions`] operation.
+        Inserted(InsertActionsOutcome),
+
         /// The persist operation does not return data but only success or failure.
         Success,
     }
@@ -37,7 +305,43 @@ mod sealed {
         fn from(value: PersistResponses) -> Self {
             match value {
                 PersistResponses::Success => (),
-                //_ => panic!("only PersistResponses::Success can be converted to the unit type"),
+                _ => panic!("only PersistResponses::Success can be converted to the unit type"),
+            }
+        }
+    }
+
+    impl From<PersistResponses> for Option<ActionExecution> {
+        fn from(value: PersistResponses) -> Self {
+            match value {
+                PersistResponses::Action(value) => value.map(|action| *action),
+                _ => panic!("unexpected result type for the given persist operation"),
+            }
+        }
+    }
+
+    impl From<PersistResponses> for ImportActionOutcome {
+        fn from(value: PersistResponses) -> Self {
+            match value {
+                PersistResponses::Imported(value) => value,
+                _ => panic!("unexpected result type for the given persist operation"),
+            }
+        }
+    }
+
+    impl From<PersistResponses> for InsertActionsOutcome {
+        fn from(value: PersistResponses) -> Self {
+            match value {
+                PersistResponses::Inserted(value) => value,
+                _ => panic!("unexpected result type for the given persist operation"),
+            }
+        }
+    }
+
+    impl From<PersistResponses> for PatchActionMetadataOutcome {
+        fn from(value: PersistResponses) -> Self {
+            match value {
+                PersistResponses::ActionMetadataPatch(value) => value,
+                _ => panic!("unexpected result type for the given persist operation"),
             }
         }
     }
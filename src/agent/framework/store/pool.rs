@@ -0,0 +1,138 @@
+//! Pool of read-only connections to spread store queries across.
+use std::num::NonZeroUsize;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use anyhow::Result;
+use rusqlite::OpenFlags;
+use tokio_rusqlite::Connection;
+
+/// Read-only connections to the store, used in turn by queries.
+///
+/// Each connection is served by its own thread so queries sent to different
+/// connections run in parallel.
+#[derive(Clone, Debug)]
+pub(super) struct ReadPool {
+    connections: Arc<Vec<Connection>>,
+    next: Arc<AtomicUsize>,
+}
+
+impl ReadPool {
+    /// Close all connections in the pool.
+    pub async fn close(&self) -> Result<()> {
+        for connection in self.connections.iter() {
+            connection.clone().close().await?;
+        }
+        Ok(())
+    }
+
+    /// Return the next connection to send a query to.
+    pub fn connection(&self) -> &Connection {
+        let next = self.next.fetch_add(1, Ordering::Relaxed);
+        &self.connections[next % self.connections.len()]
+    }
+
+    /// Open `size` read-only connections to the store at `path`.
+    pub async fn open(path: &str, size: NonZeroUsize) -> Result<ReadPool> {
+        let flags = OpenFlags::SQLITE_OPEN_READ_ONLY
+            | OpenFlags::SQLITE_OPEN_URI
+            | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+        let mut connections = Vec::with_capacity(size.get());
+        for _ in 0..size.get() {
+            let connection = Connection::open_with_flags(path, flags).await?;
+            connections.push(connection);
+        }
+        Ok(ReadPool {
+            connections: Arc::new(connections),
+            next: Default::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use crate::agent::framework::store::fixtures;
+    use crate::agent::framework::store::fixtures::TestStore;
+    use crate::agent::framework::store::query;
+    use crate::agent::framework::store::Store;
+    use crate::context::Context;
+
+    const BLOCK_FOR: Duration = Duration::from_millis(500);
+
+    /// Open a file-backed store with a pool of read connections.
+    async fn store(path: &TestStore) -> Store {
+        let context = Context::fixture();
+        Store::initialise(&context.logger, &path.path())
+            .await
+            .unwrap()
+            .with_read_pool(NonZeroUsize::new(2).unwrap())
+            .await
+            .unwrap()
+    }
+
+    /// Keep a connection busy for [`BLOCK_FOR`] to simulate a slow operation.
+    async fn block(connection: &tokio_rusqlite::Connection) {
+        connection
+            .call(|_| {
+                std::thread::sleep(BLOCK_FOR);
+                Ok(())
+            })
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn reads_proceed_in_parallel() {
+        let path = TestStore::new();
+        let store = store(&path).await;
+        let context = Context::fixture();
+        let id = uuid::Uuid::new_v4();
+        store.persist(&context, fixtures::action(id)).await.unwrap();
+
+        // Keep one reader busy while querying through the other.
+        let readers = store.readers.clone().unwrap();
+        let start = Instant::now();
+        let busy = block(readers.connection());
+        let read = async {
+            let action = store.query(&context, query::Action::new(id)).await;
+            (action, start.elapsed())
+        };
+        let (_, (action, elapsed)) = tokio::join!(busy, read);
+        assert!(action.unwrap().is_some());
+        assert!(elapsed < BLOCK_FOR);
+        store.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn reads_proceed_while_writes_serialise() {
+        let path = TestStore::new();
+        let store = store(&path).await;
+        let context = Context::fixture();
+        let id = uuid::Uuid::new_v4();
+        store.persist(&context, fixtures::action(id)).await.unwrap();
+
+        // Keep the writer busy while reading and writing.
+        let start = Instant::now();
+        let busy = block(&store.store);
+        let read = async {
+            let action = store.query(&context, query::Action::new(id)).await;
+            (action, start.elapsed())
+        };
+        let write = async {
+            let action = fixtures::action(uuid::Uuid::new_v4());
+            let result = store.persist(&context, action).await;
+            (result, start.elapsed())
+        };
+        let (_, (action, read_elapsed), (result, write_elapsed)) = tokio::join!(busy, read, write);
+        assert!(action.unwrap().is_some());
+        assert!(read_elapsed < BLOCK_FOR);
+        result.unwrap();
+        assert!(write_elapsed >= BLOCK_FOR);
+        store.close().await.unwrap();
+    }
+}
@@ -0,0 +1,337 @@
+//! Export and import actions to and from portable NDJSON streams.
+use std::collections::BTreeMap;
+
+use anyhow::Context as AnyContext;
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::io::AsyncBufRead;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncWrite;
+use tokio::io::AsyncWriteExt;
+
+use super::persist::ImportAction;
+use super::persist::ImportActionOutcome;
+use super::query::ACTIONS_LIST_MAX_LIMIT;
+use super::statements;
+use super::Store;
+use crate::agent::models::ActionExecution;
+use crate::context::Context;
+
+/// Outcome of importing actions with [`Store::import_actions`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ActionsImport {
+    /// IDs of imported actions that replaced a different record with the same ID.
+    pub conflicts: Vec<uuid::Uuid>,
+
+    /// Number of actions imported into the store.
+    pub imported: usize,
+}
+
+/// Errors exporting or importing actions to and from portable streams.
+#[derive(Debug, thiserror::Error)]
+pub enum PortableActionsError {
+    /// Unable to decode an action from the import stream.
+    ///
+    /// Error parameters:
+    ///
+    /// - The line number of the action that failed to decode.
+    #[error("unable to decode action on line {0} of the import stream")]
+    Decode(usize),
+
+    /// Unable to read from the import stream.
+    ///
+    /// Error parameters:
+    ///
+    /// - The line number that failed to be read.
+    #[error("unable to read line {0} of the import stream")]
+    Read(usize),
+
+    /// Unable to write actions to the export stream.
+    #[error("unable to write actions to the export stream")]
+    Write,
+}
+
+impl Store {
+    /// Export all [`ActionExecution`] records as NDJSON, one action per line.
+    ///
+    /// Records are exported in full, including IDs and timestamps, so they can be
+    /// imported into a different store with [`Store::import_actions`].
+    /// Handler managed state of unfinished actions is included with each action
+    /// under the `handler_state` key.
+    /// Returns the number of exported actions.
+    ///
+    /// Actions are loaded from the store and written out one page at a time.
+    /// Actions scheduled while the export is in progress may or may not be included.
+    pub async fn export_actions<W>(&self, _: &Context, mut writer: W) -> Result<usize>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let mut after = None;
+        let mut exported = 0;
+        loop {
+            let (actions, cursor) =
+                statements::actions::export_page(self.reader(), after, ACTIONS_LIST_MAX_LIMIT)
+                    .await?;
+            let mut buffer = Vec::new();
+            for action in &actions {
+                serde_json::to_writer(&mut buffer, action).context(PortableActionsError::Write)?;
+                buffer.push(b'\n');
+            }
+            writer
+                .write_all(&buffer)
+                .await
+                .context(PortableActionsError::Write)?;
+
+            // A short page means there are no more actions to export.
+            exported += actions.len();
+            if actions.len() < ACTIONS_LIST_MAX_LIMIT as usize {
+                break;
+            }
+            after = cursor;
+        }
+        writer.flush().await.context(PortableActionsError::Write)?;
+        Ok(exported)
+    }
+
+    /// Import [`ActionExecution`] records from NDJSON, one action per line.
+    ///
+    /// Actions are inserted or, if a record with the same ID exists, replaced in full
+    /// along with their handler managed state.
+    /// Actions that replace a different record with the same ID are reported as conflicts.
+    /// Blank lines are ignored.
+    ///
+    /// Actions are imported one at a time so an error leaves the actions before it imported.
+    pub async fn import_actions<R>(&self, context: &Context, reader: R) -> Result<ActionsImport>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        let mut lines = reader.lines();
+        let mut number = 0;
+        let mut report = ActionsImport::default();
+        loop {
+            number += 1;
+            let line = lines
+                .next_line()
+                .await
+                .with_context(|| PortableActionsError::Read(number))?;
+            let line = match line {
+                None => break,
+                Some(line) => line,
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: PortableAction = serde_json::from_str(&line)
+                .with_context(|| PortableActionsError::Decode(number))?;
+
+            let id = record.action.id;
+            let op = ImportAction {
+                action: record.action,
+                state: record.handler_state,
+            };
+            if self.persist(context, op).await? == ImportActionOutcome::Replaced {
+                report.conflicts.push(id);
+            }
+            report.imported += 1;
+        }
+        Ok(report)
+    }
+}
+
+/// Portable representation of an [`ActionExecution`] with its handler managed state.
+#[derive(Debug, Deserialize, Serialize)]
+pub(super) struct PortableAction {
+    /// The exported action.
+    #[serde(flatten)]
+    pub action: ActionExecution,
+
+    /// Handler managed state of the action, if any.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub handler_state: BTreeMap<String, serde_json::Value>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ActionsImport;
+    use super::PortableActionsError;
+    use crate::agent::framework::store::fixtures;
+    use crate::agent::framework::store::manage::CleanActions;
+    use crate::agent::framework::store::persist::PatchActionMetadata;
+    use crate::agent::framework::store::persist::SetActionState;
+    use crate::agent::framework::store::query::Action;
+    use crate::agent::framework::store::query::ActionState;
+    use crate::agent::framework::store::query::ActionsAll;
+    use crate::agent::framework::store::query::ACTIONS_LIST_MAX_LIMIT;
+    use crate::agent::framework::store::statements::actions::export_page;
+    use crate::agent::models::ActionExecutionPhase;
+    use crate::context::Context;
+
+    #[tokio::test]
+    async fn export_and_import_actions() {
+        let context = Context::fixture();
+        let source = fixtures::store().await;
+        let mut finished = fixtures::action(uuid::Uuid::new_v4());
+        finished.args = serde_json::json!({"target": "node-1"});
+        finished.phase_to(ActionExecutionPhase::Done);
+        let mut running = fixtures::action(uuid::Uuid::new_v4());
+        running.metadata.insert("owner".into(), "tests".into());
+        running.phase_to(ActionExecutionPhase::Running);
+        source.persist(&context, finished).await.unwrap();
+        source.persist(&context, running).await.unwrap();
+
+        let mut stream = Vec::new();
+        let exported = source.export_actions(&context, &mut stream).await.unwrap();
+        assert_eq!(exported, 2);
+        assert_eq!(stream.iter().filter(|byte| **byte == b'\n').count(), 2);
+
+        let target = fixtures::store().await;
+        let report = target
+            .import_actions(&context, stream.as_slice())
+            .await
+            .unwrap();
+        assert_eq!(
+            report,
+            ActionsImport {
+                conflicts: Vec::new(),
+                imported: 2,
+            }
+        );
+        let expected = source.query(&context, ActionsAll::default()).await.unwrap();
+        let actual = target.query(&context, ActionsAll::default()).await.unwrap();
+        assert_eq!(actual, expected);
+    }
+
+    #[tokio::test]
+    async fn export_actions_in_pages() {
+        let context = Context::fixture();
+        let store = fixtures::store().await;
+        for _ in 0..=ACTIONS_LIST_MAX_LIMIT {
+            let action = fixtures::action(uuid::Uuid::new_v4());
+            store.persist(&context, action).await.unwrap();
+        }
+
+        let mut stream = Vec::new();
+        let exported = store.export_actions(&context, &mut stream).await.unwrap();
+        let expected = ACTIONS_LIST_MAX_LIMIT as usize + 1;
+        assert_eq!(exported, expected);
+        assert_eq!(
+            stream.iter().filter(|byte| **byte == b'\n').count(),
+            expected
+        );
+    }
+
+    #[tokio::test]
+    async fn export_and_import_handler_state() {
+        let context = Context::fixture();
+        let source = fixtures::store().await;
+        let action = fixtures::action(uuid::Uuid::new_v4());
+        source.persist(&context, action.clone()).await.unwrap();
+        let op = SetActionState {
+            action_id: action.id,
+            key: "step".into(),
+            value: serde_json::json!({"attempt": 2}),
+        };
+        source.persist(&context, op).await.unwrap();
+
+        let mut stream = Vec::new();
+        source.export_actions(&context, &mut stream).await.unwrap();
+        let target = fixtures::store().await;
+        target
+            .import_actions(&context, stream.as_slice())
+            .await
+            .unwrap();
+
+        let query = ActionState {
+            action_id: action.id,
+            key: "step".into(),
+        };
+        let state = target.query(&context, query).await.unwrap();
+        assert_eq!(state, Some(serde_json::json!({"attempt": 2})));
+    }
+
+    #[tokio::test]
+    async fn export_pages_skip_nothing_when_actions_are_removed() {
+        let context = Context::fixture();
+        let store = fixtures::store().await;
+        let mut first = fixtures::action(uuid::Uuid::new_v4());
+        first.finish(ActionExecutionPhase::Done);
+        let second = fixtures::action(uuid::Uuid::new_v4());
+        let third = fixtures::action(uuid::Uuid::new_v4());
+        store.persist(&context, first.clone()).await.unwrap();
+        store.persist(&context, second.clone()).await.unwrap();
+        store.persist(&context, third.clone()).await.unwrap();
+
+        let (page, cursor) = export_page(&store.store, None, 1).await.unwrap();
+        assert_eq!(page[0].action.id, first.id);
+
+        // Removing exported actions must not shift the following pages.
+        let op = CleanActions::since(time::OffsetDateTime::now_utc() + time::Duration::hours(1));
+        store.manage(&context, op).await.unwrap();
+        let (page, cursor) = export_page(&store.store, cursor, 1).await.unwrap();
+        assert_eq!(page[0].action.id, second.id);
+        let (page, _) = export_page(&store.store, cursor, 1).await.unwrap();
+        assert_eq!(page[0].action.id, third.id);
+    }
+
+    #[tokio::test]
+    async fn import_reports_conflicts() {
+        let context = Context::fixture();
+        let store = fixtures::store().await;
+        let unchanged = fixtures::action(uuid::Uuid::new_v4());
+        let changed = fixtures::action(uuid::Uuid::new_v4());
+        store.persist(&context, unchanged.clone()).await.unwrap();
+        store.persist(&context, changed.clone()).await.unwrap();
+
+        let mut stream = Vec::new();
+        store.export_actions(&context, &mut stream).await.unwrap();
+        let mut update = changed.clone();
+        update.phase_to(ActionExecutionPhase::Running);
+        store.persist(&context, update).await.unwrap();
+
+        let report = store
+            .import_actions(&context, stream.as_slice())
+            .await
+            .unwrap();
+        assert_eq!(report.conflicts, vec![changed.id]);
+        assert_eq!(report.imported, 2);
+        let actions = store.query(&context, ActionsAll::default()).await.unwrap();
+        assert!(actions.contains(&changed));
+        assert!(actions.contains(&unchanged));
+    }
+
+    #[tokio::test]
+    async fn import_replaces_conflicting_metadata() {
+        let context = Context::fixture();
+        let store = fixtures::store().await;
+        let mut action = fixtures::action(uuid::Uuid::new_v4());
+        action.metadata.insert("owner".into(), "backup".into());
+        store.persist(&context, action.clone()).await.unwrap();
+
+        let mut stream = Vec::new();
+        store.export_actions(&context, &mut stream).await.unwrap();
+        let patch = PatchActionMetadata {
+            id: action.id,
+            metadata: [("owner".into(), "patched".into())].into(),
+        };
+        store.persist(&context, patch).await.unwrap();
+
+        let report = store
+            .import_actions(&context, stream.as_slice())
+            .await
+            .unwrap();
+        assert_eq!(report.conflicts, vec![action.id]);
+        let stored = store.query(&context, Action::new(action.id)).await.unwrap();
+        assert_eq!(stored, Some(action));
+    }
+
+    #[tokio::test]
+    async fn import_invalid_line() {
+        let context = Context::fixture();
+        let store = fixtures::store().await;
+        let stream = b"\nnot-an-action\n".as_slice();
+        let error = store.import_actions(&context, stream).await.unwrap_err();
+        let error = error.downcast_ref::<PortableActionsError>().unwrap();
+        assert!(matches!(error, PortableActionsError::Decode(2)));
+    }
+}
@@ -1,6 +1,9 @@
 //! Store querying operations.
+use std::collections::HashMap;
+
 use crate::agent::models::ActionExecution;
 use crate::agent::models::ActionExecutionList;
+use crate::agent::models::ActionExecutionPhase;
 
 pub(crate) use self::sealed::QueryOps;
 pub(crate) use self::sealed::QueryResponses;
@@ -40,10 +43,33 @@ impl Action {
     }
 }
 
+/// Lookup a key from the handler managed state of an action.
+///
+/// Keys that are not set, including for actions that have finished, return `None`.
+pub struct ActionState {
+    /// ID of the action the state belongs to.
+    pub action_id: uuid::Uuid,
+
+    /// Key of the state to lookup.
+    pub key: String,
+}
+impl SealQueryOp for ActionState {}
+impl QueryOp for ActionState {
+    type Response = Option<serde_json::Value>;
+}
+impl From<ActionState> for QueryOps {
+    fn from(value: ActionState) -> Self {
+        QueryOps::ActionState(value)
+    }
+}
+
 /// Query the store for the next [`ActionExecution`] record to execute.
 ///
 /// `ActionExecution`s are processed based on the time they were scheduled
 /// with a preference for already running actions.
+/// Actions deferred with [`DeferAction`] are skipped until their deferral expires.
+///
+/// [`DeferAction`]: super::persist::DeferAction
 pub struct ActionNextToExecute {}
 impl SealQueryOp for ActionNextToExecute {}
 impl QueryOp for ActionNextToExecute {
@@ -55,38 +81,131 @@ impl From<ActionNextToExecute> for QueryOps {
     }
 }
 
+/// Query the store for all [`ActionExecution`] records, finished or not.
+///
+/// Records are returned in full in the order they were scheduled, one page at a time.
+/// Intended for backups and migrations.
+pub struct ActionsAll {
+    /// Maximum number of records to return, capped at [`ACTIONS_LIST_MAX_LIMIT`].
+    pub limit: u32,
+
+    /// Number of records to skip before the first returned record.
+    pub offset: u32,
+}
+impl SealQueryOp for ActionsAll {}
+impl QueryOp for ActionsAll {
+    type Response = Vec<ActionExecution>;
+}
+impl From<ActionsAll> for QueryOps {
+    fn from(value: ActionsAll) -> Self {
+        QueryOps::ActionsAll(value)
+    }
+}
+
+impl Default for ActionsAll {
+    fn default() -> Self {
+        ActionsAll {
+            limit: ACTIONS_LIST_LIMIT,
+            offset: 0,
+        }
+    }
+}
+
+/// Count [`ActionExecution`] records in each [`ActionExecutionPhase`], finished or not.
+///
+/// Phases without any action are not included in the response.
+pub struct ActionsCountByPhase {}
+impl SealQueryOp for ActionsCountByPhase {}
+impl QueryOp for ActionsCountByPhase {
+    type Response = HashMap<ActionExecutionPhase, u64>;
+}
+impl From<ActionsCountByPhase> for QueryOps {
+    fn from(_: ActionsCountByPhase) -> Self {
+        QueryOps::ActionsCountByPhase
+    }
+}
+
+/// Default maximum number of records returned by action list queries.
+pub const ACTIONS_LIST_LIMIT: u32 = 50;
+
+/// Upper bound on the number of records returned by action list queries.
+///
+/// Larger limits requested by callers are reduced to this value.
+pub const ACTIONS_LIST_MAX_LIMIT: u32 = 500;
+
 /// Query the store for a list of finished [`ActionExecution`] records.
 ///
+/// Records are returned in the order they were scheduled, one page at a time.
+///
 /// [`ActionExecution`]: crate::agent::models::ActionExecution
-pub struct ActionsFinished {}
+pub struct ActionsFinished {
+    /// Maximum number of records to return, capped at [`ACTIONS_LIST_MAX_LIMIT`].
+    pub limit: u32,
+
+    /// Number of records to skip before the first returned record.
+    pub offset: u32,
+}
 impl SealQueryOp for ActionsFinished {}
 impl QueryOp for ActionsFinished {
     type Response = ActionExecutionList;
 }
 impl From<ActionsFinished> for QueryOps {
-    fn from(_: ActionsFinished) -> Self {
-        QueryOps::ActionsFinished
+    fn from(value: ActionsFinished) -> Self {
+        QueryOps::ActionsFinished(value)
+    }
+}
+
+impl Default for ActionsFinished {
+    fn default() -> Self {
+        ActionsFinished {
+            limit: ACTIONS_LIST_LIMIT,
+            offset: 0,
+        }
     }
 }
 
 /// Query the store for a list of running and queued [`ActionExecution`] records.
 ///
+/// Records are returned in the order they were scheduled, one page at a time.
+///
 /// [`ActionExecution`]: crate::agent::models::ActionExecution
-pub struct ActionsQueue {}
+pub struct ActionsQueue {
+    /// Maximum number of records to return, capped at [`ACTIONS_LIST_MAX_LIMIT`].
+    pub limit: u32,
+
+    /// Number of records to skip before the first returned record.
+    pub offset: u32,
+}
 impl SealQueryOp for ActionsQueue {}
 impl QueryOp for ActionsQueue {
     type Response = ActionExecutionList;
 }
 impl From<ActionsQueue> for QueryOps {
-    fn from(_: ActionsQueue) -> Self {
-        QueryOps::ActionsQueue
+    fn from(value: ActionsQueue) -> Self {
+        QueryOps::ActionsQueue(value)
+    }
+}
+
+impl Default for ActionsQueue {
+    fn default() -> Self {
+        ActionsQueue {
+            limit: ACTIONS_LIST_LIMIT,
+            offset: 0,
+        }
     }
 }
 
 /// Private module to seal as many implementation details as possible.
 mod sealed {
+    use std::collections::HashMap;
+
+    use super::ActionState;
+    use super::ActionsAll;
+    use super::ActionsFinished;
+    use super::ActionsQueue;
     use crate::agent::models::ActionExecution;
     use crate::agent::models::ActionExecutionList;
+    use crate::agent::models::ActionExecutionPhase;
 
     /// Super-trait to seal the [`QueryOp`](super::QueryOp) trait.
     pub trait SealQueryOp {}
@@ -96,14 +215,23 @@ mod sealed {
         /// Lookup an [`ActionExecution`] record by ID.
         Action(uuid::Uuid),
 
+        /// Lookup a key from the handler managed state of an action.
+        ActionState(ActionState),
+
         /// Query the store for the next [`ActionExecution`] record to execute.
         ActionNextToExecute,
 
+        /// List all [`ActionExecution`] records.
+        ActionsAll(ActionsAll),
+
+        /// Count [`ActionExecution`] records in each phase.
+        ActionsCountByPhase,
+
         /// List running and queued [`ActionExecution`] records.
-        ActionsQueue,
+        ActionsQueue(ActionsQueue),
 
         /// List finished [`ActionExecution`] records.
-        ActionsFinished,
+        ActionsFinished(ActionsFinished),
     }
 
     /// Enumeration of query responses for all supported query operations.
@@ -111,6 +239,15 @@ mod sealed {
         /// Result of an [`ActionExecution`] lookup query.
         Action(Option<ActionExecution>),
 
+        /// Result of an action state lookup query.
+        ActionState(Option<serde_json::Value>),
+
+        /// List of full [`ActionExecution`] records.
+        Actions(Vec<ActionExecution>),
+
+        /// Number of [`ActionExecution`] records in each phase.
+        ActionsCount(HashMap<ActionExecutionPhase, u64>),
+
         /// List of [`ActionExecution`] record summaries.
         ActionsList(ActionExecutionList),
     }
@@ -125,6 +262,24 @@ mod sealed {
         }
     }
 
+    impl From<QueryResponses> for HashMap<ActionExecutionPhase, u64> {
+        fn from(value: QueryResponses) -> Self {
+            match value {
+                QueryResponses::ActionsCount(value) => value,
+                _ => panic!("unexpected result type for the given query operation"),
+            }
+        }
+    }
+
+    impl From<QueryResponses> for Vec<ActionExecution> {
+        fn from(value: QueryResponses) -> Self {
+            match value {
+                QueryResponses::Actions(value) => value,
+                _ => panic!("unexpected result type for the given query operation"),
+            }
+        }
+    }
+
     impl From<QueryResponses> for Option<ActionExecution> {
         fn from(value: QueryResponses) -> Self {
             match value {
@@ -133,4 +288,13 @@ mod sealed {
             }
         }
     }
+
+    impl From<QueryResponses> for Option<serde_json::Value> {
+        fn from(value: QueryResponses) -> Self {
+            match value {
+                QueryResponses::ActionState(value) => value,
+                _ => panic!("unexpected result type for the given query operation"),
+            }
+        }
+    }
 }
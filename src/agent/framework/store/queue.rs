@@ -0,0 +1,154 @@
+//! Bounded write queue for persist operations on the agent store.
+//!
+//! When enabled, persist operations are sent over a bounded channel to a dedicated task
+//! that applies them to the store one at a time.
+//! Callers wait for their operation to be processed, and when the queue is full
+//! they wait for space in the queue, which applies backpressure on writers.
+//! The store connection on its own accepts an unbounded number of pending operations.
+use std::num::NonZeroUsize;
+
+use anyhow::Result;
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+use tokio_rusqlite::Connection;
+
+use super::persist::PersistOps;
+use super::persist::PersistResponses;
+use super::StoreEncoding;
+
+/// Errors encountered interacting with the store write queue.
+#[derive(Debug, thiserror::Error)]
+pub enum WriteQueueError {
+    /// The write queue task has stopped and can't process operations.
+    #[error("the store write queue task has stopped and can't process operations")]
+    Stopped,
+}
+
+/// A persist operation waiting in the queue along with the channel to send the result on.
+struct WriteRequest {
+    encoding: StoreEncoding,
+    op: PersistOps,
+    reply: oneshot::Sender<Result<PersistResponses>>,
+}
+
+/// Send persist operations to the dedicated writer task.
+#[derive(Clone, Debug)]
+pub(super) struct WriteQueue {
+    sender: mpsc::Sender<WriteRequest>,
+}
+
+impl WriteQueue {
+    /// Create a [`WriteQueue`] with the given capacity and spawn the writer task.
+    pub fn spawn(store: Connection, capacity: NonZeroUsize) -> WriteQueue {
+        let (queue, receiver) = WriteQueue::new(capacity);
+        tokio::spawn(WriteQueue::writer(store, receiver));
+        queue
+    }
+
+    /// Enqueue a persist operation and wait for the writer task to process it.
+    ///
+    /// If the queue is full this method waits for space to free up before enqueuing.
+    pub async fn persist(
+        &self,
+        op: PersistOps,
+        encoding: StoreEncoding,
+    ) -> Result<PersistResponses> {
+        let (reply, response) = oneshot::channel();
+        let request = WriteRequest {
+            encoding,
+            op,
+            reply,
+        };
+        self.sender
+            .send(request)
+            .await
+            .map_err(|_| WriteQueueError::Stopped)?;
+        response.await.map_err(|_| WriteQueueError::Stopped)?
+    }
+
+    /// Create a [`WriteQueue`] and the receiving end of the channel for a writer task.
+    fn new(capacity: NonZeroUsize) -> (WriteQueue, mpsc::Receiver<WriteRequest>) {
+        let (sender, receiver) = mpsc::channel(capacity.get());
+        (WriteQueue { sender }, receiver)
+    }
+
+    /// Process queued persist operations until all [`WriteQueue`]s are dropped.
+    async fn writer(store: Connection, mut receiver: mpsc::Receiver<WriteRequest>) {
+        while let Some(request) = receiver.recv().await {
+            let result = super::persist_op(&store, request.op, request.encoding).await;
+            // The caller may have given up waiting so ignore send errors.
+            let _ = request.reply.send(result);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+    use std::time::Duration;
+
+    use super::WriteQueue;
+    use crate::agent::framework::store::fixtures;
+    use crate::agent::framework::store::query;
+    use crate::agent::framework::store::StoreEncoding;
+    use crate::context::Context;
+
+    #[tokio::test]
+    async fn concurrent_persists_succeed() {
+        let store = fixtures::store()
+            .await
+            .with_write_queue(NonZeroUsize::new(2).unwrap());
+        let context = Context::fixture();
+        let ids: Vec<uuid::Uuid> = (0..10).map(|_| uuid::Uuid::new_v4()).collect();
+
+        let mut tasks = Vec::new();
+        for id in ids.iter().copied() {
+            let store = store.clone();
+            let context = context.clone();
+            let task = tokio::spawn(async move {
+                let action = fixtures::action(id);
+                store.persist(&context, action).await
+            });
+            tasks.push(task);
+        }
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        for id in ids {
+            let action = store.query(&context, query::Action::new(id)).await.unwrap();
+            assert!(action.is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn full_queue_applies_backpressure() {
+        let store = fixtures::store().await;
+        let (queue, receiver) = WriteQueue::new(NonZeroUsize::MIN);
+
+        // Fill the queue while no writer is processing it.
+        let first = queue.clone();
+        let first = tokio::spawn(async move {
+            let op = fixtures::action(uuid::Uuid::new_v4()).into();
+            first.persist(op, StoreEncoding::Json).await
+        });
+        while queue.sender.capacity() > 0 {
+            tokio::task::yield_now().await;
+        }
+
+        // Further writes wait for space in the queue.
+        let op = fixtures::action(uuid::Uuid::new_v4()).into();
+        let blocked = tokio::time::timeout(
+            Duration::from_millis(50),
+            queue.persist(op, StoreEncoding::Json),
+        )
+        .await;
+        assert!(blocked.is_err());
+
+        // Once the writer starts, queued operations complete.
+        tokio::spawn(WriteQueue::writer(store.store.clone(), receiver));
+        first.await.unwrap().unwrap();
+        let op = fixtures::action(uuid::Uuid::new_v4()).into();
+        queue.persist(op, StoreEncoding::Json).await.unwrap();
+    }
+}
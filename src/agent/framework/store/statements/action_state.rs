@@ -0,0 +1,118 @@
+//! Implementation of the handler managed action state portion of the store interface.
+use anyhow::Context;
+use anyhow::Result;
+use opentelemetry_api::trace::FutureExt;
+use rusqlite::types::Value;
+use tokio_rusqlite::Connection;
+
+use super::actions::decode_data;
+use super::actions::encode_data;
+use super::StatementError;
+use crate::agent::framework::metrics;
+use crate::agent::framework::store::persist::DeleteActionState;
+use crate::agent::framework::store::persist::SetActionState;
+use crate::agent::framework::store::query::ActionState;
+use crate::agent::framework::store::StoreEncoding;
+use crate::utils::metrics::CountErrExt;
+use crate::utils::metrics::CountFutureErrExt;
+use crate::utils::trace::TraceFutureStdErrExt;
+
+pub(super) const ACTION_STATE_CLEAR_SQL: &str = r#"
+    DELETE FROM actions_state
+    WHERE action_id=?1;
+"#;
+const ACTION_STATE_DELETE_SQL: &str = r#"
+    DELETE FROM actions_state
+    WHERE action_id=?1 AND key=?2;
+"#;
+const ACTION_STATE_GET_SQL: &str = r#"
+    SELECT value
+    FROM actions_state
+    WHERE action_id=?1 AND key=?2;
+"#;
+pub(super) const ACTION_STATE_LIST_SQL: &str = r#"
+    SELECT key, value
+    FROM actions_state
+    WHERE action_id=?1;
+"#;
+pub(super) const ACTION_STATE_SET_SQL: &str = r#"
+    INSERT INTO actions_state (action_id, key, value)
+    VALUES (?1, ?2, ?3)
+    ON CONFLICT(action_id, key)
+    DO UPDATE SET value=?3;
+"#;
+
+/// Delete a key from the handler managed state of an action.
+pub async fn delete(store: &Connection, op: DeleteActionState) -> Result<()> {
+    let (err_count, _timer) = metrics::store::observe_op("actions_state.delete");
+    let trace = crate::agent::framework::trace::store_op_context("actions_state.delete");
+    store
+        .call(move |connection| {
+            connection.execute(
+                ACTION_STATE_DELETE_SQL,
+                rusqlite::params![op.action_id.to_string(), op.key],
+            )?;
+            Ok(())
+        })
+        .count_on_err(err_count)
+        .trace_on_err_with_status()
+        .with_context(trace)
+        .await?;
+    Ok(())
+}
+
+/// Lookup a key from the handler managed state of an action.
+pub async fn get(store: &Connection, op: ActionState) -> Result<Option<serde_json::Value>> {
+    let (err_count, _timer) = metrics::store::observe_op("actions_state.get");
+    let trace = crate::agent::framework::trace::store_op_context("actions_state.get");
+    let value = store
+        .call(move |connection| {
+            let mut statement = connection.prepare_cached(ACTION_STATE_GET_SQL)?;
+            let mut rows = statement.query(rusqlite::params![op.action_id.to_string(), op.key])?;
+            match rows.next()? {
+                None => Ok(None),
+                Some(row) => {
+                    let value: Value = row.get("value")?;
+                    Ok(Some(value))
+                }
+            }
+        })
+        .count_on_err(err_count.clone())
+        .trace_on_err_with_status()
+        .with_context(trace)
+        .await
+        .context(StatementError::QueryFailed)?;
+
+    // Decode the stored value.
+    match value {
+        None => Ok(None),
+        Some(value) => {
+            let value = decode_data(&value).count_on_err(err_count)?;
+            Ok(Some(value))
+        }
+    }
+}
+
+/// Create or update a key in the handler managed state of an action.
+pub async fn set(
+    store: &Connection,
+    op: SetActionState,
+    store_encoding: StoreEncoding,
+) -> Result<()> {
+    let value = encode_data(&op.value, store_encoding)?;
+    let (err_count, _timer) = metrics::store::observe_op("actions_state.set");
+    let trace = crate::agent::framework::trace::store_op_context("actions_state.set");
+    store
+        .call(move |connection| {
+            connection.execute(
+                ACTION_STATE_SET_SQL,
+                rusqlite::params![op.action_id.to_string(), op.key, value],
+            )?;
+            Ok(())
+        })
+        .count_on_err(err_count)
+        .trace_on_err_with_status()
+        .with_context(trace)
+        .await?;
+    Ok(())
+}
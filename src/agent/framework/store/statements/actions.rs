@@ -1,20 +1,101 @@
 //! Implementation of the actions portion of the store interface.
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+
 use anyhow::Context;
 use anyhow::Result;
 use opentelemetry_api::trace::FutureExt;
+use rusqlite::types::Value;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use tokio_rusqlite::Connection;
 
+use super::action_state::ACTION_STATE_CLEAR_SQL;
+use super::action_state::ACTION_STATE_LIST_SQL;
+use super::action_state::ACTION_STATE_SET_SQL;
 use super::StatementError;
 use crate::agent::framework::metrics;
+use crate::agent::framework::store::persist::ClaimNextAction;
+use crate::agent::framework::store::persist::DeferAction;
+use crate::agent::framework::store::persist::ImportAction;
+use crate::agent::framework::store::persist::ImportActionOutcome;
+use crate::agent::framework::store::persist::InsertActions;
+use crate::agent::framework::store::persist::InsertActionsOutcome;
+use crate::agent::framework::store::persist::PatchActionMetadata;
+use crate::agent::framework::store::persist::PatchActionMetadataOutcome;
+use crate::agent::framework::store::persist::UpdateActionProgress;
+use crate::agent::framework::store::portable::PortableAction;
+use crate::agent::framework::store::query::ActionsAll;
+use crate::agent::framework::store::query::ActionsFinished;
+use crate::agent::framework::store::query::ActionsQueue;
+use crate::agent::framework::store::query::ACTIONS_LIST_MAX_LIMIT;
+use crate::agent::framework::store::StoreEncoding;
 use crate::agent::models::ActionExecution;
 use crate::agent::models::ActionExecutionList;
 use crate::agent::models::ActionExecutionListItem;
+use crate::agent::models::ActionExecutionPhase;
 use crate::agent::models::ActionExecutionState;
 use crate::utils::encoding;
 use crate::utils::metrics::CountErrExt;
 use crate::utils::metrics::CountFutureErrExt;
 use crate::utils::trace::TraceFutureStdErrExt;
 
+const ACTION_ALL_SQL: &str = r#"
+    SELECT
+        args,
+        created_time,
+        finished_time,
+        id,
+        kind,
+        metadata,
+        scheduled_time,
+        started_time,
+        state_error,
+        state_payload,
+        state_phase
+    FROM actions
+    ORDER BY scheduled_time ASC, ROWID ASC
+    -- Results are always limited to reduce blast radius in case of bugs.
+    -- The limit is capped to ACTIONS_LIST_MAX_LIMIT by the caller.
+    LIMIT ?1 OFFSET ?2;
+"#;
+const ACTION_CLAIM_NEXT_SQL: &str = r#"
+    UPDATE actions
+    SET claimant=?1, claim_expiry=?2
+    WHERE id=(
+        SELECT id
+        FROM actions
+        WHERE finished_time IS NULL
+            AND (claimant IS NULL OR claimant=?1 OR claim_expiry <= ?3)
+            AND (defer_until IS NULL OR defer_until <= ?3)
+        ORDER BY
+            CASE state_phase
+                WHEN '"RUNNING"' THEN 0
+                WHEN '"NEW"' THEN 1
+                ELSE 2
+            END ASC,
+            scheduled_time ASC,
+            ROWID ASC
+        LIMIT 1
+    )
+    RETURNING
+        args,
+        created_time,
+        finished_time,
+        id,
+        kind,
+        metadata,
+        scheduled_time,
+        started_time,
+        state_error,
+        state_payload,
+        state_phase;
+"#;
+const ACTION_DEFER_SQL: &str = r#"
+    UPDATE actions
+    SET defer_until=?1
+    WHERE id=?2 AND finished_time IS NULL;
+"#;
 const ACTION_GET_SQL: &str = r#"
     SELECT
         args,
@@ -24,12 +105,50 @@ const ACTION_GET_SQL: &str = r#"
         kind,
         metadata,
         scheduled_time,
+        started_time,
         state_error,
         state_payload,
         state_phase
     FROM actions
     WHERE id=?1;
 "#;
+const ACTION_IMPORT_SQL: &str = r#"
+    INSERT OR REPLACE INTO actions (
+        args,
+        created_time,
+        finished_time,
+        id,
+        kind,
+        metadata,
+        scheduled_time,
+        started_time,
+        state_error,
+        state_payload,
+        state_phase
+    )
+    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11);
+"#;
+const ACTION_EXISTS_SQL: &str = r#"
+    SELECT 1
+    FROM actions
+    WHERE id=?1;
+"#;
+const ACTION_INSERT_SQL: &str = r#"
+    INSERT INTO actions (
+        args,
+        created_time,
+        finished_time,
+        id,
+        kind,
+        metadata,
+        scheduled_time,
+        started_time,
+        state_error,
+        state_payload,
+        state_phase
+    )
+    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11);
+"#;
 const ACTION_NEXT_SQL: &str = r#"
     SELECT
         args,
@@ -39,6 +158,7 @@ const ACTION_NEXT_SQL: &str = r#"
         kind,
         metadata,
         scheduled_time,
+        started_time,
         state_error,
         state_payload,
         state_phase,
@@ -49,9 +169,15 @@ const ACTION_NEXT_SQL: &str = r#"
         END AS phase_priority
     FROM actions
     WHERE finished_time IS NULL
+        AND (defer_until IS NULL OR defer_until <= ?1)
     ORDER BY phase_priority ASC, scheduled_time ASC, ROWID ASC
     LIMIT 1;
 "#;
+const ACTION_PATCH_METADATA_SQL: &str = r#"
+    UPDATE actions
+    SET metadata=?1
+    WHERE id=?2 AND finished_time IS NULL;
+"#;
 const ACTION_PERSIST_SQL: &str = r#"
     INSERT INTO actions (
         args,
@@ -59,13 +185,14 @@ const ACTION_PERSIST_SQL: &str = r#"
         finished_time,
         id,
         kind,
-        metadata, 
+        metadata,
         scheduled_time,
+        started_time,
         state_error,
         state_payload,
         state_phase
     )
-    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
     ON CONFLICT(id)
     DO UPDATE SET
         args=?1,
@@ -73,46 +200,105 @@ const ACTION_PERSIST_SQL: &str = r#"
         finished_time=?3,
         metadata=?6,
         scheduled_time=?7,
-        state_error=?8,
-        state_payload=?9,
-        state_phase=?10
+        started_time=?8,
+        state_error=?9,
+        state_payload=?10,
+        state_phase=?11
     ;
 "#;
+const ACTION_UPDATE_PROGRESS_SQL: &str = r#"
+    UPDATE actions
+    SET
+        finished_time=?1,
+        started_time=?2,
+        state_error=?3,
+        state_payload=?4,
+        state_phase=?5
+    WHERE id=?6;
+"#;
 const ACTIONS_CLEAN_FINISHED_SQL: &str = r#"
     DELETE FROM actions
     WHERE finished_time IS NOT NULL
         AND finished_time <= ?1;
 "#;
+const ACTIONS_STATE_CLEAN_ORPHANS_SQL: &str = r#"
+    DELETE FROM actions_state
+    WHERE action_id NOT IN (
+        SELECT id FROM actions WHERE finished_time IS NULL
+    );
+"#;
+const ACTIONS_COUNT_BY_PHASE_SQL: &str = r#"
+    SELECT state_phase, COUNT(*) AS count
+    FROM actions
+    GROUP BY state_phase;
+"#;
+const ACTIONS_EXPORT_SQL: &str = r#"
+    SELECT
+        ROWID AS row_id,
+        args,
+        created_time,
+        finished_time,
+        id,
+        kind,
+        metadata,
+        scheduled_time,
+        started_time,
+        state_error,
+        state_payload,
+        state_phase
+    FROM actions
+    WHERE ?1 IS NULL
+        OR scheduled_time > ?1
+        OR (scheduled_time = ?1 AND ROWID > ?2)
+    ORDER BY scheduled_time ASC, ROWID ASC
+    -- The limit is capped to ACTIONS_LIST_MAX_LIMIT by the caller.
+    LIMIT ?3;
+"#;
 const ACTIONS_FINISHED_SQL: &str = r#"
     SELECT kind, id, state_phase
     FROM actions
     WHERE finished_time IS NOT NULL
     ORDER BY scheduled_time ASC, ROWID ASC
-    -- Limit results to reduce blast radius in case of bugs.
-    -- There really should not be many running/pending actions on an agent.
-    LIMIT 50;
+    -- Results are always limited to reduce blast radius in case of bugs.
+    -- The limit is capped to ACTIONS_LIST_MAX_LIMIT by the caller.
+    LIMIT ?1 OFFSET ?2;
 "#;
 const ACTIONS_QUEUE_SQL: &str = r#"
     SELECT kind, id, state_phase
     FROM actions
     WHERE finished_time IS NULL
     ORDER BY scheduled_time ASC, ROWID ASC
-    -- Limit results to reduce blast radius in case of bugs.
-    -- There really should not be many running/pending actions on an agent.
-    LIMIT 50;
+    -- Results are always limited to reduce blast radius in case of bugs.
+    -- The limit is capped to ACTIONS_LIST_MAX_LIMIT by the caller.
+    LIMIT ?1 OFFSET ?2;
 "#;
 
+/// Position of the last [`ActionExecution`] record in a page of exported actions.
+///
+/// Pages of exported actions start after the position of the previous page in the
+/// (`scheduled_time`, `ROWID`) order rather than at an offset, so records removed
+/// while an export is in progress don't cause other records to be skipped.
+#[derive(Clone, Copy, Debug)]
+pub struct ExportCursor {
+    row_id: i64,
+    scheduled_time: f64,
+}
+
 /// [`ActionExecution`] row partially decoded from SQLite.
+///
+/// Structured data can be stored as JSON text or tagged binary data (see [`StoreEncoding`]).
+/// The action phase is always stored as JSON text as queries depend on its value.
 struct ActionRow {
-    args: String,
+    args: Value,
     created_time: String,
     finished_time: Option<f64>,
     id: String,
     kind: String,
-    metadata: String,
+    metadata: Value,
     scheduled_time: f64,
-    state_error: Option<String>,
-    state_payload: Option<String>,
+    started_time: Option<f64>,
+    state_error: Value,
+    state_payload: Value,
     state_phase: String,
 }
 
@@ -120,15 +306,16 @@ impl<'a> TryFrom<&rusqlite::Row<'a>> for ActionRow {
     type Error = rusqlite::Error;
 
     fn try_from(row: &rusqlite::Row<'a>) -> std::result::Result<Self, Self::Error> {
-        let args: String = row.get("args")?;
+        let args: Value = row.get("args")?;
         let created_time: String = row.get("created_time")?;
         let finished_time: Option<f64> = row.get("finished_time")?;
         let id: String = row.get("id")?;
         let kind: String = row.get("kind")?;
-        let metadata: String = row.get("metadata")?;
+        let metadata: Value = row.get("metadata")?;
         let scheduled_time: f64 = row.get("scheduled_time")?;
-        let state_error: Option<String> = row.get("state_error")?;
-        let state_payload: Option<String> = row.get("state_payload")?;
+        let started_time: Option<f64> = row.get("started_time")?;
+        let state_error: Value = row.get("state_error")?;
+        let state_payload: Value = row.get("state_payload")?;
         let state_phase: String = row.get("state_phase")?;
         Ok(Self {
             args,
@@ -138,6 +325,7 @@ impl<'a> TryFrom<&rusqlite::Row<'a>> for ActionRow {
             kind,
             metadata,
             scheduled_time,
+            started_time,
             state_error,
             state_payload,
             state_phase,
@@ -148,14 +336,15 @@ impl<'a> TryFrom<&rusqlite::Row<'a>> for ActionRow {
 impl TryFrom<ActionRow> for ActionExecution {
     type Error = anyhow::Error;
     fn try_from(row: ActionRow) -> std::result::Result<Self, Self::Error> {
-        let args = encoding::decode_serde(&row.args)?;
+        let args = decode_data(&row.args)?;
         let created_time = encoding::decode_time(&row.created_time)?;
         let finished_time = encoding::decode_time_option_f64(row.finished_time)?;
         let id = uuid::Uuid::parse_str(&row.id)?;
-        let metadata = encoding::decode_serde(&row.metadata)?;
+        let metadata = decode_data(&row.metadata)?;
         let scheduled_time = encoding::decode_time_f64(row.scheduled_time)?;
-        let state_error = encoding::decode_serde_option(&row.state_error)?;
-        let state_payload = encoding::decode_serde_option(&row.state_payload)?;
+        let started_time = encoding::decode_time_option_f64(row.started_time)?;
+        let state_error = decode_data_option(&row.state_error)?;
+        let state_payload = decode_data_option(&row.state_payload)?;
         let state_phase = encoding::decode_serde(&row.state_phase)?;
         let action = ActionExecution {
             args,
@@ -165,6 +354,7 @@ impl TryFrom<ActionRow> for ActionExecution {
             kind: row.kind,
             metadata,
             scheduled_time,
+            started_time,
             state: ActionExecutionState {
                 error: state_error,
                 payload: state_payload,
@@ -175,31 +365,258 @@ impl TryFrom<ActionRow> for ActionExecution {
     }
 }
 
+/// Decode structured data stored as JSON text or as tagged binary data.
+pub(super) fn decode_data<V>(value: &Value) -> Result<V>
+where
+    V: DeserializeOwned,
+{
+    match value {
+        Value::Blob(value) => encoding::decode_serde_binary(value),
+        Value::Text(value) => encoding::decode_serde(value),
+        _ => anyhow::bail!(StatementError::DataType),
+    }
+}
+
+/// Decode optional structured data stored as JSON text or as tagged binary data.
+fn decode_data_option<V>(value: &Value) -> Result<Option<V>>
+where
+    V: DeserializeOwned,
+{
+    match value {
+        Value::Null => Ok(None),
+        value => decode_data(value).map(Some),
+    }
+}
+
+/// Decode the handler managed state of an action from its stored records.
+fn decode_state(rows: &[(String, Value)]) -> Result<BTreeMap<String, serde_json::Value>> {
+    rows.iter()
+        .map(|(key, value)| decode_data(value).map(|value| (key.clone(), value)))
+        .collect()
+}
+
+/// Encode structured data for storage with the given [`StoreEncoding`].
+pub(super) fn encode_data<V>(value: &V, store_encoding: StoreEncoding) -> Result<Value>
+where
+    V: Serialize,
+{
+    let value = match store_encoding {
+        StoreEncoding::Json => Value::Text(encoding::encode_serde(value)?),
+        StoreEncoding::MessagePack => {
+            let format = encoding::BinaryFormat::MessagePack;
+            Value::Blob(encoding::encode_serde_binary(value, format)?)
+        }
+    };
+    Ok(value)
+}
+
+/// Encode optional structured data for storage with the given [`StoreEncoding`].
+fn encode_data_option<V>(value: &Option<V>, store_encoding: StoreEncoding) -> Result<Value>
+where
+    V: Serialize,
+{
+    match value {
+        None => Ok(Value::Null),
+        Some(value) => encode_data(value, store_encoding),
+    }
+}
+
+/// List a page of all [`ActionExecution`] records, finished or not.
+pub async fn all(store: &Connection, op: ActionsAll) -> Result<Vec<ActionExecution>> {
+    let (err_count, _timer) = metrics::store::observe_op("actions.all");
+    let trace = crate::agent::framework::trace::store_op_context("actions.all");
+    let rows = store
+        .call(move |connection| {
+            let mut statement = connection.prepare_cached(ACTION_ALL_SQL)?;
+            let mut rows = statement.query([op.limit.min(ACTIONS_LIST_MAX_LIMIT), op.offset])?;
+            let mut actions = Vec::new();
+            while let Some(row) = rows.next()? {
+                actions.push(ActionRow::try_from(row)?);
+            }
+            Ok(actions)
+        })
+        .count_on_err(err_count)
+        .trace_on_err_with_status()
+        .with_context(trace)
+        .await
+        .context(StatementError::QueryFailed)?;
+
+    // Decode rows into actions.
+    rows.into_iter().map(ActionExecution::try_from).collect()
+}
+
+/// Atomically claim the next [`ActionExecution`] to execute, if any is available.
+pub async fn claim_next(
+    store: &Connection,
+    claim: ClaimNextAction,
+) -> Result<Option<ActionExecution>> {
+    let (err_count, _timer) = metrics::store::observe_op("actions.claim_next");
+    let trace = crate::agent::framework::trace::store_op_context("actions.claim_next");
+    let now = time::OffsetDateTime::now_utc();
+    let expiry = encoding::encode_time_f64(now + claim.lease).count_on_err(err_count.clone())?;
+    let now = encoding::encode_time_f64(now).count_on_err(err_count.clone())?;
+    let row = store
+        .call(move |connection| {
+            let mut statement = connection.prepare_cached(ACTION_CLAIM_NEXT_SQL)?;
+            let mut rows = statement.query(rusqlite::params![claim.claimant, expiry, now])?;
+            match rows.next()? {
+                None => Ok(None),
+                Some(row) => {
+                    let row = ActionRow::try_from(row)?;
+                    Ok(Some(row))
+                }
+            }
+        })
+        .count_on_err(err_count)
+        .trace_on_err_with_status()
+        .with_context(trace)
+        .await
+        .context(StatementError::QueryFailed)?;
+
+    // Decode the row into an action.
+    match row {
+        None => Ok(None),
+        Some(row) => {
+            let action = ActionExecution::try_from(row)?;
+            Ok(Some(action))
+        }
+    }
+}
+
 /// Clean [`ActionExecution`] records for actions finished prior to to the given time.
-pub async fn clean(store: &Connection, age: time::OffsetDateTime) -> Result<()> {
+pub async fn clean(store: &Connection, age: time::OffsetDateTime) -> Result<usize> {
     let (err_count, _timer) = metrics::store::observe_op("actions.clean");
     let trace = crate::agent::framework::trace::store_op_context("actions.clean");
     let age = encoding::encode_time_f64(age).count_on_err(err_count.clone())?;
-    store
+    let removed = store
         .call(move |connection| {
             let removed = connection.execute(ACTIONS_CLEAN_FINISHED_SQL, rusqlite::params![age])?;
+            connection.execute(ACTIONS_STATE_CLEAN_ORPHANS_SQL, [])?;
             Ok(removed)
         })
         .count_on_err(err_count)
         .trace_on_err_with_status()
         .with_context(trace)
         .await?;
+    Ok(removed)
+}
+
+/// Count actions in the store by [`ActionExecutionPhase`].
+pub async fn count_by_phase(store: &Connection) -> Result<HashMap<ActionExecutionPhase, u64>> {
+    let (err_count, _timer) = metrics::store::observe_op("actions.count_by_phase");
+    let trace = crate::agent::framework::trace::store_op_context("actions.count_by_phase");
+    let rows = store
+        .call(|connection| {
+            let mut statement = connection.prepare_cached(ACTIONS_COUNT_BY_PHASE_SQL)?;
+            let mut rows = statement.query([])?;
+            let mut counts = Vec::new();
+            while let Some(row) = rows.next()? {
+                let phase: String = row.get("state_phase")?;
+                let count: u64 = row.get("count")?;
+                counts.push((phase, count));
+            }
+            Ok(counts)
+        })
+        .count_on_err(err_count)
+        .trace_on_err_with_status()
+        .with_context(trace)
+        .await
+        .context(StatementError::QueryFailed)?;
+
+    let mut counts = HashMap::new();
+    for (phase, count) in rows {
+        let phase = encoding::decode_serde(&phase)?;
+        counts.insert(phase, count);
+    }
+    Ok(counts)
+}
+
+/// Skip an unfinished [`ActionExecution`] record until the given time.
+pub async fn defer(store: &Connection, op: DeferAction) -> Result<()> {
+    let (err_count, _timer) = metrics::store::observe_op("actions.defer");
+    let trace = crate::agent::framework::trace::store_op_context("actions.defer");
+    let until = encoding::encode_time_f64(op.until).count_on_err(err_count.clone())?;
+    store
+        .call(move |connection| {
+            let mut statement = connection.prepare_cached(ACTION_DEFER_SQL)?;
+            statement.execute(rusqlite::params![until, op.id.to_string()])?;
+            Ok(())
+        })
+        .count_on_err(err_count)
+        .trace_on_err_with_status()
+        .with_context(trace)
+        .await?;
     Ok(())
 }
 
+/// List a page of [`ActionExecution`] records, with their handler managed state, to export.
+///
+/// Records are returned with the [`ExportCursor`] to request the next page with.
+pub async fn export_page(
+    store: &Connection,
+    after: Option<ExportCursor>,
+    limit: u32,
+) -> Result<(Vec<PortableAction>, Option<ExportCursor>)> {
+    let (err_count, _timer) = metrics::store::observe_op("actions.export_page");
+    let trace = crate::agent::framework::trace::store_op_context("actions.export_page");
+    let (scheduled_time, row_id) = match after {
+        None => (None, None),
+        Some(after) => (Some(after.scheduled_time), Some(after.row_id)),
+    };
+    let rows = store
+        .call(move |connection| {
+            // Read actions and their state from the same snapshot of the store.
+            let transaction = connection.transaction()?;
+            let mut rows = Vec::new();
+            {
+                let mut statement = transaction.prepare_cached(ACTIONS_EXPORT_SQL)?;
+                let limit = limit.min(ACTIONS_LIST_MAX_LIMIT);
+                let mut actions =
+                    statement.query(rusqlite::params![scheduled_time, row_id, limit])?;
+                while let Some(row) = actions.next()? {
+                    let cursor = ExportCursor {
+                        row_id: row.get("row_id")?,
+                        scheduled_time: row.get("scheduled_time")?,
+                    };
+                    rows.push((cursor, ActionRow::try_from(row)?));
+                }
+            }
+            let mut page = Vec::new();
+            for (cursor, row) in rows {
+                let state = state_rows(&transaction, &row.id)?;
+                page.push((cursor, row, state));
+            }
+            Ok(page)
+        })
+        .count_on_err(err_count)
+        .trace_on_err_with_status()
+        .with_context(trace)
+        .await
+        .context(StatementError::QueryFailed)?;
+
+    // Decode rows into actions and remember where the page ended.
+    let mut actions = Vec::new();
+    let mut cursor = None;
+    for (row_cursor, row, state) in rows {
+        let action = ActionExecution::try_from(row)?;
+        let handler_state = decode_state(&state)?;
+        actions.push(PortableAction {
+            action,
+            handler_state,
+        });
+        cursor = Some(row_cursor);
+    }
+    Ok((actions, cursor))
+}
+
 /// List [`ActionExecution`] summaries for finished actions.
-pub async fn finished(store: &Connection) -> Result<ActionExecutionList> {
+pub async fn finished(store: &Connection, op: ActionsFinished) -> Result<ActionExecutionList> {
     let (err_count, _timer) = metrics::store::observe_op("actions.finished");
     let trace = crate::agent::framework::trace::store_op_context("actions.finished");
     let rows = store
-        .call(|connection| {
+        .call(move |connection| {
             let mut statement = connection.prepare_cached(ACTIONS_FINISHED_SQL)?;
-            let mut rows = statement.query([])?;
+            let mut rows = statement.query([op.limit.min(ACTIONS_LIST_MAX_LIMIT), op.offset])?;
             let mut queue = Vec::new();
             while let Some(row) = rows.next()? {
                 let kind: String = row.get("kind")?;
@@ -258,14 +675,160 @@ pub async fn get(store: &Connection, id: uuid::Uuid) -> Result<Option<ActionExec
     }
 }
 
+/// Insert or fully replace an [`ActionExecution`] record and its handler managed state.
+///
+/// Any existing record is compared to the imported one and replaced in a single transaction.
+pub async fn import(
+    store: &Connection,
+    op: ImportAction,
+    store_encoding: StoreEncoding,
+) -> Result<ImportActionOutcome> {
+    // Serialise special types into stings or blobs for the DB.
+    let action = &op.action;
+    let args = encode_data(&action.args, store_encoding)?;
+    let created_time = encoding::encode_time(action.created_time)?;
+    let finished_time = encoding::encode_time_option_f64(action.finished_time)?;
+    let kind = action.kind.clone();
+    let metadata = encode_data(&action.metadata, store_encoding)?;
+    let scheduled_time = encoding::encode_time_f64(action.scheduled_time)?;
+    let started_time = encoding::encode_time_option_f64(action.started_time)?;
+    let state_error = encode_data_option(&action.state.error, store_encoding)?;
+    let state_payload = encode_data_option(&action.state.payload, store_encoding)?;
+    let state_phase = encoding::encode_serde(&action.state.phase)?;
+    let state = op
+        .state
+        .iter()
+        .map(|(key, value)| encode_data(value, store_encoding).map(|value| (key.clone(), value)))
+        .collect::<Result<Vec<_>>>()?;
+
+    // Compare and replace the record in a transaction.
+    let (err_count, _timer) = metrics::store::observe_op("actions.import");
+    let trace = crate::agent::framework::trace::store_op_context("actions.import");
+    store
+        .call(move |connection| {
+            let id = op.action.id.to_string();
+            let transaction = connection.transaction()?;
+            let row = {
+                let mut statement = transaction.prepare_cached(ACTION_GET_SQL)?;
+                let mut rows = statement.query([&id])?;
+                match rows.next()? {
+                    None => None,
+                    Some(row) => Some(ActionRow::try_from(row)?),
+                }
+            };
+            let outcome = match row {
+                None => ImportActionOutcome::Inserted,
+                Some(row) => {
+                    let existing_state = state_rows(&transaction, &id)?;
+                    let existing = ActionExecution::try_from(row)
+                        .and_then(|existing| Ok((existing, decode_state(&existing_state)?)))
+                        .map_err(|error| tokio_rusqlite::Error::Other(error.into()))?;
+                    if existing == (op.action, op.state) {
+                        ImportActionOutcome::Unchanged
+                    } else {
+                        ImportActionOutcome::Replaced
+                    }
+                }
+            };
+
+            transaction.execute(
+                ACTION_IMPORT_SQL,
+                rusqlite::params![
+                    args,
+                    created_time,
+                    finished_time,
+                    id,
+                    kind,
+                    metadata,
+                    scheduled_time,
+                    started_time,
+                    state_error,
+                    state_payload,
+                    state_phase,
+                ],
+            )?;
+            transaction.execute(ACTION_STATE_CLEAR_SQL, rusqlite::params![id])?;
+            for (key, value) in state {
+                transaction.execute(ACTION_STATE_SET_SQL, rusqlite::params![id, key, value])?;
+            }
+            transaction.commit()?;
+            Ok(outcome)
+        })
+        .count_on_err(err_count)
+        .trace_on_err_with_status()
+        .with_context(trace)
+        .await
+        .context(StatementError::QueryFailed)
+}
+
+/// Atomically insert a batch of new [`ActionExecution`] records.
+///
+/// The write lock is taken before IDs are checked so no other writer can use
+/// the same IDs between the check and the insert.
+pub async fn insert_batch(
+    store: &Connection,
+    op: InsertActions,
+    store_encoding: StoreEncoding,
+) -> Result<InsertActionsOutcome> {
+    // Serialise special types into stings or blobs for the DB.
+    let mut rows = Vec::with_capacity(op.actions.len());
+    for action in op.actions {
+        let row = rusqlite::params_from_iter([
+            encode_data(&action.args, store_encoding)?,
+            Value::Text(encoding::encode_time(action.created_time)?),
+            encoding::encode_time_option_f64(action.finished_time)?.into(),
+            Value::Text(action.id.to_string()),
+            Value::Text(action.kind),
+            encode_data(&action.metadata, store_encoding)?,
+            encoding::encode_time_f64(action.scheduled_time)?.into(),
+            encoding::encode_time_option_f64(action.started_time)?.into(),
+            encode_data_option(&action.state.error, store_encoding)?,
+            encode_data_option(&action.state.payload, store_encoding)?,
+            Value::Text(encoding::encode_serde(&action.state.phase)?),
+        ]);
+        rows.push((action.id, row));
+    }
+
+    // Check IDs and insert all actions in a single transaction.
+    let (err_count, _timer) = metrics::store::observe_op("actions.insert_batch");
+    let trace = crate::agent::framework::trace::store_op_context("actions.insert_batch");
+    store
+        .call(move |connection| {
+            let transaction =
+                connection.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+            let mut in_use = Vec::new();
+            {
+                let mut statement = transaction.prepare_cached(ACTION_EXISTS_SQL)?;
+                for (id, _) in &rows {
+                    if statement.exists([id.to_string()])? {
+                        in_use.push(*id);
+                    }
+                }
+            }
+            if !in_use.is_empty() {
+                return Ok(InsertActionsOutcome::IdsInUse(in_use));
+            }
+            for (_, row) in rows {
+                transaction.execute(ACTION_INSERT_SQL, row)?;
+            }
+            transaction.commit()?;
+            Ok(InsertActionsOutcome::Inserted)
+        })
+        .count_on_err(err_count)
+        .trace_on_err_with_status()
+        .with_context(trace)
+        .await
+        .context(StatementError::QueryFailed)
+}
+
 /// List [`ActionExecution`] summaries for unfinished actions.
-pub async fn queue(store: &Connection) -> Result<ActionExecutionList> {
+pub async fn queue(store: &Connection, op: ActionsQueue) -> Result<ActionExecutionList> {
     let (err_count, _timer) = metrics::store::observe_op("actions.queue");
     let trace = crate::agent::framework::trace::store_op_context("actions.queue");
     let rows = store
-        .call(|connection| {
+        .call(move |connection| {
             let mut statement = connection.prepare_cached(ACTIONS_QUEUE_SQL)?;
-            let mut rows = statement.query([])?;
+            let mut rows = statement.query([op.limit.min(ACTIONS_LIST_MAX_LIMIT), op.offset])?;
             let mut queue = Vec::new();
             while let Some(row) = rows.next()? {
                 let kind: String = row.get("kind")?;
@@ -294,10 +857,12 @@ pub async fn queue(store: &Connection) -> Result<ActionExecutionList> {
 pub async fn next_to_execute(store: &Connection) -> Result<Option<ActionExecution>> {
     let (err_count, _timer) = metrics::store::observe_op("actions.next_to_execute");
     let trace = crate::agent::framework::trace::store_op_context("actions.next_to_execute");
+    let now = time::OffsetDateTime::now_utc();
+    let now = encoding::encode_time_f64(now).count_on_err(err_count.clone())?;
     let row = store
-        .call(|connection| {
+        .call(move |connection| {
             let mut statement = connection.prepare_cached(ACTION_NEXT_SQL)?;
-            let mut rows = statement.query([])?;
+            let mut rows = statement.query([now])?;
             match rows.next()? {
                 None => Ok(None),
                 Some(row) => {
@@ -322,16 +887,72 @@ pub async fn next_to_execute(store: &Connection) -> Result<Option<ActionExecutio
     }
 }
 
+/// Merge metadata into an unfinished [`ActionExecution`] record.
+///
+/// The action is read, updated and written back in a single transaction
+/// so concurrent patches do not lose each other's updates.
+pub async fn patch_metadata(
+    store: &Connection,
+    patch: PatchActionMetadata,
+    store_encoding: StoreEncoding,
+) -> Result<PatchActionMetadataOutcome> {
+    let (err_count, _timer) = metrics::store::observe_op("actions.patch_metadata");
+    let trace = crate::agent::framework::trace::store_op_context("actions.patch_metadata");
+    store
+        .call(move |connection| {
+            let transaction = connection.transaction()?;
+            let row = {
+                let mut statement = transaction.prepare_cached(ACTION_GET_SQL)?;
+                let mut rows = statement.query([patch.id.to_string()])?;
+                match rows.next()? {
+                    None => None,
+                    Some(row) => Some(ActionRow::try_from(row)?),
+                }
+            };
+
+            // Check the action can be patched and merge the metadata.
+            let mut action = match row {
+                None => return Ok(PatchActionMetadataOutcome::NotFound),
+                Some(row) => ActionExecution::try_from(row)
+                    .map_err(|error| tokio_rusqlite::Error::Other(error.into()))?,
+            };
+            if action.finished_time.is_some() {
+                return Ok(PatchActionMetadataOutcome::Finished);
+            }
+            action.metadata.extend(patch.metadata);
+            let metadata = encode_data(&action.metadata, store_encoding)
+                .map_err(|error| tokio_rusqlite::Error::Other(error.into()))?;
+
+            // Write the merged metadata back.
+            transaction.execute(
+                ACTION_PATCH_METADATA_SQL,
+                rusqlite::params![metadata, patch.id.to_string()],
+            )?;
+            transaction.commit()?;
+            Ok(PatchActionMetadataOutcome::Patched(Box::new(action)))
+        })
+        .count_on_err(err_count)
+        .trace_on_err_with_status()
+        .with_context(trace)
+        .await
+        .context(StatementError::QueryFailed)
+}
+
 /// Insert or update an [`ActionExecution`] record.
-pub async fn persist(store: &Connection, action: ActionExecution) -> Result<()> {
-    // Serialise special types into stings for the DB.
-    let args = encoding::encode_serde(&action.args)?;
+pub async fn persist(
+    store: &Connection,
+    action: ActionExecution,
+    store_encoding: StoreEncoding,
+) -> Result<()> {
+    // Serialise special types into stings or blobs for the DB.
+    let args = encode_data(&action.args, store_encoding)?;
     let created_time = encoding::encode_time(action.created_time)?;
     let finished_time = encoding::encode_time_option_f64(action.finished_time)?;
-    let metadata = encoding::encode_serde(&action.metadata)?;
+    let metadata = encode_data(&action.metadata, store_encoding)?;
     let scheduled_time = encoding::encode_time_f64(action.scheduled_time)?;
-    let state_error = encoding::encode_serde_option(&action.state.error)?;
-    let state_payload = encoding::encode_serde_option(&action.state.payload)?;
+    let started_time = encoding::encode_time_option_f64(action.started_time)?;
+    let state_error = encode_data_option(&action.state.error, store_encoding)?;
+    let state_payload = encode_data_option(&action.state.payload, store_encoding)?;
     let state_phase = encoding::encode_serde(&action.state.phase)?;
 
     // Execute the insert statement.
@@ -339,21 +960,31 @@ pub async fn persist(store: &Connection, action: ActionExecution) -> Result<()>
     let trace = crate::agent::framework::trace::store_op_context("actions.persist");
     store
         .call(move |connection| {
-            connection.execute(
+            let id = action.id.to_string();
+            let finished = finished_time.is_some();
+            let transaction = connection.transaction()?;
+            transaction.execute(
                 ACTION_PERSIST_SQL,
                 rusqlite::params![
                     args,
                     created_time,
                     finished_time,
-                    action.id.to_string(),
+                    id,
                     action.kind,
                     metadata,
                     scheduled_time,
+                    started_time,
                     state_error,
                     state_payload,
                     state_phase,
                 ],
             )?;
+
+            // Handler managed state is no longer needed once actions finish.
+            if finished {
+                transaction.execute(ACTION_STATE_CLEAR_SQL, rusqlite::params![id])?;
+            }
+            transaction.commit()?;
             Ok(())
         })
         .count_on_err(err_count)
@@ -363,9 +994,87 @@ pub async fn persist(store: &Connection, action: ActionExecution) -> Result<()>
     Ok(())
 }
 
+/// Update the execution progress of an existing [`ActionExecution`] record.
+///
+/// Only the state and timing fields are written so metadata patched while
+/// the action was being handled is not overwritten.
+pub async fn update_progress(
+    store: &Connection,
+    op: UpdateActionProgress,
+    store_encoding: StoreEncoding,
+) -> Result<()> {
+    // Serialise special types into stings or blobs for the DB.
+    let action = op.action;
+    let finished_time = encoding::encode_time_option_f64(action.finished_time)?;
+    let started_time = encoding::encode_time_option_f64(action.started_time)?;
+    let state_error = encode_data_option(&action.state.error, store_encoding)?;
+    let state_payload = encode_data_option(&action.state.payload, store_encoding)?;
+    let state_phase = encoding::encode_serde(&action.state.phase)?;
+
+    // Execute the update statement.
+    let (err_count, _timer) = metrics::store::observe_op("actions.update_progress");
+    let trace = crate::agent::framework::trace::store_op_context("actions.update_progress");
+    store
+        .call(move |connection| {
+            let id = action.id.to_string();
+            let finished = finished_time.is_some();
+            let transaction = connection.transaction()?;
+            transaction.execute(
+                ACTION_UPDATE_PROGRESS_SQL,
+                rusqlite::params![
+                    finished_time,
+                    started_time,
+                    state_error,
+                    state_payload,
+                    state_phase,
+                    id,
+                ],
+            )?;
+
+            // Handler managed state is no longer needed once actions finish.
+            if finished {
+                transaction.execute(ACTION_STATE_CLEAR_SQL, rusqlite::params![id])?;
+            }
+            transaction.commit()?;
+            Ok(())
+        })
+        .count_on_err(err_count)
+        .trace_on_err_with_status()
+        .with_context(trace)
+        .await?;
+    Ok(())
+}
+
+/// Read the stored records of the handler managed state of an action.
+fn state_rows(
+    connection: &rusqlite::Connection,
+    id: &str,
+) -> rusqlite::Result<Vec<(String, Value)>> {
+    let mut statement = connection.prepare_cached(ACTION_STATE_LIST_SQL)?;
+    let mut rows = statement.query([id])?;
+    let mut state = Vec::new();
+    while let Some(row) = rows.next()? {
+        state.push((row.get("key")?, row.get("value")?));
+    }
+    Ok(state)
+}
+
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
     use crate::agent::framework::store::fixtures;
+    use crate::agent::framework::store::manage::CleanActions;
+    use crate::agent::framework::store::persist::ClaimNextAction;
+    use crate::agent::framework::store::persist::PatchActionMetadata;
+    use crate::agent::framework::store::persist::PatchActionMetadataOutcome;
+    use crate::agent::framework::store::persist::SetActionState;
+    use crate::agent::framework::store::persist::UpdateActionProgress;
+    use crate::agent::framework::store::query;
+    use crate::agent::framework::store::Store;
+    use crate::agent::framework::store::StoreEncoding;
+    use crate::agent::models::ActionExecution;
     use crate::agent::models::ActionExecutionPhase;
     use crate::context::Context;
 
@@ -373,6 +1082,157 @@ mod tests {
     const ACTION_UUID_2: uuid::Uuid = uuid::uuid!("cb4995fc-c62d-41ca-9e66-156f357e2df1");
     const ACTION_UUID_3: uuid::Uuid = uuid::uuid!("156dd85c-afd9-4135-afcd-9003d351e9c9");
 
+    /// Action with structured data in all encoded fields.
+    fn action_with_data() -> ActionExecution {
+        let mut action = fixtures::action(ACTION_UUID_1);
+        action.args = serde_json::json!({"replicas": [1, 2, 3], "target": "node-1"});
+        action.metadata.insert("owner".into(), "tests".into());
+        action.state.error = Some(serde_json::json!({"error_msg": "attempt failed"}));
+        action.state.payload = Some(serde_json::json!({"attempt": 2, "ratio": 0.5}));
+        action.phase_to(ActionExecutionPhase::Running);
+        action
+    }
+
+    /// Fetch the SQLite storage type of the action arguments.
+    async fn args_type(store: &Store) -> String {
+        store
+            .store
+            .call(|connection| {
+                let mut statement = connection.prepare("SELECT typeof(args) FROM actions;")?;
+                let kind = statement.query_row([], |row| row.get(0))?;
+                Ok(kind)
+            })
+            .await
+            .unwrap()
+    }
+
+    fn claim(claimant: &str, lease: Duration) -> ClaimNextAction {
+        ClaimNextAction {
+            claimant: claimant.to_string(),
+            lease,
+        }
+    }
+
+    #[tokio::test]
+    async fn claim_next_action_concurrently() {
+        let context = Context::fixture();
+        let store = fixtures::store().await;
+        let action = fixtures::action(ACTION_UUID_1);
+        store.persist(&context, action).await.unwrap();
+        let action = fixtures::action(ACTION_UUID_2);
+        store.persist(&context, action).await.unwrap();
+
+        let lease = Duration::from_secs(60);
+        let (first, second, third) = tokio::join!(
+            store.persist(&context, claim("executor-1", lease)),
+            store.persist(&context, claim("executor-2", lease)),
+            store.persist(&context, claim("executor-3", lease)),
+        );
+        let mut claimed: Vec<uuid::Uuid> = [first, second, third]
+            .into_iter()
+            .filter_map(|action| action.unwrap().map(|action| action.id))
+            .collect();
+        claimed.sort();
+        let mut expected = vec![ACTION_UUID_1, ACTION_UUID_2];
+        expected.sort();
+        assert_eq!(claimed, expected);
+    }
+
+    #[tokio::test]
+    async fn claim_next_action_expired_lease() {
+        let context = Context::fixture();
+        let store = fixtures::store().await;
+        let action = fixtures::action(ACTION_UUID_1);
+        store.persist(&context, action).await.unwrap();
+
+        let first = store
+            .persist(&context, claim("executor-1", Duration::ZERO))
+            .await
+            .unwrap();
+        assert_eq!(first.unwrap().id, ACTION_UUID_1);
+        let second = store
+            .persist(&context, claim("executor-2", Duration::from_secs(60)))
+            .await
+            .unwrap();
+        assert_eq!(second.unwrap().id, ACTION_UUID_1);
+        let third = store
+            .persist(&context, claim("executor-3", Duration::from_secs(60)))
+            .await
+            .unwrap();
+        assert_eq!(third, None);
+    }
+
+    #[tokio::test]
+    async fn claim_next_action_same_claimant() {
+        let context = Context::fixture();
+        let store = fixtures::store().await;
+        let action = fixtures::action(ACTION_UUID_1);
+        store.persist(&context, action).await.unwrap();
+
+        let lease = Duration::from_secs(60);
+        let first = store.persist(&context, claim("executor-1", lease)).await;
+        let second = store.persist(&context, claim("executor-1", lease)).await;
+        assert_eq!(first.unwrap().unwrap().id, ACTION_UUID_1);
+        assert_eq!(second.unwrap().unwrap().id, ACTION_UUID_1);
+    }
+
+    #[tokio::test]
+    async fn clean_removes_orphaned_action_state() {
+        let context = Context::fixture();
+        let store = fixtures::store().await;
+        let action = fixtures::action(ACTION_UUID_1);
+        store.persist(&context, action).await.unwrap();
+        for id in [ACTION_UUID_1, ACTION_UUID_2] {
+            let op = SetActionState {
+                action_id: id,
+                key: "step".into(),
+                value: serde_json::json!(1),
+            };
+            store.persist(&context, op).await.unwrap();
+        }
+
+        let op = CleanActions::since(time::OffsetDateTime::now_utc());
+        store.manage(&context, op).await.unwrap();
+        let state = |action_id| query::ActionState {
+            action_id,
+            key: "step".into(),
+        };
+        let kept = store.query(&context, state(ACTION_UUID_1)).await.unwrap();
+        assert_eq!(kept, Some(serde_json::json!(1)));
+        let orphan = store.query(&context, state(ACTION_UUID_2)).await.unwrap();
+        assert_eq!(orphan, None);
+    }
+
+    #[tokio::test]
+    async fn count_actions_by_phase() {
+        let context = Context::fixture();
+        let store = fixtures::store().await;
+        let counts = store
+            .query(&context, query::ActionsCountByPhase {})
+            .await
+            .unwrap();
+        assert!(counts.is_empty());
+
+        store
+            .persist(&context, fixtures::action(ACTION_UUID_1))
+            .await
+            .unwrap();
+        let mut action = fixtures::action(ACTION_UUID_2);
+        action.state.phase = ActionExecutionPhase::Running;
+        store.persist(&context, action).await.unwrap();
+        let mut action = fixtures::action(ACTION_UUID_3);
+        action.state.phase = ActionExecutionPhase::Running;
+        store.persist(&context, action).await.unwrap();
+
+        let counts = store
+            .query(&context, query::ActionsCountByPhase {})
+            .await
+            .unwrap();
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts[&ActionExecutionPhase::New], 1);
+        assert_eq!(counts[&ActionExecutionPhase::Running], 2);
+    }
+
     #[tokio::test]
     async fn get_action() {
         let context = Context::fixture();
@@ -420,7 +1280,7 @@ mod tests {
         store.persist(&context, action).await.unwrap();
 
         // Query the actions queue.
-        let query = super::super::super::query::ActionsQueue {};
+        let query = super::super::super::query::ActionsQueue::default();
         let queue = store.query(&context, query).await.unwrap();
         let actions = queue.actions;
         assert_eq!(actions.len(), 2);
@@ -428,6 +1288,23 @@ mod tests {
         assert_eq!(actions[1].id, ACTION_UUID_1);
     }
 
+    #[tokio::test]
+    async fn query_actions_queue_limit_capped() {
+        let context = Context::fixture();
+        let store = fixtures::store().await;
+        for _ in 0..=query::ACTIONS_LIST_MAX_LIMIT {
+            let action = fixtures::action(uuid::Uuid::new_v4());
+            store.persist(&context, action).await.unwrap();
+        }
+
+        let query = query::ActionsQueue {
+            limit: u32::MAX,
+            offset: 0,
+        };
+        let queue = store.query(&context, query).await.unwrap();
+        assert_eq!(queue.actions.len(), query::ACTIONS_LIST_MAX_LIMIT as usize);
+    }
+
     #[tokio::test]
     async fn next_action_new() {
         let context = Context::fixture();
@@ -533,4 +1410,132 @@ mod tests {
         assert_eq!(metadata, r#"{"test":"value"}"#);
         assert_eq!(phase, r#""RUNNING""#);
     }
+
+    #[tokio::test]
+    async fn update_action_progress_keeps_patched_metadata() {
+        let context = Context::fixture();
+        let store = fixtures::store().await;
+        store
+            .persist(&context, fixtures::action(ACTION_UUID_1))
+            .await
+            .unwrap();
+
+        // Load the action, as the executor does, then patch it before progress is saved.
+        let query = query::Action::new(ACTION_UUID_1);
+        let mut action = store.query(&context, query).await.unwrap().unwrap();
+        let patch = PatchActionMetadata {
+            id: ACTION_UUID_1,
+            metadata: [("ticket".into(), "T-1".into())].into(),
+        };
+        store.persist(&context, patch).await.unwrap();
+        action.state.phase = ActionExecutionPhase::Running;
+        store
+            .persist(&context, UpdateActionProgress { action })
+            .await
+            .unwrap();
+
+        let query = query::Action::new(ACTION_UUID_1);
+        let stored = store.query(&context, query).await.unwrap().unwrap();
+        let expected: BTreeMap<String, String> = [("ticket".into(), "T-1".into())].into();
+        assert_eq!(stored.metadata, expected);
+        assert_eq!(stored.state.phase, ActionExecutionPhase::Running);
+    }
+
+    #[tokio::test]
+    async fn patch_action_metadata() {
+        let context = Context::fixture();
+        let store = fixtures::store().await;
+        let mut action = fixtures::action(ACTION_UUID_1);
+        action.metadata.insert("owner".into(), "tests".into());
+        action.metadata.insert("ticket".into(), "T-1".into());
+        store.persist(&context, action).await.unwrap();
+
+        let patch = PatchActionMetadata {
+            id: ACTION_UUID_1,
+            metadata: [
+                ("ticket".into(), "T-2".into()),
+                ("link".into(), "url".into()),
+            ]
+            .into(),
+        };
+        let outcome = store.persist(&context, patch).await.unwrap();
+        let action = match outcome {
+            PatchActionMetadataOutcome::Patched(action) => action,
+            outcome => panic!("unexpected patch outcome: {:?}", outcome),
+        };
+        let expected: BTreeMap<String, String> = [
+            ("link".into(), "url".into()),
+            ("owner".into(), "tests".into()),
+            ("ticket".into(), "T-2".into()),
+        ]
+        .into();
+        assert_eq!(action.metadata, expected);
+
+        let query = query::Action::new(ACTION_UUID_1);
+        let stored = store.query(&context, query).await.unwrap().unwrap();
+        assert_eq!(stored.metadata, expected);
+    }
+
+    #[tokio::test]
+    async fn patch_action_metadata_finished() {
+        let context = Context::fixture();
+        let store = fixtures::store().await;
+        let mut action = fixtures::action(ACTION_UUID_1);
+        action.finished_time = Some(action.created_time);
+        action.state.phase = ActionExecutionPhase::Done;
+        store.persist(&context, action).await.unwrap();
+
+        let patch = PatchActionMetadata {
+            id: ACTION_UUID_1,
+            metadata: [("link".into(), "url".into())].into(),
+        };
+        let outcome = store.persist(&context, patch).await.unwrap();
+        assert_eq!(outcome, PatchActionMetadataOutcome::Finished);
+
+        let patch = PatchActionMetadata {
+            id: ACTION_UUID_2,
+            metadata: Default::default(),
+        };
+        let outcome = store.persist(&context, patch).await.unwrap();
+        assert_eq!(outcome, PatchActionMetadataOutcome::NotFound);
+    }
+
+    #[rstest::rstest]
+    #[case(StoreEncoding::Json, "text")]
+    #[case(StoreEncoding::MessagePack, "blob")]
+    #[tokio::test]
+    async fn persist_round_trip(#[case] encoding: StoreEncoding, #[case] storage: &str) {
+        let context = Context::fixture();
+        let store = fixtures::store().await.with_encoding(encoding);
+        let action = action_with_data();
+        store.persist(&context, action.clone()).await.unwrap();
+        assert_eq!(args_type(&store).await, storage);
+
+        let query = query::Action::new(ACTION_UUID_1);
+        let stored = store.query(&context, query).await.unwrap();
+        assert_eq!(stored, Some(action));
+    }
+
+    #[tokio::test]
+    async fn read_json_rows_with_binary_encoding() {
+        let context = Context::fixture();
+        let store = fixtures::store().await;
+        let action = action_with_data();
+        store.persist(&context, action.clone()).await.unwrap();
+        assert_eq!(args_type(&store).await, "text");
+
+        let store = store.with_encoding(StoreEncoding::MessagePack);
+        let query = query::Action::new(ACTION_UUID_1);
+        let stored = store.query(&context, query).await.unwrap();
+        assert_eq!(stored, Some(action.clone()));
+
+        // Updates re-encode the record with the current encoding.
+        let mut action = action;
+        action.state.phase = ActionExecutionPhase::Done;
+        store.persist(&context, action.clone()).await.unwrap();
+        assert_eq!(args_type(&store).await, "blob");
+        let query = query::Action::new(ACTION_UUID_1);
+        let stored = store.query(&context, query).await.unwrap();
+        assert_eq!(stored, Some(action));
+    }
 }
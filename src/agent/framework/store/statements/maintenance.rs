@@ -0,0 +1,44 @@
+//! Implementation of store maintenance operations.
+use anyhow::Result;
+use opentelemetry_api::trace::FutureExt;
+use tokio_rusqlite::Connection;
+
+use crate::agent::framework::metrics;
+use crate::utils::metrics::CountFutureErrExt;
+use crate::utils::trace::TraceFutureStdErrExt;
+
+const CHECKPOINT_SQL: &str = "PRAGMA wal_checkpoint(TRUNCATE);";
+const VACUUM_SQL: &str = "VACUUM;";
+
+/// Checkpoint the write-ahead log, if one is in use, and truncate it.
+pub async fn checkpoint(store: &Connection) -> Result<()> {
+    let (err_count, _timer) = metrics::store::observe_op("maintenance.checkpoint");
+    let trace = crate::agent::framework::trace::store_op_context("maintenance.checkpoint");
+    store
+        .call(|connection| {
+            // The pragma returns a status row that must be consumed for it to run.
+            connection.query_row(CHECKPOINT_SQL, [], |_| Ok(()))?;
+            Ok(())
+        })
+        .count_on_err(err_count)
+        .trace_on_err_with_status()
+        .with_context(trace)
+        .await?;
+    Ok(())
+}
+
+/// Rebuild the store file to release free pages.
+pub async fn vacuum(store: &Connection) -> Result<()> {
+    let (err_count, _timer) = metrics::store::observe_op("maintenance.vacuum");
+    let trace = crate::agent::framework::trace::store_op_context("maintenance.vacuum");
+    store
+        .call(|connection| {
+            connection.execute_batch(VACUUM_SQL)?;
+            Ok(())
+        })
+        .count_on_err(err_count)
+        .trace_on_err_with_status()
+        .with_context(trace)
+        .await?;
+    Ok(())
+}
@@ -1,9 +1,15 @@
 //! Implementation of the store interface using SQLite.
+pub mod action_state;
 pub mod actions;
+pub mod maintenance;
 
 /// Errors while executing SQLite statements.
 #[derive(Debug, thiserror::Error)]
 pub enum StatementError {
+    /// Structured data in the store is neither text nor binary data.
+    #[error("structured data in the store is neither text nor binary data")]
+    DataType,
+
     /// Error while querying data from the store.
     #[error("error while querying data from the store")]
     QueryFailed,
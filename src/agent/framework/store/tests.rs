@@ -1,7 +1,12 @@
 //! Tests for the Agent Store module.
+use std::num::NonZeroUsize;
+
 use rusqlite::Connection;
 
 use super::fixtures;
+use super::query;
+use super::StoreError;
+use crate::context::Context;
 
 #[tokio::test]
 async fn initialise() {
@@ -15,6 +20,40 @@ async fn initialise() {
     assert!(migrations >= 1);
 }
 
+#[tokio::test]
+async fn quiesce_drains_pending_writes() {
+    let store = fixtures::store()
+        .await
+        .with_write_queue(NonZeroUsize::new(2).unwrap());
+    let context = Context::fixture();
+    let ids: Vec<uuid::Uuid> = (0..10).map(|_| uuid::Uuid::new_v4()).collect();
+
+    // Enqueue writes ahead of quiescing the store (join polls futures in order).
+    let writes = ids
+        .iter()
+        .map(|id| store.persist(&context, fixtures::action(*id)));
+    let writes = futures::future::join_all(writes);
+    let (results, _) = tokio::join!(writes, store.quiesce());
+    for result in results {
+        result.unwrap();
+    }
+
+    // New writes are rejected but all pending writes completed.
+    let error = store
+        .persist(&context, fixtures::action(uuid::Uuid::new_v4()))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<StoreError>(),
+        Some(StoreError::Quiesced)
+    ));
+    for id in ids {
+        let action = store.query(&context, query::Action::new(id)).await.unwrap();
+        assert!(action.is_some());
+    }
+    store.close().await.unwrap();
+}
+
 fn fetch_migrations_count(connection: &mut Connection) -> tokio_rusqlite::Result<i32> {
     let mut statement = connection.prepare("SELECT COUNT(*) FROM refinery_schema_history;")?;
     let count = statement.query_row([], |row| row.get(0))?;
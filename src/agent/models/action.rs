@@ -1,5 +1,6 @@
 //! Replicante Agent action models.
 use std::collections::BTreeMap;
+use std::fmt;
 
 use serde::Deserialize;
 use serde::Serialize;
@@ -7,6 +8,10 @@ use serde_json::Value as Json;
 use time::OffsetDateTime;
 use uuid::Uuid;
 
+use super::NodeStatus;
+use crate::utils::validate::Validate;
+use crate::utils::validate::ValidationErrors;
+
 /// Information about an Agent Action execution.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct ActionExecution {
@@ -32,7 +37,7 @@ pub struct ActionExecution {
     /// Identifier of the action implementation to execute.
     pub kind: String,
 
-    /// Unstructured metadata attached to the action when it was created.
+    /// Unstructured metadata attached to the action, can be updated until the action finishes.
     #[serde(default)]
     pub metadata: BTreeMap<String, String>,
 
@@ -40,6 +45,10 @@ pub struct ActionExecution {
     #[serde(with = "time::serde::rfc3339")]
     pub scheduled_time: OffsetDateTime,
 
+    /// Time the agent first invoked the action handler, for actions that started running.
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub started_time: Option<OffsetDateTime>,
+
     /// Current state of an Agent Action execution.
     pub state: ActionExecutionState,
 }
@@ -64,6 +73,118 @@ impl ActionExecution {
         }
         self.state.phase = phase;
     }
+
+    /// Render a one-line human-readable summary of the action.
+    ///
+    /// Refer to [`ActionExecutionSummary`] for details on the information included.
+    pub fn summary(&self) -> String {
+        self.summary_at(OffsetDateTime::now_utc()).to_string()
+    }
+
+    /// Collect summary information about the action, with age relative to the given time.
+    pub fn summary_at(&self, now: OffsetDateTime) -> ActionExecutionSummary {
+        let age = (now - self.created_time).max(time::Duration::ZERO);
+        let error = self.state.error.as_ref().map(error_head);
+        let id = self.id.simple().to_string()[..ActionExecutionSummary::SHORT_ID_LEN].to_string();
+        ActionExecutionSummary {
+            age,
+            error,
+            id,
+            kind: self.kind.clone(),
+            phase: self.state.phase,
+        }
+    }
+}
+
+/// Structured summary of an [`ActionExecution`] for display in CLIs and admin UIs.
+///
+/// The summary is rendered on a single line by its [`Display`](fmt::Display) implementation:
+///
+/// ```text
+/// 67e55044 agent.replicante.io/test.fail FAILED age=1h2m error="the action failed"
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ActionExecutionSummary {
+    /// Time elapsed since the action was created.
+    pub age: time::Duration,
+
+    /// First line of the action error message, for actions that encountered an error.
+    pub error: Option<String>,
+
+    /// Short form of the action ID.
+    pub id: String,
+
+    /// Identifier of the action implementation to execute.
+    pub kind: String,
+
+    /// Current phase of the action execution process.
+    pub phase: ActionExecutionPhase,
+}
+
+impl ActionExecutionSummary {
+    /// Maximum number of characters of the error message included in the summary.
+    const ERROR_MAX_LEN: usize = 80;
+
+    /// Number of characters of the action ID included in the summary.
+    const SHORT_ID_LEN: usize = 8;
+}
+
+impl fmt::Display for ActionExecutionSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} age={}",
+            self.id,
+            self.kind,
+            self.phase.as_str(),
+            format_age(self.age)
+        )?;
+        if let Some(error) = &self.error {
+            write!(f, " error={:?}", error)?;
+        }
+        Ok(())
+    }
+}
+
+/// Format an age with its two most significant units (for example `1h2m` or `42s`).
+fn format_age(age: time::Duration) -> String {
+    let secs = age.whole_seconds();
+    let (days, hours, mins, secs) = (
+        secs / 86_400,
+        secs % 86_400 / 3_600,
+        secs % 3_600 / 60,
+        secs % 60,
+    );
+    match (days, hours, mins) {
+        (0, 0, 0) => format!("{}s", secs),
+        (0, 0, _) => format!("{}m{}s", mins, secs),
+        (0, _, _) => format!("{}h{}m", hours, mins),
+        _ => format!("{}d{}h", days, hours),
+    }
+}
+
+/// Extract the first line of an action error message, truncated for display.
+///
+/// Errors are usually encoded with an `error_msg` attribute but other values are supported.
+fn error_head(error: &Json) -> String {
+    let message = match error {
+        Json::Object(error) => match error.get("error_msg") {
+            Some(Json::String(message)) => message.clone(),
+            _ => Json::Object(error.clone()).to_string(),
+        },
+        Json::String(message) => message.clone(),
+        error => error.to_string(),
+    };
+    let line = message.lines().next().unwrap_or_default();
+    if line.chars().count() <= ActionExecutionSummary::ERROR_MAX_LEN {
+        return line.to_string();
+    }
+    let mut line: String = line
+        .chars()
+        .take(ActionExecutionSummary::ERROR_MAX_LEN - 1)
+        .collect();
+    line.push('…');
+    line
 }
 
 /// API response for lookups of lists of [`ActionExecution`]s records.
@@ -87,7 +208,7 @@ pub struct ActionExecutionListItem {
 }
 
 /// Phases of the action execution process.
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
 pub enum ActionExecutionPhase {
     /// The action execution completed successfully.
     #[serde(rename = "DONE")]
@@ -106,8 +227,23 @@ pub enum ActionExecutionPhase {
     Running,
 }
 
+impl ActionExecutionPhase {
+    /// Name of the phase, as used in API responses.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActionExecutionPhase::Done => "DONE",
+            ActionExecutionPhase::Failed => "FAILED",
+            ActionExecutionPhase::New => "NEW",
+            ActionExecutionPhase::Running => "RUNNING",
+        }
+    }
+}
+
 /// API Request schema for an [`ActionExecution`] schedule call.
+///
+/// Unknown fields are rejected so typos in requests are reported instead of silently ignored.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct ActionExecutionRequest {
     /// Arguments passed to the action execution being created.
     #[serde(default)]
@@ -127,6 +263,13 @@ pub struct ActionExecutionRequest {
     #[serde(default)]
     pub id: Option<Uuid>,
 
+    /// Schedule the action only if the node is currently in the given status.
+    ///
+    /// Enables clients to guard against racing concurrent node state changes.
+    /// The condition is checked when the action is scheduled only.
+    #[serde(default)]
+    pub if_node_status: Option<NodeStatus>,
+
     /// Identifier of the action implementation to execute.
     pub kind: String,
 
@@ -146,6 +289,7 @@ impl From<ActionExecutionRequest> for ActionExecution {
             kind: value.kind,
             metadata: value.metadata,
             scheduled_time: now,
+            started_time: None,
             state: ActionExecutionState {
                 error: None,
                 payload: None,
@@ -155,6 +299,19 @@ impl From<ActionExecutionRequest> for ActionExecution {
     }
 }
 
+impl Validate for ActionExecutionRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        if let Some(created_time) = &self.created_time {
+            if !created_time.offset().is_utc() {
+                errors.add("created_time", "value must be in UTC");
+            }
+        }
+        errors.require_not_empty("kind", &self.kind);
+        errors.into_result()
+    }
+}
+
 /// API Response schema for an [`ActionExecution`] schedule call.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct ActionExecutionResponse {
@@ -176,3 +333,107 @@ pub struct ActionExecutionState {
     /// Current phase of the action execution process.
     pub phase: ActionExecutionPhase,
 }
+
+#[cfg(test)]
+mod tests {
+    use time::OffsetDateTime;
+
+    use super::ActionExecution;
+    use super::ActionExecutionPhase;
+    use super::ActionExecutionRequest;
+    use super::ActionExecutionState;
+    use crate::utils::validate::Validate;
+
+    fn action(phase: ActionExecutionPhase) -> ActionExecution {
+        let created_time = OffsetDateTime::parse(
+            "2023-04-05T06:07:08Z",
+            &time::format_description::well_known::Rfc3339,
+        )
+        .unwrap();
+        ActionExecution {
+            args: serde_json::Value::Null,
+            created_time,
+            finished_time: None,
+            id: uuid::uuid!("67e55044-10b1-426f-9247-bb680e5fe0c8"),
+            kind: "agent.replicante.io/test.success".into(),
+            metadata: Default::default(),
+            scheduled_time: created_time,
+            started_time: None,
+            state: ActionExecutionState {
+                error: None,
+                payload: None,
+                phase,
+            },
+        }
+    }
+
+    #[test]
+    fn summary_running() {
+        let action = action(ActionExecutionPhase::Running);
+        let now = action.created_time + time::Duration::seconds(5 * 60 + 3);
+        let summary = action.summary_at(now);
+        assert_eq!(summary.id, "67e55044");
+        assert_eq!(summary.error, None);
+        assert_eq!(
+            summary.to_string(),
+            "67e55044 agent.replicante.io/test.success RUNNING age=5m3s",
+        );
+    }
+
+    #[test]
+    fn summary_failed_with_error() {
+        let mut action = action(ActionExecutionPhase::Failed);
+        action.kind = "agent.replicante.io/test.fail".into();
+        action.state.error = Some(serde_json::json!({
+            "error_msg": "the action failed\nwith more details",
+            "error_cause": "root cause",
+        }));
+        let now = action.created_time + time::Duration::seconds(3_600 + 2 * 60 + 30);
+        let summary = action.summary_at(now);
+        assert_eq!(summary.error.as_deref(), Some("the action failed"));
+        assert_eq!(
+            summary.to_string(),
+            r#"67e55044 agent.replicante.io/test.fail FAILED age=1h2m error="the action failed""#,
+        );
+    }
+
+    #[test]
+    fn summary_long_error_is_truncated() {
+        let mut action = action(ActionExecutionPhase::Failed);
+        action.state.error = Some(serde_json::Value::String("x".repeat(200)));
+        let summary = action.summary_at(action.created_time);
+        let error = summary.error.unwrap();
+        assert_eq!(error.chars().count(), 80);
+        assert!(error.ends_with('…'));
+    }
+
+    #[test]
+    fn validate_request() {
+        let request = ActionExecutionRequest {
+            args: serde_json::Value::Null,
+            created_time: Some(OffsetDateTime::now_utc()),
+            id: None,
+            if_node_status: None,
+            kind: "agent.replicante.io/test.success".into(),
+            metadata: Default::default(),
+        };
+        assert!(request.validate().is_ok());
+    }
+
+    #[test]
+    fn validate_request_fails() {
+        let created_time =
+            OffsetDateTime::now_utc().to_offset(time::UtcOffset::from_hms(3, 0, 0).unwrap());
+        let request = ActionExecutionRequest {
+            args: serde_json::Value::Null,
+            created_time: Some(created_time),
+            id: None,
+            if_node_status: None,
+            kind: "".into(),
+            metadata: Default::default(),
+        };
+        let errors = request.validate().unwrap_err();
+        let paths: Vec<&str> = errors.errors().iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["created_time", "kind"]);
+    }
+}
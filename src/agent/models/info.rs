@@ -63,6 +63,12 @@ impl From<String> for AttributeValue {
     }
 }
 
+/// Node attribute agents can set to report the address their service is reachable at.
+///
+/// The attribute value is expected to be a string in the same format as the
+/// agent address reported by Platforms during cluster discovery.
+pub const NODE_ATTRIBUTE_AGENT_ADDRESS: &str = "agent.replicante.io/address";
+
 /// Map of Node attribute identifies to values.
 pub type AttributesMap = BTreeMap<String, AttributeValue>;
 
@@ -255,3 +261,60 @@ pub struct StoreVersion {
     #[serde(default)]
     pub extra: Option<String>,
 }
+
+impl StoreVersion {
+    /// Check if the store version number satisfies the given version requirement.
+    ///
+    /// Returns an error if the version number is not a valid semantic version.
+    pub fn satisfies(&self, req: &semver::VersionReq) -> Result<bool, StoreVersionNotSemver> {
+        let version =
+            semver::Version::parse(&self.number).map_err(|source| StoreVersionNotSemver {
+                number: self.number.clone(),
+                source,
+            })?;
+        Ok(req.matches(&version))
+    }
+}
+
+/// The store version number is not a valid [Semantic Version](https://semver.org/).
+#[derive(Debug, thiserror::Error)]
+#[error("store version '{number}' is not a valid semantic version")]
+pub struct StoreVersionNotSemver {
+    /// The store version number that could not be parsed.
+    pub number: String,
+
+    /// The error encountered parsing the version number.
+    #[source]
+    pub source: semver::Error,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StoreVersion;
+
+    fn version(number: &str) -> StoreVersion {
+        StoreVersion {
+            checkout: None,
+            number: number.into(),
+            extra: None,
+        }
+    }
+
+    #[rstest::rstest]
+    #[case("6.0.0", true)]
+    #[case("6.2.1", true)]
+    #[case("5.9.9", false)]
+    #[case("7.0.0", false)]
+    fn satisfies(#[case] number: &str, #[case] expected: bool) {
+        let req = semver::VersionReq::parse(">=6.0, <7.0").unwrap();
+        let satisfies = version(number).satisfies(&req).unwrap();
+        assert_eq!(satisfies, expected);
+    }
+
+    #[test]
+    fn satisfies_not_semver() {
+        let req = semver::VersionReq::parse(">=6.0").unwrap();
+        let error = version("6.0-custom-build").satisfies(&req).unwrap_err();
+        assert_eq!(error.number, "6.0-custom-build");
+    }
+}
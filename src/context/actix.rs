@@ -1,4 +1,6 @@
 //! Additional [`Context`] feature to integrate with [ActixWeb](actix_web).
+use std::fmt::Display;
+use std::fmt::Formatter;
 use std::future::Ready;
 
 use actix_web::dev::forward_ready;
@@ -42,6 +44,12 @@ where
 
         // Derive the per-request context.
         let mut context = root.derive();
+        let request_id = request.extensions().get::<RequestId>().cloned();
+        if let Some(request_id) = request_id {
+            context = context
+                .public_log_value("request_id", request_id.as_str())
+                .value(request_id);
+        }
         if let Some(config) = config {
             for hook in &config.hooks {
                 context = hook(context);
@@ -104,6 +112,35 @@ impl FromRequest for Context {
     }
 }
 
+/// Identifier of the request being handled, shared with clients to correlate requests.
+///
+/// Request IDs attached to request extensions by other middleware, such as the
+/// [`AppFactory`](crate::runtime::actix_web::AppFactory) request ID middleware, are added
+/// to the per-request [`Context`] as values and public log values under the `request_id` key.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct RequestId(String);
+
+impl RequestId {
+    /// Access the request ID as a string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Wrap an identifier to attach to requests.
+    pub fn new<S>(id: S) -> RequestId
+    where
+        S: Into<String>,
+    {
+        RequestId(id.into())
+    }
+}
+
+impl Display for RequestId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
 /// Configuration of the per-request [`Context`] derivation process.
 pub struct ContextConfig {
     #[cfg(any(feature = "opentelemetry", feature = "opentelemetry_api"))]
@@ -145,6 +182,7 @@ impl Default for ContextConfig {
 
 #[cfg(test)]
 mod tests {
+    use actix_web::dev::Service;
     use actix_web::test::call_and_read_body_json;
     use actix_web::test::init_service;
     use actix_web::test::TestRequest;
@@ -154,6 +192,7 @@ mod tests {
 
     use super::super::Context;
     use super::ContextConfig;
+    use super::RequestId;
 
     #[actix_web::get("/")]
     async fn inspect(context: Context) -> HttpResponse {
@@ -184,6 +223,34 @@ mod tests {
         assert_eq!(response, 26u64);
     }
 
+    #[actix_web::test]
+    async fn inject_context_with_request_id() {
+        #[actix_web::get("/")]
+        async fn request_id(context: Context) -> HttpResponse {
+            let id = context.require::<RequestId>();
+            let public = context.public_values().get("request_id");
+            HttpResponse::Ok().json((id.as_str(), public))
+        }
+
+        let root = Context::fixture();
+        let app = actix_web::App::new()
+            .service(request_id)
+            .app_data(actix_web::web::Data::new(root))
+            .wrap(super::ActixTransform)
+            .wrap_fn(|request, service| {
+                request
+                    .extensions_mut()
+                    .insert(RequestId::new("test-request"));
+                service.call(request)
+            });
+        let app = init_service(app).await;
+
+        let request = TestRequest::get().uri("/").to_request();
+        let response: (String, Option<String>) = call_and_read_body_json(&app, request).await;
+        assert_eq!(response.0, "test-request");
+        assert_eq!(response.1.as_deref(), Some("test-request"));
+    }
+
     #[actix_web::test]
     async fn inject_context_with_derives() {
         let conf = ContextConfig::default().customise(|builder| builder.value::<u64>(33));
@@ -13,14 +13,48 @@
 //! For the root context this is the process-wide logger with no additional attributes.
 //! But for individual operations a derived context can be provided with a [`Logger`] decorated
 //! with the operation trace ID or other request attributes.
+//!
+//! ## Public values
+//!
+//! Some contextual information, such as a request ID, is useful to clients when reporting errors.
+//! Values attached with [`ContextBuilder::public_log_value`] are added to the context logger
+//! and are also marked as safe to share outside the process.
+//!
+//! Only values explicitly marked as public are returned by [`Context::public_values`]
+//! and can be included in error responses returned to clients.
+//!
+//! ## Deadlines
+//!
+//! Contexts can carry a deadline, attached with [`ContextBuilder::with_deadline`],
+//! by which work scoped to them should complete.
+//! Derived contexts keep the earliest of their own and their parent's deadlines
+//! so operations can check [`Context::time_remaining`] to enforce timeouts consistently.
+//!
+//! ## Cancellation
+//!
+//! With the `context-cancellation` feature contexts can carry a [`CancellationToken`]
+//! attached with [`ContextBuilder::with_cancellation`] and returned by [`Context::cancellation`].
+//! Contexts derived from one with a token carry a child token, so cancelling a context
+//! also cancels all contexts derived from it (but not its parents).
+//!
+//! Long running operations can wait on [`CancellationToken::cancelled`] alongside their work
+//! to stop early once the scope they run in is no longer interested in their result.
+//!
+//! [`CancellationToken`]: tokio_util::sync::CancellationToken
+//! [`CancellationToken::cancelled`]: tokio_util::sync::CancellationToken::cancelled
 use std::any::Any;
 use std::any::TypeId;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
 
 use slog::Logger;
 use slog::OwnedKV;
 use slog::SendSyncRefUnwindSafeKV;
+#[cfg(feature = "context-cancellation")]
+use tokio_util::sync::CancellationToken;
 
 #[cfg(feature = "actix-web")]
 mod actix;
@@ -28,7 +62,10 @@ mod actix;
 mod otel;
 
 #[cfg(feature = "actix-web")]
-pub use {self::actix::ActixMiddleware, self::actix::ActixTransform, self::actix::ContextConfig};
+pub use {
+    self::actix::ActixMiddleware, self::actix::ActixTransform, self::actix::ContextConfig,
+    self::actix::RequestId,
+};
 
 /// The [`Context`] is a general purpose container to carry scoped values around.
 ///
@@ -38,16 +75,52 @@ pub struct Context {
     /// Logger with contextual attributes attached to it.
     pub logger: Logger,
 
+    /// Token to cooperatively cancel work scoped to the context.
+    #[cfg(feature = "context-cancellation")]
+    cancellation: Option<CancellationToken>,
+
+    /// Instant by which work scoped to the context should complete.
+    deadline: Option<Instant>,
+
     /// Store arbitrary data attached to the context.
     entries: HashMap<TypeId, Arc<dyn Any + Sync + Send>>,
+
+    /// Store arbitrary data attached to the context under a key.
+    keyed: HashMap<(TypeId, &'static str), Arc<dyn Any + Sync + Send>>,
+
+    /// Log values marked as safe to share outside the process.
+    public: BTreeMap<String, String>,
 }
 
 impl Context {
+    /// Token to cooperatively cancel work scoped to the context, if one is attached.
+    ///
+    /// Tokens are attached with [`ContextBuilder::with_cancellation`].
+    #[cfg(feature = "context-cancellation")]
+    pub fn cancellation(&self) -> Option<&CancellationToken> {
+        self.cancellation.as_ref()
+    }
+
+    /// Instant by which work scoped to the context should complete, if one is set.
+    ///
+    /// This is the earliest deadline set on this context or any context it was derived from.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
     /// Derive a new [`Context`] by making changes to the current one.
     pub fn derive(&self) -> ContextBuilder {
         ContextBuilder {
+            #[cfg(feature = "context-cancellation")]
+            cancellation: self
+                .cancellation
+                .as_ref()
+                .map(CancellationToken::child_token),
+            deadline: self.deadline,
             entries: self.entries.clone(),
+            keyed: self.keyed.clone(),
             logger: self.logger.clone(),
+            public: self.public.clone(),
         }
     }
 
@@ -82,6 +155,41 @@ impl Context {
             .and_then(|entry| entry.downcast_ref())
     }
 
+    /// Retrieve a custom value by type and key from the context.
+    ///
+    /// Keyed values are separate from values attached by type only, which allows
+    /// the context to hold several values of the same type.
+    pub fn get_keyed<T>(&self, key: &'static str) -> Option<&T>
+    where
+        T: 'static + Send + Sync,
+    {
+        self.keyed
+            .get(&(TypeId::of::<T>(), key))
+            .and_then(|entry| entry.downcast_ref())
+    }
+
+    /// Return a [`Context`] guaranteed to hold a value of type `T`.
+    ///
+    /// If the current context already holds a `T` it is returned unchanged.
+    /// Otherwise the value is computed with the given callback and attached to a derived context.
+    /// Contexts derived from the returned one share the same value, which makes this useful
+    /// for lazily creating scoped resources such as caches.
+    pub fn get_or_insert_with<T, F>(&self, callback: F) -> Context
+    where
+        F: FnOnce() -> T,
+        T: 'static + Send + Sync,
+    {
+        if self.get::<T>().is_some() {
+            return self.clone();
+        }
+        self.derive().value(callback()).build()
+    }
+
+    /// Log values marked as safe to share outside the process, such as in error responses.
+    pub fn public_values(&self) -> &BTreeMap<String, String> {
+        &self.public
+    }
+
     /// Retrieve a custom value by type from the context.
     ///
     /// ## Panics
@@ -94,11 +202,37 @@ impl Context {
         self.expect::<T>("context does not hold a value for the required type")
     }
 
+    /// Time left until the context [deadline](Context::deadline), if one is set.
+    ///
+    /// Returns a zero duration once the deadline has passed.
+    pub fn time_remaining(&self) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Retrieve a custom value by type from the context.
+    ///
+    /// Unlike [`Context::require`] this method returns a [`ContextMissing`] error
+    /// if the specified type does not have a value.
+    pub fn try_require<T>(&self) -> Result<&T, ContextMissing>
+    where
+        T: 'static + Send + Sync,
+    {
+        self.get::<T>().ok_or(ContextMissing {
+            type_name: std::any::type_name::<T>(),
+        })
+    }
+
     /// Initialise a new root context with no values attached.
     pub fn root(logger: Logger) -> ContextBuilder {
         ContextBuilder {
+            #[cfg(feature = "context-cancellation")]
+            cancellation: None,
+            deadline: None,
             entries: Default::default(),
+            keyed: Default::default(),
             logger,
+            public: Default::default(),
         }
     }
 }
@@ -110,15 +244,33 @@ impl Context {
         let logger = Logger::root(slog::Discard, slog::o!());
         Context {
             logger,
+            #[cfg(feature = "context-cancellation")]
+            cancellation: None,
+            deadline: None,
             entries: Default::default(),
+            keyed: Default::default(),
+            public: Default::default(),
         }
     }
 }
 
+/// The context does not hold a value for a required type.
+#[derive(Debug, thiserror::Error)]
+#[error("context does not hold a value for the required type {type_name}")]
+pub struct ContextMissing {
+    /// Name of the type without a value in the context.
+    pub type_name: &'static str,
+}
+
 /// A builder for root and derived contexts.
 pub struct ContextBuilder {
+    #[cfg(feature = "context-cancellation")]
+    cancellation: Option<CancellationToken>,
+    deadline: Option<Instant>,
     entries: HashMap<TypeId, Arc<dyn Any + Sync + Send>>,
+    keyed: HashMap<(TypeId, &'static str), Arc<dyn Any + Sync + Send>>,
     logger: Logger,
+    public: BTreeMap<String, String>,
 }
 
 impl ContextBuilder {
@@ -126,10 +278,28 @@ impl ContextBuilder {
     pub fn build(self) -> Context {
         Context {
             logger: self.logger,
+            #[cfg(feature = "context-cancellation")]
+            cancellation: self.cancellation,
+            deadline: self.deadline,
             entries: self.entries,
+            keyed: self.keyed,
+            public: self.public,
         }
     }
 
+    /// Set an instant by which work scoped to the context should complete.
+    ///
+    /// If the context already has an earlier deadline, for example inherited from
+    /// its parent, the earlier deadline is kept.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        let deadline = match self.deadline {
+            Some(current) if current < deadline => current,
+            _ => deadline,
+        };
+        self.deadline = Some(deadline);
+        self
+    }
+
     /// Update the [`Context`] logger to attach new log key/pair values.
     pub fn log_values<T>(mut self, entries: OwnedKV<T>) -> Self
     where
@@ -139,6 +309,46 @@ impl ContextBuilder {
         self
     }
 
+    /// Attach a log key/value pair to the [`Context`] and mark it as public.
+    ///
+    /// Public values are safe to share outside the process, for example in error responses.
+    /// Only attach values that do not expose sensitive information.
+    pub fn public_log_value<V>(mut self, key: &'static str, value: V) -> Self
+    where
+        V: Into<String>,
+    {
+        let value = value.into();
+        self.logger = self.logger.new(slog::o!(key => value.clone()));
+        self.public.insert(key.to_string(), value);
+        self
+    }
+
+    /// Remove a value, by type, from the context.
+    ///
+    /// Useful to derive narrower contexts that should not have access to some values,
+    /// such as request credentials that should not reach untrusted code.
+    pub fn remove<T>(mut self) -> Self
+    where
+        T: 'static + Send + Sync,
+    {
+        self.entries.remove(&TypeId::of::<T>());
+        self
+    }
+
+    /// Attach a new [`CancellationToken`] to the context.
+    ///
+    /// If the context already carries a token the new token is a child of it,
+    /// so it is cancelled along with its parent but can also be cancelled on its own.
+    #[cfg(feature = "context-cancellation")]
+    pub fn with_cancellation(mut self) -> Self {
+        let token = match self.cancellation.take() {
+            None => CancellationToken::new(),
+            Some(parent) => parent.child_token(),
+        };
+        self.cancellation = Some(token);
+        self
+    }
+
     /// Attach a value to the context.
     pub fn value<T>(mut self, value: T) -> Self
     where
@@ -147,15 +357,101 @@ impl ContextBuilder {
         self.entries.insert(TypeId::of::<T>(), Arc::new(value));
         self
     }
+
+    /// Attach a value to the context under the given key.
+    ///
+    /// Values are identified by both key and type so the same key can be used with different
+    /// types and several values of the same type can be attached with different keys.
+    /// Retrieve keyed values with [`Context::get_keyed`].
+    pub fn value_keyed<T>(mut self, key: &'static str, value: T) -> Self
+    where
+        T: 'static + Send + Sync,
+    {
+        self.keyed.insert((TypeId::of::<T>(), key), Arc::new(value));
+        self
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::any::TypeId;
     use std::sync::Arc;
+    use std::time::Duration;
+    use std::time::Instant;
 
     use super::Context;
 
+    #[cfg(feature = "context-cancellation")]
+    #[test]
+    fn cancellation_propagates_to_children() {
+        let root = Context::fixture();
+        assert!(root.cancellation().is_none());
+        assert!(root.derive().build().cancellation().is_none());
+
+        let parent = root.derive().with_cancellation().build();
+        let child = parent.derive().build();
+        let sibling = parent.derive().with_cancellation().build();
+        sibling.cancellation().unwrap().cancel();
+        assert!(!parent.cancellation().unwrap().is_cancelled());
+        assert!(!child.cancellation().unwrap().is_cancelled());
+
+        parent.cancellation().unwrap().cancel();
+        assert!(child.cancellation().unwrap().is_cancelled());
+    }
+
+    #[cfg(feature = "context-cancellation")]
+    #[tokio::test]
+    async fn cancellation_aborts_work() {
+        let context = Context::fixture().derive().with_cancellation().build();
+        let child = context.derive().build();
+        let work = tokio::spawn(async move {
+            tokio::select! {
+                _ = child.cancellation().unwrap().cancelled() => "cancelled",
+                _ = tokio::time::sleep(std::time::Duration::from_secs(60)) => "completed",
+            }
+        });
+        context.cancellation().unwrap().cancel();
+        assert_eq!(work.await.unwrap(), "cancelled");
+    }
+
+    #[test]
+    fn deadline_keeps_earliest() {
+        let now = Instant::now();
+        let root = Context::fixture();
+        assert_eq!(root.deadline(), None);
+        assert_eq!(root.time_remaining(), None);
+
+        let parent = root
+            .derive()
+            .with_deadline(now + Duration::from_secs(10))
+            .build();
+        let tighter = parent
+            .derive()
+            .with_deadline(now + Duration::from_secs(5))
+            .build();
+        let looser = parent
+            .derive()
+            .with_deadline(now + Duration::from_secs(60))
+            .build();
+        assert_eq!(tighter.deadline(), Some(now + Duration::from_secs(5)));
+        assert_eq!(looser.deadline(), Some(now + Duration::from_secs(10)));
+        assert_eq!(looser.derive().build().deadline(), parent.deadline());
+
+        let remaining = looser.time_remaining().unwrap();
+        assert!(remaining <= Duration::from_secs(10));
+        assert!(remaining > Duration::from_secs(5));
+    }
+
+    #[test]
+    fn deadline_passed() {
+        let context = Context::fixture()
+            .derive()
+            .with_deadline(Instant::now())
+            .build();
+        std::thread::sleep(Duration::from_millis(1));
+        assert_eq!(context.time_remaining(), Some(Duration::ZERO));
+    }
+
     #[test]
     fn derive_log_attributes() {
         let root = Context::fixture();
@@ -180,6 +476,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn derive_public_log_values() {
+        let parent = Context::fixture()
+            .derive()
+            .public_log_value("request_id", "abc")
+            .build();
+        let context = parent
+            .derive()
+            .log_values(slog::o!("secret" => "value"))
+            .public_log_value("cluster_id", "cluster")
+            .build();
+        assert_eq!(
+            format!("{:?}", context.logger.list()),
+            "(cluster_id, secret, request_id)"
+        );
+        let public: Vec<(&str, &str)> = context
+            .public_values()
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        assert_eq!(public, [("cluster_id", "cluster"), ("request_id", "abc")]);
+    }
+
     #[test]
     fn extra_expect_with() {
         let mut context = Context::fixture();
@@ -210,6 +529,76 @@ mod tests {
         assert_eq!(value, None);
     }
 
+    #[test]
+    fn extra_get_or_insert_with_with() {
+        let mut parent = Context::fixture();
+        parent.entries.insert(TypeId::of::<u64>(), Arc::new(42u64));
+        let context =
+            parent.get_or_insert_with::<u64, _>(|| panic!("value should not be computed"));
+        assert_eq!(context.get::<u64>(), Some(&42));
+    }
+
+    #[test]
+    fn extra_get_or_insert_with_without() {
+        let parent = Context::fixture();
+        let context = parent.get_or_insert_with(|| 42u64);
+        assert_eq!(context.get::<u64>(), Some(&42));
+        assert_eq!(parent.get::<u64>(), None);
+
+        // Derived contexts share the value attached to their parent.
+        let child = context.derive().build();
+        let child = child.get_or_insert_with(|| 24u64);
+        let shared = std::ptr::eq(context.require::<u64>(), child.require::<u64>());
+        assert!(shared);
+    }
+
+    #[test]
+    fn extra_get_keyed_with() {
+        let context = Context::fixture()
+            .derive()
+            .value(String::from("unkeyed"))
+            .value_keyed("cluster_id", String::from("cluster"))
+            .value_keyed("node_id", String::from("node"))
+            .value_keyed("node_id", 42u64)
+            .build();
+        let cluster = context.get_keyed::<String>("cluster_id");
+        assert_eq!(cluster.map(String::as_str), Some("cluster"));
+        let node = context.get_keyed::<String>("node_id");
+        assert_eq!(node.map(String::as_str), Some("node"));
+        assert_eq!(context.get_keyed::<u64>("node_id"), Some(&42));
+        assert_eq!(context.get::<String>().map(String::as_str), Some("unkeyed"));
+    }
+
+    #[test]
+    fn extra_get_keyed_without() {
+        let context = Context::fixture()
+            .derive()
+            .value(String::from("unkeyed"))
+            .value_keyed("node_id", 42u64)
+            .build();
+        assert_eq!(context.get_keyed::<String>("node_id"), None);
+        assert_eq!(context.get_keyed::<String>("cluster_id"), None);
+        assert_eq!(context.get::<u64>(), None);
+    }
+
+    #[test]
+    fn extra_remove_with() {
+        let mut parent = Context::fixture();
+        parent.entries.insert(TypeId::of::<u64>(), Arc::new(42u64));
+        parent.entries.insert(TypeId::of::<u32>(), Arc::new(24u32));
+        let context = parent.derive().remove::<u64>().build();
+        assert_eq!(context.get::<u64>(), None);
+        assert_eq!(context.get::<u32>(), Some(&24));
+        assert_eq!(parent.get::<u64>(), Some(&42));
+    }
+
+    #[test]
+    fn extra_remove_without() {
+        let context = Context::fixture().derive().remove::<u64>().build();
+        let value = context.get::<u64>();
+        assert_eq!(value, None);
+    }
+
     #[test]
     fn extra_require_with() {
         let mut context = Context::fixture();
@@ -218,6 +607,25 @@ mod tests {
         assert_eq!(value, &42);
     }
 
+    #[test]
+    fn extra_try_require_with() {
+        let mut context = Context::fixture();
+        context.entries.insert(TypeId::of::<u64>(), Arc::new(42u64));
+        let value = context.try_require::<u64>().unwrap();
+        assert_eq!(value, &42);
+    }
+
+    #[test]
+    fn extra_try_require_without() {
+        let context = Context::fixture();
+        let error = context.try_require::<u64>().unwrap_err();
+        assert_eq!(error.type_name, "u64");
+        assert_eq!(
+            error.to_string(),
+            "context does not hold a value for the required type u64",
+        );
+    }
+
     #[test]
     #[should_panic(expected = "context does not hold a value for the required type")]
     fn extra_require_without() {
@@ -0,0 +1,71 @@
+//! Checks that the SDK feature list is consistent with the cargo manifest.
+use super::enabled_features;
+use super::FEATURES;
+
+/// Extract the names of features declared in the crate manifest.
+fn manifest_features() -> Vec<&'static str> {
+    let manifest = include_str!("../Cargo.toml");
+    manifest
+        .lines()
+        .skip_while(|line| *line != "[features]")
+        .skip(1)
+        .take_while(|line| !line.starts_with('['))
+        .filter(|line| line.starts_with(|c: char| c.is_ascii_lowercase()))
+        .filter_map(|line| line.split('=').next())
+        .map(str::trim)
+        .collect()
+}
+
+#[test]
+fn known_features_match_manifest() {
+    let known: Vec<&str> = FEATURES.iter().map(|(name, _)| *name).collect();
+    assert_eq!(known, manifest_features());
+}
+
+#[test]
+fn enabled_features_are_known() {
+    for feature in enabled_features() {
+        assert!(manifest_features().contains(feature), "{}", feature);
+    }
+}
+
+#[test]
+#[cfg(all(feature = "agent-framework", feature = "context"))]
+fn enabled_features_with_agent_framework() {
+    let features = enabled_features();
+    assert!(features.contains(&"agent-framework"));
+    assert!(features.contains(&"context"));
+}
+
+#[test]
+#[cfg(not(feature = "platform-models"))]
+fn enabled_features_without_platform_models() {
+    let features = enabled_features();
+    assert!(!features.contains(&"platform-models"));
+    assert!(!features.contains(&"platform"));
+}
+
+#[test]
+#[cfg(all(
+    feature = "agent",
+    feature = "context",
+    feature = "context-cancellation",
+    feature = "platform",
+    feature = "replicore",
+    feature = "runtime",
+    feature = "runtime-shutdown_metrics",
+    feature = "runtime-telemetry_jaeger",
+    feature = "test-fixture",
+    feature = "utils-actix_error",
+    feature = "utils-actix_metrics",
+    feature = "utils-config",
+    feature = "utils-encoding",
+    feature = "utils-error_json",
+    feature = "utils-error_slog",
+    feature = "utils-metrics",
+    feature = "utils-trace",
+    feature = "utils-validate",
+))]
+fn enabled_features_all() {
+    assert_eq!(enabled_features(), manifest_features());
+}
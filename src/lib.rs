@@ -40,6 +40,8 @@
 //! The `context` feature enables a general purpose container to carry scoped values around.
 //! Different frameworks in the SDK use contexts to carry request specific information.
 //!
+//! The `context-cancellation` feature adds cooperative cancellation tokens to contexts.
+//!
 //! ## Platforms
 //!
 //! The following features are available for the platforms area:
@@ -61,7 +63,12 @@
 //! - `runtime-actix_builder`: Enable Actix Web server runtime configuration utilities.
 //! - `runtime-shutdown`: Enable tools to manage process shutdown on error or at user's request.
 //! - `runtime-shutdown_acitx`: Enable process shutdown extension to watch for `actix_web` servers.
+//! - `runtime-shutdown_metrics`: Enable process shutdown extension to record Prometheus metrics.
 //! - `runtime-telemetry`: Enable utilities to initialise runtime telemetry of the process.
+//! - `runtime-telemetry_jaeger`: Enable export of telemetry data to Jaeger agents.
+//! - `runtime-telemetry_otlp_http`: Enable export of telemetry data using OTLP over HTTP.
+//! - `runtime-telemetry_otlp_tls`: Enable TLS connections for telemetry exported using OTLP over gRPC.
+//! - `runtime-telemetry_pushgateway`: Enable pushes of Prometheus metrics to a Pushgateway.
 //! - `runtime-tokio_conf`: Enable tokio runtime configuration utilities.
 //!
 //! ## Testing
@@ -74,11 +81,15 @@
 //!
 //! - `utils-actix_error`: An `actix_web` error type that works with `anyhow::Error`.
 //! - `utils-actix_metrics`: Collect metrics about processed requests and an exporter all metrics.
+//! - `utils-config`: Human friendly types for configuration options, such as durations and sizes.
 //! - `utils-encoding`: Utilities to encode and decode advanced types into storable data.
-//! - `utils-error_json`: Utility function to encode an error into a JSON object.
+//! - `utils-error_json`: Utilities to encode errors into JSON objects and decode them back.
 //! - `utils-error_slog`: Standard way to log errors as slog key/value pairs.
 //! - `utils-metrics`: Utilities to introspect applications and libraries with metrics more easley.
 //! - `utils-trace`: Utilities to introspect applications and libraries with traces more easley.
+//! - `utils-validate`: Trait and error types to validate models beyond what their types enforce.
+//!
+//! The features the SDK was compiled with can be inspected at runtime with [`enabled_features`].
 //!
 //! # The experimental crate
 //!
@@ -93,6 +104,7 @@
 //!
 //! [Rust Lang]: https://www.rust-lang.org/
 #![deny(missing_docs)]
+use std::sync::OnceLock;
 
 #[cfg(any(feature = "agent-framework", feature = "agent-models"))]
 pub mod agent;
@@ -114,5 +126,92 @@ pub mod platform;
 ))]
 pub mod runtime;
 
-#[cfg(any(feature = "utils-actix_error", feature = "utils-error_slog"))]
+#[cfg(any(
+    feature = "utils-actix_error",
+    feature = "utils-config",
+    feature = "utils-error_slog",
+    feature = "utils-validate",
+))]
 pub mod utils;
+
+#[cfg(test)]
+mod features;
+#[cfg(all(test, feature = "slog"))]
+mod testing;
+
+/// All cargo features defined by the SDK and whether they are enabled in this build.
+const FEATURES: [(&str, bool); 32] = [
+    ("agent", cfg!(feature = "agent")),
+    ("agent-framework", cfg!(feature = "agent-framework")),
+    ("agent-models", cfg!(feature = "agent-models")),
+    ("context", cfg!(feature = "context")),
+    (
+        "context-cancellation",
+        cfg!(feature = "context-cancellation"),
+    ),
+    ("platform", cfg!(feature = "platform")),
+    ("platform-framework", cfg!(feature = "platform-framework")),
+    (
+        "platform-framework_actix",
+        cfg!(feature = "platform-framework_actix"),
+    ),
+    ("platform-models", cfg!(feature = "platform-models")),
+    ("replicore", cfg!(feature = "replicore")),
+    ("replicore-models", cfg!(feature = "replicore-models")),
+    ("runtime", cfg!(feature = "runtime")),
+    (
+        "runtime-actix_builder",
+        cfg!(feature = "runtime-actix_builder"),
+    ),
+    ("runtime-shutdown", cfg!(feature = "runtime-shutdown")),
+    (
+        "runtime-shutdown_actix",
+        cfg!(feature = "runtime-shutdown_actix"),
+    ),
+    (
+        "runtime-shutdown_metrics",
+        cfg!(feature = "runtime-shutdown_metrics"),
+    ),
+    ("runtime-telemetry", cfg!(feature = "runtime-telemetry")),
+    (
+        "runtime-telemetry_jaeger",
+        cfg!(feature = "runtime-telemetry_jaeger"),
+    ),
+    (
+        "runtime-telemetry_otlp_http",
+        cfg!(feature = "runtime-telemetry_otlp_http"),
+    ),
+    (
+        "runtime-telemetry_otlp_tls",
+        cfg!(feature = "runtime-telemetry_otlp_tls"),
+    ),
+    (
+        "runtime-telemetry_pushgateway",
+        cfg!(feature = "runtime-telemetry_pushgateway"),
+    ),
+    ("runtime-tokio_conf", cfg!(feature = "runtime-tokio_conf")),
+    ("test-fixture", cfg!(feature = "test-fixture")),
+    ("utils-actix_error", cfg!(feature = "utils-actix_error")),
+    ("utils-actix_metrics", cfg!(feature = "utils-actix_metrics")),
+    ("utils-config", cfg!(feature = "utils-config")),
+    ("utils-encoding", cfg!(feature = "utils-encoding")),
+    ("utils-error_json", cfg!(feature = "utils-error_json")),
+    ("utils-error_slog", cfg!(feature = "utils-error_slog")),
+    ("utils-metrics", cfg!(feature = "utils-metrics")),
+    ("utils-trace", cfg!(feature = "utils-trace")),
+    ("utils-validate", cfg!(feature = "utils-validate")),
+];
+
+/// List the cargo features the SDK was compiled with.
+///
+/// Useful to report build information for support or to diagnose why an SDK area is missing.
+pub fn enabled_features() -> &'static [&'static str] {
+    static ENABLED: OnceLock<Vec<&'static str>> = OnceLock::new();
+    ENABLED.get_or_init(|| {
+        FEATURES
+            .iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|(name, _)| *name)
+            .collect()
+    })
+}
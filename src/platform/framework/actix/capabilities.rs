@@ -0,0 +1,17 @@
+//! [`actix_web`] handler for platform capabilities requests.
+use actix_web::web::Data;
+use actix_web::FromRequest;
+use actix_web::HttpResponse;
+use actix_web::Responder;
+
+use crate::platform::framework::capabilities as platform_capabilities;
+use crate::platform::framework::IPlatform;
+
+/// Return the optional features advertised by the [`IPlatform`].
+pub async fn capabilities<P>(platform: Data<P>) -> impl Responder
+where
+    P: IPlatform,
+    P::Context: FromRequest,
+{
+    HttpResponse::Ok().json(platform_capabilities(platform.as_ref()))
+}
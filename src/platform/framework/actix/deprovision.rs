@@ -8,6 +8,7 @@ use actix_web::Responder;
 use crate::platform::framework::IPlatform;
 use crate::platform::models::NodeDeprovisionRequest;
 use crate::utils::actix::error::Result;
+use crate::utils::validate::Validate;
 
 /// Decode a node deprovision request and calls the [`IPlatform`] implementation.
 pub async fn deprovision<P>(
@@ -20,6 +21,7 @@ where
     P::Context: FromRequest,
 {
     let payload = payload.into_inner();
+    payload.validate()?;
     platform.deprovision(&context, payload).await?;
     Ok(HttpResponse::NoContent())
 }
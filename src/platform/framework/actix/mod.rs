@@ -9,6 +9,7 @@ use super::IPlatform;
 use crate::platform::models::ClusterDefinitionNodeGroup;
 use crate::platform::models::NodeProvisionRequest;
 
+mod capabilities;
 mod deprovision;
 mod discover;
 mod provision;
@@ -52,6 +53,11 @@ where
         let scope = actix_web::web::scope("")
             .app_data(Data::new(self.logger))
             .app_data(Data::new(self.platform))
+            .service(
+                actix_web::web::resource("/capabilities")
+                    .guard(actix_web::guard::Get())
+                    .to(capabilities::capabilities::<P>),
+            )
             .service(
                 actix_web::web::resource("/deprovision")
                     .guard(actix_web::guard::Post())
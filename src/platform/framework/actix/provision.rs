@@ -8,6 +8,7 @@ use actix_web::Responder;
 use crate::platform::framework::IPlatform;
 use crate::platform::models::NodeProvisionRequest;
 use crate::utils::actix::error::Result;
+use crate::utils::validate::Validate;
 
 /// Encode and decode API request and response for [`IPlatform`] discovery implementation.
 pub async fn provision<P>(
@@ -20,6 +21,8 @@ where
     P::Context: FromRequest,
 {
     let payload = payload.into_inner();
+    payload.validate()?;
     let response = platform.provision(&context, payload).await?;
+    response.validate().map_err(anyhow::Error::from)?;
     Ok(HttpResponse::Ok().json(response))
 }
@@ -10,12 +10,15 @@ use anyhow::Result;
 
 use crate::platform::framework::DefaultContext;
 use crate::platform::framework::IPlatform;
+use crate::platform::framework::IPlatformPower;
 use crate::platform::models::ClusterDiscovery;
 use crate::platform::models::ClusterDiscoveryNode;
 use crate::platform::models::ClusterDiscoveryResponse;
 use crate::platform::models::NodeDeprovisionRequest;
+use crate::platform::models::NodePowerRequest;
 use crate::platform::models::NodeProvisionRequest;
 use crate::platform::models::NodeProvisionResponse;
+use crate::platform::models::PlatformCapabilities;
 
 use super::into_actix_service;
 
@@ -84,6 +87,83 @@ impl IPlatform for FakePlatform {
     }
 }
 
+/// Platform implementing power operations.
+struct PowerPlatform(FakePlatform);
+
+#[async_trait::async_trait]
+impl IPlatform for PowerPlatform {
+    type Context = DefaultContext;
+
+    fn as_power(&self) -> Option<&dyn IPlatformPower<Context = Self::Context>> {
+        Some(self)
+    }
+
+    async fn deprovision(
+        &self,
+        context: &Self::Context,
+        request: NodeDeprovisionRequest,
+    ) -> Result<()> {
+        self.0.deprovision(context, request).await
+    }
+
+    async fn discover(&self, context: &Self::Context) -> Result<ClusterDiscoveryResponse> {
+        self.0.discover(context).await
+    }
+
+    async fn provision(
+        &self,
+        context: &Self::Context,
+        request: NodeProvisionRequest,
+    ) -> Result<NodeProvisionResponse> {
+        self.0.provision(context, request).await
+    }
+}
+
+#[async_trait::async_trait]
+impl IPlatformPower for PowerPlatform {
+    async fn power_off(&self, _: &Self::Context, _request: NodePowerRequest) -> Result<()> {
+        Ok(())
+    }
+
+    async fn power_on(&self, _: &Self::Context, _request: NodePowerRequest) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn capabilities_default() {
+    let logger = slog::Logger::root(slog::Discard {}, slog::o!());
+    let platform = into_actix_service(FakePlatform::new(), logger);
+    let app = actix_web::App::new().service(platform);
+
+    let req = TestRequest::get().uri("/capabilities").to_request();
+    let app = init_service(app).await;
+    let res = call_service(&app, req).await;
+    assert_eq!(res.status(), actix_web::http::StatusCode::OK);
+
+    let res: PlatformCapabilities = read_body_json(res).await;
+    assert_eq!(res, PlatformCapabilities::default());
+}
+
+#[tokio::test]
+async fn capabilities_power() {
+    let logger = slog::Logger::root(slog::Discard {}, slog::o!());
+    let platform = into_actix_service(PowerPlatform(FakePlatform::new()), logger);
+    let app = actix_web::App::new().service(platform);
+
+    let req = TestRequest::get().uri("/capabilities").to_request();
+    let app = init_service(app).await;
+    let res = call_service(&app, req).await;
+    assert_eq!(res.status(), actix_web::http::StatusCode::OK);
+
+    let res: PlatformCapabilities = read_body_json(res).await;
+    let expected = PlatformCapabilities {
+        power: true,
+        ..Default::default()
+    };
+    assert_eq!(res, expected);
+}
+
 #[tokio::test]
 async fn deprovision() {
     let platform = FakePlatform::new();
@@ -1,10 +1,13 @@
 //! Tools to implement Replicante Platform servers.
 use anyhow::Result;
 
+use crate::platform::models::ClusterDiscoveryPage;
 use crate::platform::models::ClusterDiscoveryResponse;
 use crate::platform::models::NodeDeprovisionRequest;
+use crate::platform::models::NodePowerRequest;
 use crate::platform::models::NodeProvisionRequest;
 use crate::platform::models::NodeProvisionResponse;
+use crate::platform::models::PlatformCapabilities;
 
 mod context;
 pub use self::context::DefaultContext;
@@ -30,6 +33,27 @@ pub trait IPlatform: 'static {
     /// Additional context passed to requests.
     type Context;
 
+    /// Access the [`IPlatformDryRun`] implementation of the platform, if any.
+    ///
+    /// Platforms implementing [`IPlatformDryRun`] override this method to return `Some(self)`.
+    fn as_dry_run(&self) -> Option<&dyn IPlatformDryRun<Context = Self::Context>> {
+        None
+    }
+
+    /// Access the [`IPlatformPagination`] implementation of the platform, if any.
+    ///
+    /// Platforms implementing [`IPlatformPagination`] override this method to return `Some(self)`.
+    fn as_pagination(&self) -> Option<&dyn IPlatformPagination<Context = Self::Context>> {
+        None
+    }
+
+    /// Access the [`IPlatformPower`] implementation of the platform, if any.
+    ///
+    /// Platforms implementing [`IPlatformPower`] override this method to return `Some(self)`.
+    fn as_power(&self) -> Option<&dyn IPlatformPower<Context = Self::Context>> {
+        None
+    }
+
     /// Deprovision (terminate) a node in a cluster.
     async fn deprovision(
         &self,
@@ -47,3 +71,52 @@ pub trait IPlatform: 'static {
         request: NodeProvisionRequest,
     ) -> Result<NodeProvisionResponse>;
 }
+
+/// Optional interface for Platforms that can validate requests without acting on them.
+#[async_trait::async_trait]
+pub trait IPlatformDryRun: IPlatform {
+    /// Validate a provision request without creating any node.
+    async fn provision_dry_run(
+        &self,
+        context: &Self::Context,
+        request: NodeProvisionRequest,
+    ) -> Result<()>;
+}
+
+/// Optional interface for Platforms that can list clusters in pages.
+#[async_trait::async_trait]
+pub trait IPlatformPagination: IPlatform {
+    /// List a page of clusters on the platform.
+    ///
+    /// The first page is requested with no `page` token.
+    async fn discover_page(
+        &self,
+        context: &Self::Context,
+        page: Option<String>,
+    ) -> Result<ClusterDiscoveryPage>;
+}
+
+/// Optional interface for Platforms that can power nodes on and off without deprovisioning them.
+#[async_trait::async_trait]
+pub trait IPlatformPower: IPlatform {
+    /// Power off a node in a cluster.
+    async fn power_off(&self, context: &Self::Context, request: NodePowerRequest) -> Result<()>;
+
+    /// Power on a node in a cluster.
+    async fn power_on(&self, context: &Self::Context, request: NodePowerRequest) -> Result<()>;
+}
+
+/// Optional features supported by an [`IPlatform`].
+///
+/// Capabilities are derived from the optional interfaces the platform exposes
+/// so they can't drift from what is actually implemented.
+pub fn capabilities<P>(platform: &P) -> PlatformCapabilities
+where
+    P: IPlatform,
+{
+    PlatformCapabilities {
+        dry_run: platform.as_dry_run().is_some(),
+        pagination: platform.as_pagination().is_some(),
+        power: platform.as_power().is_some(),
+    }
+}
@@ -1,11 +1,19 @@
 //! Data structures for Platform related entities.
 use std::collections::HashMap;
+use std::pin::Pin;
 
+use anyhow::Context;
+use anyhow::Result;
+use futures::Stream;
+use futures::StreamExt;
 use serde::Deserialize;
 use serde::Serialize;
 use serde_json::Map;
 use serde_json::Value;
 
+use crate::utils::validate::Validate;
+use crate::utils::validate::ValidationErrors;
+
 /// Declarative definition of a cluster and its nodes.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct ClusterDefinition {
@@ -30,6 +38,21 @@ pub struct ClusterDefinition {
     pub nodes: HashMap<String, ClusterDefinitionNodeGroup>,
 }
 
+impl Validate for ClusterDefinition {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        errors.require_not_empty("cluster_id", &self.cluster_id);
+        let mut groups: Vec<_> = self.nodes.iter().collect();
+        groups.sort_by_key(|(id, _)| *id);
+        for (id, group) in groups {
+            errors.nested(&format!("nodes.{}", id), group);
+        }
+        errors.require_not_empty("store", &self.store);
+        errors.require_not_empty("store_version", &self.store_version);
+        errors.into_result()
+    }
+}
+
 /// Declarative definition of a cluster's node group.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct ClusterDefinitionNodeGroup {
@@ -54,6 +77,17 @@ pub struct ClusterDefinitionNodeGroup {
     pub store_version: Option<String>,
 }
 
+impl Validate for ClusterDefinitionNodeGroup {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        errors.require_not_empty("node_class", &self.node_class);
+        if let Some(store_version) = &self.store_version {
+            errors.require_not_empty("store_version", store_version);
+        }
+        errors.into_result()
+    }
+}
+
 /// Information about a cluster and all existing nodes within.
 #[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
 pub struct ClusterDiscovery {
@@ -64,6 +98,17 @@ pub struct ClusterDiscovery {
     pub nodes: Vec<ClusterDiscoveryNode>,
 }
 
+/// A page of clusters returned by Platforms that support paginated discovery.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct ClusterDiscoveryPage {
+    /// List of clusters in this page.
+    pub clusters: Vec<ClusterDiscovery>,
+
+    /// Opaque token to request the next page, if any.
+    #[serde(default)]
+    pub next_page: Option<String>,
+}
+
 /// API Response schema for a Platform node provision action.
 #[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
 pub struct ClusterDiscoveryResponse {
@@ -71,6 +116,84 @@ pub struct ClusterDiscoveryResponse {
     pub clusters: Vec<ClusterDiscovery>,
 }
 
+impl ClusterDiscoveryResponse {
+    /// Incrementally decode a newline delimited JSON (NDJSON) stream of [`ClusterDiscovery`]s.
+    ///
+    /// Each line in the response body is a single [`ClusterDiscovery`] record.
+    /// Records are decoded one at a time as body chunks arrive so clients do not need
+    /// to buffer the full list of clusters in memory.
+    /// Blank lines are ignored and the last record does not need to end with a newline.
+    ///
+    /// The stream ends after the first error returned by the body.
+    pub fn stream_ndjson<S, B, E>(body: S) -> impl Stream<Item = Result<ClusterDiscovery>>
+    where
+        S: Stream<Item = std::result::Result<B, E>>,
+        B: AsRef<[u8]>,
+        E: Into<anyhow::Error>,
+    {
+        let state = DiscoveryStream {
+            body: Box::pin(body),
+            buffer: Vec::new(),
+            done: false,
+        };
+        futures::stream::unfold(state, |mut state| async move {
+            let record = state.next_record().await?;
+            Some((record, state))
+        })
+    }
+}
+
+/// Track decoding progress of an NDJSON stream of [`ClusterDiscovery`] records.
+struct DiscoveryStream<S> {
+    body: Pin<Box<S>>,
+    buffer: Vec<u8>,
+    done: bool,
+}
+
+impl<S, B, E> DiscoveryStream<S>
+where
+    S: Stream<Item = std::result::Result<B, E>>,
+    B: AsRef<[u8]>,
+    E: Into<anyhow::Error>,
+{
+    /// Decode the next record from the body, reading more chunks as needed.
+    async fn next_record(&mut self) -> Option<Result<ClusterDiscovery>> {
+        loop {
+            if let Some(end) = self.buffer.iter().position(|byte| *byte == b'\n') {
+                let line: Vec<u8> = self.buffer.drain(..=end).collect();
+                if let Some(record) = DiscoveryStream::<S>::decode(&line) {
+                    return Some(record);
+                }
+                continue;
+            }
+
+            if self.done {
+                let line = std::mem::take(&mut self.buffer);
+                return DiscoveryStream::<S>::decode(&line);
+            }
+
+            match self.body.next().await {
+                Some(Ok(chunk)) => self.buffer.extend_from_slice(chunk.as_ref()),
+                Some(Err(error)) => {
+                    self.buffer.clear();
+                    self.done = true;
+                    return Some(Err(error.into()));
+                }
+                None => self.done = true,
+            }
+        }
+    }
+
+    /// Decode a line into a [`ClusterDiscovery`] record, skipping blank lines.
+    fn decode(line: &[u8]) -> Option<Result<ClusterDiscovery>> {
+        if line.iter().all(u8::is_ascii_whitespace) {
+            return None;
+        }
+        let record = serde_json::from_slice(line).context("invalid cluster discovery record");
+        Some(record)
+    }
+}
+
 /// Information about an individual cluster node.
 #[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
 pub struct ClusterDiscoveryNode {
@@ -98,6 +221,35 @@ pub struct ClusterDiscoveryNode {
     pub node_id: String,
 }
 
+#[cfg(feature = "agent-models")]
+impl ClusterDiscoveryNode {
+    /// Map information reported by a node's agent to a [`ClusterDiscoveryNode`].
+    ///
+    /// The `node_id` is copied from the [`Node`] and the `agent_address` is taken from
+    /// the [`NODE_ATTRIBUTE_AGENT_ADDRESS`] attribute, if the agent reports it as a string.
+    ///
+    /// Agents do not always know the address they can be reached at (for example when
+    /// behind a proxy or load balancer) so the attribute is optional.
+    /// When the attribute is not available the returned `agent_address` is empty
+    /// and callers must populate it from another source, such as Platform discovery records.
+    ///
+    /// [`Node`]: crate::agent::models::Node
+    /// [`NODE_ATTRIBUTE_AGENT_ADDRESS`]: crate::agent::models::NODE_ATTRIBUTE_AGENT_ADDRESS
+    pub fn from_node(node: &crate::agent::models::Node) -> ClusterDiscoveryNode {
+        use crate::agent::models::AttributeValue;
+        use crate::agent::models::NODE_ATTRIBUTE_AGENT_ADDRESS;
+
+        let agent_address = match node.attributes.get(NODE_ATTRIBUTE_AGENT_ADDRESS) {
+            Some(AttributeValue::String(address)) => address.clone(),
+            _ => String::new(),
+        };
+        ClusterDiscoveryNode {
+            agent_address,
+            node_id: node.node_id.clone(),
+        }
+    }
+}
+
 /// API Request schema for a Platform node deprovision action.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct NodeDeprovisionRequest {
@@ -108,6 +260,34 @@ pub struct NodeDeprovisionRequest {
     pub node_id: String,
 }
 
+impl Validate for NodeDeprovisionRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        errors.require_not_empty("cluster_id", &self.cluster_id);
+        errors.require_not_empty("node_id", &self.node_id);
+        errors.into_result()
+    }
+}
+
+/// API Request schema for a Platform node power action.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct NodePowerRequest {
+    /// ID of the cluster the node to power on or off is part of.
+    pub cluster_id: String,
+
+    /// Platform defined ID on the node to power on or off.
+    pub node_id: String,
+}
+
+impl Validate for NodePowerRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        errors.require_not_empty("cluster_id", &self.cluster_id);
+        errors.require_not_empty("node_id", &self.node_id);
+        errors.into_result()
+    }
+}
+
 /// API Request schema for a Platform node provision action.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct NodeProvisionRequest {
@@ -118,6 +298,22 @@ pub struct NodeProvisionRequest {
     pub provision: NodeProvisionRequestDetails,
 }
 
+impl Validate for NodeProvisionRequest {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        errors.nested("cluster", &self.cluster);
+        let group = &self.provision.node_group_id;
+        errors.require_not_empty("provision.node_group_id", group);
+        if !group.trim().is_empty() && !self.cluster.nodes.contains_key(group) {
+            errors.add(
+                "provision.node_group_id",
+                format!("node group '{}' is not defined by the cluster", group),
+            );
+        }
+        errors.into_result()
+    }
+}
+
 /// Details of the node(s) to provisions in a NodeProvision action.
 #[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
 pub struct NodeProvisionRequestDetails {
@@ -129,6 +325,9 @@ pub struct NodeProvisionRequestDetails {
 #[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
 pub struct NodeProvisionResponse {
     /// Number of nodes being provisioned due to this request.
+    ///
+    /// This can be zero when the Platform accepts a request with nothing to provision,
+    /// for example because the requested nodes already exist.
     pub count: u32,
 
     /// If available, the Platform can return a list of node IDs being provisioned.
@@ -145,3 +344,352 @@ pub struct NodeProvisionResponse {
     #[serde(default)]
     pub node_ids: Option<Vec<String>>,
 }
+
+impl NodeProvisionResponse {
+    /// Build a [`NodeProvisionResponse`] with validation of its fields.
+    pub fn build() -> NodeProvisionResponseBuilder {
+        NodeProvisionResponseBuilder::default()
+    }
+
+    /// Check the response fields are consistent with each other.
+    pub fn validate(&self) -> Result<(), NodeProvisionResponseError> {
+        if let Some(node_ids) = &self.node_ids {
+            if node_ids.len() != self.count as usize {
+                return Err(NodeProvisionResponseError::NodeIdsCountMismatch {
+                    count: self.count,
+                    node_ids: node_ids.len(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Build a [`NodeProvisionResponse`] and validate it.
+#[derive(Clone, Debug, Default)]
+pub struct NodeProvisionResponseBuilder {
+    count: Option<u32>,
+    node_ids: Option<Vec<String>>,
+}
+
+impl NodeProvisionResponseBuilder {
+    /// Validate the provided fields and return the [`NodeProvisionResponse`].
+    pub fn finish(self) -> Result<NodeProvisionResponse, NodeProvisionResponseError> {
+        let count = self.count.ok_or(NodeProvisionResponseError::MissingCount)?;
+        let response = NodeProvisionResponse {
+            count,
+            node_ids: self.node_ids,
+        };
+        response.validate()?;
+        Ok(response)
+    }
+
+    /// Set the number of nodes being provisioned due to the request.
+    pub fn count(mut self, count: u32) -> Self {
+        self.count = Some(count);
+        self
+    }
+
+    /// Add the ID of a node being provisioned to the response.
+    pub fn node_id<S>(mut self, node_id: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.node_ids
+            .get_or_insert_with(Default::default)
+            .push(node_id.into());
+        self
+    }
+
+    /// Set the IDs of all nodes being provisioned.
+    pub fn node_ids<I, S>(mut self, node_ids: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let node_ids = node_ids.into_iter().map(Into::into).collect();
+        self.node_ids = Some(node_ids);
+        self
+    }
+}
+
+/// Errors detected while validating a [`NodeProvisionResponse`].
+#[derive(Debug, thiserror::Error)]
+pub enum NodeProvisionResponseError {
+    /// The required number of nodes being provisioned was not set.
+    #[error("the number of nodes being provisioned is required but was not set")]
+    MissingCount,
+
+    /// The number of node IDs does not match the number of nodes being provisioned.
+    #[error("the response lists {node_ids} node IDs but {count} nodes are being provisioned")]
+    NodeIdsCountMismatch {
+        /// Number of nodes being provisioned.
+        count: u32,
+
+        /// Number of node IDs in the response.
+        node_ids: usize,
+    },
+}
+
+/// API Response schema listing optional features supported by a Platform.
+///
+/// Clients such as Core use this information to adapt their behaviour to the platform
+/// instead of discovering supported features by trial and error.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct PlatformCapabilities {
+    /// The platform can validate requests without acting on them.
+    #[serde(default)]
+    pub dry_run: bool,
+
+    /// The platform can return results, such as discovered clusters, in pages.
+    #[serde(default)]
+    pub pagination: bool,
+
+    /// The platform can power nodes on and off without deprovisioning them.
+    #[serde(default)]
+    pub power: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::ClusterDefinition;
+    use super::ClusterDefinitionNodeGroup;
+    use super::ClusterDiscovery;
+    use super::ClusterDiscoveryNode;
+    use super::ClusterDiscoveryResponse;
+    use super::NodeProvisionRequest;
+    use super::NodeProvisionRequestDetails;
+    use super::NodeProvisionResponse;
+    use super::NodeProvisionResponseError;
+    use crate::utils::validate::Validate;
+
+    #[cfg(feature = "agent-models")]
+    fn agent_node() -> crate::agent::models::Node {
+        use crate::agent::models::AgentVersion;
+        use crate::agent::models::Node;
+        use crate::agent::models::NodeStatus;
+        use crate::agent::models::StoreVersion;
+        Node {
+            agent_version: AgentVersion {
+                checkout: "abcdef".into(),
+                number: "0.1.0".into(),
+                taint: "not tainted".into(),
+            },
+            attributes: Default::default(),
+            node_id: "node-1".into(),
+            node_status: NodeStatus::Healthy,
+            store_id: "mongodb".into(),
+            store_version: StoreVersion {
+                checkout: None,
+                number: "6.0.0".into(),
+                extra: None,
+            },
+        }
+    }
+
+    fn discovery(cluster_id: &str, node_id: &str) -> ClusterDiscovery {
+        ClusterDiscovery {
+            cluster_id: cluster_id.into(),
+            nodes: vec![ClusterDiscoveryNode {
+                agent_address: format!("https://{}:8000", node_id),
+                node_id: node_id.into(),
+            }],
+        }
+    }
+
+    fn provision_request(node_group_id: &str) -> NodeProvisionRequest {
+        let group = ClusterDefinitionNodeGroup {
+            attributes: Default::default(),
+            desired_count: 3,
+            node_class: "small".into(),
+            store_version: None,
+        };
+        NodeProvisionRequest {
+            cluster: ClusterDefinition {
+                attributes: Default::default(),
+                cluster_id: "cluster-1".into(),
+                store: "mongodb".into(),
+                store_version: "6.0.0".into(),
+                nodes: [("default".to_string(), group)].into_iter().collect(),
+            },
+            provision: NodeProvisionRequestDetails {
+                node_group_id: node_group_id.into(),
+            },
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "agent-models")]
+    fn discovery_node_from_node_with_address() {
+        let mut node = agent_node();
+        node.attributes.insert(
+            crate::agent::models::NODE_ATTRIBUTE_AGENT_ADDRESS.into(),
+            "https://node-1:8000".into(),
+        );
+        let discovery = ClusterDiscoveryNode::from_node(&node);
+        assert_eq!(
+            discovery,
+            ClusterDiscoveryNode {
+                agent_address: "https://node-1:8000".into(),
+                node_id: "node-1".into(),
+            },
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "agent-models")]
+    fn discovery_node_from_node_without_address() {
+        let node = agent_node();
+        let discovery = ClusterDiscoveryNode::from_node(&node);
+        assert_eq!(discovery.agent_address, "");
+        assert_eq!(discovery.node_id, "node-1");
+    }
+
+    #[test]
+    fn validate_provision_request() {
+        let request = provision_request("default");
+        assert!(request.validate().is_ok());
+    }
+
+    #[test]
+    fn validate_provision_request_fails() {
+        let mut request = provision_request("missing");
+        request.cluster.store = "".into();
+        let group = request.cluster.nodes.get_mut("default").unwrap();
+        group.node_class = "".into();
+        group.store_version = Some(" ".into());
+        let errors = request.validate().unwrap_err();
+        let errors: Vec<String> = errors.errors().iter().map(ToString::to_string).collect();
+        assert_eq!(
+            errors,
+            [
+                "cluster.nodes.default.node_class: value must not be empty",
+                "cluster.nodes.default.store_version: value must not be empty",
+                "cluster.store: value must not be empty",
+                "provision.node_group_id: node group 'missing' is not defined by the cluster",
+            ],
+        );
+    }
+
+    #[test]
+    fn stream_ndjson_chunked() {
+        let body = concat!(
+            r#"{"cluster_id":"c1","nodes":[{"agent_address":"https://n1:8000","node_id":"n1"}]}"#,
+            "\n\n",
+            r#"{"cluster_id":"c2","nodes":[{"agent_address":"https://n2:8000","node_id":"n2"}]}"#,
+            "\n",
+            r#"{"cluster_id":"c3","nodes":[{"agent_address":"https://n3:8000","node_id":"n3"}]}"#,
+        );
+        let chunks: Vec<Result<Vec<u8>, std::io::Error>> = body
+            .as_bytes()
+            .chunks(7)
+            .map(|chunk| Ok(chunk.to_vec()))
+            .collect();
+        let stream = ClusterDiscoveryResponse::stream_ndjson(futures::stream::iter(chunks));
+        let clusters: Vec<ClusterDiscovery> =
+            futures::executor::block_on(stream.collect::<Vec<_>>())
+                .into_iter()
+                .map(Result::unwrap)
+                .collect();
+        assert_eq!(
+            clusters,
+            vec![
+                discovery("c1", "n1"),
+                discovery("c2", "n2"),
+                discovery("c3", "n3"),
+            ],
+        );
+    }
+
+    #[test]
+    fn stream_ndjson_invalid_record() {
+        let chunks: Vec<Result<&[u8], std::io::Error>> = vec![
+            Ok(b"{\"cluster_id\":\"c1\",\"nodes\":[]}\nnot json\n"),
+            Ok(b"{\"cluster_id\":\"c2\",\"nodes\":[]}\n"),
+        ];
+        let stream = ClusterDiscoveryResponse::stream_ndjson(futures::stream::iter(chunks));
+        let records = futures::executor::block_on(stream.collect::<Vec<_>>());
+        assert_eq!(records.len(), 3);
+        assert!(records[0].is_ok());
+        assert!(records[1].is_err());
+        assert_eq!(records[2].as_ref().unwrap().cluster_id, "c2");
+    }
+
+    #[test]
+    fn stream_ndjson_body_error() {
+        let chunks: Vec<Result<&[u8], std::io::Error>> = vec![
+            Ok(b"{\"cluster_id\":\"c1\",\"nodes\":[]}\n{\"cluster"),
+            Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "connection reset",
+            )),
+            Ok(b"_id\":\"c2\",\"nodes\":[]}\n"),
+        ];
+        let stream = ClusterDiscoveryResponse::stream_ndjson(futures::stream::iter(chunks));
+        let records = futures::executor::block_on(stream.collect::<Vec<_>>());
+        assert_eq!(records.len(), 2);
+        assert!(records[0].is_ok());
+        assert_eq!(
+            records[1].as_ref().unwrap_err().to_string(),
+            "connection reset"
+        );
+    }
+
+    #[test]
+    fn build_provision_response() {
+        let response = NodeProvisionResponse::build()
+            .count(2)
+            .node_id("node-1")
+            .node_id("node-2")
+            .finish()
+            .unwrap();
+        assert_eq!(
+            response,
+            NodeProvisionResponse {
+                count: 2,
+                node_ids: Some(vec!["node-1".into(), "node-2".into()]),
+            }
+        );
+    }
+
+    #[test]
+    fn build_provision_response_without_ids() {
+        let response = NodeProvisionResponse::build().count(3).finish().unwrap();
+        assert_eq!(response.count, 3);
+        assert_eq!(response.node_ids, None);
+    }
+
+    #[test]
+    fn build_provision_response_no_nodes() {
+        let response = NodeProvisionResponse::build()
+            .count(0)
+            .node_ids(Vec::<String>::new())
+            .finish()
+            .unwrap();
+        assert_eq!(response.count, 0);
+    }
+
+    #[test]
+    fn build_provision_response_missing_count() {
+        let error = NodeProvisionResponse::build()
+            .node_ids(["node-1"])
+            .finish()
+            .unwrap_err();
+        assert!(matches!(error, NodeProvisionResponseError::MissingCount));
+    }
+
+    #[test]
+    fn build_provision_response_ids_mismatch() {
+        let error = NodeProvisionResponse::build()
+            .count(2)
+            .node_ids(["node-1"])
+            .finish()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the response lists 1 node IDs but 2 nodes are being provisioned"
+        );
+    }
+}
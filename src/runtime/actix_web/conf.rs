@@ -11,11 +11,14 @@ use actix_web::HttpServer;
 use anyhow::Context;
 use anyhow::Result;
 use openssl::ssl::SslAcceptor;
+use openssl::ssl::SslAcceptorBuilder;
 use openssl::ssl::SslVerifyMode;
 use serde::Deserialize;
 use serde::Serialize;
 
 use super::BuildError;
+use crate::utils::config::ByteSize;
+use crate::utils::config::HumanDuration;
 
 /// User focused configuration options for [`HttpServer`]s.
 ///
@@ -38,6 +41,14 @@ use super::BuildError;
 /// try clearing all build caches with `cargo clean`.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct ServerConfig {
+    /// Serve administrative endpoints (metrics, health) on a separate address.
+    ///
+    /// When set these endpoints are served by a dedicated admin server bound to this address
+    /// and are no longer exposed by the main server.
+    /// The admin server does not use TLS so it should only be reachable on internal networks.
+    #[serde(default)]
+    pub admin_bind: Option<String>,
+
     /// Sets the maximum number of pending connections.
     ///
     /// Refer to [`HttpServer::backlog`] for more details.
@@ -95,10 +106,30 @@ pub struct ServerConfig {
     #[serde(default)]
     pub max_connections_tls: Option<usize>,
 
-    /// Time in seconds workers are given to complete requests in progress when a shutdown
+    /// Response of the metrics endpoint when metrics export is disabled.
+    ///
+    /// Keeping the endpoint around avoids scrape errors flooding Prometheus logs
+    /// when scrape configurations still target the process.
+    #[serde(default)]
+    pub metrics_disabled_response: MetricsDisabledResponse,
+
+    /// Export metrics in prometheus format on the metrics endpoint.
+    #[serde(default = "ServerConfig::default_metrics_enabled")]
+    pub metrics_enabled: bool,
+
+    /// Maximum size of request payloads extracted by handlers.
+    ///
+    /// Takes precedence over the limit set with
+    /// [`AppFactoryBuilder::payload_limit`](super::AppFactoryBuilder::payload_limit).
+    #[serde(default)]
+    pub payload_limit: Option<ByteSize>,
+
+    /// Time workers are given to complete requests in progress when a shutdown
     /// signal is received.
+    ///
+    /// The timeout is applied with a precision of seconds so it must be a whole number of seconds.
     #[serde(default)]
-    pub shutdown_timeout: Option<u64>,
+    pub shutdown_timeout: Option<HumanDuration>,
 
     /// Configure the server to run with TLS encryption.
     #[serde(default)]
@@ -119,11 +150,16 @@ impl ServerConfig {
     fn default_compress_responses() -> bool {
         true
     }
+
+    fn default_metrics_enabled() -> bool {
+        true
+    }
 }
 
 impl Default for ServerConfig {
     fn default() -> Self {
         ServerConfig {
+            admin_bind: None,
             backlog: Default::default(),
             bind: Self::default_bind(),
             client_request_timeout: None,
@@ -132,6 +168,9 @@ impl Default for ServerConfig {
             log_format: None,
             max_connections: None,
             max_connections_tls: None,
+            metrics_disabled_response: Default::default(),
+            metrics_enabled: Self::default_metrics_enabled(),
+            payload_limit: None,
             shutdown_timeout: None,
             tls: None,
             workers: None,
@@ -170,7 +209,7 @@ impl ServerConfig {
             server = server.max_connection_rate(max);
         }
         if let Some(timeout) = self.shutdown_timeout {
-            server = server.shutdown_timeout(timeout);
+            server = server.shutdown_timeout(timeout.whole_secs()?);
         }
         if let Some(workers) = self.workers {
             server = server.workers(workers);
@@ -179,21 +218,7 @@ impl ServerConfig {
         // Bind the server, with TLS if configured.
         let server = match self.tls {
             Some(tls) if tls.enabled => {
-                let mut engine = SslAcceptor::mozilla_modern_v5(openssl::ssl::SslMethod::tls())
-                    .context(BuildError::TlsInit("openssl"))?;
-                engine
-                    .set_certificate_file(&tls.server_private_cert, openssl::ssl::SslFiletype::PEM)
-                    .with_context(|| BuildError::TlsServerCert(tls.server_private_cert))?;
-                engine
-                    .set_private_key_file(&tls.server_private_key, openssl::ssl::SslFiletype::PEM)
-                    .with_context(|| BuildError::TlsServerKey(tls.server_private_key))?;
-
-                if let Some(bundle) = tls.client_ca_bundle {
-                    engine
-                        .set_ca_file(&bundle)
-                        .with_context(|| BuildError::TlsClientCAs(bundle))?;
-                    engine.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
-                }
+                let engine = tls.openssl_acceptor()?;
                 if let Some(timeout) = tls.handshake_timeout {
                     let timeout = std::time::Duration::from_millis(timeout);
                     server = server.tls_handshake_timeout(timeout);
@@ -207,12 +232,63 @@ impl ServerConfig {
     }
 }
 
+/// Verification of client certificates for TLS servers.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ClientAuthMode {
+    /// Client certificates are not requested nor verified.
+    #[serde(alias = "NONE", alias = "none")]
+    None,
+
+    /// Client certificates are verified when provided but clients may omit them.
+    #[serde(alias = "OPTIONAL", alias = "optional")]
+    Optional,
+
+    /// Clients MUST provide a valid certificate.
+    #[default]
+    #[serde(alias = "REQUIRE", alias = "require")]
+    Require,
+}
+
+impl From<ClientAuthMode> for SslVerifyMode {
+    fn from(value: ClientAuthMode) -> Self {
+        match value {
+            ClientAuthMode::None => SslVerifyMode::NONE,
+            ClientAuthMode::Optional => SslVerifyMode::PEER,
+            ClientAuthMode::Require => SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT,
+        }
+    }
+}
+
+/// Response of the metrics endpoint when metrics export is disabled.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum MetricsDisabledResponse {
+    /// Respond with a successful and empty metrics page.
+    #[serde(alias = "EMPTY", alias = "empty")]
+    Empty,
+
+    /// Respond with a metrics page holding only a comment explaining metrics are disabled.
+    #[serde(alias = "MESSAGE", alias = "message")]
+    Message,
+
+    /// Respond with a not found error, as if the endpoint did not exist.
+    #[default]
+    #[serde(alias = "NOT_FOUND", alias = "not_found")]
+    NotFound,
+}
+
 /// Configure the server to run with TLS encryption.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct ServerConfigTls {
+    /// Verification of client certificates, when `client_ca_bundle` is set.
+    ///
+    /// Client certificates are never verified when `client_ca_bundle` is not set.
+    #[serde(default)]
+    pub client_auth: ClientAuthMode,
+
     /// Path to a PEM bundle of Certificate Authorities to verify client certificates with.
     ///
-    /// When this option is set, clients MUST provide a certificate that is valid.
+    /// When this option is set, clients MUST provide a certificate that is valid
+    /// unless a different `client_auth` mode is set.
     #[serde(default)]
     pub client_ca_bundle: Option<String>,
 
@@ -237,4 +313,218 @@ impl ServerConfigTls {
     fn default_enabled() -> bool {
         true
     }
+
+    /// Create an openssl acceptor for the TLS configuration.
+    fn openssl_acceptor(&self) -> Result<SslAcceptorBuilder> {
+        let mut engine = SslAcceptor::mozilla_modern_v5(openssl::ssl::SslMethod::tls())
+            .context(BuildError::TlsInit("openssl"))?;
+        engine
+            .set_certificate_file(&self.server_private_cert, openssl::ssl::SslFiletype::PEM)
+            .with_context(|| BuildError::TlsServerCert(self.server_private_cert.clone()))?;
+        engine
+            .set_private_key_file(&self.server_private_key, openssl::ssl::SslFiletype::PEM)
+            .with_context(|| BuildError::TlsServerKey(self.server_private_key.clone()))?;
+
+        if let Some(bundle) = &self.client_ca_bundle {
+            engine
+                .set_ca_file(bundle)
+                .with_context(|| BuildError::TlsClientCAs(bundle.clone()))?;
+            engine.set_verify(self.client_auth.into());
+        }
+        Ok(engine)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::net::TcpStream;
+    use std::path::PathBuf;
+
+    use openssl::asn1::Asn1Time;
+    use openssl::ec::EcGroup;
+    use openssl::ec::EcKey;
+    use openssl::hash::MessageDigest;
+    use openssl::nid::Nid;
+    use openssl::pkey::PKey;
+    use openssl::pkey::Private;
+    use openssl::ssl::SslConnector;
+    use openssl::ssl::SslFiletype;
+    use openssl::ssl::SslMethod;
+    use openssl::ssl::SslVerifyMode;
+    use openssl::x509::extension::BasicConstraints;
+    use openssl::x509::X509Name;
+    use openssl::x509::X509;
+
+    use super::ClientAuthMode;
+    use super::ServerConfig;
+    use super::ServerConfigTls;
+    use crate::utils::config::HumanDuration;
+    use crate::utils::config::HumanDurationSecondsError;
+
+    /// Paths to PEM files for the CA, server and client certificates used in tests.
+    ///
+    /// The files are removed along with their temporary directory when fixtures are dropped.
+    struct Fixtures {
+        ca_cert: String,
+        client_cert: String,
+        client_key: String,
+        server_cert: String,
+        server_key: String,
+        _dir: tempfile::TempDir,
+    }
+
+    impl Fixtures {
+        /// Generate a CA with server and client certificates in a temporary directory.
+        fn generate() -> Fixtures {
+            let dir = tempfile::tempdir().unwrap();
+            let (ca, ca_key) = certificate("ca", None);
+            let (server, server_key) = certificate("localhost", Some((&ca, &ca_key)));
+            let (client, client_key) = certificate("client", Some((&ca, &ca_key)));
+            Fixtures {
+                ca_cert: write(dir.path(), "ca.crt", &ca.to_pem().unwrap()),
+                client_cert: write(dir.path(), "client.crt", &client.to_pem().unwrap()),
+                client_key: write(dir.path(), "client.key", &key_pem(&client_key)),
+                server_cert: write(dir.path(), "server.crt", &server.to_pem().unwrap()),
+                server_key: write(dir.path(), "server.key", &key_pem(&server_key)),
+                _dir: dir,
+            }
+        }
+
+        fn tls(&self, client_auth: ClientAuthMode) -> ServerConfigTls {
+            ServerConfigTls {
+                client_auth,
+                client_ca_bundle: Some(self.ca_cert.clone()),
+                enabled: true,
+                handshake_timeout: None,
+                server_private_cert: self.server_cert.clone(),
+                server_private_key: self.server_key.clone(),
+            }
+        }
+    }
+
+    /// Generate a certificate, self-signed or signed by the given issuer.
+    fn certificate(name: &str, issuer: Option<(&X509, &PKey<Private>)>) -> (X509, PKey<Private>) {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+        let mut subject = X509Name::builder().unwrap();
+        subject.append_entry_by_text("CN", name).unwrap();
+        let subject = subject.build();
+
+        let mut cert = X509::builder().unwrap();
+        cert.set_version(2).unwrap();
+        cert.set_subject_name(&subject).unwrap();
+        cert.set_pubkey(&key).unwrap();
+        cert.set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        cert.set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        match issuer {
+            None => {
+                let constraints = BasicConstraints::new().critical().ca().build().unwrap();
+                cert.append_extension(constraints).unwrap();
+                cert.set_issuer_name(&subject).unwrap();
+                cert.sign(&key, MessageDigest::sha256()).unwrap();
+            }
+            Some((issuer, issuer_key)) => {
+                cert.set_issuer_name(issuer.subject_name()).unwrap();
+                cert.sign(issuer_key, MessageDigest::sha256()).unwrap();
+            }
+        }
+        (cert.build(), key)
+    }
+
+    fn key_pem(key: &PKey<Private>) -> Vec<u8> {
+        key.private_key_to_pem_pkcs8().unwrap()
+    }
+
+    fn write(dir: &std::path::Path, name: &str, data: &[u8]) -> String {
+        let path: PathBuf = dir.join(name);
+        std::fs::write(&path, data).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    /// Perform a TLS handshake and return if the server accepted the connection.
+    fn handshake(tls: &ServerConfigTls, client: Option<&Fixtures>) -> bool {
+        let acceptor = tls.openssl_acceptor().unwrap().build();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+        connector.set_verify(SslVerifyMode::NONE);
+        if let Some(client) = client {
+            connector
+                .set_certificate_file(&client.client_cert, SslFiletype::PEM)
+                .unwrap();
+            connector
+                .set_private_key_file(&client.client_key, SslFiletype::PEM)
+                .unwrap();
+        }
+        let connector = connector.build();
+        let client = std::thread::spawn(move || {
+            let stream = TcpStream::connect(address).unwrap();
+            let _ = connector.connect("localhost", stream);
+        });
+
+        let (stream, _) = listener.accept().unwrap();
+        let accepted = acceptor.accept(stream).is_ok();
+        client.join().unwrap();
+        accepted
+    }
+
+    #[test]
+    fn client_auth_none_ignores_certificates() {
+        let fixtures = Fixtures::generate();
+        let tls = fixtures.tls(ClientAuthMode::None);
+        assert!(handshake(&tls, None));
+        assert!(handshake(&tls, Some(&fixtures)));
+    }
+
+    #[test]
+    fn client_auth_optional_accepts_without_certificate() {
+        let fixtures = Fixtures::generate();
+        let tls = fixtures.tls(ClientAuthMode::Optional);
+        assert!(handshake(&tls, None));
+        assert!(handshake(&tls, Some(&fixtures)));
+    }
+
+    #[test]
+    fn client_auth_require_rejects_without_certificate() {
+        let fixtures = Fixtures::generate();
+        let tls = fixtures.tls(ClientAuthMode::Require);
+        assert!(!handshake(&tls, None));
+        assert!(handshake(&tls, Some(&fixtures)));
+    }
+
+    #[test]
+    fn client_auth_defaults_to_require() {
+        let tls: ServerConfigTls = serde_json::from_value(serde_json::json!({
+            "server_private_cert": "server.crt",
+            "server_private_key": "server.key",
+        }))
+        .unwrap();
+        assert_eq!(tls.client_auth, ClientAuthMode::Require);
+        let tls: ServerConfigTls = serde_json::from_value(serde_json::json!({
+            "client_auth": "optional",
+            "server_private_cert": "server.crt",
+            "server_private_key": "server.key",
+        }))
+        .unwrap();
+        assert_eq!(tls.client_auth, ClientAuthMode::Optional);
+    }
+
+    #[actix_web::test]
+    async fn shutdown_timeout_whole_seconds() {
+        let conf = ServerConfig {
+            bind: "127.0.0.1:0".into(),
+            shutdown_timeout: Some(HumanDuration::from_millis(500)),
+            ..Default::default()
+        };
+        let server = actix_web::HttpServer::new(actix_web::App::new);
+        let error = conf
+            .apply(server)
+            .err()
+            .expect("sub-second shutdown timeout to be rejected");
+        assert!(error.is::<HumanDurationSecondsError>());
+    }
 }
@@ -0,0 +1,78 @@
+//! Cross-Origin Resource Sharing (CORS) policy for apps created by an `AppFactory`.
+use actix_cors::Cors;
+use actix_web::http::header::HeaderName;
+use actix_web::http::Method;
+use actix_web::http::Uri;
+use anyhow::Context;
+use anyhow::Result;
+
+use super::BuildError;
+
+/// Cross-Origin Resource Sharing (CORS) policy applied to apps by [`AppFactory::finalise`].
+///
+/// Browsers only allow cross-origin requests to servers that allow them with CORS headers.
+/// Set a policy with [`AppFactoryBuilder::cors`] to allow browser-based clients,
+/// such as dashboards, to call the server APIs.
+///
+/// [`AppFactory::finalise`]: super::AppFactory::finalise
+/// [`AppFactoryBuilder::cors`]: super::AppFactoryBuilder::cors
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CorsPolicy {
+    /// Allow requests to include credentials, such as cookies.
+    pub allow_credentials: bool,
+
+    /// Request headers clients are allowed to send, any header is allowed when empty.
+    pub allowed_headers: Vec<HeaderName>,
+
+    /// Request methods clients are allowed to use, any method is allowed when empty.
+    pub allowed_methods: Vec<Method>,
+
+    /// Origins allowed to make requests, such as `https://dashboard.example.com`.
+    ///
+    /// The special `*` origin allows requests from any origin.
+    /// Origins are validated by [`AppFactoryBuilder::done`], which fails on invalid origins.
+    ///
+    /// [`AppFactoryBuilder::done`]: super::AppFactoryBuilder::done
+    pub allowed_origins: Vec<String>,
+
+    /// How long, in seconds, clients can cache the results of preflight requests.
+    pub max_age: Option<usize>,
+}
+
+impl CorsPolicy {
+    /// Check the policy can be enforced by the `actix_cors` middleware.
+    pub(super) fn validate(&self) -> Result<()> {
+        for origin in &self.allowed_origins {
+            if origin != "*" {
+                Uri::try_from(origin.as_str())
+                    .with_context(|| BuildError::CorsOrigin(origin.clone()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Build the `actix_cors` middleware enforcing the policy.
+    pub(super) fn middleware(&self) -> Cors {
+        let mut cors = Cors::default();
+        for origin in &self.allowed_origins {
+            cors = match origin.as_str() {
+                "*" => cors.allow_any_origin(),
+                origin => cors.allowed_origin(origin),
+            };
+        }
+        cors = if self.allowed_methods.is_empty() {
+            cors.allow_any_method()
+        } else {
+            cors.allowed_methods(self.allowed_methods.clone())
+        };
+        cors = if self.allowed_headers.is_empty() {
+            cors.allow_any_header()
+        } else {
+            cors.allowed_headers(self.allowed_headers.clone())
+        };
+        if self.allow_credentials {
+            cors = cors.supports_credentials();
+        }
+        cors.max_age(self.max_age)
+    }
+}
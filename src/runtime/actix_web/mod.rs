@@ -1,27 +1,90 @@
 //! Actix Web server runtime configuration utilities.
+use std::collections::HashMap;
 use std::fmt::Debug;
+use std::future::Future;
 use std::sync::Arc;
 
+use actix_web::body::BoxBody;
 use actix_web::body::MessageBody;
 use actix_web::dev::ServiceFactory;
 use actix_web::dev::ServiceRequest;
 use actix_web::dev::ServiceResponse;
+use actix_web::middleware::from_fn;
 use actix_web::middleware::Compress;
 use actix_web::middleware::Condition;
+use actix_web::middleware::Next;
+use actix_web::web::JsonConfig;
+use actix_web::web::PayloadConfig;
 use actix_web::web::ServiceConfig;
 use actix_web::App;
 use actix_web::Error;
+use actix_web::HttpResponse;
+use actix_web::HttpServer;
+use anyhow::Context;
+use anyhow::Result;
+use futures::future::LocalBoxFuture;
 use prometheus::Registry;
 
 use crate::utils::actix::metrics::MetricsCollector;
 use crate::utils::actix::metrics::MetricsExporter;
 
+use self::optional::Optional;
+use self::slot::SlotMiddleware;
+
 mod conf;
+mod cors;
+mod optional;
+mod request_id;
+mod slot;
 
+pub use self::conf::ClientAuthMode;
+pub use self::conf::MetricsDisabledResponse;
 pub use self::conf::ServerConfig;
+pub use self::conf::ServerConfigTls;
+pub use self::cors::CorsPolicy;
+pub use self::request_id::REQUEST_ID_HEADER;
 
 type ConfCallback = Arc<dyn Fn(&mut ServiceConfig) + Send + Sync + 'static>;
 
+/// Short-hand for readiness probes checked by the readiness endpoint.
+type ReadinessProbe = Arc<dyn Fn() -> bool + Send + Sync + 'static>;
+
+/// Short-hand for custom middleware functions applied by [`AppFactory::finalise`].
+type MiddlewareCallback = Arc<
+    dyn Fn(ServiceRequest, Next<BoxBody>) -> LocalBoxFuture<'static, MiddlewareResult>
+        + Send
+        + Sync
+        + 'static,
+>;
+
+/// Short-hand for the result of custom middleware functions.
+type MiddlewareResult = Result<ServiceResponse<BoxBody>, Error>;
+
+/// Positions in the [`AppFactory::finalise`] middleware stack custom middleware can be added at.
+///
+/// Middleware listed first process requests first (and responses last):
+///
+/// 1. [`MiddlewareSlot::Outermost`]
+/// 2. Request tracing.
+/// 3. Request logging.
+/// 4. Request ID attachment, if enabled with [`AppFactoryBuilder::request_id`].
+/// 5. CORS policy enforcement, if a [`CorsPolicy`] is set.
+/// 6. [`MiddlewareSlot::BeforeMetrics`]
+/// 7. Request metrics collection.
+/// 8. [`MiddlewareSlot::AfterMetrics`]
+/// 9. Response compression.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum MiddlewareSlot {
+    /// Process requests after metrics collection, so metrics include requests it rejects.
+    AfterMetrics,
+
+    /// Process requests before metrics collection, so metrics exclude requests it rejects.
+    BeforeMetrics,
+
+    /// Process requests before any other middleware, including tracing and logging.
+    Outermost,
+}
+
 /// Dynamic collection of configuration logic for [`actix_web::App`]s.
 #[derive(Clone, Default)]
 pub struct AppConfigurer {
@@ -61,7 +124,7 @@ impl AppConfigurer {
 /// // Initialise the ActixWeb HttPServer with an app factory.
 /// let factory = AppFactory::configure()
 ///     // Configure the AppFactory as needed.
-///     .done();
+///     .done()?;
 /// let server = HttpServer::new(|| {
 ///     let app = factory.initialise();
 ///     // Customise the app here as desired.
@@ -75,29 +138,77 @@ impl AppConfigurer {
 /// ```
 #[derive(Clone)]
 pub struct AppFactory {
+    admin_conf: AppConfigurer,
     app_conf: AppConfigurer,
     conf: ServerConfig,
+    cors: Option<CorsPolicy>,
+    liveness_path: Option<&'static str>,
     metrics_collector: MetricsCollector,
     metrics_exporter: MetricsExporter,
     metrics_path: &'static str,
+    middleware: HashMap<MiddlewareSlot, MiddlewareCallback>,
+    payload_limit: Option<usize>,
+    readiness: Option<(&'static str, ReadinessProbe)>,
+    request_id: bool,
 }
 
 impl AppFactory {
     /// Begin configuration of an [`AppFactory`].
     pub fn configure(app_conf: AppConfigurer, conf: ServerConfig) -> AppFactoryBuilder {
         AppFactoryBuilder {
+            admin_conf: AppConfigurer::default(),
             app_conf,
             conf,
+            cors: None,
+            liveness_path: None,
             metrics_path: "/metrics",
             metrics_prefix: None,
             metrics_registry: None,
+            middleware: HashMap::new(),
+            payload_limit: None,
+            readiness: None,
+            request_id: false,
         }
     }
 
+    /// Build the admin [`HttpServer`], if an admin address is configured.
+    ///
+    /// The admin server exposes the following endpoints, sharing the metrics registry
+    /// with apps created by this factory:
+    ///
+    /// - Endpoint to expose metrics in prometheus format.
+    /// - A `/health` endpoint that responds once the server is running.
+    /// - Any endpoint added with [`AppFactoryBuilder::admin_conf`], such as debug endpoints.
+    ///
+    /// The returned server should be run alongside the main server
+    /// (for example by watching both with the runtime `ShutdownManager`).
+    pub fn admin_server(&self) -> Result<Option<actix_web::dev::Server>> {
+        let bind = match &self.conf.admin_bind {
+            None => return Ok(None),
+            Some(bind) => bind.clone(),
+        };
+        let admin_conf = self.admin_conf.clone();
+        let factory = self.clone();
+        let server = HttpServer::new(move || {
+            let metrics_endpoint = factory.metrics_endpoint();
+            App::new()
+                .configure(|app| admin_conf.configure(app))
+                .service(metrics_endpoint)
+                .route("/health", actix_web::web::get().to(health))
+        })
+        .disable_signals()
+        .workers(1)
+        .bind(&bind)
+        .with_context(|| BuildError::Bind(bind))?;
+        Ok(Some(server.run()))
+    }
+
     /// Initialise an [`actix_web::App`] with defaults and provided customisations.
     ///
     /// The following customisations are applied:
     ///
+    /// - Request payload size limits, if set with [`ServerConfig::payload_limit`]
+    ///   or [`AppFactoryBuilder::payload_limit`].
     /// - All customisations defined in the [`AppConfigurer`] are applied.
     pub fn initialise(
         &self,
@@ -110,7 +221,13 @@ impl AppFactory {
             InitError = (),
         >,
     > {
-        App::new().configure(|app| self.app_conf.configure(app))
+        let mut app = App::new();
+        if let Some(limit) = self.payload_limit {
+            app = app
+                .app_data(JsonConfig::default().limit(limit))
+                .app_data(PayloadConfig::new(limit));
+        }
+        app.configure(|app| self.app_conf.configure(app))
     }
 
     /// Finalise the [`actix_web::App`] with middleware to wrap every request.
@@ -118,13 +235,19 @@ impl AppFactory {
     /// The following middleware are applied:
     ///
     /// - User configurable request/response de/compression.
-    /// - Request metrics collection.
+    /// - Request metrics collection, except for the metrics and health probe endpoints.
     /// - Request logging.
     /// - Request tracing.
+    /// - CORS policy enforcement, if a policy is set with [`AppFactoryBuilder::cors`].
+    /// - Request ID attachment, if enabled with [`AppFactoryBuilder::request_id`].
+    /// - Custom middleware added with [`AppFactoryBuilder::middleware`], see [`MiddlewareSlot`].
     ///
     /// The following customisations are also applied:
     ///
-    /// - Endpoint to expose metrics in prometheus format.
+    /// - Endpoint to expose metrics in prometheus format, unless served by the
+    ///   [admin server](AppFactory::admin_server).
+    /// - Liveness and readiness endpoints, if set with [`AppFactoryBuilder::liveness_probe`]
+    ///   and [`AppFactoryBuilder::readiness_probe`].
     pub fn finalise<B, T>(
         &self,
         app: App<T>,
@@ -153,36 +276,153 @@ impl AppFactory {
             Some(format) => actix_web::middleware::Logger::new(format),
         };
 
-        // Define endpoint for metrics export.
-        let metrics_exporter = self.metrics_exporter.clone();
-        let metrics_endpoint = actix_web::web::resource(self.metrics_path)
-            .route(actix_web::web::get().to(metrics_exporter));
+        // Define endpoint for metrics export, unless the admin server exports them.
+        let metrics_endpoint = self
+            .conf
+            .admin_bind
+            .is_none()
+            .then(|| self.metrics_endpoint());
 
-        app.service(metrics_endpoint)
-            .wrap(Condition::new(
-                self.conf.compress_responses,
-                Compress::default(),
-            ))
-            .wrap(self.metrics_collector.clone())
-            .wrap(logger)
-            .wrap(actix_web_opentelemetry::RequestTracing::new())
+        // Enforce the CORS policy, if one is set.
+        let cors = Optional(self.cors.as_ref().map(CorsPolicy::middleware));
+
+        // Prepare custom middleware for each slot, if any is set.
+        let after_metrics = Optional(self.middleware_slot(MiddlewareSlot::AfterMetrics));
+        let before_metrics = Optional(self.middleware_slot(MiddlewareSlot::BeforeMetrics));
+        let outermost = Optional(self.middleware_slot(MiddlewareSlot::Outermost));
+
+        // Define liveness and readiness endpoints, if requested.
+        let liveness_endpoint = self
+            .liveness_path
+            .map(|path| actix_web::web::resource(path).route(actix_web::web::get().to(health)));
+        let readiness_endpoint = self.readiness.clone().map(|(path, probe)| {
+            actix_web::web::resource(path)
+                .route(actix_web::web::get().to(move || readiness(probe.clone())))
+        });
+
+        app.configure(|app| {
+            if let Some(metrics_endpoint) = metrics_endpoint {
+                app.service(metrics_endpoint);
+            }
+            if let Some(liveness_endpoint) = liveness_endpoint {
+                app.service(liveness_endpoint);
+            }
+            if let Some(readiness_endpoint) = readiness_endpoint {
+                app.service(readiness_endpoint);
+            }
+        })
+        .wrap(Condition::new(
+            self.conf.compress_responses,
+            Compress::default(),
+        ))
+        .wrap(after_metrics)
+        .wrap(self.metrics_collector.clone())
+        .wrap(before_metrics)
+        .wrap(cors)
+        .wrap(Condition::new(
+            self.request_id,
+            from_fn(self::request_id::request_id),
+        ))
+        .wrap(logger)
+        .wrap(actix_web_opentelemetry::RequestTracing::new())
+        .wrap(outermost)
+    }
+
+    /// Endpoint to export metrics on, or to respond as configured when metrics are disabled.
+    fn metrics_endpoint(&self) -> actix_web::Resource {
+        let resource = actix_web::web::resource(self.metrics_path);
+        if self.conf.metrics_enabled {
+            let metrics_exporter = self.metrics_exporter.clone();
+            return resource.route(actix_web::web::get().to(metrics_exporter));
+        }
+        let disabled = self.conf.metrics_disabled_response;
+        resource.route(actix_web::web::get().to(move || metrics_disabled(disabled)))
+    }
+
+    /// Middleware to apply the custom middleware in the given slot, if any is set.
+    fn middleware_slot(&self, slot: MiddlewareSlot) -> Option<SlotMiddleware> {
+        self.middleware.get(&slot).cloned().map(SlotMiddleware)
+    }
+}
+
+/// Respond to admin server health checks.
+async fn health() -> HttpResponse {
+    HttpResponse::Ok().finish()
+}
+
+/// Respond to readiness checks based on the outcome of the readiness probe.
+async fn readiness(probe: ReadinessProbe) -> HttpResponse {
+    if probe() {
+        HttpResponse::Ok().finish()
+    } else {
+        HttpResponse::ServiceUnavailable().finish()
+    }
+}
+
+/// Respond to metrics requests when metrics export is disabled.
+async fn metrics_disabled(disabled: MetricsDisabledResponse) -> HttpResponse {
+    match disabled {
+        MetricsDisabledResponse::Empty => HttpResponse::Ok()
+            .content_type(prometheus::TEXT_FORMAT)
+            .finish(),
+        MetricsDisabledResponse::Message => HttpResponse::Ok()
+            .content_type(prometheus::TEXT_FORMAT)
+            .body("# Metrics export is disabled for this process.\n"),
+        MetricsDisabledResponse::NotFound => HttpResponse::NotFound().finish(),
     }
 }
 
 /// Builder pattern for [`AppFactory`] instances.
 #[derive(Clone)]
 pub struct AppFactoryBuilder {
+    admin_conf: AppConfigurer,
     app_conf: AppConfigurer,
     conf: ServerConfig,
+    cors: Option<CorsPolicy>,
+    liveness_path: Option<&'static str>,
     metrics_path: &'static str,
     metrics_prefix: Option<&'static str>,
     metrics_registry: Option<prometheus::Registry>,
+    middleware: HashMap<MiddlewareSlot, MiddlewareCallback>,
+    payload_limit: Option<usize>,
+    readiness: Option<(&'static str, ReadinessProbe)>,
+    request_id: bool,
 }
 
 impl AppFactoryBuilder {
+    /// Customise the admin server app, for example to add debug endpoints.
+    ///
+    /// Customisations are only applied if an admin server is configured.
+    pub fn admin_conf(mut self, admin_conf: AppConfigurer) -> Self {
+        self.admin_conf = admin_conf;
+        self
+    }
+
+    /// Enforce a Cross-Origin Resource Sharing (CORS) policy on requests to the app.
+    ///
+    /// No CORS middleware is added to apps unless a policy is set.
+    pub fn cors(mut self, policy: CorsPolicy) -> Self {
+        self.cors = Some(policy);
+        self
+    }
+
     /// Complete [`AppFactory`] configuration and validate provided options.
-    pub fn done(self) -> AppFactory {
+    ///
+    /// Returns an error if the [`CorsPolicy`] set with [`AppFactoryBuilder::cors`] is invalid
+    /// or the [`ServerConfig::payload_limit`] is too large for the platform.
+    pub fn done(self) -> Result<AppFactory> {
         // Validate the builder.
+        if let Some(cors) = &self.cors {
+            cors.validate()?;
+        }
+        let payload_limit = match self.conf.payload_limit {
+            None => self.payload_limit,
+            Some(limit) => {
+                let bytes = usize::try_from(limit.bytes())
+                    .map_err(|_| BuildError::PayloadLimit(limit.to_string()))?;
+                Some(bytes)
+            }
+        };
         let metrics_prefix = self
             .metrics_prefix
             .expect("prefix for metrics names MUST be provided");
@@ -192,19 +432,39 @@ impl AppFactoryBuilder {
 
         // Prepare metrics collection middleware and report endpoint.
         let metrics_exporter = MetricsExporter::new(metrics_registry.clone());
+        let mut excluded = vec![self.metrics_path];
+        excluded.extend(self.liveness_path);
+        excluded.extend(self.readiness.as_ref().map(|(path, _)| *path));
         let metrics_collector = MetricsCollector::build()
+            .exclude_paths(excluded)
+            .normalise_unmatched(true)
             .prefix(metrics_prefix)
             .registry(metrics_registry)
             .finish();
 
         // Return the factory that can initialise and finalise Apps.
-        AppFactory {
+        Ok(AppFactory {
+            admin_conf: self.admin_conf,
             app_conf: self.app_conf,
             conf: self.conf,
+            cors: self.cors,
+            liveness_path: self.liveness_path,
             metrics_collector,
             metrics_exporter,
             metrics_path: self.metrics_path,
-        }
+            middleware: self.middleware,
+            payload_limit,
+            readiness: self.readiness,
+            request_id: self.request_id,
+        })
+    }
+
+    /// Serve a liveness endpoint at the given path.
+    ///
+    /// The endpoint responds with `200 OK` to `GET` requests once the server is running.
+    pub fn liveness_probe(mut self, path: &'static str) -> Self {
+        self.liveness_path = Some(path);
+        self
     }
 
     /// Provide the required request metrics parameters.
@@ -214,11 +474,67 @@ impl AppFactoryBuilder {
         self
     }
 
+    /// Add a custom middleware function at the given position in the middleware stack.
+    ///
+    /// Middleware functions have the same signature as functions passed to
+    /// [`actix_web::middleware::from_fn`] and operate on [`BoxBody`] responses.
+    /// Each [`MiddlewareSlot`] holds at most one middleware: setting a middleware
+    /// for a slot that already has one replaces it.
+    pub fn middleware<F, Fut>(mut self, slot: MiddlewareSlot, middleware: F) -> Self
+    where
+        F: Fn(ServiceRequest, Next<BoxBody>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = MiddlewareResult> + 'static,
+    {
+        let middleware: MiddlewareCallback =
+            Arc::new(move |request, next| Box::pin(middleware(request, next)));
+        self.middleware.insert(slot, middleware);
+        self
+    }
+
+    /// Serve a readiness endpoint at the given path, backed by the given probe.
+    ///
+    /// The endpoint responds to `GET` requests with `200 OK` when the probe returns `true`
+    /// and with `503 Service Unavailable` otherwise.
+    /// The probe is called for every request so it should be cheap to check.
+    pub fn readiness_probe<F>(mut self, path: &'static str, probe: F) -> Self
+    where
+        F: Fn() -> bool + Send + Sync + 'static,
+    {
+        let probe: ReadinessProbe = Arc::new(probe);
+        self.readiness = Some((path, probe));
+        self
+    }
+
+    /// Attach a request ID to every request and return it in the response headers.
+    ///
+    /// Request IDs are taken from the [`REQUEST_ID_HEADER`] of incoming requests when set,
+    /// or generated otherwise, and are returned to clients in the same header.
+    /// Per-request [`Context`](crate::context::Context)s include the ID as a
+    /// [`RequestId`](crate::context::RequestId) value and as a public log value.
+    pub fn request_id(mut self, enabled: bool) -> Self {
+        self.request_id = enabled;
+        self
+    }
+
     /// Set the endpoint path to export metrics on.
     pub fn metrics_path(mut self, path: &'static str) -> Self {
         self.metrics_path = path;
         self
     }
+
+    /// Limit the size, in bytes, of request payloads extracted by handlers.
+    ///
+    /// The limit applies to payloads extracted with [`actix_web::web::Json`],
+    /// [`actix_web::web::Bytes`] and [`String`].
+    /// Requests with larger payloads are rejected with a `413 Payload Too Large` response.
+    ///
+    /// Apps can still set their own [`JsonConfig`] or [`PayloadConfig`] to override
+    /// the limit for specific scopes or resources.
+    /// A limit set with [`ServerConfig::payload_limit`] takes precedence over this one.
+    pub fn payload_limit(mut self, bytes: usize) -> Self {
+        self.payload_limit = Some(bytes);
+        self
+    }
 }
 
 /// Errors encountered while building an [`HttpServer`](actix_web::HttpServer).
@@ -232,6 +548,22 @@ pub enum BuildError {
     #[error("unable to bind the server to '{0}'")]
     Bind(String),
 
+    /// Invalid origin allowed by the CORS policy.
+    ///
+    /// Error parameters:
+    ///
+    /// - The invalid origin.
+    #[error("invalid CORS allowed origin '{0}'")]
+    CorsOrigin(String),
+
+    /// The configured request payload limit can't be represented on this platform.
+    ///
+    /// Error parameters:
+    ///
+    /// - The configured payload limit.
+    #[error("request payload limit '{0}' is too large for this platform")]
+    PayloadLimit(String),
+
     /// Unable to set client CA certificates from file.
     ///
     /// Error parameters:
@@ -264,3 +596,465 @@ pub enum BuildError {
     #[error("unable to set server private key from PEM file '{0}")]
     TlsServerKey(String),
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    use actix_web::body::BoxBody;
+    use actix_web::dev::ServiceRequest;
+    use actix_web::http::header;
+    use actix_web::http::Method;
+    use actix_web::http::StatusCode;
+    use actix_web::middleware::Next;
+    use actix_web::test::call_service;
+    use actix_web::test::init_service;
+    use actix_web::test::try_call_service;
+    use actix_web::test::TestRequest;
+    use actix_web::HttpResponse;
+    use prometheus::Registry;
+
+    use super::AppConfigurer;
+    use super::AppFactory;
+    use super::BuildError;
+    use super::CorsPolicy;
+    use super::MetricsDisabledResponse;
+    use super::MiddlewareResult;
+    use super::MiddlewareSlot;
+    use super::ServerConfig;
+    use super::REQUEST_ID_HEADER;
+    use crate::context::ActixTransform;
+    use crate::context::Context;
+    use crate::context::RequestId;
+
+    /// Send a GET request to a running server and return the response status code.
+    async fn get_status(address: std::net::SocketAddr, path: &'static str) -> u16 {
+        actix_web::rt::task::spawn_blocking(move || {
+            use std::io::Read;
+            use std::io::Write;
+            let mut stream = std::net::TcpStream::connect(address).unwrap();
+            let request = format!(
+                "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+                path
+            );
+            stream.write_all(request.as_bytes()).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            let status = response.split(' ').nth(1).unwrap();
+            status.parse().unwrap()
+        })
+        .await
+        .unwrap()
+    }
+
+    /// Find a free local address for a test server to bind to.
+    fn free_address() -> std::net::SocketAddr {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap()
+    }
+
+    /// Record the order middleware run in and reject requests to the path of the slot.
+    fn recorder(
+        calls: &Arc<Mutex<Vec<&'static str>>>,
+        name: &'static str,
+    ) -> impl Fn(
+        ServiceRequest,
+        Next<BoxBody>,
+    ) -> futures::future::LocalBoxFuture<'static, MiddlewareResult> {
+        let calls = Arc::clone(calls);
+        move |request, next| {
+            calls.lock().unwrap().push(name);
+            Box::pin(async move {
+                if request.path() == format!("/{}", name) {
+                    let response = HttpResponse::Forbidden().finish();
+                    return Ok(request.into_response(response));
+                }
+                next.call(request).await
+            })
+        }
+    }
+
+    /// Count requests to the given path observed by the metrics middleware.
+    fn requests_count(registry: &Registry, path: &str) -> u64 {
+        registry
+            .gather()
+            .iter()
+            .filter(|family| family.get_name() == "test_request_durations")
+            .flat_map(|family| family.get_metric())
+            .filter(|metric| {
+                metric
+                    .get_label()
+                    .iter()
+                    .any(|label| label.get_name() == "path" && label.get_value() == path)
+            })
+            .map(|metric| metric.get_histogram().get_sample_count())
+            .sum()
+    }
+
+    #[actix_web::test]
+    async fn cors_policy_applied() {
+        let policy = CorsPolicy {
+            allowed_methods: vec![Method::GET],
+            allowed_origins: vec!["https://dashboard.example.com".into()],
+            ..Default::default()
+        };
+        let factory = AppFactory::configure(AppConfigurer::default(), ServerConfig::default())
+            .cors(policy)
+            .metrics("test", Registry::new())
+            .done()
+            .unwrap();
+        let app = factory.initialise();
+        let app = init_service(factory.finalise(app)).await;
+
+        let request = TestRequest::default()
+            .method(Method::OPTIONS)
+            .uri("/metrics")
+            .insert_header((header::ORIGIN, "https://dashboard.example.com"))
+            .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "GET"))
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let origin = response
+            .headers()
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .expect("CORS allow origin header");
+        assert_eq!(origin, "https://dashboard.example.com");
+
+        let request = TestRequest::get()
+            .uri("/metrics")
+            .insert_header((header::ORIGIN, "https://other.example.com"))
+            .to_request();
+        let response = call_service(&app, request).await;
+        let origin = response.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN);
+        assert!(origin.is_none());
+    }
+
+    #[test]
+    fn cors_policy_invalid_origin() {
+        let policy = CorsPolicy {
+            allowed_origins: vec!["not an origin".into()],
+            ..Default::default()
+        };
+        let error = AppFactory::configure(AppConfigurer::default(), ServerConfig::default())
+            .cors(policy)
+            .metrics("test", Registry::new())
+            .done()
+            .err()
+            .expect("invalid origin to be rejected");
+        assert!(matches!(
+            error.downcast_ref::<BuildError>(),
+            Some(BuildError::CorsOrigin(origin)) if origin == "not an origin",
+        ));
+    }
+
+    #[actix_web::get("/request-id")]
+    async fn request_id_context(context: Context) -> HttpResponse {
+        let id = context.require::<RequestId>();
+        HttpResponse::Ok().body(id.to_string())
+    }
+
+    #[rstest::rstest]
+    #[case(None, None)]
+    #[case(Some("client-id"), Some("client-id"))]
+    #[case(Some("not valid"), None)]
+    #[actix_web::test]
+    async fn request_id(#[case] sent: Option<&str>, #[case] expected: Option<&str>) {
+        let factory = AppFactory::configure(AppConfigurer::default(), ServerConfig::default())
+            .metrics("test", Registry::new())
+            .request_id(true)
+            .done()
+            .unwrap();
+        let app = factory
+            .initialise()
+            .service(request_id_context)
+            .app_data(actix_web::web::Data::new(Context::fixture()))
+            .wrap(ActixTransform);
+        let app = init_service(factory.finalise(app)).await;
+
+        let mut request = TestRequest::get().uri("/request-id");
+        if let Some(sent) = sent {
+            request = request.insert_header((REQUEST_ID_HEADER, sent));
+        }
+        let response = call_service(&app, request.to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let header = response
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .expect("request ID header")
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = actix_web::test::read_body(response).await;
+        assert_eq!(body, header.as_bytes());
+        match expected {
+            Some(expected) => assert_eq!(header, expected),
+            None => assert!(uuid::Uuid::parse_str(&header).is_ok()),
+        }
+    }
+
+    #[actix_web::test]
+    async fn request_id_on_error() {
+        let factory = AppFactory::configure(AppConfigurer::default(), ServerConfig::default())
+            .metrics("test", Registry::new())
+            .middleware(MiddlewareSlot::BeforeMetrics, |_, _| async {
+                Err(actix_web::error::ErrorForbidden("rejected"))
+            })
+            .request_id(true)
+            .done()
+            .unwrap();
+        let app = factory.initialise();
+        let app = init_service(factory.finalise(app)).await;
+
+        let request = TestRequest::get()
+            .uri("/metrics")
+            .insert_header((REQUEST_ID_HEADER, "client-id"))
+            .to_request();
+        let error = try_call_service(&app, request)
+            .await
+            .err()
+            .expect("middleware error to be returned");
+        let response = error.error_response();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let header = response.headers().get(REQUEST_ID_HEADER);
+        assert_eq!(header.expect("request ID header"), "client-id");
+    }
+
+    #[actix_web::test]
+    async fn request_id_not_set() {
+        let factory = AppFactory::configure(AppConfigurer::default(), ServerConfig::default())
+            .metrics("test", Registry::new())
+            .done()
+            .unwrap();
+        let app = factory.initialise();
+        let app = init_service(factory.finalise(app)).await;
+        let request = TestRequest::get().uri("/metrics").to_request();
+        let response = call_service(&app, request).await;
+        assert!(response.headers().get(REQUEST_ID_HEADER).is_none());
+    }
+
+    #[actix_web::test]
+    async fn cors_policy_not_set() {
+        let factory = AppFactory::configure(AppConfigurer::default(), ServerConfig::default())
+            .metrics("test", Registry::new())
+            .done()
+            .unwrap();
+        let app = factory.initialise();
+        let app = init_service(factory.finalise(app)).await;
+
+        let request = TestRequest::get()
+            .uri("/metrics")
+            .insert_header((header::ORIGIN, "https://dashboard.example.com"))
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let origin = response.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN);
+        assert!(origin.is_none());
+    }
+
+    #[actix_web::test]
+    async fn custom_middleware_order() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let registry = Registry::new();
+        let factory = AppFactory::configure(AppConfigurer::default(), ServerConfig::default())
+            .metrics("test", registry.clone())
+            .middleware(MiddlewareSlot::AfterMetrics, recorder(&calls, "after"))
+            .middleware(MiddlewareSlot::BeforeMetrics, recorder(&calls, "before"))
+            .middleware(MiddlewareSlot::Outermost, recorder(&calls, "outermost"))
+            .done()
+            .unwrap();
+        let app = factory.initialise().route(
+            "/{name}",
+            actix_web::web::get().to(|| async { HttpResponse::Ok().finish() }),
+        );
+        let app = init_service(factory.finalise(app)).await;
+
+        // All middleware run, in order, for requests they let through.
+        let request = TestRequest::get().uri("/ok").to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(*calls.lock().unwrap(), ["outermost", "before", "after"]);
+
+        // Requests rejected before metrics collection are not observed.
+        let request = TestRequest::get().uri("/before").to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        // Requests rejected after metrics collection are observed.
+        let request = TestRequest::get().uri("/after").to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(requests_count(&registry, "/{name}"), 2);
+    }
+
+    #[actix_web::test]
+    async fn metrics_on_admin_server() {
+        let admin_address = free_address();
+        let conf = ServerConfig {
+            admin_bind: Some(admin_address.to_string()),
+            ..Default::default()
+        };
+        let factory = AppFactory::configure(AppConfigurer::default(), conf)
+            .metrics("test", Registry::new())
+            .done()
+            .unwrap();
+        let admin = factory.admin_server().unwrap().unwrap();
+        let admin_handle = admin.handle();
+        let admin = actix_web::rt::spawn(admin);
+
+        // Admin endpoints are served by the admin server.
+        assert_eq!(get_status(admin_address, "/metrics").await, 200);
+        assert_eq!(get_status(admin_address, "/health").await, 200);
+
+        // Metrics are no longer exposed by the main server.
+        let app = factory.initialise();
+        let app = init_service(factory.finalise(app)).await;
+        let request = TestRequest::get().uri("/metrics").to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        admin_handle.stop(true).await;
+        admin.await.unwrap().unwrap();
+    }
+
+    #[rstest::rstest]
+    #[case(MetricsDisabledResponse::Empty, StatusCode::OK, "")]
+    #[case(
+        MetricsDisabledResponse::Message,
+        StatusCode::OK,
+        "# Metrics export is disabled for this process.\n"
+    )]
+    #[case(MetricsDisabledResponse::NotFound, StatusCode::NOT_FOUND, "")]
+    #[actix_web::test]
+    async fn metrics_disabled(
+        #[case] disabled: MetricsDisabledResponse,
+        #[case] status: StatusCode,
+        #[case] body: &str,
+    ) {
+        let conf = ServerConfig {
+            metrics_disabled_response: disabled,
+            metrics_enabled: false,
+            ..Default::default()
+        };
+        let factory = AppFactory::configure(AppConfigurer::default(), conf)
+            .metrics("test", Registry::new())
+            .done()
+            .unwrap();
+        let app = factory.initialise();
+        let app = init_service(factory.finalise(app)).await;
+        let request = TestRequest::get().uri("/metrics").to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), status);
+        let actual = actix_web::test::read_body(response).await;
+        assert_eq!(actual, body.as_bytes());
+    }
+
+    #[actix_web::test]
+    async fn payload_limit() {
+        let factory = AppFactory::configure(AppConfigurer::default(), ServerConfig::default())
+            .metrics("test", Registry::new())
+            .payload_limit(32)
+            .done()
+            .unwrap();
+        let app = factory.initialise().route(
+            "/echo",
+            actix_web::web::post().to(|body: actix_web::web::Json<serde_json::Value>| async move {
+                HttpResponse::Ok().json(body.into_inner())
+            }),
+        );
+        let app = init_service(factory.finalise(app)).await;
+
+        let request = TestRequest::post()
+            .uri("/echo")
+            .set_json(serde_json::json!({"small": true}))
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let request = TestRequest::post()
+            .uri("/echo")
+            .set_json(serde_json::json!({"large": "x".repeat(64)}))
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[actix_web::test]
+    async fn payload_limit_from_config() {
+        let conf = ServerConfig {
+            payload_limit: Some("32B".parse().unwrap()),
+            ..Default::default()
+        };
+        let factory = AppFactory::configure(AppConfigurer::default(), conf)
+            .metrics("test", Registry::new())
+            .payload_limit(1024)
+            .done()
+            .unwrap();
+        let app = factory.initialise().route(
+            "/echo",
+            actix_web::web::post().to(|body: actix_web::web::Json<serde_json::Value>| async move {
+                HttpResponse::Ok().json(body.into_inner())
+            }),
+        );
+        let app = init_service(factory.finalise(app)).await;
+
+        let request = TestRequest::post()
+            .uri("/echo")
+            .set_json(serde_json::json!({"large": "x".repeat(64)}))
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[actix_web::test]
+    async fn health_probes() {
+        let ready = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let probe = Arc::clone(&ready);
+        let factory = AppFactory::configure(AppConfigurer::default(), ServerConfig::default())
+            .liveness_probe("/healthz")
+            .metrics("test", Registry::new())
+            .readiness_probe("/readyz", move || {
+                probe.load(std::sync::atomic::Ordering::SeqCst)
+            })
+            .done()
+            .unwrap();
+        let app = factory.initialise();
+        let app = init_service(factory.finalise(app)).await;
+
+        let request = TestRequest::get().uri("/healthz").to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let request = TestRequest::get().uri("/readyz").to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        ready.store(true, std::sync::atomic::Ordering::SeqCst);
+        let request = TestRequest::get().uri("/readyz").to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn health_probes_not_set() {
+        let factory = AppFactory::configure(AppConfigurer::default(), ServerConfig::default())
+            .metrics("test", Registry::new())
+            .done()
+            .unwrap();
+        let app = factory.initialise();
+        let app = init_service(factory.finalise(app)).await;
+        for path in ["/healthz", "/readyz"] {
+            let request = TestRequest::get().uri(path).to_request();
+            let response = call_service(&app, request).await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        }
+    }
+
+    #[test]
+    fn no_admin_server_by_default() {
+        let factory = AppFactory::configure(AppConfigurer::default(), ServerConfig::default())
+            .metrics("test", Registry::new())
+            .done()
+            .unwrap();
+        assert!(factory.admin_server().unwrap().is_none());
+    }
+}
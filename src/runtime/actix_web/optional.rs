@@ -0,0 +1,85 @@
+//! Middleware for apps created by an `AppFactory` that are applied only when set.
+use std::task::Context;
+use std::task::Poll;
+
+use actix_web::body::EitherBody;
+use actix_web::dev::Service;
+use actix_web::dev::ServiceRequest;
+use actix_web::dev::ServiceResponse;
+use actix_web::dev::Transform;
+use futures::future::Either;
+use futures::future::LocalBoxFuture;
+use futures::future::MapOk;
+use futures::FutureExt;
+use futures::TryFutureExt;
+
+/// Apply the wrapped middleware only if one is set.
+///
+/// Unlike [`Condition`](actix_web::middleware::Condition) no placeholder middleware
+/// is needed when the middleware is not set: requests go straight to the wrapped service.
+pub(super) struct Optional<T>(pub Option<T>);
+
+impl<S, T, BE, BD, Err> Transform<S, ServiceRequest> for Optional<T>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BD>, Error = Err> + 'static,
+    T: Transform<S, ServiceRequest, Response = ServiceResponse<BE>, Error = Err>,
+    T::Future: 'static,
+    T::InitError: 'static,
+    T::Transform: 'static,
+{
+    type Response = ServiceResponse<EitherBody<BE, BD>>;
+    type Error = Err;
+    type Transform = OptionalMiddleware<T::Transform, S>;
+    type InitError = T::InitError;
+    type Future = LocalBoxFuture<'static, Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        match &self.0 {
+            None => futures::future::ok(OptionalMiddleware::Disabled(service)).boxed_local(),
+            Some(middleware) => middleware
+                .new_transform(service)
+                .map_ok(OptionalMiddleware::Enabled)
+                .boxed_local(),
+        }
+    }
+}
+
+/// Service created by the [`Optional`] middleware.
+pub(super) enum OptionalMiddleware<E, D> {
+    Enabled(E),
+    Disabled(D),
+}
+
+/// Short-hand for response mapping functions used by [`OptionalMiddleware`] services.
+type MapBody<B, BE, BD> = fn(ServiceResponse<B>) -> ServiceResponse<EitherBody<BE, BD>>;
+
+impl<E, D, BE, BD, Err> Service<ServiceRequest> for OptionalMiddleware<E, D>
+where
+    E: Service<ServiceRequest, Response = ServiceResponse<BE>, Error = Err>,
+    D: Service<ServiceRequest, Response = ServiceResponse<BD>, Error = Err>,
+{
+    type Response = ServiceResponse<EitherBody<BE, BD>>;
+    type Error = Err;
+    type Future =
+        Either<MapOk<E::Future, MapBody<BE, BE, BD>>, MapOk<D::Future, MapBody<BD, BE, BD>>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self {
+            OptionalMiddleware::Enabled(service) => service.poll_ready(cx),
+            OptionalMiddleware::Disabled(service) => service.poll_ready(cx),
+        }
+    }
+
+    fn call(&self, request: ServiceRequest) -> Self::Future {
+        match self {
+            OptionalMiddleware::Enabled(service) => {
+                let map: MapBody<BE, BE, BD> = ServiceResponse::map_into_left_body;
+                Either::Left(service.call(request).map_ok(map))
+            }
+            OptionalMiddleware::Disabled(service) => {
+                let map: MapBody<BD, BE, BD> = ServiceResponse::map_into_right_body;
+                Either::Right(service.call(request).map_ok(map))
+            }
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//! Request ID middleware for apps created by an `AppFactory`.
+use actix_web::body::MessageBody;
+use actix_web::dev::ServiceRequest;
+use actix_web::dev::ServiceResponse;
+use actix_web::http::header::HeaderName;
+use actix_web::http::header::HeaderValue;
+use actix_web::http::StatusCode;
+use actix_web::middleware::Next;
+use actix_web::Error;
+use actix_web::HttpMessage;
+use actix_web::HttpResponse;
+use actix_web::ResponseError;
+
+use crate::context::RequestId;
+
+/// Header used to receive and return request IDs.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest request ID accepted from clients before a new ID is generated instead.
+const REQUEST_ID_MAX_LEN: usize = 128;
+
+/// Attach a [`RequestId`] to requests and return it to clients in the response headers.
+///
+/// Valid IDs provided by clients with the [`REQUEST_ID_HEADER`] are used as is,
+/// otherwise a new UUID is generated for the request.
+///
+/// Errors from inner services are wrapped so the responses they are converted into
+/// also carry the request ID.
+pub(super) async fn request_id<B>(
+    request: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<B>, Error>
+where
+    B: MessageBody + 'static,
+{
+    let id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| valid_id(value))
+        .map(String::from)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let value = HeaderValue::from_str(&id).ok();
+    request.extensions_mut().insert(RequestId::new(id));
+
+    // The request is moved into the inner service so errors can't be turned into
+    // responses here and are instead wrapped to add the header once they are.
+    let response = next.call(request).await;
+    let value = match value {
+        None => return response,
+        Some(value) => value,
+    };
+    let mut response = match response {
+        Ok(response) => response,
+        Err(error) => return Err(RequestIdError { error, id: value }.into()),
+    };
+    response
+        .headers_mut()
+        .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+    Ok(response)
+}
+
+/// Error from an inner service returned with the ID of the request that caused it.
+#[derive(Debug)]
+struct RequestIdError {
+    error: Error,
+    id: HeaderValue,
+}
+
+impl std::fmt::Display for RequestIdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.error, f)
+    }
+}
+
+impl ResponseError for RequestIdError {
+    fn status_code(&self) -> StatusCode {
+        self.error.as_response_error().status_code()
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = self.error.error_response();
+        response
+            .headers_mut()
+            .insert(HeaderName::from_static(REQUEST_ID_HEADER), self.id.clone());
+        response
+    }
+}
+
+/// Check if a client provided request ID is acceptable.
+fn valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= REQUEST_ID_MAX_LEN
+        && id.bytes().all(|byte| byte.is_ascii_graphic())
+}
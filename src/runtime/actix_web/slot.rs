@@ -0,0 +1,45 @@
+//! Custom middleware applied to apps created by an `AppFactory` in a given slot.
+use std::sync::Arc;
+
+use actix_service::boxed::BoxService;
+use actix_service::ServiceExt;
+use actix_web::body::BoxBody;
+use actix_web::body::MessageBody;
+use actix_web::dev::Service;
+use actix_web::dev::ServiceRequest;
+use actix_web::dev::ServiceResponse;
+use actix_web::dev::Transform;
+use actix_web::middleware::from_fn;
+use actix_web::Error;
+use futures::future::LocalBoxFuture;
+use futures::FutureExt;
+use futures::TryFutureExt;
+
+use super::MiddlewareCallback;
+
+/// Apply a custom [`MiddlewareCallback`] to requests.
+///
+/// Responses from the wrapped service are converted into [`BoxBody`] responses
+/// so custom middleware does not need to be generic over the response body.
+pub(super) struct SlotMiddleware(pub MiddlewareCallback);
+
+impl<S, B> Transform<S, ServiceRequest> for SlotMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Transform = BoxService<ServiceRequest, Self::Response, Self::Error>;
+    type InitError = ();
+    type Future = LocalBoxFuture<'static, Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        let service = service.map(ServiceResponse::map_into_boxed_body);
+        let callback = Arc::clone(&self.0);
+        from_fn(move |request, next| callback(request, next))
+            .new_transform(service)
+            .map_ok(actix_service::boxed::service)
+            .boxed_local()
+    }
+}
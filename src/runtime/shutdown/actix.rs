@@ -0,0 +1,122 @@
+//! [`ShutdownManager`](super::ShutdownManager) extension to watch `actix_web` servers.
+use anyhow::Result;
+use tokio::sync::watch;
+
+use super::ShutdownError;
+use super::ShutdownManagerBuilder;
+
+/// Reason a watched [`actix_web::dev::Server`] stopped.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ActixServerStop {
+    /// The server stopped with an error, independently of the shutdown sequence.
+    Crashed,
+
+    /// The server stopped on its own without error, independently of the shutdown sequence.
+    Exited,
+
+    /// The server was stopped as part of the shutdown sequence.
+    Shutdown,
+}
+
+/// Observe why a watched [`actix_web::dev::Server`] stopped.
+///
+/// Handles are returned by [`ShutdownManagerBuilder::watch_actix_named`].
+#[derive(Clone, Debug)]
+pub struct ActixServerHandle {
+    name: String,
+    reason: watch::Receiver<Option<ActixServerStop>>,
+}
+
+impl ActixServerHandle {
+    /// Name of the watched server.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Reason the server stopped, if it has stopped.
+    pub fn stop_reason(&self) -> Option<ActixServerStop> {
+        *self.reason.borrow()
+    }
+
+    /// Wait for the server to stop and return the reason it stopped.
+    ///
+    /// Returns `None` if the server was cancelled at the end of the shutdown sequence
+    /// before it could stop.
+    pub async fn stopped(&self) -> Option<ActixServerStop> {
+        let mut reason = self.reason.clone();
+        loop {
+            if let Some(stop) = *reason.borrow_and_update() {
+                return Some(stop);
+            }
+            if reason.changed().await.is_err() {
+                return *reason.borrow();
+            }
+        }
+    }
+}
+
+impl<T: Send + 'static> ShutdownManagerBuilder<T> {
+    /// Watch [`actix_web::dev::Server`] for exit, returning the given value.
+    pub fn watch_actix(&mut self, server: actix_web::dev::Server, value: T) -> &mut Self {
+        self.watch_actix_server(None, server, value);
+        self
+    }
+
+    /// Watch a named [`actix_web::dev::Server`] for exit, returning the given value.
+    ///
+    /// The returned [`ActixServerHandle`] reports if the server was stopped by the
+    /// shutdown sequence or if it stopped (or crashed) independently.
+    /// Errors from the server also include its name.
+    ///
+    /// Returns an error, and the server is not watched, if a server with the same name
+    /// is already watched.
+    pub fn watch_actix_named(
+        &mut self,
+        name: &str,
+        server: actix_web::dev::Server,
+        value: T,
+    ) -> Result<ActixServerHandle> {
+        if !self.actix_names.insert(name.to_string()) {
+            anyhow::bail!(ShutdownError::ActixServerDuplicate(name.to_string()));
+        }
+        let reason = self.watch_actix_server(Some(name.to_string()), server, value);
+        Ok(ActixServerHandle {
+            name: name.to_string(),
+            reason,
+        })
+    }
+
+    /// Watch an [`actix_web::dev::Server`] and report the reason it stopped.
+    fn watch_actix_server(
+        &mut self,
+        name: Option<String>,
+        server: actix_web::dev::Server,
+        value: T,
+    ) -> watch::Receiver<Option<ActixServerStop>> {
+        let notification = self.shutdown_notification();
+        let (sender, receiver) = watch::channel(None);
+        self.watch_future(async move {
+            let handle = server.handle();
+            tokio::select! {
+                reason = server => {
+                    if let Err(error) = reason {
+                        sender.send_replace(Some(ActixServerStop::Crashed));
+                        let context = match name {
+                            None => ShutdownError::ActixServer,
+                            Some(name) => ShutdownError::ActixServerNamed(name),
+                        };
+                        let error = anyhow::anyhow!(error).context(context);
+                        anyhow::bail!(error);
+                    }
+                    sender.send_replace(Some(ActixServerStop::Exited));
+                },
+                _ = notification => {
+                    handle.stop(true).await;
+                    sender.send_replace(Some(ActixServerStop::Shutdown));
+                },
+            };
+            Ok(value)
+        });
+        receiver
+    }
+}
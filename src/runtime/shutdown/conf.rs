@@ -0,0 +1,80 @@
+//! Configuration options for process shutdown.
+use serde::Serialize;
+
+use super::ShutdownManagerBuilder;
+use super::DEFAULT_SHUTDOWN_GRACE_TIMEOUT;
+use super::DEFAULT_SHUTDOWN_PROGRESS_INTERVAL;
+use crate::utils::config::HumanDuration;
+
+/// Process shutdown configuration options.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, serde::Deserialize)]
+pub struct ShutdownConfig {
+    /// Allowed time for operations to complete once process shutdown begins.
+    #[serde(default = "ShutdownConfig::default_grace_timeout")]
+    pub grace_timeout: HumanDuration,
+
+    /// Interval between progress reports logged while waiting for operations to complete.
+    ///
+    /// Set to zero to disable progress reports.
+    #[serde(default = "ShutdownConfig::default_progress_interval")]
+    pub progress_interval: HumanDuration,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        ShutdownConfig {
+            grace_timeout: ShutdownConfig::default_grace_timeout(),
+            progress_interval: ShutdownConfig::default_progress_interval(),
+        }
+    }
+}
+
+impl ShutdownConfig {
+    /// Apply the configuration to a [`ShutdownManagerBuilder`].
+    pub fn apply<'builder, T>(
+        &self,
+        builder: &'builder mut ShutdownManagerBuilder<T>,
+    ) -> &'builder mut ShutdownManagerBuilder<T> {
+        builder
+            .graceful_shutdown_timeout(self.grace_timeout.duration())
+            .graceful_shutdown_progress_interval(self.progress_interval.duration())
+    }
+
+    fn default_grace_timeout() -> HumanDuration {
+        HumanDuration::from_secs(DEFAULT_SHUTDOWN_GRACE_TIMEOUT)
+    }
+
+    fn default_progress_interval() -> HumanDuration {
+        HumanDuration::from_secs(DEFAULT_SHUTDOWN_PROGRESS_INTERVAL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::ShutdownConfig;
+    use crate::runtime::shutdown::ShutdownManager;
+
+    #[test]
+    fn build_manager_from_config() {
+        let conf = ShutdownConfig {
+            grace_timeout: "2m".parse().unwrap(),
+            progress_interval: "10s".parse().unwrap(),
+        };
+        let mut builder = ShutdownManager::<()>::builder();
+        conf.apply(&mut builder).watch_signal_with_default();
+        assert_eq!(builder.grace_duration, Duration::from_secs(120));
+        assert_eq!(builder.progress_interval, Duration::from_secs(10));
+        let manager = builder.build();
+        assert_eq!(manager.grace_timeout, Duration::from_secs(120));
+        assert_eq!(manager.progress_interval, Duration::from_secs(10));
+    }
+
+    #[test]
+    fn default_config() {
+        let conf = ShutdownConfig::default();
+        assert_eq!(conf.grace_timeout.duration(), Duration::from_secs(120));
+        assert_eq!(conf.progress_interval.duration(), Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,61 @@
+//! Prometheus metrics about the process shutdown sequence.
+use anyhow::Context;
+use anyhow::Result;
+use prometheus::Counter;
+use prometheus::Histogram;
+use prometheus::HistogramOpts;
+use prometheus::Opts;
+use prometheus::Registry;
+
+use super::ShutdownError;
+
+/// Metrics updated by [`ShutdownManager::wait`](super::ShutdownManager::wait).
+pub(super) struct ShutdownMetrics {
+    /// Duration (in seconds) of the graceful shutdown sequence.
+    pub duration: Histogram,
+
+    /// Number of watched tasks cancelled during shutdown.
+    pub tasks_aborted: Counter,
+
+    /// Number of watched tasks that exited on their own during shutdown.
+    pub tasks_completed: Counter,
+}
+
+impl ShutdownMetrics {
+    /// Create shutdown metrics with the given name prefix and register them.
+    pub fn register(registry: &Registry, prefix: &str) -> Result<ShutdownMetrics> {
+        let duration = Histogram::with_opts(
+            HistogramOpts::new(
+                format!("{}_shutdown_duration", prefix),
+                "Duration (in seconds) of the graceful shutdown sequence",
+            )
+            .buckets(vec![0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0]),
+        )
+        .context(ShutdownError::Metrics)?;
+        let tasks_aborted = Counter::with_opts(Opts::new(
+            format!("{}_shutdown_tasks_aborted", prefix),
+            "Number of watched tasks cancelled during shutdown",
+        ))
+        .context(ShutdownError::Metrics)?;
+        let tasks_completed = Counter::with_opts(Opts::new(
+            format!("{}_shutdown_tasks_completed", prefix),
+            "Number of watched tasks that exited on their own during shutdown",
+        ))
+        .context(ShutdownError::Metrics)?;
+
+        registry
+            .register(Box::new(duration.clone()))
+            .context(ShutdownError::Metrics)?;
+        registry
+            .register(Box::new(tasks_aborted.clone()))
+            .context(ShutdownError::Metrics)?;
+        registry
+            .register(Box::new(tasks_completed.clone()))
+            .context(ShutdownError::Metrics)?;
+        Ok(ShutdownMetrics {
+            duration,
+            tasks_aborted,
+            tasks_completed,
+        })
+    }
+}
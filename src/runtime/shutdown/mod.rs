@@ -1,17 +1,35 @@
 //! Tools to manage process shutdown on error or at user's request.
+use std::cell::Cell;
 use std::future::Future;
+use std::sync::Arc;
+use std::sync::Mutex;
 use std::time::Duration;
 
 use anyhow::Result;
 use futures::stream::FuturesUnordered;
 use futures::stream::StreamExt;
 use slog::Logger;
+use tokio::sync::oneshot;
 use tokio::sync::watch;
+use tokio::task::AbortHandle;
 use tokio::task::JoinHandle;
 
+#[cfg(feature = "runtime-shutdown_actix")]
+mod actix;
+mod conf;
+#[cfg(feature = "runtime-shutdown_metrics")]
+mod metrics;
 #[cfg(test)]
 mod tests;
 
+#[cfg(feature = "runtime-shutdown_actix")]
+pub use self::actix::ActixServerHandle;
+#[cfg(feature = "runtime-shutdown_actix")]
+pub use self::actix::ActixServerStop;
+pub use self::conf::ShutdownConfig;
+pub use crate::utils::config::HumanDuration;
+pub use crate::utils::config::HumanDurationError;
+
 /// Short-hand for tokio task handles that can return an [`anyhow::Result`].
 type WatchTask<T> = JoinHandle<Result<T>>;
 
@@ -21,17 +39,38 @@ type WatchTaskOutput<T> = Option<<WatchTask<T> as Future>::Output>;
 /// Default time to wait for graceful shutdown to complete.
 pub const DEFAULT_SHUTDOWN_GRACE_TIMEOUT: u64 = 2 * 60;
 
+/// Default time, in seconds, between progress reports while waiting for graceful shutdown.
+pub const DEFAULT_SHUTDOWN_PROGRESS_INTERVAL: u64 = 5;
+
 /// Exit code for abrupt exit caused by user signal during graceful shutdown.
-const FORCE_SHUTDOWN_EXIT_CODE: i32 = 42;
+///
+/// Metrics and logs can't be reliably flushed when the process is forced to exit
+/// so this exit code is how operators can tell a forced exit happened.
+pub const FORCE_SHUTDOWN_EXIT_CODE: i32 = 42;
 
 /// Errors waiting for exit or during the shutdown sequence.
 #[derive(Debug, thiserror::Error)]
 pub enum ShutdownError {
-    #[cfg(feature = "runtime-shutdown_actix")]
     /// Actix-web HttpServer stopped with an error.
+    #[cfg(feature = "runtime-shutdown_actix")]
     #[error("actix-web HttpServer stopped with an error")]
     ActixServer,
 
+    /// An actix-web HttpServer with the same name is already watched.
+    #[cfg(feature = "runtime-shutdown_actix")]
+    #[error("actix-web HttpServer '{0}' is already watched")]
+    ActixServerDuplicate(String),
+
+    /// Named actix-web HttpServer stopped with an error.
+    #[cfg(feature = "runtime-shutdown_actix")]
+    #[error("actix-web HttpServer '{0}' stopped with an error")]
+    ActixServerNamed(String),
+
+    /// Unable to create or register shutdown metrics.
+    #[cfg(feature = "runtime-shutdown_metrics")]
+    #[error("unable to create or register shutdown metrics")]
+    Metrics,
+
     /// Unable to wait for exit signal from the OS.
     #[error("unable to wait for exit signal from the OS")]
     SignalError,
@@ -60,6 +99,7 @@ pub enum ShutdownError {
 ///
 /// * Watching [`tokio::task`s]: begin exit when any registered task exists.
 /// * Process signals (from users): begin exit when the process receives an exit signal from the OS.
+/// * Programmatic triggers: begin exit when a [`ShutdownHandle`] is used to request it.
 ///
 /// The clean shutdown sequence works as follows:
 ///
@@ -67,6 +107,9 @@ pub enum ShutdownError {
 ///    (see [`ShutdownManagerBuilder::shutdown_notification`]).
 /// 2. The [`ShutdownManager`] instance awaits for all registered [`tokio::task`s] to complete
 ///    or for a configurable timeout to expire, whichever comes first.
+///    While waiting, the number of remaining tasks is periodically logged.
+///    Tasks watched with [`ShutdownManagerBuilder::watch_tokio_with_grace`] are cancelled
+///    as soon as their own grace period expires, within the overall timeout.
 /// 3. All [`tokio::task`s] that have not completed yet are cancelled.
 /// 4. The shutdown sequence returns, with the original error if a task triggered shutdown.
 ///
@@ -87,12 +130,18 @@ pub enum ShutdownError {
 ///
 /// When the shutdown signal is received once the above mentioned shutdown sequence begins.
 /// If a second signal is sent to the process while shutdown is in progress the process is
-/// terminated abruptly [`std::process::exit`].
+/// terminated abruptly [`std::process::exit`] with the [`FORCE_SHUTDOWN_EXIT_CODE`].
 ///
 /// Process signals are only used as an exit condition if an exit signal value is defined with
 /// [`ShutdownManagerBuilder::watch_signal`] or
 /// [`ShutdownManagerBuilder::watch_signal_with_default`].
 ///
+/// ## Programmatic triggers
+///
+/// Application code can request shutdown with a [`ShutdownHandle`] returned by
+/// [`ShutdownManagerBuilder::trigger`], for example from an admin HTTP endpoint.
+/// Requesting shutdown this way begins the same sequence as an exit signal would.
+///
 /// ## Receiving the shutdown signal
 ///
 /// During process initialisation components can register interest into shutdown notifications.
@@ -128,9 +177,14 @@ pub enum ShutdownError {
 pub struct ShutdownManager<T> {
     exit_logger: Option<Logger>,
     grace_timeout: Duration,
+    #[cfg(feature = "runtime-shutdown_metrics")]
+    metrics: Option<self::metrics::ShutdownMetrics>,
+    progress_interval: Duration,
     shutdown_notification_sender: watch::Sender<bool>,
     signal_exit_value: Option<Result<T>>,
+    task_graces: Vec<(AbortHandle, Duration)>,
     tasks: FuturesUnordered<WatchTask<T>>,
+    trigger_receiver: Option<oneshot::Receiver<Result<T>>>,
 }
 
 impl<T> ShutdownManager<T> {
@@ -140,12 +194,22 @@ impl<T> ShutdownManager<T> {
     pub fn builder() -> ShutdownManagerBuilder<T> {
         let (sender, receiver) = watch::channel(false);
         ShutdownManagerBuilder {
+            #[cfg(feature = "runtime-shutdown_actix")]
+            actix_names: Default::default(),
             exit_logger: None,
             grace_duration: Duration::from_secs(DEFAULT_SHUTDOWN_GRACE_TIMEOUT),
+            max_tasks: None,
+            max_tasks_warned: false,
+            #[cfg(feature = "runtime-shutdown_metrics")]
+            metrics: None,
+            progress_interval: Duration::from_secs(DEFAULT_SHUTDOWN_PROGRESS_INTERVAL),
             shutdown_notification_receiver: receiver,
             shutdown_notification_sender: sender,
             signal_exit_value: None,
+            task_graces: Vec::new(),
             tasks: Vec::new(),
+            trigger: None,
+            trigger_receiver: None,
         }
     }
 
@@ -162,11 +226,23 @@ impl<T> ShutdownManager<T> {
             self.signal_exit_value,
             self.exit_logger.as_ref(),
         );
+        let exit_on_trigger = ShutdownManager::exit_condition_trigger(
+            self.trigger_receiver,
+            self.exit_logger.as_ref(),
+        );
         let exit = tokio::select! {
             exit = exit_on_tokio_task => exit,
             exit = exit_on_signal => exit,
+            exit = exit_on_trigger => exit,
         };
 
+        #[cfg(feature = "runtime-shutdown_metrics")]
+        let metrics = self.metrics;
+        #[cfg(feature = "runtime-shutdown_metrics")]
+        let timer = metrics
+            .as_ref()
+            .map(|metrics| metrics.duration.start_timer());
+
         // Notify any interested parties about the graceful shutdown.
         let _ = self.shutdown_notification_sender.send(true);
         drop(self.shutdown_notification_sender);
@@ -176,8 +252,34 @@ impl<T> ShutdownManager<T> {
         // - All tasks have exited.
         // - Further user signals (this causes an abrupt exit and does not return here).
         // - The shutdown timeout has elapsed.
+        //
+        // Progress is logged periodically while waiting to help debug stuck shutdowns.
+        //
+        // Tasks with their own grace period are cancelled as it expires, which counts as exiting.
+        let mut task_graces: FuturesUnordered<_> = self
+            .task_graces
+            .into_iter()
+            .map(|(task, grace)| async move {
+                tokio::time::sleep(grace).await;
+                task.abort();
+            })
+            .collect();
+        let abort_on_task_grace = async {
+            while task_graces.next().await.is_some() {}
+            std::future::pending::<()>().await;
+        };
+        let deadline = tokio::time::Instant::now() + self.grace_timeout;
+        let remaining_tasks = Cell::new(self.tasks.len());
         let await_all_tokio = async {
             while let Some(task) = self.tasks.next().await {
+                remaining_tasks.set(remaining_tasks.get() - 1);
+                #[cfg(feature = "runtime-shutdown_metrics")]
+                if let Some(metrics) = &metrics {
+                    match &task {
+                        Err(error) if error.is_cancelled() => metrics.tasks_aborted.inc(),
+                        _ => metrics.tasks_completed.inc(),
+                    }
+                }
                 let logger = match &self.exit_logger {
                     None => continue,
                     Some(logger) => logger,
@@ -210,16 +312,40 @@ impl<T> ShutdownManager<T> {
             let _ = tokio::signal::ctrl_c().await;
             std::process::exit(FORCE_SHUTDOWN_EXIT_CODE);
         };
-        let grace_timeout = tokio::time::sleep(self.grace_timeout);
+        let report_progress = ShutdownManager::<T>::report_progress(
+            self.exit_logger.as_ref(),
+            self.progress_interval,
+            deadline,
+            &remaining_tasks,
+        );
+        let grace_timeout = tokio::time::sleep_until(deadline);
         tokio::select! {
             _ = await_all_tokio => (),
+            _ = report_progress => (),
+            _ = abort_on_task_grace => (),
             _ = exit_on_more_signals => (),
-            _ = grace_timeout => (),
+            _ = grace_timeout => {
+                if let Some(logger) = &self.exit_logger {
+                    slog::warn!(
+                        logger, "Graceful shutdown timed out, aborting remaining tasks";
+                        "grace_timeout" => ?self.grace_timeout,
+                        "remaining_tasks" => remaining_tasks.get(),
+                    );
+                }
+            }
         };
 
         // Ensure all tasks that have not completed still are cancelled.
         for task in self.tasks {
             task.abort();
+            #[cfg(feature = "runtime-shutdown_metrics")]
+            if let Some(metrics) = &metrics {
+                metrics.tasks_aborted.inc();
+            }
+        }
+        #[cfg(feature = "runtime-shutdown_metrics")]
+        if let Some(timer) = timer {
+            timer.observe_duration();
         }
 
         // Return the value/error that triggered shutdown.
@@ -229,6 +355,34 @@ impl<T> ShutdownManager<T> {
         exit
     }
 
+    /// Periodically log how many watched tasks are still running during graceful shutdown.
+    ///
+    /// This future never resolves and is dropped once waiting for graceful shutdown ends.
+    /// If no logger is set for the [`ShutdownManager`] instance, or the interval is zero,
+    /// no progress is reported.
+    async fn report_progress(
+        logger: Option<&Logger>,
+        interval: Duration,
+        deadline: tokio::time::Instant,
+        remaining_tasks: &Cell<usize>,
+    ) {
+        let logger = match logger {
+            Some(logger) if !interval.is_zero() => logger,
+            _ => return std::future::pending().await,
+        };
+        let start = tokio::time::Instant::now() + interval;
+        let mut ticker = tokio::time::interval_at(start, interval);
+        loop {
+            let now = ticker.tick().await;
+            let grace_left = deadline.saturating_duration_since(now);
+            slog::info!(
+                logger, "Waiting for watched tasks to complete graceful shutdown";
+                "remaining_tasks" => remaining_tasks.get(),
+                "grace_left_sec" => grace_left.as_secs(),
+            );
+        }
+    }
+
     /// Watch for exit signals from the OS.
     ///
     /// When an exit signal is received this future will return a value
@@ -261,6 +415,30 @@ impl<T> ShutdownManager<T> {
         exit_value.expect("signal exit value function must be set to get here")
     }
 
+    /// Watch for shutdown requests from a [`ShutdownHandle`].
+    ///
+    /// If no [`ShutdownHandle`] was created, or all are dropped without requesting shutdown,
+    /// this future never resolves.
+    ///
+    /// As exit conditions manipulate different [`ShutdownManager`] fields we decompose the
+    /// structure in [`ShutdownManager::wait`] and only take the needed fields for this condition.
+    async fn exit_condition_trigger(
+        receiver: Option<oneshot::Receiver<Result<T>>>,
+        logger: Option<&Logger>,
+    ) -> Result<T> {
+        let exit_value = match receiver {
+            None => std::future::pending().await,
+            Some(receiver) => match receiver.await {
+                Err(_) => std::future::pending().await,
+                Ok(exit_value) => exit_value,
+            },
+        };
+        if let Some(logger) = logger {
+            slog::info!(logger, "Shutdown requested: beginning graceful shutdown");
+        }
+        exit_value
+    }
+
     /// Watch for any tokio tasks to exit.
     ///
     /// This future resolves as soon as any of the registered tokio tasks ends regardless
@@ -305,12 +483,22 @@ impl<T> ShutdownManager<T> {
 
 /// Build [`ShutdownManager`] instances.
 pub struct ShutdownManagerBuilder<T> {
+    #[cfg(feature = "runtime-shutdown_actix")]
+    actix_names: std::collections::HashSet<String>,
     exit_logger: Option<Logger>,
     grace_duration: Duration,
+    max_tasks: Option<usize>,
+    max_tasks_warned: bool,
+    #[cfg(feature = "runtime-shutdown_metrics")]
+    metrics: Option<self::metrics::ShutdownMetrics>,
+    progress_interval: Duration,
     shutdown_notification_receiver: watch::Receiver<bool>,
     shutdown_notification_sender: watch::Sender<bool>,
     signal_exit_value: Option<Result<T>>,
+    task_graces: Vec<(AbortHandle, Duration)>,
     tasks: Vec<WatchTask<T>>,
+    trigger: Option<ShutdownHandle<T>>,
+    trigger_receiver: Option<oneshot::Receiver<Result<T>>>,
 }
 
 impl<T> ShutdownManagerBuilder<T> {
@@ -321,11 +509,17 @@ impl<T> ShutdownManagerBuilder<T> {
     /// This method panics if no exit condition is watched for.
     /// Make sure to call at least one of:
     ///
+    /// * [`ShutdownManagerBuilder::trigger`]
     /// * [`ShutdownManagerBuilder::watch_signal`]
+    /// * [`ShutdownManagerBuilder::watch_future`]
     /// * [`ShutdownManagerBuilder::watch_signal_with_default`]
     /// * [`ShutdownManagerBuilder::watch_tokio`]
+    /// * [`ShutdownManagerBuilder::watch_tokio_with_grace`]
     pub fn build(self) -> ShutdownManager<T> {
-        if self.tasks.is_empty() && self.signal_exit_value.is_none() {
+        if self.tasks.is_empty()
+            && self.signal_exit_value.is_none()
+            && self.trigger_receiver.is_none()
+        {
             panic!("ShutdownManager needs at least one exit condition to watch for");
         }
 
@@ -333,9 +527,14 @@ impl<T> ShutdownManagerBuilder<T> {
         ShutdownManager {
             exit_logger: self.exit_logger,
             grace_timeout: self.grace_duration,
+            #[cfg(feature = "runtime-shutdown_metrics")]
+            metrics: self.metrics,
+            progress_interval: self.progress_interval,
             shutdown_notification_sender: self.shutdown_notification_sender,
             signal_exit_value: self.signal_exit_value,
+            task_graces: self.task_graces,
             tasks,
+            trigger_receiver: self.trigger_receiver,
         }
     }
 
@@ -345,6 +544,50 @@ impl<T> ShutdownManagerBuilder<T> {
         self
     }
 
+    /// Set the interval between progress reports logged while waiting for graceful shutdown.
+    ///
+    /// Reports include the number of watched tasks still running and the time left
+    /// before the graceful shutdown timeout expires.
+    ///
+    /// A zero interval disables progress reports.
+    pub fn graceful_shutdown_progress_interval(&mut self, interval: Duration) -> &mut Self {
+        self.progress_interval = interval;
+        self
+    }
+
+    /// Set the maximum number of tasks expected to be watched for exit.
+    ///
+    /// Watching more tasks than the limit does not fail but logs a warning, once,
+    /// with the logger set by [`ShutdownManagerBuilder::logger`].
+    /// This helps catch bugs where tasks are registered in a loop by mistake.
+    ///
+    /// By default there is no limit to the number of watched tasks.
+    pub fn max_watched_tasks(&mut self, limit: usize) -> &mut Self {
+        self.max_tasks = Some(limit);
+        self
+    }
+
+    /// Record metrics about the shutdown sequence, registered with the given [`Registry`].
+    ///
+    /// The following metrics are created, with names starting with `prefix`:
+    ///
+    /// - `{prefix}_shutdown_duration`: duration (in seconds) of the graceful shutdown sequence.
+    /// - `{prefix}_shutdown_tasks_aborted`: number of watched tasks cancelled during shutdown.
+    /// - `{prefix}_shutdown_tasks_completed`: number of watched tasks that exited during shutdown.
+    ///
+    /// No metrics are created unless this method is called.
+    ///
+    /// Returns an error if the metrics can't be created or registered,
+    /// for example because metrics with the same names are already registered.
+    ///
+    /// [`Registry`]: prometheus::Registry
+    #[cfg(feature = "runtime-shutdown_metrics")]
+    pub fn metrics(&mut self, registry: prometheus::Registry, prefix: &str) -> Result<&mut Self> {
+        let metrics = self::metrics::ShutdownMetrics::register(&registry, prefix)?;
+        self.metrics = Some(metrics);
+        Ok(self)
+    }
+
     /// Set the logger used to inform of shutdown events and issues.
     pub fn logger(&mut self, logger: Logger) -> &mut Self {
         self.exit_logger = Some(logger);
@@ -365,6 +608,22 @@ impl<T> ShutdownManagerBuilder<T> {
         }
     }
 
+    /// Return a [`ShutdownHandle`] for application code to request shutdown with.
+    ///
+    /// All calls return handles to the same trigger so the first request wins.
+    pub fn trigger(&mut self) -> ShutdownHandle<T> {
+        if let Some(trigger) = &self.trigger {
+            return trigger.clone();
+        }
+        let (sender, receiver) = oneshot::channel();
+        let trigger = ShutdownHandle {
+            sender: Arc::new(Mutex::new(Some(sender))),
+        };
+        self.trigger = Some(trigger.clone());
+        self.trigger_receiver = Some(receiver);
+        trigger
+    }
+
     /// Watch [`tokio::signal::ctrl_c`] for exit, returning the given value.
     pub fn watch_signal(&mut self, exit_value: Result<T>) -> &mut Self {
         self.signal_exit_value = Some(exit_value);
@@ -374,26 +633,89 @@ impl<T> ShutdownManagerBuilder<T> {
     /// Watch a [`tokio::task::JoinHandle`] for exit.
     pub fn watch_tokio(&mut self, task: JoinHandle<Result<T>>) -> &mut Self {
         self.tasks.push(task);
+        self.check_max_tasks();
         self
     }
+
+    /// Watch a [`tokio::task::JoinHandle`] for exit, with its own graceful shutdown timeout.
+    ///
+    /// During graceful shutdown the task is cancelled once `grace` expires, even if other
+    /// tasks are still running.
+    /// The [`ShutdownManagerBuilder::graceful_shutdown_timeout`] still bounds the overall
+    /// shutdown sequence, even if `grace` is longer.
+    pub fn watch_tokio_with_grace(
+        &mut self,
+        task: JoinHandle<Result<T>>,
+        grace: Duration,
+    ) -> &mut Self {
+        self.task_graces.push((task.abort_handle(), grace));
+        self.watch_tokio(task)
+    }
+
+    /// Warn, once, if more tasks than the configured maximum are watched.
+    fn check_max_tasks(&mut self) {
+        let limit = match self.max_tasks {
+            Some(limit) if self.tasks.len() > limit => limit,
+            _ => return,
+        };
+        if self.max_tasks_warned {
+            return;
+        }
+        // Keep checking until a logger is set so the warning is not lost.
+        if let Some(logger) = &self.exit_logger {
+            slog::warn!(
+                logger, "Watching more tasks for exit than expected";
+                "limit" => limit,
+                "tasks" => self.tasks.len(),
+            );
+            self.max_tasks_warned = true;
+        }
+    }
+}
+
+/// Request graceful shutdown of the process from application code.
+///
+/// Handles are returned by [`ShutdownManagerBuilder::trigger`] and can be cloned
+/// and shared across tasks and threads.
+pub struct ShutdownHandle<T> {
+    sender: Arc<Mutex<Option<oneshot::Sender<Result<T>>>>>,
+}
+
+impl<T> Clone for ShutdownHandle<T> {
+    fn clone(&self) -> Self {
+        ShutdownHandle {
+            sender: Arc::clone(&self.sender),
+        }
+    }
+}
+
+impl<T> ShutdownHandle<T> {
+    /// Begin the graceful shutdown sequence, with [`ShutdownManager::wait`] returning `value`.
+    ///
+    /// Only the first request has any effect: later calls, from any clone of the handle,
+    /// are ignored and so is a request made after the process started shutting down.
+    pub fn shutdown(&self, value: Result<T>) {
+        let sender = self
+            .sender
+            .lock()
+            .expect("ShutdownHandle Mutex poisoned")
+            .take();
+        if let Some(sender) = sender {
+            let _ = sender.send(value);
+        }
+    }
 }
 
-#[cfg(feature = "runtime-shutdown_actix")]
 impl<T: Send + 'static> ShutdownManagerBuilder<T> {
-    /// Watch [`actix_web::dev::Server`] for exit, returning the given value.
-    pub fn watch_actix(&mut self, server: actix_web::dev::Server, value: T) -> &mut Self {
-        let notification = self.shutdown_notification();
-        self.watch_tokio(tokio::spawn(async {
-            let handle = server.handle();
-            tokio::select! {
-                reason = server => if let Err(error) = reason {
-                    let error = anyhow::anyhow!(error).context(ShutdownError::ActixServer);
-                    anyhow::bail!(error);
-                },
-                _ = notification => handle.stop(true).await,
-            };
-            Ok(value)
-        }))
+    /// Watch a [`Future`] for exit.
+    ///
+    /// The future is spawned as a [`tokio::task`] and watched as if passed to
+    /// [`ShutdownManagerBuilder::watch_tokio`], including cancellation at the end of shutdown.
+    pub fn watch_future<F>(&mut self, future: F) -> &mut Self
+    where
+        F: Future<Output = Result<T>> + Send + 'static,
+    {
+        self.watch_tokio(tokio::spawn(future))
     }
 }
 
@@ -1,5 +1,6 @@
 use super::ShutdownError;
 use super::ShutdownManager;
+use crate::testing::CaptureDrain;
 
 #[tokio::test]
 #[should_panic(expected = "at least one exit condition")]
@@ -38,6 +39,116 @@ async fn graceful_shutdown_timeout() {
     assert!(test_duration.as_millis() < 200);
 }
 
+#[tokio::test]
+async fn graceful_shutdown_timeout_warns() {
+    let drain = CaptureDrain::default();
+    let logger = slog::Logger::root(drain.clone(), slog::o!());
+    let task_stuck = tokio::spawn(std::future::pending());
+    let task_shutdown = tokio::spawn(async { Ok(()) });
+
+    let mut shutdown = ShutdownManager::builder();
+    shutdown
+        .logger(logger)
+        .graceful_shutdown_timeout(std::time::Duration::from_millis(10))
+        .watch_tokio(task_stuck)
+        .watch_tokio(task_shutdown);
+    let _ = shutdown.build().wait().await;
+
+    let messages = drain.messages();
+    let warning = "Graceful shutdown timed out, aborting remaining tasks";
+    assert!(messages.iter().any(|message| message == warning));
+}
+
+#[tokio::test]
+async fn max_watched_tasks_warns_once() {
+    let drain = CaptureDrain::default();
+    let logger = slog::Logger::root(drain.clone(), slog::o!());
+    let mut shutdown = ShutdownManager::<()>::builder();
+    shutdown.logger(logger).max_watched_tasks(2);
+    for _ in 0..2 {
+        shutdown.watch_tokio(tokio::spawn(async { Ok(()) }));
+    }
+    assert!(drain.messages().is_empty());
+
+    for _ in 0..3 {
+        shutdown.watch_tokio(tokio::spawn(async { Ok(()) }));
+    }
+    let messages = drain.messages();
+    assert_eq!(messages, ["Watching more tasks for exit than expected"]);
+    shutdown.build().wait().await.unwrap();
+}
+
+#[tokio::test]
+async fn max_watched_tasks_warns_once_logger_is_set() {
+    let drain = CaptureDrain::default();
+    let logger = slog::Logger::root(drain.clone(), slog::o!());
+    let mut shutdown = ShutdownManager::<()>::builder();
+    shutdown.max_watched_tasks(1);
+    for _ in 0..2 {
+        shutdown.watch_tokio(tokio::spawn(async { Ok(()) }));
+    }
+
+    shutdown.logger(logger);
+    shutdown.watch_tokio(tokio::spawn(async { Ok(()) }));
+    let messages = drain.messages();
+    assert_eq!(messages, ["Watching more tasks for exit than expected"]);
+    shutdown.build().wait().await.unwrap();
+}
+
+#[tokio::test]
+async fn progress_reported_during_grace() {
+    let drain = CaptureDrain::default();
+    let logger = slog::Logger::root(drain.clone(), slog::o!());
+    let task_long = tokio::spawn(async {
+        tokio::time::sleep(std::time::Duration::from_secs(5 * 60)).await;
+        Ok(())
+    });
+    let task_shutdown = tokio::spawn(async { Ok(()) });
+
+    let mut shutdown = ShutdownManager::builder();
+    shutdown
+        .logger(logger)
+        .graceful_shutdown_timeout(std::time::Duration::from_millis(100))
+        .graceful_shutdown_progress_interval(std::time::Duration::from_millis(20))
+        .watch_tokio(task_long)
+        .watch_tokio(task_shutdown);
+    let _ = shutdown.build().wait().await;
+
+    let messages = drain.messages();
+    let reports = messages
+        .iter()
+        .filter(|message| *message == "Waiting for watched tasks to complete graceful shutdown")
+        .count();
+    assert!(reports >= 2, "{:?}", messages);
+}
+
+#[tokio::test]
+async fn progress_reports_disabled_with_zero_interval() {
+    let drain = CaptureDrain::default();
+    let logger = slog::Logger::root(drain.clone(), slog::o!());
+    let task_long = tokio::spawn(async {
+        tokio::time::sleep(std::time::Duration::from_secs(5 * 60)).await;
+        Ok(())
+    });
+    let task_shutdown = tokio::spawn(async { Ok(()) });
+
+    let mut shutdown = ShutdownManager::builder();
+    shutdown
+        .logger(logger)
+        .graceful_shutdown_timeout(std::time::Duration::from_millis(50))
+        .graceful_shutdown_progress_interval(std::time::Duration::ZERO)
+        .watch_tokio(task_long)
+        .watch_tokio(task_shutdown);
+    let _ = shutdown.build().wait().await;
+
+    let messages = drain.messages();
+    let reports = messages
+        .iter()
+        .filter(|message| *message == "Waiting for watched tasks to complete graceful shutdown")
+        .count();
+    assert_eq!(reports, 0, "{:?}", messages);
+}
+
 #[tokio::test]
 async fn shutdown_notifications() {
     let mut shutdown = ShutdownManager::builder();
@@ -69,6 +180,37 @@ async fn shutdown_notifications() {
     assert!(flag);
 }
 
+#[tokio::test]
+async fn trigger_shutdown() {
+    let mut shutdown = ShutdownManager::builder();
+    let handle = shutdown.trigger();
+    let notification = shutdown.shutdown_notification();
+    let task = tokio::spawn(async move {
+        notification.await;
+        Ok("from task")
+    });
+    shutdown.watch_tokio(task);
+    let shutdown = shutdown.build();
+
+    let remote = handle.clone();
+    std::thread::spawn(move || remote.shutdown(Ok("from trigger")))
+        .join()
+        .unwrap();
+    handle.shutdown(Ok("ignored"));
+    let result = shutdown.wait().await.unwrap();
+    assert_eq!(result, "from trigger");
+}
+
+#[tokio::test]
+async fn trigger_shutdown_with_error() {
+    let mut shutdown = ShutdownManager::<()>::builder();
+    let handle = shutdown.trigger();
+    let shutdown = shutdown.build();
+    handle.shutdown(Err(anyhow::anyhow!("reload failed")));
+    let error = shutdown.wait().await.unwrap_err();
+    assert_eq!(error.to_string(), "reload failed");
+}
+
 #[tokio::test]
 async fn wait_for_task() {
     let task = tokio::spawn(async { Ok("test result") });
@@ -118,3 +260,174 @@ async fn wait_for_task_panic() {
         Err(error) => panic!("expected task to panic but got {:?}", error),
     }
 }
+
+#[tokio::test]
+async fn watch_future_exit() {
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    let mut shutdown = ShutdownManager::builder();
+    shutdown.watch_future(async move {
+        let value = receiver.await?;
+        Ok(value)
+    });
+    let shutdown = shutdown.build();
+
+    sender.send("from future").unwrap();
+    let result = shutdown.wait().await.unwrap();
+    assert_eq!(result, "from future");
+}
+
+#[tokio::test]
+async fn graceful_shutdown_task_grace() {
+    // The first task never completes on its own but has a short grace period.
+    let cancelled = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(true));
+    let cancelled_setter = std::sync::Arc::clone(&cancelled);
+    let task_graced = tokio::spawn(async move {
+        tokio::time::sleep(std::time::Duration::from_secs(5 * 60)).await;
+        cancelled_setter.store(false, std::sync::atomic::Ordering::SeqCst);
+        Ok(())
+    });
+
+    // The second task needs some time to clean up after shutdown is requested.
+    let mut shutdown = ShutdownManager::builder();
+    let notification = shutdown.shutdown_notification();
+    let flag = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let flag_setter = std::sync::Arc::clone(&flag);
+    let task_cleanup = tokio::spawn(async move {
+        notification.await;
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        flag_setter.store(true, std::sync::atomic::Ordering::SeqCst);
+        Ok(())
+    });
+
+    // The third task exists immediately to trigger shutdown logic.
+    let task_shutdown = tokio::spawn(async { Ok(()) });
+
+    // Wait for shutdown, which should not wait for the global timeout.
+    shutdown
+        .graceful_shutdown_timeout(std::time::Duration::from_secs(5 * 60))
+        .watch_tokio_with_grace(task_graced, std::time::Duration::from_millis(10))
+        .watch_tokio(task_cleanup)
+        .watch_tokio(task_shutdown);
+    let shutdown = shutdown.build();
+    let start_time = std::time::Instant::now();
+    let _ = shutdown.wait().await;
+    let test_duration = start_time.elapsed();
+
+    // Ensure the graced task was cancelled while the other task completed cleanly.
+    assert!(cancelled.load(std::sync::atomic::Ordering::SeqCst));
+    assert!(flag.load(std::sync::atomic::Ordering::SeqCst));
+    assert!(test_duration.as_secs() < 5);
+}
+
+#[cfg(feature = "runtime-shutdown_metrics")]
+#[tokio::test]
+async fn shutdown_metrics() {
+    let registry = prometheus::Registry::new();
+    let mut shutdown = ShutdownManager::builder();
+    shutdown
+        .graceful_shutdown_timeout(std::time::Duration::from_millis(10))
+        .metrics(registry.clone(), "test")
+        .unwrap();
+
+    // One task exits on shutdown, one needs to be aborted and one triggers shutdown.
+    let notification = shutdown.shutdown_notification();
+    let task_graceful = tokio::spawn(async move {
+        notification.await;
+        Ok(())
+    });
+    let task_long = tokio::spawn(async {
+        tokio::time::sleep(std::time::Duration::from_secs(5 * 60)).await;
+        Ok(())
+    });
+    let task_shutdown = tokio::spawn(async { Ok(()) });
+    shutdown
+        .watch_tokio(task_graceful)
+        .watch_tokio(task_long)
+        .watch_tokio(task_shutdown);
+    let _ = shutdown.build().wait().await;
+
+    let families = registry.gather();
+    let value = |name: &str| {
+        families
+            .iter()
+            .find(|family| family.get_name() == name)
+            .map(|family| family.get_metric()[0].clone())
+            .unwrap_or_else(|| panic!("metric {} not found", name))
+    };
+    assert_eq!(
+        value("test_shutdown_tasks_aborted")
+            .get_counter()
+            .get_value(),
+        1.0
+    );
+    assert_eq!(
+        value("test_shutdown_tasks_completed")
+            .get_counter()
+            .get_value(),
+        1.0
+    );
+    let duration = value("test_shutdown_duration");
+    assert_eq!(duration.get_histogram().get_sample_count(), 1);
+}
+
+#[cfg(feature = "runtime-shutdown_actix")]
+fn actix_server() -> actix_web::dev::Server {
+    actix_web::HttpServer::new(actix_web::App::new)
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap()
+        .run()
+}
+
+#[cfg(feature = "runtime-shutdown_actix")]
+#[actix_web::test]
+async fn watch_actix_named_stop_reason() {
+    let mut shutdown = ShutdownManager::builder();
+    let trigger = shutdown.trigger();
+    let api = shutdown
+        .watch_actix_named("api", actix_server(), "from api")
+        .unwrap();
+    let admin = shutdown
+        .watch_actix_named("admin", actix_server(), "from admin")
+        .unwrap();
+    assert_eq!(api.name(), "api");
+    assert_eq!(api.stop_reason(), None);
+
+    trigger.shutdown(Ok("from trigger"));
+    let result = shutdown.build().wait().await.unwrap();
+    assert_eq!(result, "from trigger");
+    assert_eq!(api.stopped().await, Some(super::ActixServerStop::Shutdown));
+    assert_eq!(admin.stop_reason(), Some(super::ActixServerStop::Shutdown));
+}
+
+#[cfg(feature = "runtime-shutdown_actix")]
+#[actix_web::test]
+async fn watch_actix_named_duplicate() {
+    let mut shutdown = ShutdownManager::<()>::builder();
+    shutdown
+        .watch_actix_named("api", actix_server(), ())
+        .unwrap();
+    let error = shutdown
+        .watch_actix_named("api", actix_server(), ())
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "actix-web HttpServer 'api' is already watched"
+    );
+}
+
+#[cfg(feature = "runtime-shutdown_metrics")]
+#[test]
+fn shutdown_metrics_registered_twice() {
+    let registry = prometheus::Registry::new();
+    let mut shutdown = ShutdownManager::<()>::builder();
+    shutdown.metrics(registry.clone(), "test").unwrap();
+    let error = match shutdown.metrics(registry, "test") {
+        Ok(_) => panic!("metrics registered twice"),
+        Err(error) => error,
+    };
+    assert!(matches!(
+        error.downcast_ref::<ShutdownError>(),
+        Some(ShutdownError::Metrics)
+    ));
+}
@@ -1,10 +1,23 @@
 //! Logging related telemetry logic.
 use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::path::PathBuf;
 use std::sync::Arc;
 
+use anyhow::Context;
+use anyhow::Result;
+use arc_swap::ArcSwap;
+use serde::de::MapAccess;
+use serde::de::Visitor;
 use serde::Deserialize;
+use serde::Deserializer;
 use serde::Serialize;
+use serde_json::value::RawValue;
 use slog::Drain;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::RollingFileAppender;
+
+use super::repli_sentry::BreadcrumbsDrain;
 
 /// Type erased Drain trait object for the builder to use.
 type ErasedDrain = Arc<dyn slog::SendSyncRefUnwindSafeDrain<Ok = (), Err = slog::Never>>;
@@ -17,8 +30,49 @@ pub struct LogBuilder {
 }
 
 impl LogBuilder {
+    /// Build a root logger that will emit events to the given [`slog::Drain`].
+    ///
+    /// This enables applications to use their own logging backends while
+    /// level filtering and root logger values are applied as for the built-in drains.
+    pub fn from_drain<D>(drain: D) -> LogBuilder
+    where
+        D: slog::SendSyncRefUnwindSafeDrain<Ok = (), Err = slog::Never> + 'static,
+    {
+        LogBuilder {
+            drain: Arc::new(drain),
+            level: Default::default(),
+            levels: Default::default(),
+        }
+    }
+
     /// Build a root logger that will emit JSON lines to the given stream.
     pub fn json<W>(stream: W, with_async: bool) -> LogBuilder
+    where
+        W: std::io::Write + Send + 'static,
+    {
+        LogBuilder::json_with_keys(stream, with_async, JsonLogKeys::default())
+    }
+
+    /// Build a root logger that will emit JSON lines to the given stream.
+    ///
+    /// The order and set of keys in each JSON line is controlled by [`JsonLogKeys`].
+    pub fn json_with_keys<W>(stream: W, with_async: bool, keys: JsonLogKeys) -> LogBuilder
+    where
+        W: std::io::Write + Send + 'static,
+    {
+        if keys.is_default() {
+            return LogBuilder::json_stream(stream, with_async);
+        }
+        let stream = JsonKeysWriter {
+            buffer: Vec::new(),
+            keys,
+            stream,
+        };
+        LogBuilder::json_stream(stream, with_async)
+    }
+
+    /// Build a root logger that will emit JSON lines to the given stream.
+    fn json_stream<W>(stream: W, with_async: bool) -> LogBuilder
     where
         W: std::io::Write + Send + 'static,
     {
@@ -70,23 +124,43 @@ impl LogBuilder {
 
     /// Complete logger initialisation and returns a root logger.
     pub fn finish(self) -> slog::Logger {
-        // Configure log level filtering using slog-envlogger.
-        let drain = if std::env::var("RUST_LOG").is_ok() {
-            slog_envlogger::new(self.drain)
+        let (logger, _) = self.finish_with_handle();
+        logger
+    }
+
+    /// Complete logger initialisation and returns a root logger with a [`LogLevelHandle`].
+    ///
+    /// The handle can be used to change log levels while the process is running.
+    /// Log levels are configured with `slog-envlogger` instead if `RUST_LOG` is set,
+    /// in which case the handle has no effect.
+    pub fn finish_with_handle(self) -> (slog::Logger, LogLevelHandle) {
+        let handle = LogLevelHandle::new(self.level, self.levels);
+
+        // Configure log level filtering using slog-envlogger if requested.
+        let drain: ErasedDrain = if std::env::var("RUST_LOG").is_ok() {
+            Arc::new(slog_envlogger::new(self.drain))
         } else {
-            let mut builder =
-                slog_envlogger::LogBuilder::new(self.drain).filter(None, self.level.into());
-            for (prefix, level) in self.levels {
-                builder = builder.filter(Some(&prefix), level.into());
-            }
-            builder.build()
+            Arc::new(LevelFilter {
+                drain: self.drain,
+                levels: handle.clone(),
+            })
         };
 
         // Attach global extra information and create root logger.
         let values = slog::o!(
             "module" => slog::FnValue(|record : &slog::Record| record.module()),
         );
-        slog::Logger::root(drain, values)
+        (slog::Logger::root(drain, values), handle)
+    }
+
+    /// Record events at or above the given level as Sentry breadcrumbs.
+    ///
+    /// Breadcrumbs are recorded after log level filtering so events dropped
+    /// by the root logger are never recorded.
+    pub(super) fn sentry_breadcrumbs(mut self, level: LogLevel) -> Self {
+        let drain = BreadcrumbsDrain::new(self.drain, level);
+        self.drain = Arc::new(drain);
+        self
     }
 
     /// Configure the default logging level for the process.
@@ -102,6 +176,269 @@ impl LogBuilder {
     }
 }
 
+/// Change log levels of a running process.
+///
+/// Log levels are applied to all loggers derived from the root logger the handle
+/// was created with (see [`LogBuilder::finish_with_handle`]).
+/// Changes take effect immediately and are not persisted across restarts.
+#[derive(Clone, Debug)]
+pub struct LogLevelHandle(Arc<ArcSwap<LogLevels>>);
+
+impl LogLevelHandle {
+    /// Set the default logging level for the process.
+    pub fn set_level(&self, level: LogLevel) {
+        let level = slog::FilterLevel::from(level).as_usize();
+        self.0.rcu(|levels| {
+            let mut levels = LogLevels::clone(levels);
+            levels.level = level;
+            levels.update_max();
+            levels
+        });
+    }
+
+    /// Set the logging level for modules starting with the given prefix.
+    ///
+    /// Longer prefixes override their parents, as for the `levels` configuration option.
+    pub fn set_module_level<S>(&self, prefix: S, level: LogLevel)
+    where
+        S: Into<String>,
+    {
+        let prefix = prefix.into();
+        let level = slog::FilterLevel::from(level).as_usize();
+        self.0.rcu(|levels| {
+            let mut levels = LogLevels::clone(levels);
+            levels.modules.retain(|(module, _)| module != &prefix);
+            levels.modules.push((prefix.clone(), level));
+            levels
+                .modules
+                .sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+            levels.update_max();
+            levels
+        });
+    }
+
+    /// Create a handle with the given initial log levels.
+    fn new(level: LogLevel, levels: BTreeMap<String, LogLevel>) -> LogLevelHandle {
+        let level = slog::FilterLevel::from(level).as_usize();
+        let mut levels = LogLevels {
+            level,
+            max: level,
+            modules: levels
+                .into_iter()
+                .map(|(prefix, level)| (prefix, slog::FilterLevel::from(level).as_usize()))
+                .collect(),
+        };
+        levels
+            .modules
+            .sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        levels.update_max();
+        LogLevelHandle(Arc::new(ArcSwap::from_pointee(levels)))
+    }
+
+    /// Check if events with the given level may be emitted by any module.
+    fn is_level_enabled(&self, level: slog::Level) -> bool {
+        level.as_usize() <= self.0.load().max
+    }
+
+    /// Check if events with the given level and module should be emitted.
+    fn is_enabled(&self, level: slog::Level, module: &str) -> bool {
+        let levels = self.0.load();
+        let limit = levels
+            .modules
+            .iter()
+            .find(|(prefix, _)| module.starts_with(prefix.as_str()))
+            .map(|(_, level)| *level)
+            .unwrap_or(levels.level);
+        level.as_usize() <= limit
+    }
+}
+
+/// Snapshot of the active log levels shared by a [`LogLevelHandle`] and its filter.
+///
+/// Snapshots are never modified once shared: changes swap in an updated copy
+/// so filtering events never waits on a lock.
+#[derive(Clone, Debug)]
+struct LogLevels {
+    /// Default log level, as a [`slog::FilterLevel`] value.
+    level: usize,
+
+    /// Most verbose level enabled by the default or any module prefix.
+    max: usize,
+
+    /// Log levels for module prefixes, sorted with longer prefixes first.
+    modules: Vec<(String, usize)>,
+}
+
+impl LogLevels {
+    /// Recompute the most verbose level enabled after a change.
+    fn update_max(&mut self) {
+        self.max = self
+            .modules
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.level, usize::max);
+    }
+}
+
+/// [`slog::Drain`] filtering events with the levels of a [`LogLevelHandle`].
+struct LevelFilter {
+    drain: ErasedDrain,
+    levels: LogLevelHandle,
+}
+
+impl slog::Drain for LevelFilter {
+    type Ok = ();
+    type Err = slog::Never;
+
+    fn log(
+        &self,
+        record: &slog::Record,
+        values: &slog::OwnedKVList,
+    ) -> std::result::Result<(), slog::Never> {
+        if self.levels.is_enabled(record.level(), record.module()) {
+            self.drain.log(record, values)?;
+        }
+        Ok(())
+    }
+
+    fn is_enabled(&self, level: slog::Level) -> bool {
+        self.levels.is_level_enabled(level) && self.drain.is_enabled(level)
+    }
+}
+
+/// Control the order and set of keys included in JSON log events.
+///
+/// Log events include the `ts`, `level` and `msg` keys followed by the event and logger values.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct JsonLogKeys {
+    /// Keys emitted first, in the given order, when present in a log event.
+    ///
+    /// Other keys follow in the order they are emitted by the logger.
+    #[serde(default)]
+    pub first: Vec<String>,
+
+    /// When set, only keys in this list (or in `first`) are emitted and others are dropped.
+    #[serde(default)]
+    pub only: Option<Vec<String>>,
+}
+
+impl JsonLogKeys {
+    /// Check if the options leave log events unchanged.
+    fn is_default(&self) -> bool {
+        self.first.is_empty() && self.only.is_none()
+    }
+
+    /// Reorder and filter the keys of an encoded JSON log event.
+    fn apply<V>(&self, mut entries: Vec<(String, V)>) -> Vec<(String, V)> {
+        if let Some(only) = &self.only {
+            entries.retain(|(key, _)| only.contains(key) || self.first.contains(key));
+        }
+        let position = |key: &str| {
+            self.first
+                .iter()
+                .position(|first| first == key)
+                .unwrap_or(self.first.len())
+        };
+        // Sorting is stable so keys not in `first` retain their order.
+        entries.sort_by_key(|(key, _)| position(key));
+        entries
+    }
+}
+
+/// Intercept encoded JSON log events to reorder and filter their keys.
+///
+/// Only keys are decoded: values are copied to the stream as they were encoded.
+struct JsonKeysWriter<W> {
+    buffer: Vec<u8>,
+    keys: JsonLogKeys,
+    stream: W,
+}
+
+impl<W: std::io::Write> JsonKeysWriter<W> {
+    /// Write a complete JSON line to the stream after processing its keys.
+    ///
+    /// Lines that can't be decoded are written to the stream unchanged.
+    fn write_line(&mut self, line: &[u8]) -> std::io::Result<()> {
+        let entries = match serde_json::from_slice::<OrderedEntries>(line) {
+            Err(_) => return self.stream.write_all(line),
+            Ok(entries) => self.keys.apply(entries.0),
+        };
+        let mut event = Vec::with_capacity(line.len());
+        event.push(b'{');
+        for (index, (key, value)) in entries.iter().enumerate() {
+            if index > 0 {
+                event.push(b',');
+            }
+            serde_json::to_writer(&mut event, key)?;
+            event.push(b':');
+            event.extend_from_slice(value.get().as_bytes());
+        }
+        event.extend_from_slice(b"}\n");
+        self.stream.write_all(&event)
+    }
+}
+
+impl<W: std::io::Write> std::io::Write for JsonKeysWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        // JSON encoding escapes newlines so they only appear at the end of events.
+        while let Some(end) = self.buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            self.write_line(&line)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+/// Decode a JSON object into its entries, preserving the order of keys.
+///
+/// Values are borrowed from the encoded object without decoding them.
+struct OrderedEntries<'a>(Vec<(String, &'a RawValue)>);
+
+impl<'de> Deserialize<'de> for OrderedEntries<'de> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct OrderedVisitor;
+        impl<'de> Visitor<'de> for OrderedVisitor {
+            type Value = OrderedEntries<'de>;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a JSON object")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut entries = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(OrderedEntries(entries))
+            }
+        }
+        deserializer.deserialize_map(OrderedVisitor)
+    }
+}
+
+/// Errors initialising logging for the process.
+#[derive(Debug, thiserror::Error)]
+pub enum LogError {
+    /// Unable to open the file to write logs to.
+    #[error("unable to open log file '{0}'")]
+    OpenFile(String),
+
+    /// Unable to initialise rotation of log files.
+    #[error("unable to initialise rolling log files at '{0}'")]
+    RollingFile(String),
+}
+
 /// Configuration option for process logging.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct LogConfig {
@@ -119,6 +456,10 @@ pub struct LogConfig {
     #[serde(default)]
     pub levels: BTreeMap<String, LogLevel>,
 
+    /// Order and filter keys in JSON log events.
+    #[serde(default)]
+    pub json_keys: JsonLogKeys,
+
     /// Asynchronously emit log events.
     ///
     /// Asynchronous logging can improve performance but can result in some loss
@@ -134,6 +475,7 @@ pub struct LogConfig {
 impl Default for LogConfig {
     fn default() -> Self {
         LogConfig {
+            json_keys: Default::default(),
             level: Default::default(),
             levels: Default::default(),
             log_async: LogConfig::default_log_async(),
@@ -200,11 +542,87 @@ pub enum LogMode {
     #[serde(alias = "JSON", alias = "json")]
     Json,
 
+    /// Format logs as a stream of JSON encoded lines to a file.
+    #[serde(alias = "FILE", alias = "file")]
+    File {
+        /// Append logs to the file if it exists instead of truncating it.
+        #[serde(default = "LogMode::default_file_append")]
+        append: bool,
+
+        /// Path to the file to write logs to, created if it does not exist.
+        path: PathBuf,
+    },
+
+    /// Format logs as a stream of JSON encoded lines to files rotated over time.
+    ///
+    /// Rotated files are named after `path` with the date and time of the rotation
+    /// inserted before the file extension (for example `agent.2023-04-05.log`).
+    /// Log lines are written to files by a background thread.
+    #[serde(alias = "ROLLING_FILE", alias = "rolling_file")]
+    RollingFile {
+        /// Number of log files to keep, with the oldest files deleted first.
+        ///
+        /// Set to `0` to keep all log files.
+        #[serde(default = "LogMode::default_rolling_max_files")]
+        max_files: usize,
+
+        /// Path to the log files, with the directory created if it does not exist.
+        path: PathBuf,
+
+        /// How often log files are rotated.
+        #[serde(default)]
+        rotation: LogRotation,
+    },
+
     /// Display logs onto a terminal, with optional colour support.
     #[serde(alias = "TERMINAL", alias = "terminal")]
     Terminal,
 }
 
+impl LogMode {
+    /// Default value for the `append` option of file logging.
+    fn default_file_append() -> bool {
+        true
+    }
+
+    /// Default value for the `max_files` option of rolling file logging.
+    fn default_rolling_max_files() -> usize {
+        7
+    }
+}
+
+/// How often rolling log files are rotated.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum LogRotation {
+    /// Rotate log files every day.
+    #[default]
+    #[serde(alias = "DAILY", alias = "daily")]
+    Daily,
+
+    /// Rotate log files every hour.
+    #[serde(alias = "HOURLY", alias = "hourly")]
+    Hourly,
+
+    /// Rotate log files every minute.
+    #[serde(alias = "MINUTELY", alias = "minutely")]
+    Minutely,
+
+    /// Never rotate log files.
+    #[serde(alias = "NEVER", alias = "never")]
+    Never,
+}
+
+impl From<LogRotation> for tracing_appender::rolling::Rotation {
+    fn from(value: LogRotation) -> Self {
+        match value {
+            LogRotation::Daily => tracing_appender::rolling::Rotation::DAILY,
+            LogRotation::Hourly => tracing_appender::rolling::Rotation::HOURLY,
+            LogRotation::Minutely => tracing_appender::rolling::Rotation::MINUTELY,
+            LogRotation::Never => tracing_appender::rolling::Rotation::NEVER,
+        }
+    }
+}
+
 /// Programmatic options for logging.
 pub struct LogOptions {
     /// Forward events from the pseudo-standard `log` crate to the root logger.
@@ -256,14 +674,59 @@ impl Drop for StdLogSafeGuard {
     }
 }
 
+/// Root logger and related resources created by [`initialise`].
+pub struct Logging {
+    /// Handle to change log levels while the process is running.
+    pub levels: LogLevelHandle,
+
+    /// Root logger for the process.
+    pub logger: slog::Logger,
+
+    /// Guard for the global `slog_scope` logger, when `log` capture is enabled.
+    pub slog_scope_guard: StdLogSafeGuard,
+
+    /// Guard to flush pending log lines written to rolling files.
+    pub worker_guard: Option<WorkerGuard>,
+}
+
 /// Initialise a root logger based on the provided configuration.
-pub fn initialise(conf: LogConfig, options: LogOptions) -> (slog::Logger, StdLogSafeGuard) {
+///
+/// Log events at or above the `breadcrumbs` level, if set, are recorded as Sentry breadcrumbs.
+pub fn initialise(
+    conf: LogConfig,
+    options: LogOptions,
+    breadcrumbs: Option<LogLevel>,
+) -> Result<Logging> {
     // Build the root logger first.
+    let mut worker_guard = None;
     let builder = match conf.mode {
-        LogMode::Json => LogBuilder::json(std::io::stdout(), conf.log_async),
+        LogMode::File { append, path } => {
+            let file = open_file(&path, append)?;
+            LogBuilder::json_with_keys(file, conf.log_async, conf.json_keys)
+        }
+        LogMode::RollingFile {
+            max_files,
+            path,
+            rotation,
+        } => {
+            let appender = rolling_file(&path, rotation, max_files)?;
+            let (stream, guard) = tracing_appender::non_blocking(appender);
+            worker_guard = Some(guard);
+            LogBuilder::json_with_keys(stream, conf.log_async, conf.json_keys)
+        }
+        LogMode::Json => {
+            LogBuilder::json_with_keys(std::io::stdout(), conf.log_async, conf.json_keys)
+        }
         LogMode::Terminal => LogBuilder::term(conf.log_async),
     };
-    let logger = builder.level(conf.level).levels(conf.levels).finish();
+    let builder = match breadcrumbs {
+        None => builder,
+        Some(level) => builder.sentry_breadcrumbs(level),
+    };
+    let (logger, levels) = builder
+        .level(conf.level)
+        .levels(conf.levels)
+        .finish_with_handle();
 
     // Initialise slog_scope and slog_stdlog libraries if `log` capture is desired.
     let mut slog_scope_guard = StdLogSafeGuard(None);
@@ -274,12 +737,208 @@ pub fn initialise(conf: LogConfig, options: LogOptions) -> (slog::Logger, StdLog
     }
 
     // Return the root logger.
-    (logger, slog_scope_guard)
+    Ok(Logging {
+        levels,
+        logger,
+        slog_scope_guard,
+        worker_guard,
+    })
+}
+
+/// Open (or create) the file to write logs to.
+fn open_file(path: &std::path::Path, append: bool) -> Result<std::fs::File> {
+    OpenOptions::new()
+        .append(append)
+        .create(true)
+        .truncate(!append)
+        .write(true)
+        .open(path)
+        .with_context(|| LogError::OpenFile(path.display().to_string()))
+}
+
+/// Create an appender to write logs to files rotated over time.
+fn rolling_file(
+    path: &std::path::Path,
+    rotation: LogRotation,
+    max_files: usize,
+) -> Result<RollingFileAppender> {
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => std::path::Path::new("."),
+    };
+    let mut builder = RollingFileAppender::builder().rotation(rotation.into());
+    if let Some(prefix) = path.file_stem() {
+        builder = builder.filename_prefix(prefix.to_string_lossy());
+    }
+    if let Some(suffix) = path.extension() {
+        builder = builder.filename_suffix(suffix.to_string_lossy());
+    }
+    if max_files > 0 {
+        builder = builder.max_log_files(max_files);
+    }
+    builder
+        .build(directory)
+        .with_context(|| LogError::RollingFile(path.display().to_string()))
 }
 
 #[cfg(test)]
 mod tests {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    use slog::Drain;
+
+    use super::JsonLogKeys;
     use super::LogBuilder;
+    use super::LogError;
+    use super::LogLevel;
+    use super::LogRotation;
+    use crate::testing::CaptureDrain;
+
+    /// Collect log lines in a buffer tests can inspect.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn log_to_custom_drain() {
+        let drain = CaptureDrain::default();
+        let logger = LogBuilder::from_drain(drain.clone())
+            .level(LogLevel::Warning)
+            .finish();
+        slog::info!(logger, "filtered");
+        slog::warn!(logger, "emitted");
+        assert_eq!(drain.messages(), ["emitted"]);
+    }
+
+    #[test]
+    fn log_level_handle() {
+        let drain = CaptureDrain::default();
+        let (logger, levels) = LogBuilder::from_drain(drain.clone())
+            .level(LogLevel::Warning)
+            .finish_with_handle();
+        slog::info!(logger, "filtered");
+
+        levels.set_level(LogLevel::Info);
+        slog::info!(logger, "raised");
+        slog::debug!(logger, "filtered");
+
+        levels.set_module_level(module_path!(), LogLevel::Debug);
+        slog::debug!(logger, "module raised");
+        levels.set_module_level("other::module", LogLevel::Critical);
+        levels.set_level(LogLevel::Error);
+        slog::debug!(logger, "module still raised");
+
+        assert_eq!(
+            drain.messages(),
+            ["raised", "module raised", "module still raised"],
+        );
+    }
+
+    #[test]
+    fn log_level_handle_is_enabled() {
+        let drain = CaptureDrain::default();
+        let (logger, levels) = LogBuilder::from_drain(drain)
+            .level(LogLevel::Error)
+            .finish_with_handle();
+        assert!(!logger.is_enabled(slog::Level::Warning));
+
+        levels.set_module_level("some::module", LogLevel::Warning);
+        assert!(logger.is_enabled(slog::Level::Warning));
+        assert!(!logger.is_enabled(slog::Level::Info));
+
+        levels.set_module_level("some::module", LogLevel::Critical);
+        assert!(!logger.is_enabled(slog::Level::Warning));
+    }
+
+    #[test]
+    fn log_to_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("agent.log");
+        std::fs::write(&path, "existing\n").unwrap();
+
+        // Appending keeps the existing logs.
+        let stream = super::open_file(&path, true).unwrap();
+        let logger = LogBuilder::json(stream, false).finish();
+        slog::info!(logger, "test"; "append" => true);
+        let logs = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = logs.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], "existing");
+        assert!(lines[1].contains("\"append\":true"));
+
+        // Otherwise the file is truncated.
+        let stream = super::open_file(&path, false).unwrap();
+        let logger = LogBuilder::json(stream, false).finish();
+        slog::info!(logger, "test"; "append" => false);
+        let logs = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = logs.lines().collect();
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].contains("\"append\":false"));
+    }
+
+    #[test]
+    fn log_to_file_missing_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("missing").join("agent.log");
+        let error = super::open_file(&path, true).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<LogError>(),
+            Some(LogError::OpenFile(_))
+        ));
+    }
+
+    #[test]
+    fn log_to_rolling_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("agent.log");
+        let appender = super::rolling_file(&path, LogRotation::Daily, 2).unwrap();
+        let (stream, guard) = tracing_appender::non_blocking(appender);
+        let logger = LogBuilder::json(stream, false).finish();
+        slog::info!(logger, "test"; "key" => "value");
+        drop(logger);
+        drop(guard);
+
+        let files: Vec<PathBuf> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(files.len(), 1);
+        let name = files[0].file_name().unwrap().to_string_lossy().to_string();
+        assert!(name.starts_with("agent."));
+        assert!(name.ends_with(".log"));
+        let logs = std::fs::read_to_string(&files[0]).unwrap();
+        assert!(logs.contains("\"key\":\"value\""));
+    }
+
+    #[test]
+    fn log_to_json_with_keys() {
+        let buffer = SharedBuffer::default();
+        let keys = JsonLogKeys {
+            first: vec!["msg".into(), "level".into()],
+            only: Some(vec!["key".into()]),
+        };
+        let builder = LogBuilder::json_with_keys(buffer.clone(), false, keys);
+        let logger = builder.finish();
+        slog::info!(logger, "test"; "key" => "value", "dropped" => 42);
+
+        let lines = buffer.0.lock().unwrap();
+        let lines = String::from_utf8(lines.clone()).unwrap();
+        assert_eq!(
+            lines,
+            "{\"msg\":\"test\",\"level\":\"INFO\",\"key\":\"value\"}\n"
+        );
+    }
 
     #[test]
     fn log_to_json_async() {
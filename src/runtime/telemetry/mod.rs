@@ -13,6 +13,9 @@
 //! Additional customisation options are defined in the ['LogOptions`] object.
 //! These options are for application developers to tune process logging to their preferences.
 //!
+//! Log levels can be changed while the process is running with the [`LogLevelHandle`]
+//! returned as part of the [`Telemetry`] resources.
+//!
 //! ## Async Logging
 //!
 //! Users should be aware that asynchronous logging can provide performance improvements
@@ -37,7 +40,12 @@
 //! When enabled, the telemetry data can be exported in one of the following formats.
 //! The protocol, as well as its exporter options, can be configured at runtime.
 //!
-//! - Open Telemetry Protocol (OTLP): export data in the OpenTelemetry native protocol.
+//! - Open Telemetry Protocol (OTLP): export data in the OpenTelemetry native protocol,
+//!   over gRPC or HTTP with optional TLS (HTTP requires the `runtime-telemetry_otlp_http` feature
+//!   and TLS over gRPC requires the `runtime-telemetry_otlp_tls` feature).
+//! - Jaeger: export data to a Jaeger agent (requires the `runtime-telemetry_jaeger` feature).
+//! - Local file: append spans to a file as JSON lines, for offline debugging
+//!   where no OpenTelemetry agent is available.
 //!
 //! ## Configuration
 //!
@@ -47,6 +55,16 @@
 //! Additional user configuration options can be provided with [`OTelConfig`]
 //! and applications can tune the OpenTelemetry integration with [`OTelOptions`].
 //!
+//! Resource attributes describing the process (such as `service.name` or deployment tags)
+//! can be set with [`OTelOptions`] and are combined with any set in the
+//! `OTEL_RESOURCE_ATTRIBUTES` environment variable, with programmatic options taking precedence.
+//!
+//! ## Process identity
+//!
+//! Applications can set identifying attributes (such as node ID or platform name) once
+//! with [`TelemetryOptionsBuilder::span_identity`].
+//! These are attached to all root spans created with [`crate::utils::trace::root`].
+//!
 //! # Prometheus Metrics
 //!
 //! The [Prometheus](https://prometheus.io/) metrics integration provides a
@@ -55,6 +73,16 @@
 //!
 //! On Linux systems, this integration can also register a set of process wide metrics.
 //!
+//! Constant labels, such as the process identity, can be attached to all metrics in the
+//! registry with [`PrometheusConfig::labels`] or [`PrometheusOptions::labels`],
+//! keeping individual metric definitions free of them.
+//!
+//! Short-lived processes can also push metrics to a Prometheus Pushgateway
+//! by setting [`PrometheusConfig::push_gateway`] (requires the `runtime-telemetry_pushgateway` feature).
+//! Pushes happen in a background task returned as [`Telemetry::metrics_push`]
+//! that processes must run, for example with the [`runtime::shutdown`](crate::runtime::shutdown)
+//! utilities so a final push is performed on exit.
+//!
 //! ## Prometheus vs OpenTelemetry
 //!
 //! Prometheus is used to generate and export metrics instead of OpenTelemetry
@@ -88,8 +116,18 @@
 //!   ```
 //! - [`SentryOptions::in_app_include`]: a list of module prefixes for Sentry to consider part
 //!   of the instrumented applications.
+//!
+//! ## Breadcrumbs
+//!
+//! Log events emitted by the root logger can be recorded as Sentry breadcrumbs
+//! so events sent to Sentry include the log lines leading up to them.
+//! Set [`SentryConfig::capture_breadcrumbs`] to `true` to enable this and
+//! [`SentryConfig::breadcrumbs_level`] to limit which log events are recorded.
 use anyhow::Result;
 use opentelemetry::sdk::Resource;
+use opentelemetry::Key;
+use opentelemetry::KeyValue;
+use opentelemetry::Value;
 use opentelemetry_semantic_conventions::resource::SERVICE_NAME;
 use opentelemetry_semantic_conventions::resource::SERVICE_VERSION;
 use serde::Deserialize;
@@ -97,18 +135,32 @@ use serde::Serialize;
 
 mod logging;
 mod opentel;
+mod opentel_file;
 mod prom;
 mod repli_sentry;
 
+pub use self::logging::JsonLogKeys;
 pub use self::logging::LogBuilder;
 pub use self::logging::LogConfig;
+pub use self::logging::LogError;
 pub use self::logging::LogLevel;
+pub use self::logging::LogLevelHandle;
 pub use self::logging::LogMode;
 pub use self::logging::LogOptions;
+pub use self::logging::LogRotation;
+pub use self::opentel::OTelCompression;
 pub use self::opentel::OTelConfig;
+pub use self::opentel::OTelExportError;
+pub use self::opentel::OTelExporter;
 pub use self::opentel::OTelOptions;
+pub use self::opentel::OTelSamplingRatioInvalid;
+pub use self::opentel::OTelTlsConfig;
+pub use self::opentel::OtlpProtocol;
 pub use self::prom::PrometheusConfig;
 pub use self::prom::PrometheusError;
+pub use self::prom::PrometheusOptions;
+pub use self::prom::PushGateway;
+pub use self::prom::PushGatewayConfig;
 pub use self::repli_sentry::SentryConfig;
 pub use self::repli_sentry::SentryError;
 pub use self::repli_sentry::SentryOptions;
@@ -121,15 +173,25 @@ pub struct Telemetry {
     /// Root logger for the process.
     pub logger: slog::Logger,
 
+    /// Change log levels of the root logger while the process is running.
+    pub log_levels: LogLevelHandle,
+
     /// Registry for the process to attach Prometheus metrics to.
     pub metrics: prometheus::Registry,
 
+    /// Background task pushing metrics to a Prometheus Pushgateway, if configured.
+    pub metrics_push: Option<PushGateway>,
+
     // Initialisation guards for global scopes.
     #[allow(dead_code)]
     sentry: Option<sentry::ClientInitGuard>,
 
     #[allow(dead_code)]
     slog_scope_guard: self::logging::StdLogSafeGuard,
+
+    // Flush pending log lines when rolling file logs are dropped.
+    #[allow(dead_code)]
+    log_worker_guard: Option<tracing_appender::non_blocking::WorkerGuard>,
 }
 
 /// Telemetry configuration options.
@@ -152,6 +214,34 @@ pub struct TelemetryConfig {
     pub sentry: SentryConfig,
 }
 
+impl TelemetryConfig {
+    /// Check the configuration for combinations of options that can't work together.
+    ///
+    /// This is done by [`initialise`] so misconfiguration is reported at startup.
+    pub fn validate(&self) -> Result<()> {
+        let sentry_dsn = self.sentry.dsn.is_some() || std::env::var_os("SENTRY_DSN").is_some();
+        if self.sentry.enabled && !sentry_dsn {
+            anyhow::bail!(TelemetryConfigError::SentryWithoutDsn);
+        }
+        if self.otel.enabled && !self.otel.export && self.otel.file_path.is_none() {
+            anyhow::bail!(TelemetryConfigError::OTelWithoutDestination);
+        }
+        Ok(())
+    }
+}
+
+/// Errors reported for conflicting telemetry configuration options.
+#[derive(Debug, thiserror::Error)]
+pub enum TelemetryConfigError {
+    /// OpenTelemetry is enabled but spans are neither exported to an agent nor to a file.
+    #[error("OpenTelemetry is enabled but neither export nor file_path are set")]
+    OTelWithoutDestination,
+
+    /// Sentry is enabled but no DSN is set in the configuration or environment.
+    #[error("Sentry is enabled but no DSN is configured or set in the SENTRY_DSN variable")]
+    SentryWithoutDsn,
+}
+
 /// Programmatic telemetry options.
 ///
 /// Where config options are intended for user/runtime configuration,
@@ -163,6 +253,9 @@ pub struct TelemetryOptions {
     /// OpenTelemetry programmatic options.
     pub otel: OTelOptions,
 
+    /// Prometheus registry programmatic options.
+    pub prom_metrics: PrometheusOptions,
+
     /// Sentry programmatic options.
     pub sentry: SentryOptions,
 }
@@ -176,6 +269,7 @@ impl TelemetryOptions {
         TelemetryOptionsBuilder {
             logs: Default::default(),
             otel: Default::default(),
+            prom_metrics: Default::default(),
             sentry: SentryOptions::for_release(release),
         }
     }
@@ -185,6 +279,7 @@ impl TelemetryOptions {
 pub struct TelemetryOptionsBuilder {
     logs: LogOptions,
     otel: OTelOptions,
+    prom_metrics: PrometheusOptions,
     sentry: SentryOptions,
 }
 
@@ -194,28 +289,124 @@ impl TelemetryOptionsBuilder {
         TelemetryOptions {
             logs: self.logs,
             otel: self.otel,
+            prom_metrics: self.prom_metrics,
             sentry: self.sentry,
         }
     }
 
+    /// Attach a constant label (such as node ID) to all metrics in the Prometheus registry.
+    ///
+    /// Labels set in [`PrometheusConfig::labels`] take precedence over these.
+    pub fn metrics_label<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.prom_metrics.labels.insert(key.into(), value.into());
+        self
+    }
+
+    /// Prefix the name of all metrics in the Prometheus registry.
+    pub fn metrics_prefix<S>(mut self, prefix: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.prom_metrics.prefix = Some(prefix.into());
+        self
+    }
+
     /// Configure OpenTelemetry service metadata.
     pub fn for_app(mut self, app: &'static str, version: &'static str) -> Self {
         let resource = Resource::new([SERVICE_NAME.string(app), SERVICE_VERSION.string(version)]);
         self.otel.resource = self.otel.resource.merge(&resource);
         self
     }
+
+    /// Attach an attribute (such as a deployment tag) to the OpenTelemetry resource.
+    ///
+    /// Refer to [`OTelOptions`] for how resource attributes are combined.
+    pub fn resource_attribute<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<Key>,
+        V: Into<Value>,
+    {
+        self.otel
+            .resource_attributes
+            .insert(key.into(), value.into());
+        self
+    }
+
+    /// Attach a process identity attribute (such as node ID) to all root spans.
+    pub fn span_identity<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<Key>,
+        V: Into<Value>,
+    {
+        self.otel.identity.push(KeyValue::new(key, value));
+        self
+    }
 }
 
 /// Initialise telemetry for the process.
 pub async fn initialise(conf: TelemetryConfig, options: TelemetryOptions) -> Result<Telemetry> {
-    let (logger, slog_scope_guard) = self::logging::initialise(conf.logs, options.logs);
-    self::opentel::initialise(conf.otel, options.otel, logger.clone())?;
+    conf.validate()?;
+    let breadcrumbs = self::repli_sentry::breadcrumbs_level(&conf.sentry);
+    let logging = self::logging::initialise(conf.logs, options.logs, breadcrumbs)?;
+    self::opentel::initialise(conf.otel, options.otel, logging.logger.clone())?;
     let sentry = self::repli_sentry::initialise(conf.sentry, options.sentry)?;
-    let metrics = self::prom::initialise(conf.prom_metrics)?;
+    let push_gateway = conf.prom_metrics.push_gateway.clone();
+    let metrics = self::prom::initialise(conf.prom_metrics, options.prom_metrics)?;
+    let metrics_push = self::prom::push_gateway(push_gateway, &metrics, &logging.logger)?;
     Ok(Telemetry {
-        logger,
+        log_levels: logging.levels,
+        log_worker_guard: logging.worker_guard,
+        logger: logging.logger,
         metrics,
+        metrics_push,
         sentry,
-        slog_scope_guard,
+        slog_scope_guard: logging.slog_scope_guard,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::TelemetryConfig;
+    use super::TelemetryConfigError;
+
+    /// Assert the configuration is rejected with the given error.
+    fn assert_invalid(conf: TelemetryConfig, expected: TelemetryConfigError) {
+        let error = conf
+            .validate()
+            .expect_err("configuration should be invalid");
+        let error = error
+            .downcast_ref::<TelemetryConfigError>()
+            .expect("error should be a TelemetryConfigError");
+        assert_eq!(error.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn default_config_is_valid() {
+        TelemetryConfig::default().validate().unwrap();
+    }
+
+    #[test]
+    fn otel_without_destination() {
+        let mut conf = TelemetryConfig::default();
+        conf.otel.enabled = true;
+        conf.otel.export = false;
+        assert_invalid(conf.clone(), TelemetryConfigError::OTelWithoutDestination);
+
+        conf.otel.file_path = Some("spans.jsonl".into());
+        conf.validate().unwrap();
+    }
+
+    #[test]
+    fn sentry_without_dsn() {
+        let mut conf = TelemetryConfig::default();
+        conf.sentry.enabled = true;
+        assert_invalid(conf.clone(), TelemetryConfigError::SentryWithoutDsn);
+
+        conf.sentry.dsn = Some("https://public@sentry.example.com/1".into());
+        conf.validate().unwrap();
+    }
+}
@@ -1,37 +1,118 @@
 //! OpenTelemetry initialisation related logic.
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[cfg(any(
+    feature = "runtime-telemetry_otlp_http",
+    feature = "runtime-telemetry_otlp_tls",
+))]
+use anyhow::Context;
 use anyhow::Result;
+use opentelemetry::sdk::trace::BatchConfig;
+use opentelemetry::sdk::trace::BatchSpanProcessor;
 use opentelemetry::sdk::trace::Sampler as SdkSampler;
+use opentelemetry::sdk::trace::TracerProvider;
+use opentelemetry::sdk::Resource;
+use opentelemetry::Key;
+use opentelemetry::KeyValue;
+use opentelemetry::Value;
+use opentelemetry_otlp::SpanExporterBuilder;
+use opentelemetry_otlp::TonicExporterBuilder;
 use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_semantic_conventions::resource::SERVICE_NAME;
+use opentelemetry_semantic_conventions::resource::SERVICE_VERSION;
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::utils::config::HumanDuration;
+
+/// Endpoint spans are sent to by the HTTP OTLP exporter when none is configured.
+#[cfg(feature = "runtime-telemetry_otlp_http")]
+const OTLP_HTTP_DEFAULT_ENDPOINT: &str = "http://localhost:4318/v1/traces";
+
 /// Configuration options for process telemetry data using OpenTelemetry.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct OTelConfig {
+    /// Maximum number of spans buffered for export before new spans are dropped.
+    #[serde(default)]
+    pub batch_max_queue_size: Option<usize>,
+
+    /// Delay between two consecutive exports of span batches.
+    #[serde(default)]
+    pub batch_scheduled_delay: Option<HumanDuration>,
+
+    /// Compress data exported to the OpenTelemetry agent.
+    ///
+    /// Compression reduces the bandwidth needed to export large volumes of telemetry data
+    /// at the cost of extra CPU usage.
+    /// Only supported by the OTLP exporter.
+    #[serde(default)]
+    pub compression: Option<OTelCompression>,
+
     /// Enable export of telemetry data.
     #[serde(default = "OTelConfig::default_enabled")]
     pub enabled: bool,
 
-    /// GRPC endpoint to export OpenTelemetry data to.
+    /// Endpoint to export OpenTelemetry data to.
+    ///
+    /// For the OTLP exporter this is the GRPC endpoint of the OpenTelemetry agent
+    /// or the full URL to send spans to when using an HTTP [`OTelConfig::protocol`].
+    /// For the Jaeger exporter this is the `host:port` address of the Jaeger agent.
     #[serde(default)]
     pub endpoint: Option<String>,
 
+    /// Export spans to an agent using the selected [`OTelConfig::exporter`].
+    ///
+    /// Set to `false` to only export spans to [`OTelConfig::file_path`].
+    #[serde(default = "OTelConfig::default_export")]
+    pub export: bool,
+
+    /// Protocol used to export spans to an agent.
+    #[serde(default)]
+    pub exporter: OTelExporter,
+
+    /// Append spans to the file at this path as JSON lines, one span per line.
+    ///
+    /// Intended for offline debugging where no OpenTelemetry agent is available.
+    /// Spans are written as they end to avoid losing them if the process exits abruptly.
+    #[serde(default)]
+    pub file_path: Option<String>,
+
+    /// Transport protocol used by the OTLP exporter.
+    #[serde(default)]
+    pub protocol: OtlpProtocol,
+
     /// Configure sampling of traces.
     #[serde(default)]
     pub sampling: Sampler,
 
-    /// Timeout in seconds when communicating with the OpenTelemetry agent.
+    /// Timeout when communicating with the OpenTelemetry agent.
+    ///
+    /// The timeout also limits the time allowed to export a batch of spans.
+    /// The Jaeger exporter only applies the limit to batch exports.
+    #[serde(default, alias = "timeout_sec")]
+    pub timeout: Option<HumanDuration>,
+
+    /// TLS options for connections made by the OTLP exporter.
     #[serde(default)]
-    pub timeout_sec: Option<u64>,
+    pub tls: Option<OTelTlsConfig>,
 }
 
 impl Default for OTelConfig {
     fn default() -> Self {
         OTelConfig {
+            batch_max_queue_size: None,
+            batch_scheduled_delay: None,
+            compression: None,
             enabled: OTelConfig::default_enabled(),
             endpoint: None,
+            export: OTelConfig::default_export(),
+            exporter: OTelExporter::default(),
+            file_path: None,
+            protocol: OtlpProtocol::default(),
             sampling: Sampler::default(),
-            timeout_sec: None,
+            timeout: None,
+            tls: None,
         }
     }
 }
@@ -40,16 +121,230 @@ impl OTelConfig {
     fn default_enabled() -> bool {
         false
     }
+
+    fn default_export() -> bool {
+        true
+    }
+}
+
+/// Errors configuring the export of spans with the OpenTelemetry Protocol (OTLP).
+#[derive(Debug, thiserror::Error)]
+pub enum OTelExportError {
+    /// Compression of exported data is only available when exporting over gRPC.
+    #[error("compression of OTLP exports is only supported by the gRPC protocol")]
+    CompressionNotSupported,
+
+    /// Unable to build the HTTP client used to export spans.
+    #[error("unable to build the HTTP client for the OTLP exporter")]
+    HttpClient,
+
+    /// The HTTP JSON protocol is not supported by the OTLP exporter.
+    #[error("the OTLP exporter does not support the HTTP JSON protocol")]
+    HttpJsonNotSupported,
+
+    /// An option that only applies to the OTLP exporter was set with the Jaeger exporter.
+    #[error("the '{0}' option is not supported by the Jaeger exporter")]
+    JaegerOptionNotSupported(&'static str),
+
+    /// Unable to load the TLS Certificate Authorities bundle.
+    #[error("unable to load the OTLP exporter TLS CA bundle from '{0}'")]
+    TlsCaBundle(String),
+
+    /// Unable to load the TLS client certificate.
+    #[error("unable to load the OTLP exporter TLS client certificate from '{0}'")]
+    TlsClientCert(String),
+
+    /// Unable to load the TLS client private key.
+    #[error("unable to load the OTLP exporter TLS client key from '{0}'")]
+    TlsClientKey(String),
+
+    /// Only one of the TLS client certificate and key was configured.
+    #[error("the OTLP exporter TLS client certificate and key must be set together")]
+    TlsClientIdentityIncomplete,
+}
+
+/// Compression algorithms for data exported using the OpenTelemetry Protocol (OTLP).
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum OTelCompression {
+    /// Compress exported data with gzip.
+    #[serde(alias = "GZIP", alias = "gzip")]
+    Gzip,
+}
+
+impl From<OTelCompression> for opentelemetry_otlp::Compression {
+    fn from(value: OTelCompression) -> Self {
+        match value {
+            OTelCompression::Gzip => opentelemetry_otlp::Compression::Gzip,
+        }
+    }
+}
+
+/// Protocols supported to export spans to an agent.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum OTelExporter {
+    /// Export spans to a Jaeger agent.
+    ///
+    /// Requires the `runtime-telemetry_jaeger` feature.
+    #[serde(alias = "JAEGER", alias = "jaeger")]
+    Jaeger,
+
+    /// Export spans using the OpenTelemetry Protocol (OTLP).
+    #[default]
+    #[serde(alias = "OTLP", alias = "otlp")]
+    Otlp,
+}
+
+/// Transport protocols supported by the OpenTelemetry Protocol (OTLP) exporter.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum OtlpProtocol {
+    /// Export spans over gRPC.
+    #[default]
+    #[serde(alias = "GRPC", alias = "grpc")]
+    Grpc,
+
+    /// Export spans as binary protobuf messages over HTTP.
+    ///
+    /// Requires the `runtime-telemetry_otlp_http` feature.
+    #[serde(alias = "HTTP_BINARY", alias = "http-binary", alias = "http_binary")]
+    HttpBinary,
+
+    /// Export spans as JSON encoded messages over HTTP.
+    ///
+    /// NOTE: this protocol is not yet supported by the OTLP exporter
+    /// and selecting it results in an initialisation error.
+    #[serde(alias = "HTTP_JSON", alias = "http-json", alias = "http_json")]
+    HttpJson,
+}
+
+/// TLS configuration for connections made by the OTLP exporter.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OTelTlsConfig {
+    /// Path to a PEM bundle of trusted CAs used to verify the agent certificate.
+    #[serde(default)]
+    pub ca_bundle: Option<String>,
+
+    /// Path to the PEM encoded certificate presented to the agent for client authentication.
+    #[serde(default)]
+    pub client_cert: Option<String>,
+
+    /// Path to the PEM encoded private key for the client certificate.
+    #[serde(default)]
+    pub client_key: Option<String>,
+}
+
+/// PEM encoded TLS material loaded from the files listed in [`OTelTlsConfig`].
+#[cfg(any(
+    feature = "runtime-telemetry_otlp_http",
+    feature = "runtime-telemetry_otlp_tls",
+))]
+struct OTelTlsMaterial {
+    ca_bundle: Option<Vec<u8>>,
+    identity: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+#[cfg(any(
+    feature = "runtime-telemetry_otlp_http",
+    feature = "runtime-telemetry_otlp_tls",
+))]
+impl OTelTlsMaterial {
+    /// Read the TLS files referenced by the configuration.
+    fn load(conf: &OTelTlsConfig) -> Result<OTelTlsMaterial> {
+        let ca_bundle = match &conf.ca_bundle {
+            None => None,
+            Some(path) => {
+                let bundle = std::fs::read(path)
+                    .with_context(|| OTelExportError::TlsCaBundle(path.clone()))?;
+                Some(bundle)
+            }
+        };
+        let identity = match (&conf.client_cert, &conf.client_key) {
+            (None, None) => None,
+            (Some(cert), Some(key)) => {
+                let cert = std::fs::read(cert)
+                    .with_context(|| OTelExportError::TlsClientCert(cert.clone()))?;
+                let key = std::fs::read(key)
+                    .with_context(|| OTelExportError::TlsClientKey(key.clone()))?;
+                Some((cert, key))
+            }
+            _ => anyhow::bail!(OTelExportError::TlsClientIdentityIncomplete),
+        };
+        Ok(OTelTlsMaterial {
+            ca_bundle,
+            identity,
+        })
+    }
+}
+
+/// The configured trace sampling ratio is outside the `0.0..=1.0` range.
+#[derive(Debug, thiserror::Error)]
+#[error("trace sampling ratio must be between 0.0 and 1.0 (inclusive) but {ratio} was given")]
+pub struct OTelSamplingRatioInvalid {
+    /// The invalid ratio found in the configuration.
+    pub ratio: f64,
 }
 
 /// Programmatic options for the OpenTelemetry framework.
+///
+/// ## Resource attributes
+///
+/// The [`Resource`] attached to exported traces is built by merging, in order:
+///
+/// 1. Attributes detected from the environment, such as `OTEL_SERVICE_NAME`
+///    and `OTEL_RESOURCE_ATTRIBUTES`.
+/// 2. The [`OTelOptions::resource`].
+/// 3. The [`OTelOptions::resource_attributes`].
+/// 4. The [`OTelOptions::service_name`] and [`OTelOptions::service_version`], if set.
+///
+/// When the same attribute is set more than once, the later source wins
+/// so programmatic options take precedence over the environment.
 #[derive(Default)]
 pub struct OTelOptions {
     /// Configuration for the batch exporter.
-    pub batch_config: Option<opentelemetry::sdk::trace::BatchConfig>,
+    ///
+    /// Batch options set in [`OTelConfig`] take precedence over this configuration.
+    pub batch_config: Option<BatchConfig>,
+
+    /// Process identity attributes attached to all root spans.
+    ///
+    /// Refer to [`set_span_identity`](crate::utils::trace::set_span_identity) for details.
+    pub identity: Vec<KeyValue>,
 
     /// Attributes representing the process that produces telemetry data.
-    pub resource: opentelemetry::sdk::Resource,
+    pub resource: Resource,
+
+    /// Additional attributes, such as deployment tags, representing the process.
+    pub resource_attributes: HashMap<Key, Value>,
+
+    /// Name of the service producing telemetry data (the `service.name` resource attribute).
+    pub service_name: Option<String>,
+
+    /// Version of the service producing telemetry data (the `service.version` resource attribute).
+    pub service_version: Option<String>,
+}
+
+impl OTelOptions {
+    /// Build the [`Resource`] for exported traces from the programmatic options and environment.
+    fn build_resource(&self) -> Resource {
+        let attributes = self
+            .resource_attributes
+            .iter()
+            .map(|(key, value)| KeyValue::new(key.clone(), value.clone()));
+        let attributes = Resource::new(attributes);
+
+        let mut service = Vec::new();
+        if let Some(name) = &self.service_name {
+            service.push(SERVICE_NAME.string(name.clone()));
+        }
+        if let Some(version) = &self.service_version {
+            service.push(SERVICE_VERSION.string(version.clone()));
+        }
+        let service = Resource::new(service);
+
+        Resource::default()
+            .merge(&self.resource)
+            .merge(&attributes)
+            .merge(&service)
+    }
 }
 
 /// Trace sampling configuration.
@@ -88,6 +383,8 @@ pub enum SamplerMode {
     Never,
 
     /// Sample a portion of traces based on the configured ratio.
+    ///
+    /// The ratio must be between `0.0` and `1.0` (inclusive).
     #[serde(alias = "RATIO", alias = "RATIO")]
     Ratio(f64),
 }
@@ -110,33 +407,715 @@ pub fn initialise(conf: OTelConfig, options: OTelOptions, logger: slog::Logger)
         let attrs = crate::utils::error::slog::ErrorAttributes::from(&error);
         slog::warn!(logger, "Unhandled OpenTelemetry error occurred"; attrs);
     })?;
+    let resource = options.build_resource();
+
+    // Attach process identity to root spans, even if export is disabled.
+    if !options.identity.is_empty() {
+        crate::utils::trace::set_span_identity(options.identity)?;
+    }
 
     // Skip further setup if tracing is not enabled.
     if !conf.enabled {
         return Ok(());
     }
 
-    // Create and configure OTel Exporter.
+    let provider = tracer_provider(conf, options.batch_config, resource)?;
+    opentelemetry::global::set_tracer_provider(provider);
+    Ok(())
+}
+
+/// Build a [`TracerProvider`] exporting spans to the configured destinations.
+fn tracer_provider(
+    conf: OTelConfig,
+    batch_config: Option<BatchConfig>,
+    resource: Resource,
+) -> Result<TracerProvider> {
+    // Apply configured batch options before the exporter consumes the configuration.
+    let batch_config = self::batch_config(&conf, batch_config);
+
+    // Create and configure OTel TracerProvider.
+    let provider_conf = opentelemetry::sdk::trace::config()
+        .with_sampler(sampler(&conf)?)
+        .with_resource(resource.clone());
+    let mut provider = TracerProvider::builder().with_config(provider_conf);
+
+    // Export spans to a local file if requested.
+    if let Some(path) = &conf.file_path {
+        let exporter = super::opentel_file::FileSpanExporter::open(path)?;
+        provider = provider.with_simple_exporter(exporter);
+    }
+
+    // Create and configure the selected OTel Exporter if requested.
+    if !conf.export {
+        return Ok(provider.build());
+    }
+    let batch_config = batch_config.unwrap_or_default();
+    let processor = match conf.exporter {
+        OTelExporter::Jaeger => {
+            jaeger_options(&conf)?;
+            jaeger_processor(&conf, batch_config, resource)?
+        }
+        OTelExporter::Otlp => {
+            let exporter = match conf.protocol {
+                OtlpProtocol::Grpc => SpanExporterBuilder::from(otlp_exporter(&conf)?),
+                OtlpProtocol::HttpBinary => otlp_http_exporter(&conf)?,
+                OtlpProtocol::HttpJson => anyhow::bail!(OTelExportError::HttpJsonNotSupported),
+            };
+            let exporter = exporter.build_span_exporter()?;
+            BatchSpanProcessor::builder(exporter, opentelemetry::runtime::Tokio)
+                .with_batch_config(batch_config)
+                .build()
+        }
+    };
+    provider = provider.with_span_processor(processor);
+    Ok(provider.build())
+}
+
+/// Reject options set in the [`OTelConfig`] that the Jaeger exporter would ignore.
+fn jaeger_options(conf: &OTelConfig) -> Result<()> {
+    if conf.compression.is_some() {
+        anyhow::bail!(OTelExportError::JaegerOptionNotSupported("compression"));
+    }
+    if conf.protocol != OtlpProtocol::default() {
+        anyhow::bail!(OTelExportError::JaegerOptionNotSupported("protocol"));
+    }
+    if conf.tls.is_some() {
+        anyhow::bail!(OTelExportError::JaegerOptionNotSupported("tls"));
+    }
+    Ok(())
+}
+
+/// Configure a batch processor exporting to the Jaeger agent from the [`OTelConfig`].
+#[cfg(feature = "runtime-telemetry_jaeger")]
+fn jaeger_processor(
+    conf: &OTelConfig,
+    batch_config: BatchConfig,
+    resource: Resource,
+) -> Result<BatchSpanProcessor<opentelemetry::runtime::Tokio>> {
+    // The Jaeger exporter reports the service name from the trace configuration resource.
+    let trace_config = opentelemetry::sdk::trace::config().with_resource(resource);
+    let mut pipeline = opentelemetry_jaeger::new_agent_pipeline().with_trace_config(trace_config);
+    if let Some(endpoint) = &conf.endpoint {
+        pipeline = pipeline.with_endpoint(endpoint);
+    }
+    let exporter = pipeline.build_async_agent_exporter(opentelemetry::runtime::Tokio)?;
+    let processor = BatchSpanProcessor::builder(exporter, opentelemetry::runtime::Tokio)
+        .with_batch_config(batch_config)
+        .build();
+    Ok(processor)
+}
+
+/// Reject the Jaeger exporter when support for it is not compiled in.
+#[cfg(not(feature = "runtime-telemetry_jaeger"))]
+fn jaeger_processor(
+    _: &OTelConfig,
+    _: BatchConfig,
+    _: Resource,
+) -> Result<BatchSpanProcessor<opentelemetry::runtime::Tokio>> {
+    anyhow::bail!("the Jaeger exporter requires the runtime-telemetry_jaeger feature")
+}
+
+/// Configure the trace [`SdkSampler`] from the [`OTelConfig`].
+fn sampler(conf: &OTelConfig) -> Result<SdkSampler> {
+    if let SamplerMode::Ratio(ratio) = conf.sampling.mode {
+        if !(0.0..=1.0).contains(&ratio) {
+            anyhow::bail!(OTelSamplingRatioInvalid { ratio });
+        }
+    }
+    Ok(SdkSampler::from(conf.sampling.clone()))
+}
+
+/// Configure the gRPC OTLP exporter from the [`OTelConfig`].
+fn otlp_exporter(conf: &OTelConfig) -> Result<TonicExporterBuilder> {
     let mut exporter = opentelemetry_otlp::new_exporter().tonic();
-    if let Some(endpoint) = conf.endpoint {
+    if let Some(compression) = conf.compression {
+        exporter = exporter.with_compression(compression.into());
+    }
+    if let Some(endpoint) = &conf.endpoint {
         exporter = exporter.with_endpoint(endpoint);
     }
-    if let Some(timeout) = conf.timeout_sec {
-        let timeout = std::time::Duration::from_secs(timeout);
-        exporter = exporter.with_timeout(timeout);
+    if let Some(timeout) = conf.timeout {
+        exporter = exporter.with_timeout(timeout.duration());
+    }
+    if let Some(tls) = &conf.tls {
+        exporter = otlp_tls(exporter, tls)?;
     }
+    Ok(exporter)
+}
 
-    // Create and configure OTel Pipeline.
-    let pipeline_conf = opentelemetry::sdk::trace::config()
-        .with_sampler(SdkSampler::from(conf.sampling))
-        .with_resource(options.resource);
-    let mut pipeline = opentelemetry_otlp::new_pipeline()
-        .tracing()
-        .with_exporter(exporter)
-        .with_trace_config(pipeline_conf);
-    if let Some(batch_config) = options.batch_config {
-        pipeline = pipeline.with_batch_config(batch_config);
+/// Configure TLS connections for the gRPC OTLP exporter.
+#[cfg(feature = "runtime-telemetry_otlp_tls")]
+fn otlp_tls(exporter: TonicExporterBuilder, conf: &OTelTlsConfig) -> Result<TonicExporterBuilder> {
+    let material = OTelTlsMaterial::load(conf)?;
+    let mut tls = tonic::transport::ClientTlsConfig::new();
+    if let Some(bundle) = material.ca_bundle {
+        tls = tls.ca_certificate(tonic::transport::Certificate::from_pem(bundle));
+    }
+    if let Some((cert, key)) = material.identity {
+        tls = tls.identity(tonic::transport::Identity::from_pem(cert, key));
+    }
+    Ok(exporter.with_tls_config(tls))
+}
+
+/// Reject TLS for the gRPC OTLP exporter when support for it is not compiled in.
+#[cfg(not(feature = "runtime-telemetry_otlp_tls"))]
+fn otlp_tls(_: TonicExporterBuilder, _: &OTelTlsConfig) -> Result<TonicExporterBuilder> {
+    anyhow::bail!("TLS for the gRPC OTLP exporter requires the runtime-telemetry_otlp_tls feature")
+}
+
+/// Configure the HTTP (binary protobuf) OTLP exporter from the [`OTelConfig`].
+#[cfg(feature = "runtime-telemetry_otlp_http")]
+fn otlp_http_exporter(conf: &OTelConfig) -> Result<SpanExporterBuilder> {
+    if conf.compression.is_some() {
+        anyhow::bail!(OTelExportError::CompressionNotSupported);
+    }
+
+    let endpoint = conf
+        .endpoint
+        .clone()
+        .unwrap_or_else(|| OTLP_HTTP_DEFAULT_ENDPOINT.to_string());
+    let mut exporter = opentelemetry_otlp::new_exporter()
+        .http()
+        .with_endpoint(endpoint);
+    if let Some(timeout) = conf.timeout {
+        exporter = exporter.with_timeout(timeout.duration());
+    }
+
+    let mut client = reqwest::Client::builder();
+    if let Some(tls) = &conf.tls {
+        let material = OTelTlsMaterial::load(tls)?;
+        if let Some(bundle) = material.ca_bundle {
+            let path = tls.ca_bundle.clone().unwrap_or_default();
+            let bundle = reqwest::Certificate::from_pem(&bundle)
+                .with_context(|| OTelExportError::TlsCaBundle(path))?;
+            client = client.add_root_certificate(bundle);
+        }
+        if let Some((mut cert, key)) = material.identity {
+            // Reqwest expects the certificate and key in a single PEM buffer.
+            let path = tls.client_cert.clone().unwrap_or_default();
+            cert.extend(key);
+            let identity = reqwest::Identity::from_pem(&cert)
+                .with_context(|| OTelExportError::TlsClientCert(path))?;
+            client = client.identity(identity);
+        }
+    }
+    let client = client.build().context(OTelExportError::HttpClient)?;
+    Ok(exporter.with_http_client(client).into())
+}
+
+/// Reject the HTTP OTLP exporter when support for it is not compiled in.
+#[cfg(not(feature = "runtime-telemetry_otlp_http"))]
+fn otlp_http_exporter(_: &OTelConfig) -> Result<SpanExporterBuilder> {
+    anyhow::bail!("the HTTP OTLP exporter requires the runtime-telemetry_otlp_http feature")
+}
+
+/// Batch span processor options that can be set from an [`OTelConfig`].
+trait BatchOptions: Default {
+    /// Set the maximum duration to export a batch of spans.
+    fn with_max_export_timeout(self, timeout: Duration) -> Self;
+
+    /// Set the maximum number of spans buffered for export.
+    fn with_max_queue_size(self, size: usize) -> Self;
+
+    /// Set the delay between two consecutive exports of span batches.
+    fn with_scheduled_delay(self, delay: Duration) -> Self;
+}
+
+impl BatchOptions for BatchConfig {
+    fn with_max_export_timeout(self, timeout: Duration) -> Self {
+        BatchConfig::with_max_export_timeout(self, timeout)
+    }
+
+    fn with_max_queue_size(self, size: usize) -> Self {
+        BatchConfig::with_max_queue_size(self, size)
+    }
+
+    fn with_scheduled_delay(self, delay: Duration) -> Self {
+        BatchConfig::with_scheduled_delay(self, delay)
+    }
+}
+
+/// Apply batch options from the [`OTelConfig`] on top of the programmatic [`BatchConfig`].
+fn batch_config<B>(conf: &OTelConfig, batch_config: Option<B>) -> Option<B>
+where
+    B: BatchOptions,
+{
+    let configured = conf.batch_max_queue_size.is_some()
+        || conf.batch_scheduled_delay.is_some()
+        || conf.timeout.is_some();
+    if !configured {
+        return batch_config;
+    }
+
+    let mut batch_config = batch_config.unwrap_or_default();
+    if let Some(size) = conf.batch_max_queue_size {
+        batch_config = batch_config.with_max_queue_size(size);
+    }
+    if let Some(delay) = conf.batch_scheduled_delay {
+        batch_config = batch_config.with_scheduled_delay(delay.duration());
+    }
+    if let Some(timeout) = conf.timeout {
+        batch_config = batch_config.with_max_export_timeout(timeout.duration());
+    }
+    Some(batch_config)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use opentelemetry::sdk::export::trace::SpanData;
+    use opentelemetry::sdk::trace::Span;
+    use opentelemetry::sdk::trace::SpanProcessor;
+    use opentelemetry::sdk::trace::TracerProvider;
+    use opentelemetry::trace::Span as _;
+    use opentelemetry::trace::TraceContextExt;
+    use opentelemetry::trace::TraceResult;
+    use opentelemetry::trace::Tracer as _;
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry::Context;
+    use opentelemetry::Key;
+    use opentelemetry::KeyValue;
+    use opentelemetry::Value;
+
+    use super::batch_config;
+    use super::otlp_exporter;
+    use super::BatchOptions;
+    use super::HumanDuration;
+    use super::OTelCompression;
+    use super::OTelConfig;
+    use super::OTelExportError;
+    use super::OTelExporter;
+    use super::OTelOptions;
+    use super::OTelSamplingRatioInvalid;
+    use super::OTelTlsConfig;
+    use super::OtlpProtocol;
+    use super::Resource;
+    use super::Sampler;
+    use super::SamplerMode;
+
+    /// Capture ended spans for inspection.
+    #[derive(Clone, Debug, Default)]
+    struct CaptureSpans(Arc<Mutex<Vec<SpanData>>>);
+
+    impl SpanProcessor for CaptureSpans {
+        fn on_start(&self, _: &mut Span, _: &Context) {}
+
+        fn on_end(&self, span: SpanData) {
+            self.0.lock().unwrap().push(span);
+        }
+
+        fn force_flush(&self) -> TraceResult<()> {
+            Ok(())
+        }
+
+        fn shutdown(&mut self) -> TraceResult<()> {
+            Ok(())
+        }
+    }
+
+    /// Record batch options applied from the configuration.
+    #[derive(Debug, Default, PartialEq)]
+    struct TestBatch {
+        max_export_batch_size: Option<usize>,
+        max_export_timeout: Option<Duration>,
+        max_queue_size: Option<usize>,
+        scheduled_delay: Option<Duration>,
+    }
+
+    impl BatchOptions for TestBatch {
+        fn with_max_export_timeout(mut self, timeout: Duration) -> Self {
+            self.max_export_timeout = Some(timeout);
+            self
+        }
+
+        fn with_max_queue_size(mut self, size: usize) -> Self {
+            self.max_queue_size = Some(size);
+            self
+        }
+
+        fn with_scheduled_delay(mut self, delay: Duration) -> Self {
+            self.scheduled_delay = Some(delay);
+            self
+        }
+    }
+
+    #[test]
+    fn batch_config_not_configured() {
+        let conf = OTelConfig::default();
+        let batch = batch_config::<TestBatch>(&conf, None);
+        assert!(batch.is_none());
+    }
+
+    #[test]
+    fn batch_config_from_conf() {
+        let conf = OTelConfig {
+            batch_max_queue_size: Some(42),
+            batch_scheduled_delay: Some(HumanDuration::from_millis(250)),
+            timeout: Some(HumanDuration::from_secs(3)),
+            ..Default::default()
+        };
+        let batch = batch_config::<TestBatch>(&conf, None).unwrap();
+        let expected = TestBatch {
+            max_export_batch_size: None,
+            max_export_timeout: Some(Duration::from_secs(3)),
+            max_queue_size: Some(42),
+            scheduled_delay: Some(Duration::from_millis(250)),
+        };
+        assert_eq!(batch, expected);
+    }
+
+    #[test]
+    fn batch_config_overrides_options() {
+        let conf = OTelConfig {
+            batch_max_queue_size: Some(42),
+            ..Default::default()
+        };
+        let options = TestBatch {
+            max_export_batch_size: Some(21),
+            ..Default::default()
+        };
+        let batch = batch_config(&conf, Some(options)).unwrap();
+        let expected = TestBatch {
+            max_export_batch_size: Some(21),
+            max_queue_size: Some(42),
+            ..Default::default()
+        };
+        assert_eq!(batch, expected);
+    }
+
+    #[test]
+    fn otlp_exporter_compression() {
+        let conf = OTelConfig::default();
+        let exporter = format!("{:?}", otlp_exporter(&conf).unwrap());
+        assert!(exporter.contains("compression: None"));
+
+        let conf = OTelConfig {
+            compression: Some(OTelCompression::Gzip),
+            ..Default::default()
+        };
+        let exporter = format!("{:?}", otlp_exporter(&conf).unwrap());
+        assert!(exporter.contains("compression: Some(Gzip)"));
+    }
+
+    #[rstest::rstest]
+    #[case(r#"{"compression": "Gzip"}"#)]
+    #[case(r#"{"compression": "GZIP"}"#)]
+    #[case(r#"{"compression": "gzip"}"#)]
+    fn otlp_compression_aliases(#[case] conf: &str) {
+        let conf: OTelConfig = serde_json::from_str(conf).unwrap();
+        assert_eq!(conf.compression, Some(OTelCompression::Gzip));
+        let compression = conf.compression.map(opentelemetry_otlp::Compression::from);
+        assert_eq!(compression, Some(opentelemetry_otlp::Compression::Gzip));
+    }
+
+    #[test]
+    fn otlp_protocol_selection() {
+        let conf: OTelConfig = serde_json::from_str("{}").unwrap();
+        assert_eq!(conf.protocol, OtlpProtocol::Grpc);
+        let conf: OTelConfig = serde_json::from_str(r#"{"protocol": "http-binary"}"#).unwrap();
+        assert_eq!(conf.protocol, OtlpProtocol::HttpBinary);
+        let conf: OTelConfig = serde_json::from_str(r#"{"protocol": "HttpJson"}"#).unwrap();
+        assert_eq!(conf.protocol, OtlpProtocol::HttpJson);
+    }
+
+    // The batch processor blocks on shutdown so it needs a worker to run on.
+    #[tokio::test(flavor = "multi_thread")]
+    #[cfg(feature = "runtime-telemetry_otlp_http")]
+    async fn otlp_http_exporter() {
+        let conf = OTelConfig {
+            enabled: true,
+            protocol: OtlpProtocol::HttpBinary,
+            ..Default::default()
+        };
+        let provider = super::tracer_provider(conf, None, Default::default()).unwrap();
+        assert_eq!(provider.span_processors().len(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "runtime-telemetry_otlp_http")]
+    fn otlp_http_exporter_rejects_compression() {
+        let conf = OTelConfig {
+            compression: Some(OTelCompression::Gzip),
+            protocol: OtlpProtocol::HttpBinary,
+            ..Default::default()
+        };
+        let error = super::otlp_http_exporter(&conf).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<OTelExportError>(),
+            Some(OTelExportError::CompressionNotSupported),
+        ));
+    }
+
+    #[test]
+    #[cfg(not(feature = "runtime-telemetry_otlp_http"))]
+    fn otlp_http_exporter_not_enabled() {
+        let conf = OTelConfig {
+            enabled: true,
+            protocol: OtlpProtocol::HttpBinary,
+            ..Default::default()
+        };
+        let error = super::tracer_provider(conf, None, Default::default()).unwrap_err();
+        assert!(error.to_string().contains("runtime-telemetry_otlp_http"));
+    }
+
+    #[test]
+    fn otlp_http_json_not_supported() {
+        let conf = OTelConfig {
+            enabled: true,
+            protocol: OtlpProtocol::HttpJson,
+            ..Default::default()
+        };
+        let error = super::tracer_provider(conf, None, Default::default()).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<OTelExportError>(),
+            Some(OTelExportError::HttpJsonNotSupported),
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "runtime-telemetry_otlp_tls")]
+    fn otlp_tls_missing_ca_bundle() {
+        let conf = OTelConfig {
+            tls: Some(OTelTlsConfig {
+                ca_bundle: Some("/path/to/missing/ca.pem".into()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let error = otlp_exporter(&conf).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<OTelExportError>(),
+            Some(OTelExportError::TlsCaBundle(path)) if path == "/path/to/missing/ca.pem",
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "runtime-telemetry_otlp_tls")]
+    fn otlp_tls_incomplete_identity() {
+        let conf = OTelConfig {
+            tls: Some(OTelTlsConfig {
+                client_cert: Some("/path/to/client.crt".into()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let error = otlp_exporter(&conf).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<OTelExportError>(),
+            Some(OTelExportError::TlsClientIdentityIncomplete),
+        ));
+    }
+
+    #[test]
+    #[cfg(not(feature = "runtime-telemetry_otlp_tls"))]
+    fn otlp_tls_not_enabled() {
+        let conf = OTelConfig {
+            tls: Some(OTelTlsConfig::default()),
+            ..Default::default()
+        };
+        let error = otlp_exporter(&conf).unwrap_err();
+        assert!(error.to_string().contains("runtime-telemetry_otlp_tls"));
+    }
+
+    #[test]
+    fn exporter_selection() {
+        let conf: OTelConfig = serde_json::from_str("{}").unwrap();
+        assert_eq!(conf.exporter, OTelExporter::Otlp);
+        let conf: OTelConfig = serde_json::from_str(r#"{"exporter": "jaeger"}"#).unwrap();
+        assert_eq!(conf.exporter, OTelExporter::Jaeger);
+    }
+
+    // The batch processor blocks on shutdown so it needs a worker to run on.
+    #[tokio::test(flavor = "multi_thread")]
+    #[cfg(feature = "runtime-telemetry_jaeger")]
+    async fn jaeger_exporter() {
+        let conf = OTelConfig {
+            enabled: true,
+            endpoint: Some("127.0.0.1:6831".into()),
+            exporter: OTelExporter::Jaeger,
+            ..Default::default()
+        };
+        let provider = super::tracer_provider(conf, None, Default::default()).unwrap();
+        assert_eq!(provider.span_processors().len(), 1);
+    }
+
+    #[rstest::rstest]
+    #[case(
+        OTelConfig {
+            compression: Some(OTelCompression::Gzip),
+            ..Default::default()
+        },
+        "compression",
+    )]
+    #[case(
+        OTelConfig {
+            protocol: OtlpProtocol::HttpBinary,
+            ..Default::default()
+        },
+        "protocol",
+    )]
+    #[case(
+        OTelConfig {
+            tls: Some(OTelTlsConfig::default()),
+            ..Default::default()
+        },
+        "tls",
+    )]
+    fn jaeger_rejects_otlp_options(#[case] conf: OTelConfig, #[case] option: &str) {
+        let conf = OTelConfig {
+            enabled: true,
+            exporter: OTelExporter::Jaeger,
+            ..conf
+        };
+        let error = super::tracer_provider(conf, None, Default::default()).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<OTelExportError>(),
+            Some(OTelExportError::JaegerOptionNotSupported(name)) if *name == option,
+        ));
+    }
+
+    #[test]
+    #[cfg(not(feature = "runtime-telemetry_jaeger"))]
+    fn jaeger_exporter_not_enabled() {
+        let conf = OTelConfig {
+            enabled: true,
+            exporter: OTelExporter::Jaeger,
+            ..Default::default()
+        };
+        let error = super::tracer_provider(conf, None, Default::default()).unwrap_err();
+        assert!(error.to_string().contains("runtime-telemetry_jaeger"));
+    }
+
+    #[rstest::rstest]
+    #[case(0.0)]
+    #[case(0.25)]
+    #[case(1.0)]
+    fn sampling_ratio_valid(#[case] ratio: f64) {
+        let conf = OTelConfig {
+            sampling: Sampler {
+                follow_parent: true,
+                mode: SamplerMode::Ratio(ratio),
+            },
+            ..Default::default()
+        };
+        assert!(super::sampler(&conf).is_ok());
+    }
+
+    #[rstest::rstest]
+    #[case(-0.1)]
+    #[case(1.5)]
+    #[case(f64::NAN)]
+    fn sampling_ratio_invalid(#[case] ratio: f64) {
+        let conf = OTelConfig {
+            sampling: Sampler {
+                follow_parent: false,
+                mode: SamplerMode::Ratio(ratio),
+            },
+            ..Default::default()
+        };
+        let error = super::sampler(&conf).unwrap_err();
+        assert!(error.is::<OTelSamplingRatioInvalid>());
+    }
+
+    #[test]
+    fn file_export_writes_spans() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("spans.jsonl");
+        let conf = OTelConfig {
+            enabled: true,
+            file_path: Some(path.to_string_lossy().into_owned()),
+            export: false,
+            ..Default::default()
+        };
+        let provider = super::tracer_provider(conf, None, Default::default()).unwrap();
+        let tracer = provider.tracer("test");
+        let mut span = tracer.start("file-export");
+        span.set_attribute(KeyValue::new("answer", 42));
+        span.end();
+        drop(tracer);
+        drop(provider);
+
+        let spans = std::fs::read_to_string(&path).unwrap();
+        let spans: Vec<serde_json::Value> = spans
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0]["name"], "file-export");
+        assert_eq!(spans[0]["attributes"]["answer"], 42);
+    }
+
+    #[test]
+    fn resource_merges_programmatic_attributes() {
+        let mut options = OTelOptions {
+            resource: Resource::new([
+                KeyValue::new("deployment.environment", "staging"),
+                KeyValue::new("host.name", "node-1"),
+                KeyValue::new("service.name", "from-resource"),
+            ]),
+            service_name: Some("agent".into()),
+            service_version: Some("1.2.3".into()),
+            ..Default::default()
+        };
+        options.resource_attributes.insert(
+            Key::new("deployment.environment"),
+            Value::from("production"),
+        );
+        let resource = options.build_resource();
+        assert_eq!(
+            resource.get(Key::new("deployment.environment")),
+            Some(Value::from("production")),
+        );
+        assert_eq!(
+            resource.get(Key::new("host.name")),
+            Some(Value::from("node-1")),
+        );
+        assert_eq!(
+            resource.get(Key::new("service.name")),
+            Some(Value::from("agent")),
+        );
+        assert_eq!(
+            resource.get(Key::new("service.version")),
+            Some(Value::from("1.2.3")),
+        );
+    }
+
+    #[test]
+    fn root_span_carries_identity() {
+        let options = || OTelOptions {
+            identity: vec![
+                KeyValue::new("node_id", "node-1"),
+                KeyValue::new("platform", "test"),
+            ],
+            ..Default::default()
+        };
+        let logger = slog::Logger::root(slog::Discard, slog::o!());
+        super::initialise(OTelConfig::default(), options(), logger.clone()).unwrap();
+
+        // Initialising again with the same identity, or none, is allowed.
+        super::initialise(OTelConfig::default(), options(), logger.clone()).unwrap();
+        super::initialise(OTelConfig::default(), OTelOptions::default(), logger).unwrap();
+
+        let spans = CaptureSpans::default();
+        let provider = TracerProvider::builder()
+            .with_span_processor(spans.clone())
+            .build();
+        let tracer = provider.tracer("test");
+        let context = crate::utils::trace::root(&tracer, "root");
+        context.span().end();
+
+        let spans = spans.0.lock().unwrap();
+        let attributes = &spans[0].attributes;
+        assert_eq!(
+            attributes.get(&Key::new("node_id")),
+            Some(&Value::from("node-1")),
+        );
+        assert_eq!(
+            attributes.get(&Key::new("platform")),
+            Some(&Value::from("test")),
+        );
     }
-    pipeline.install_batch(opentelemetry::runtime::Tokio)?;
-    Ok(())
 }
@@ -0,0 +1,134 @@
+//! Export OpenTelemetry spans to a local file for offline debugging.
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::BufWriter;
+use std::io::Write;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use anyhow::Context;
+use anyhow::Result;
+use futures::future::BoxFuture;
+use opentelemetry::sdk::export::trace::ExportResult;
+use opentelemetry::sdk::export::trace::SpanData;
+use opentelemetry::sdk::export::trace::SpanExporter;
+use opentelemetry::trace::TraceError;
+use opentelemetry::Value;
+use serde_json::json;
+use serde_json::Map;
+use serde_json::Value as Json;
+
+/// Errors setting up export of spans to a file.
+#[derive(Debug, thiserror::Error)]
+pub enum FileExportError {
+    /// Unable to open the file to export spans to.
+    #[error("unable to open file '{0}' to export spans to")]
+    Open(String),
+}
+
+/// Append spans to a file as JSON lines, one span per line.
+#[derive(Debug)]
+pub struct FileSpanExporter {
+    file: Option<File>,
+}
+
+impl FileSpanExporter {
+    /// Open (or create) the file at `path` to append spans to.
+    pub fn open(path: &str) -> Result<FileSpanExporter> {
+        let file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)
+            .with_context(|| FileExportError::Open(path.to_string()))?;
+        Ok(FileSpanExporter { file: Some(file) })
+    }
+
+    /// Write a batch of spans to the file.
+    fn write(&mut self, batch: Vec<SpanData>) -> std::io::Result<()> {
+        let file = match &self.file {
+            None => return Ok(()),
+            Some(file) => file,
+        };
+        let mut writer = BufWriter::new(file);
+        for span in batch {
+            serde_json::to_writer(&mut writer, &span_json(span))?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()
+    }
+}
+
+impl SpanExporter for FileSpanExporter {
+    fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+        let result = self
+            .write(batch)
+            .map_err(|error| TraceError::Other(Box::new(error)));
+        Box::pin(std::future::ready(result))
+    }
+
+    fn shutdown(&mut self) {
+        self.file = None;
+    }
+}
+
+/// Convert OpenTelemetry attributes into a JSON object.
+fn attributes_json<'a, I>(attributes: I) -> Json
+where
+    I: IntoIterator<Item = (&'a opentelemetry::Key, &'a Value)>,
+{
+    let attributes: Map<String, Json> = attributes
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value_json(value)))
+        .collect();
+    Json::Object(attributes)
+}
+
+/// Convert a [`SpanData`] into a JSON object.
+fn span_json(span: SpanData) -> Json {
+    let events: Vec<Json> = span
+        .events
+        .iter()
+        .map(|event| {
+            let attributes = event
+                .attributes
+                .iter()
+                .map(|attribute| (&attribute.key, &attribute.value));
+            json!({
+                "attributes": attributes_json(attributes),
+                "name": event.name,
+                "time_unix_nano": unix_nano(event.timestamp),
+            })
+        })
+        .collect();
+    json!({
+        "attributes": attributes_json(span.attributes.iter()),
+        "end_time_unix_nano": unix_nano(span.end_time),
+        "events": events,
+        "kind": format!("{:?}", span.span_kind),
+        "name": span.name,
+        "parent_span_id": span.parent_span_id.to_string(),
+        "resource": attributes_json(span.resource.iter()),
+        "span_id": span.span_context.span_id().to_string(),
+        "start_time_unix_nano": unix_nano(span.start_time),
+        "status": format!("{:?}", span.status),
+        "trace_id": span.span_context.trace_id().to_string(),
+    })
+}
+
+/// Nanoseconds since the UNIX epoch, or zero for times before the epoch.
+fn unix_nano(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default()
+}
+
+/// Convert an OpenTelemetry attribute value into JSON.
+fn value_json(value: &Value) -> Json {
+    match value {
+        Value::Bool(value) => Json::from(*value),
+        Value::F64(value) => Json::from(*value),
+        Value::I64(value) => Json::from(*value),
+        Value::String(value) => Json::from(value.as_str()),
+        Value::Array(value) => Json::from(value.to_string()),
+    }
+}
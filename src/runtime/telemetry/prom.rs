@@ -1,22 +1,37 @@
 //! Prometheus metrics initialisation related logic.
 use std::collections::BTreeMap;
+use std::future::Future;
 
 use anyhow::Context;
 use anyhow::Result;
+use prometheus::Encoder;
 use prometheus::Registry;
+use prometheus::TextEncoder;
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::utils::config::HumanDuration;
+use crate::utils::error::slog::ErrorAttributes;
+
 /// Configuration of Prometheus metrics collection.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct PrometheusConfig {
     /// Additional labels to attach to all process metrics.
+    ///
+    /// Labels set here take precedence over [`PrometheusOptions::labels`] with the same name.
     #[serde(default)]
     pub labels: BTreeMap<String, String>,
 
     /// Enable or disable collecting process-level metrics (linux only).
     #[serde(default = "PrometheusConfig::default_process_metrics")]
     pub process_metrics: bool,
+
+    /// Periodically push metrics to a Prometheus Pushgateway.
+    ///
+    /// Intended for short-lived processes that may exit before Prometheus scrapes them.
+    /// Requires the `runtime-telemetry_pushgateway` feature.
+    #[serde(default)]
+    pub push_gateway: Option<PushGatewayConfig>,
 }
 
 impl Default for PrometheusConfig {
@@ -24,6 +39,7 @@ impl Default for PrometheusConfig {
         PrometheusConfig {
             labels: Default::default(),
             process_metrics: PrometheusConfig::default_process_metrics(),
+            push_gateway: None,
         }
     }
 }
@@ -34,23 +50,80 @@ impl PrometheusConfig {
     }
 }
 
+/// Programmatic options for the Prometheus registry.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PrometheusOptions {
+    /// Constant labels attached to all metrics in the registry, such as instance identity.
+    pub labels: BTreeMap<String, String>,
+
+    /// Prefix prepended to the name of all metrics in the registry.
+    pub prefix: Option<String>,
+}
+
+/// Configuration of metrics pushes to a Prometheus Pushgateway.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PushGatewayConfig {
+    /// Interval between pushes of the process metrics.
+    #[serde(default = "PushGatewayConfig::default_interval")]
+    pub interval: HumanDuration,
+
+    /// Name of the job to group pushed metrics under.
+    pub job: String,
+
+    /// Maximum time to wait for each push to complete.
+    #[serde(default = "PushGatewayConfig::default_timeout")]
+    pub timeout: HumanDuration,
+
+    /// Base URL of the Pushgateway, such as `http://localhost:9091`.
+    pub url: String,
+}
+
+impl PushGatewayConfig {
+    fn default_interval() -> HumanDuration {
+        HumanDuration::from_secs(15)
+    }
+
+    fn default_timeout() -> HumanDuration {
+        HumanDuration::from_secs(10)
+    }
+}
+
 /// Errors initialising the Prometheus registry for the process.
 #[derive(Debug, thiserror::Error)]
 pub enum PrometheusError {
     /// Returned when the configured global labels are not valid.
     #[error("invalid prometheus global labels found in the configuration")]
     InvalidLabels,
+
+    /// Returned when the configured Pushgateway push interval or timeout is zero.
+    #[error("prometheus pushgateway {0} must be greater than zero")]
+    InvalidPushDuration(&'static str),
+
+    /// Returned when the configured Pushgateway job name is not valid.
+    #[error("invalid prometheus pushgateway job name '{0}'")]
+    InvalidPushJob(String),
+
+    /// Returned when the configured Pushgateway URL is not valid.
+    #[error("invalid prometheus pushgateway url '{0}'")]
+    InvalidPushUrl(String),
+
+    /// Returned when the Pushgateway rejects or fails to receive pushed metrics.
+    #[error("unable to push metrics to the prometheus pushgateway")]
+    PushFailed,
 }
 
 /// Initialise a Prometheus metrics registry for the process.
-pub fn initialise(conf: PrometheusConfig) -> Result<Registry> {
-    // Create the registry with globally configured labels.
-    let labels = if conf.labels.is_empty() {
+pub fn initialise(conf: PrometheusConfig, options: PrometheusOptions) -> Result<Registry> {
+    // Create the registry with global labels from options and configuration.
+    let mut labels = options.labels;
+    labels.extend(conf.labels);
+    let labels = if labels.is_empty() {
         None
     } else {
-        Some(conf.labels.into_iter().collect())
+        Some(labels.into_iter().collect())
     };
-    let reg = Registry::new_custom(None, labels).context(PrometheusError::InvalidLabels)?;
+    let reg =
+        Registry::new_custom(options.prefix, labels).context(PrometheusError::InvalidLabels)?;
 
     // If configured and supported enable process level metrics.
     #[cfg(target_os = "linux")]
@@ -64,9 +137,179 @@ pub fn initialise(conf: PrometheusConfig) -> Result<Registry> {
     Ok(reg)
 }
 
+/// Background task pushing the process metrics to a Prometheus Pushgateway.
+///
+/// Metrics are pushed every [`PushGatewayConfig::interval`] and once more
+/// when the process shuts down so the final values are not lost.
+///
+/// The task is returned as part of the [`Telemetry`](super::Telemetry) resources
+/// for the process to run and watch, for example with
+/// [`ShutdownManagerBuilder::watch_future`](crate::runtime::shutdown::ShutdownManagerBuilder::watch_future).
+#[derive(Clone)]
+pub struct PushGateway {
+    interval: std::time::Duration,
+    logger: slog::Logger,
+    registry: Registry,
+    target: PushTarget,
+}
+
+impl PushGateway {
+    /// Push the current value of all metrics in the registry to the Pushgateway.
+    pub async fn push(&self) -> Result<()> {
+        let encoder = TextEncoder::new();
+        let mut body = Vec::new();
+        encoder.encode(&self.registry.gather(), &mut body)?;
+        self.target.send(encoder.format_type(), body).await
+    }
+
+    /// Periodically push metrics until the `shutdown` future resolves, then push a final time.
+    pub async fn task<S>(self, shutdown: S) -> Result<()>
+    where
+        S: Future<Output = ()>,
+    {
+        tokio::pin!(shutdown);
+        slog::debug!(
+            self.logger,
+            "Starting prometheus pushgateway metrics pushes"
+        );
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(self.interval) => {},
+                _ = &mut shutdown => break,
+            }
+            if let Err(error) = self.push().await {
+                slog::warn!(
+                    self.logger,
+                    "Unable to push metrics to the prometheus pushgateway";
+                    ErrorAttributes::from(&error),
+                );
+            }
+        }
+
+        slog::debug!(
+            self.logger,
+            "Pushing final metrics to the prometheus pushgateway"
+        );
+        if let Err(error) = self.push().await {
+            slog::warn!(
+                self.logger,
+                "Unable to push final metrics to the prometheus pushgateway";
+                ErrorAttributes::from(&error),
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Configure pushes of the registry metrics to a Pushgateway, if enabled.
+pub fn push_gateway(
+    conf: Option<PushGatewayConfig>,
+    registry: &Registry,
+    logger: &slog::Logger,
+) -> Result<Option<PushGateway>> {
+    let conf = match conf {
+        None => return Ok(None),
+        Some(conf) => conf,
+    };
+    if conf.interval.duration().is_zero() {
+        anyhow::bail!(PrometheusError::InvalidPushDuration("interval"));
+    }
+    if conf.timeout.duration().is_zero() {
+        anyhow::bail!(PrometheusError::InvalidPushDuration("timeout"));
+    }
+    if conf.job.is_empty() || conf.job.contains('/') {
+        anyhow::bail!(PrometheusError::InvalidPushJob(conf.job));
+    }
+
+    let target = PushTarget::new(&conf)?;
+    let logger = logger.new(slog::o!("pushgateway" => target.url().to_string()));
+    Ok(Some(PushGateway {
+        interval: conf.interval.into(),
+        logger,
+        registry: registry.clone(),
+        target,
+    }))
+}
+
+/// HTTP endpoint metrics are pushed to.
+#[cfg(feature = "runtime-telemetry_pushgateway")]
+#[derive(Clone)]
+struct PushTarget {
+    client: reqwest::Client,
+    url: reqwest::Url,
+}
+
+#[cfg(feature = "runtime-telemetry_pushgateway")]
+impl PushTarget {
+    /// Build the HTTP client and URL for the configured Pushgateway job.
+    fn new(conf: &PushGatewayConfig) -> Result<PushTarget> {
+        // Path segments are percent-encoded as they are appended to the URL.
+        let mut url = reqwest::Url::parse(&conf.url)
+            .with_context(|| PrometheusError::InvalidPushUrl(conf.url.clone()))?;
+        url.path_segments_mut()
+            .map_err(|_| PrometheusError::InvalidPushUrl(conf.url.clone()))?
+            .pop_if_empty()
+            .extend(["metrics", "job", &conf.job]);
+
+        let client = reqwest::Client::builder()
+            .timeout(conf.timeout.duration())
+            .build()?;
+        Ok(PushTarget { client, url })
+    }
+
+    /// Send encoded metrics to the Pushgateway.
+    async fn send(&self, content_type: &str, body: Vec<u8>) -> Result<()> {
+        self.client
+            .put(self.url.clone())
+            .header(reqwest::header::CONTENT_TYPE, content_type)
+            .body(body)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .context(PrometheusError::PushFailed)?;
+        Ok(())
+    }
+
+    fn url(&self) -> &str {
+        self.url.as_str()
+    }
+}
+
+/// Pushgateway endpoints can't be created when support for them is not compiled in.
+#[cfg(not(feature = "runtime-telemetry_pushgateway"))]
+#[derive(Clone)]
+enum PushTarget {}
+
+#[cfg(not(feature = "runtime-telemetry_pushgateway"))]
+impl PushTarget {
+    /// Reject Pushgateway configurations when support for them is not compiled in.
+    fn new(_: &PushGatewayConfig) -> Result<PushTarget> {
+        anyhow::bail!(
+            "pushing metrics to a Pushgateway requires the runtime-telemetry_pushgateway feature"
+        )
+    }
+
+    async fn send(&self, _: &str, _: Vec<u8>) -> Result<()> {
+        match *self {}
+    }
+
+    fn url(&self) -> &str {
+        match *self {}
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    #[cfg(feature = "runtime-telemetry_pushgateway")]
+    use tokio::io::AsyncReadExt;
+    #[cfg(feature = "runtime-telemetry_pushgateway")]
+    use tokio::io::AsyncWriteExt;
+
     use super::PrometheusConfig;
+    use super::PrometheusError;
+    use super::PrometheusOptions;
+    use super::PushGatewayConfig;
+    use crate::utils::config::HumanDuration;
 
     #[test]
     fn global_labels_added() {
@@ -74,7 +317,8 @@ mod tests {
             labels: [("test".into(), "value".into())].into_iter().collect(),
             ..Default::default()
         };
-        let reg = super::initialise(conf).expect("prometheus to registry initialise");
+        let reg =
+            super::initialise(conf, Default::default()).expect("prometheus to registry initialise");
         let counter = prometheus::Counter::new("test_metrics", "test metric")
             .expect("unable to create test metric");
         reg.register(Box::new(counter))
@@ -98,6 +342,44 @@ mod tests {
         assert_eq!(label.get_value(), "value");
     }
 
+    #[test]
+    fn options_labels_and_prefix() {
+        let conf = PrometheusConfig {
+            labels: [("cluster_id".into(), "from-conf".into())]
+                .into_iter()
+                .collect(),
+            process_metrics: false,
+            ..Default::default()
+        };
+        let options = PrometheusOptions {
+            labels: [
+                ("cluster_id".into(), "from-options".into()),
+                ("node_id".into(), "node-1".into()),
+            ]
+            .into_iter()
+            .collect(),
+            prefix: Some("agent".into()),
+        };
+        let reg = super::initialise(conf, options).expect("prometheus registry to initialise");
+        let counter = prometheus::Counter::new("test_metrics", "test metric")
+            .expect("unable to create test metric");
+        reg.register(Box::new(counter))
+            .expect("unable to register test metric");
+
+        let families = reg.gather();
+        assert_eq!(families[0].get_name(), "agent_test_metrics");
+        let mut labels: Vec<(&str, &str)> = families[0].get_metric()[0]
+            .get_label()
+            .iter()
+            .map(|label| (label.get_name(), label.get_value()))
+            .collect();
+        labels.sort();
+        assert_eq!(
+            labels,
+            vec![("cluster_id", "from-conf"), ("node_id", "node-1")],
+        );
+    }
+
     #[cfg(target_os = "linux")]
     #[test]
     fn skip_process_metrics() {
@@ -105,8 +387,134 @@ mod tests {
             process_metrics: false,
             ..Default::default()
         };
-        let reg = super::initialise(conf).expect("prometheus registry to initialise");
+        let reg =
+            super::initialise(conf, Default::default()).expect("prometheus registry to initialise");
         let metrics = reg.gather();
         assert_eq!(metrics.len(), 0);
     }
+
+    fn push_conf(url: String, job: &str) -> PushGatewayConfig {
+        PushGatewayConfig {
+            interval: PushGatewayConfig::default_interval(),
+            job: job.into(),
+            timeout: PushGatewayConfig::default_timeout(),
+            url,
+        }
+    }
+
+    #[test]
+    fn push_gateway_disabled() {
+        let logger = slog::Logger::root(slog::Discard, slog::o!());
+        let push = super::push_gateway(None, &Default::default(), &logger).unwrap();
+        assert!(push.is_none());
+    }
+
+    #[test]
+    fn push_gateway_invalid_job() {
+        let logger = slog::Logger::root(slog::Discard, slog::o!());
+        let conf = push_conf("http://localhost:9091".into(), "batch/job");
+        let error = super::push_gateway(Some(conf), &Default::default(), &logger)
+            .err()
+            .expect("invalid job name to be rejected");
+        assert!(matches!(
+            error.downcast_ref::<PrometheusError>(),
+            Some(PrometheusError::InvalidPushJob(job)) if job == "batch/job",
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "runtime-telemetry_pushgateway")]
+    fn push_gateway_encodes_job() {
+        let logger = slog::Logger::root(slog::Discard, slog::o!());
+        let conf = push_conf("http://localhost:9091/prefix/".into(), "batch job?");
+        let push = super::push_gateway(Some(conf), &Default::default(), &logger)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            push.target.url(),
+            "http://localhost:9091/prefix/metrics/job/batch%20job%3F",
+        );
+    }
+
+    #[rstest::rstest]
+    #[case(
+        HumanDuration::from_secs(0),
+        PushGatewayConfig::default_timeout(),
+        "interval"
+    )]
+    #[case(
+        PushGatewayConfig::default_interval(),
+        HumanDuration::from_secs(0),
+        "timeout"
+    )]
+    fn push_gateway_zero_duration(
+        #[case] interval: HumanDuration,
+        #[case] timeout: HumanDuration,
+        #[case] expected: &str,
+    ) {
+        let logger = slog::Logger::root(slog::Discard, slog::o!());
+        let conf = PushGatewayConfig {
+            interval,
+            timeout,
+            ..push_conf("http://localhost:9091".into(), "batch")
+        };
+        let error = super::push_gateway(Some(conf), &Default::default(), &logger)
+            .err()
+            .expect("zero duration to be rejected");
+        assert!(matches!(
+            error.downcast_ref::<PrometheusError>(),
+            Some(PrometheusError::InvalidPushDuration(name)) if *name == expected,
+        ));
+    }
+
+    #[test]
+    #[cfg(not(feature = "runtime-telemetry_pushgateway"))]
+    fn push_gateway_not_enabled() {
+        let logger = slog::Logger::root(slog::Discard, slog::o!());
+        let conf = push_conf("http://localhost:9091".into(), "batch");
+        let error = super::push_gateway(Some(conf), &Default::default(), &logger)
+            .err()
+            .expect("pushgateway to be rejected");
+        assert!(error.to_string().contains("runtime-telemetry_pushgateway"));
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "runtime-telemetry_pushgateway")]
+    async fn push_gateway_pushes_metrics() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0; 1024];
+            while !String::from_utf8_lossy(&request).contains("test_pushed") {
+                let read = stream.read(&mut buffer).await.unwrap();
+                if read == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buffer[..read]);
+            }
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        let registry = prometheus::Registry::new();
+        let counter = prometheus::Counter::new("test_pushed", "test metric").unwrap();
+        registry.register(Box::new(counter.clone())).unwrap();
+        counter.inc();
+
+        let logger = slog::Logger::root(slog::Discard, slog::o!());
+        let conf = push_conf(format!("http://{}/", address), "batch");
+        let push = super::push_gateway(Some(conf), &registry, &logger)
+            .unwrap()
+            .unwrap();
+        push.push().await.unwrap();
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("PUT /metrics/job/batch HTTP/1.1"));
+        assert!(request.contains("test_pushed 1"));
+    }
 }
@@ -9,9 +9,19 @@ use sentry::ClientInitGuard;
 use serde::Deserialize;
 use serde::Serialize;
 
+use super::LogLevel;
+
 /// Initialise the Sentry framework for the process.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct SentryConfig {
+    /// Minimum level of log events recorded as breadcrumbs, when breadcrumbs are captured.
+    #[serde(default = "SentryConfig::default_breadcrumbs_level")]
+    pub breadcrumbs_level: LogLevel,
+
+    /// Record log events as breadcrumbs attached to later Sentry events.
+    #[serde(default)]
+    pub capture_breadcrumbs: bool,
+
     /// Sentry DSN (Data Source Name) to send events to.
     #[serde(default)]
     pub dsn: Option<String>,
@@ -20,16 +30,36 @@ pub struct SentryConfig {
     #[serde(default = "SentryConfig::default_enabled")]
     pub enabled: bool,
 
+    /// Name of the environment (such as staging or production) to attach to events.
+    ///
+    /// If not set, Sentry uses the `SENTRY_ENVIRONMENT` environment variable or its own default.
+    #[serde(default)]
+    pub environment: Option<String>,
+
     /// The ratio of generated events that are submitted to Sentry (between 0.0 and 1.0).
     #[serde(default = "SentryConfig::default_sample_ratio")]
     pub sample_ratio: f32,
 
+    /// Name of the server to attach to events, instead of the detected hostname.
+    #[serde(default)]
+    pub server_name: Option<String>,
+
     /// Maximum delay in seconds to process shutdown to flush pending events to Sentry.
     #[serde(default = "SentryConfig::default_shutdown_timeout")]
     pub shutdown_timeout: u64,
+
+    /// Enable performance monitoring, submitting this ratio of transactions (between 0.0 and 1.0).
+    ///
+    /// Performance monitoring is disabled when this option is not set.
+    #[serde(default)]
+    pub traces_sample_rate: Option<f32>,
 }
 
 impl SentryConfig {
+    fn default_breadcrumbs_level() -> LogLevel {
+        LogLevel::Info
+    }
+
     fn default_enabled() -> bool {
         false
     }
@@ -46,10 +76,15 @@ impl SentryConfig {
 impl Default for SentryConfig {
     fn default() -> Self {
         SentryConfig {
+            breadcrumbs_level: Self::default_breadcrumbs_level(),
+            capture_breadcrumbs: false,
             dsn: None,
             enabled: Self::default_enabled(),
+            environment: None,
             sample_ratio: Self::default_sample_ratio(),
+            server_name: None,
             shutdown_timeout: Self::default_shutdown_timeout(),
+            traces_sample_rate: None,
         }
     }
 }
@@ -90,6 +125,89 @@ pub enum SentryError {
     /// Error returned when the configured sample ration is outside the valid range.
     #[error("the sampling ratio must be between 0 and 1")]
     InvalidSampleRatio,
+
+    /// Error returned when the configured traces sample rate is outside the valid range.
+    #[error("the traces sample rate must be between 0 and 1")]
+    InvalidTracesSampleRate,
+}
+
+/// [`slog::Drain`] recording log events as Sentry breadcrumbs before forwarding them.
+///
+/// Breadcrumbs are only recorded for events at or above the configured level.
+pub(super) struct BreadcrumbsDrain<D> {
+    drain: D,
+    level: slog::FilterLevel,
+}
+
+impl<D> BreadcrumbsDrain<D> {
+    /// Wrap a drain to record events at or above `level` as breadcrumbs.
+    pub(super) fn new(drain: D, level: LogLevel) -> BreadcrumbsDrain<D> {
+        let level = level.into();
+        BreadcrumbsDrain { drain, level }
+    }
+}
+
+impl<D> slog::Drain for BreadcrumbsDrain<D>
+where
+    D: slog::Drain<Ok = (), Err = slog::Never>,
+{
+    type Ok = ();
+    type Err = slog::Never;
+
+    fn log(
+        &self,
+        record: &slog::Record,
+        values: &slog::OwnedKVList,
+    ) -> std::result::Result<(), slog::Never> {
+        // Breadcrumbs are built lazily so nothing is done without an active Sentry client.
+        if self.level.accepts(record.level()) {
+            sentry::add_breadcrumb(|| breadcrumb(record, values));
+        }
+        self.drain.log(record, values)
+    }
+}
+
+/// Collect log event key/value pairs into breadcrumb data.
+struct BreadcrumbData(sentry::protocol::Map<String, sentry::protocol::Value>);
+
+impl slog::Serializer for BreadcrumbData {
+    fn emit_arguments(&mut self, key: slog::Key, value: &std::fmt::Arguments) -> slog::Result {
+        self.0.insert(key.to_string(), value.to_string().into());
+        Ok(())
+    }
+}
+
+/// Convert a log event into a Sentry breadcrumb.
+fn breadcrumb(record: &slog::Record, values: &slog::OwnedKVList) -> sentry::Breadcrumb {
+    use slog::KV;
+
+    // Serialisation into a map can't fail so errors are ignored.
+    let mut data = BreadcrumbData(Default::default());
+    let _ = values.serialize(record, &mut data);
+    let _ = record.kv().serialize(record, &mut data);
+    let level = match record.level() {
+        slog::Level::Critical => sentry::Level::Fatal,
+        slog::Level::Error => sentry::Level::Error,
+        slog::Level::Warning => sentry::Level::Warning,
+        slog::Level::Info => sentry::Level::Info,
+        slog::Level::Debug | slog::Level::Trace => sentry::Level::Debug,
+    };
+    sentry::Breadcrumb {
+        ty: "log".into(),
+        category: Some(record.module().into()),
+        level,
+        message: Some(record.msg().to_string()),
+        data: data.0,
+        ..Default::default()
+    }
+}
+
+/// Log level to record breadcrumbs at, if breadcrumbs should be captured at all.
+pub(super) fn breadcrumbs_level(conf: &SentryConfig) -> Option<LogLevel> {
+    if conf.enabled && conf.capture_breadcrumbs {
+        return Some(conf.breadcrumbs_level.clone());
+    }
+    None
 }
 
 /// Initialise the Sentry framework for the process.
@@ -97,7 +215,13 @@ pub fn initialise(conf: SentryConfig, options: SentryOptions) -> Result<Option<C
     if !conf.enabled {
         return Ok(None);
     }
+    let options = client_options(conf, options)?;
+    let guard = sentry::init(options);
+    Ok(Some(guard))
+}
 
+/// Validate the configuration and prepare the sentry client options.
+fn client_options(conf: SentryConfig, options: SentryOptions) -> Result<sentry::ClientOptions> {
     // Validated configuration.
     let dsn = conf
         .dsn
@@ -106,23 +230,30 @@ pub fn initialise(conf: SentryConfig, options: SentryOptions) -> Result<Option<C
     if conf.sample_ratio < 0.0 || conf.sample_ratio > 1.0 {
         anyhow::bail!(SentryError::InvalidSampleRatio);
     }
+    let traces_sample_rate = conf.traces_sample_rate.unwrap_or(0.0);
+    if !(0.0..=1.0).contains(&traces_sample_rate) {
+        anyhow::bail!(SentryError::InvalidTracesSampleRate);
+    }
 
     // Prepare the sentry client configuration.
+    // Unset options, such as the environment, are filled in by sentry::init.
     let mut in_app_include = options.in_app_include;
     in_app_include.push("replisdk");
     in_app_include.push("replisdk_experimental");
     let options = sentry::ClientOptions {
         dsn,
+        environment: conf.environment.map(Into::into),
         in_app_exclude: options.in_app_exclude,
         in_app_include,
         release: Some(options.release),
         sample_rate: conf.sample_ratio,
+        server_name: conf.server_name.map(Into::into),
         shutdown_timeout: std::time::Duration::from_secs(conf.shutdown_timeout),
+        traces_sample_rate,
         before_send: Some(std::sync::Arc::new(sentry_inject_trace_id)),
         ..Default::default()
     };
-    let guard = sentry::init(options);
-    Ok(Some(guard))
+    Ok(options)
 }
 
 /// Process every sentry event before it is sent to inject OpenTelemetry trace and span IDs.
@@ -148,10 +279,48 @@ fn sentry_inject_trace_id(mut event: Event) -> Option<Event> {
 
 #[cfg(test)]
 mod tests {
+    use super::BreadcrumbsDrain;
+    use super::LogLevel;
     use super::SentryConfig;
     use super::SentryError;
     use super::SentryOptions;
 
+    #[test]
+    fn breadcrumbs_level_opt_in() {
+        let conf = SentryConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        assert_eq!(super::breadcrumbs_level(&conf), None);
+
+        let conf = SentryConfig {
+            breadcrumbs_level: LogLevel::Warning,
+            capture_breadcrumbs: true,
+            enabled: true,
+            ..Default::default()
+        };
+        assert_eq!(super::breadcrumbs_level(&conf), Some(LogLevel::Warning));
+    }
+
+    #[test]
+    fn breadcrumbs_recorded_from_logs() {
+        let events = sentry::test::with_captured_events(|| {
+            let drain = BreadcrumbsDrain::new(slog::Discard, LogLevel::Info);
+            let logger = slog::Logger::root(drain, slog::o!("component" => "tests"));
+            slog::debug!(logger, "below threshold");
+            slog::info!(logger, "step completed"; "step" => 1);
+            sentry::capture_message("step failed", sentry::Level::Error);
+        });
+
+        let breadcrumbs = &events[0].breadcrumbs.values;
+        assert_eq!(breadcrumbs.len(), 1);
+        let breadcrumb = &breadcrumbs[0];
+        assert_eq!(breadcrumb.level, sentry::Level::Info);
+        assert_eq!(breadcrumb.message.as_deref(), Some("step completed"));
+        assert_eq!(breadcrumb.data["component"], "tests");
+        assert_eq!(breadcrumb.data["step"], "1");
+    }
+
     #[test]
     fn dsn_not_valid() {
         let conf = SentryConfig {
@@ -215,6 +384,77 @@ mod tests {
         }
     }
 
+    #[test]
+    fn traces_sample_rate_disabled_by_default() {
+        let conf = SentryConfig::default();
+        let opts = SentryOptions::for_release("replisdk-telemetry-tests@0.0.0");
+        let options = super::client_options(conf, opts).unwrap();
+        assert_eq!(options.traces_sample_rate, 0.0);
+    }
+
+    #[test]
+    fn traces_sample_rate_set() {
+        let conf = SentryConfig {
+            traces_sample_rate: Some(0.25),
+            ..Default::default()
+        };
+        let opts = SentryOptions::for_release("replisdk-telemetry-tests@0.0.0");
+        let options = super::client_options(conf, opts).unwrap();
+        assert_eq!(options.traces_sample_rate, 0.25);
+    }
+
+    #[test]
+    fn traces_sample_rate_invalid() {
+        let conf = SentryConfig {
+            enabled: true,
+            traces_sample_rate: Some(1.5),
+            ..Default::default()
+        };
+        let opts = SentryOptions::for_release("replisdk-telemetry-tests@0.0.0");
+        match super::initialise(conf, opts) {
+            Ok(_) => panic!("sentry should not have initialised"),
+            Err(error) if error.is::<SentryError>() => {
+                let error = error.downcast_ref::<SentryError>().unwrap();
+                assert!(
+                    matches!(error, SentryError::InvalidTracesSampleRate),
+                    "unexpected SentryError variant",
+                );
+            }
+            Err(error) => panic!("unexpected error: {:?}", error),
+        }
+    }
+
+    #[test]
+    fn environment_and_server_name() {
+        let conf = SentryConfig {
+            environment: Some("staging".into()),
+            server_name: Some("node-1".into()),
+            ..Default::default()
+        };
+        let opts = SentryOptions::for_release("replisdk-telemetry-tests@0.0.0");
+        let options = super::client_options(conf, opts).unwrap();
+        assert_eq!(options.environment.as_deref(), Some("staging"));
+        assert_eq!(options.server_name.as_deref(), Some("node-1"));
+    }
+
+    #[test]
+    fn environment_defaults_applied_by_sentry() {
+        let opts = SentryOptions::for_release("replisdk-telemetry-tests@0.0.0");
+        let options = super::client_options(SentryConfig::default(), opts).unwrap();
+        assert_eq!(options.environment, None);
+        let options = sentry::apply_defaults(options);
+        assert!(options.environment.is_some());
+
+        let conf = SentryConfig {
+            environment: Some("from-conf".into()),
+            ..Default::default()
+        };
+        let opts = SentryOptions::for_release("replisdk-telemetry-tests@0.0.0");
+        let options = super::client_options(conf, opts).unwrap();
+        let options = sentry::apply_defaults(options);
+        assert_eq!(options.environment.as_deref(), Some("from-conf"));
+    }
+
     #[test]
     fn sentry_not_configured() {
         let conf = SentryConfig::default();
@@ -0,0 +1,24 @@
+//! Fixtures shared by tests across SDK areas.
+use std::sync::Arc;
+use std::sync::Mutex;
+
+/// Collect messages of log events in a list tests can inspect.
+#[derive(Clone, Default)]
+pub struct CaptureDrain(Arc<Mutex<Vec<String>>>);
+
+impl CaptureDrain {
+    /// Messages of the log events captured so far.
+    pub fn messages(&self) -> Vec<String> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+impl slog::Drain for CaptureDrain {
+    type Ok = ();
+    type Err = slog::Never;
+
+    fn log(&self, record: &slog::Record, _: &slog::OwnedKVList) -> Result<(), slog::Never> {
+        self.0.lock().unwrap().push(record.msg().to_string());
+        Ok(())
+    }
+}
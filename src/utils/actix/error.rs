@@ -1,4 +1,5 @@
 //! An [`actix_web`] error type that works with [`anyhow::Error`].
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
 use actix_web::body::BoxBody;
@@ -10,9 +11,18 @@ use actix_web::HttpResponse;
 type CustomRenderFn =
     Arc<dyn Fn(StatusCode, &anyhow::Error) -> HttpResponse<BoxBody> + Send + Sync>;
 
+/// Short-hand type for custom status code mapping functions.
+type StatusMapFn = Arc<dyn Fn(StatusCode, &anyhow::Error) -> StatusCode + Send + Sync>;
+
 /// Error type to bridging [`anyhow::Error`] to [`actix_web`].
 #[derive(Debug, thiserror::Error)]
 pub struct Error {
+    /// Optional machine-readable code to include in JSON error responses.
+    code: Option<&'static str>,
+
+    /// Public context values to include in JSON error responses.
+    context: BTreeMap<String, String>,
+
     /// The underlying [`anyhow::Error`] error.
     #[source]
     source: anyhow::Error,
@@ -25,18 +35,68 @@ pub struct Error {
 }
 
 impl Error {
+    /// Bridge an error to create responses with the status code the error maps to.
+    ///
+    /// Refer to [`IntoStatusCode`] for details.
+    pub fn from_typed<E>(source: E) -> Self
+    where
+        E: IntoStatusCode + Into<anyhow::Error>,
+    {
+        let status = source.status_code();
+        Error::with_status(status, source)
+    }
+
+    /// Bridge an [`anyhow::Error`] using the status code of `E` if the error is an `E`.
+    ///
+    /// Errors of other types are converted with [`Error::from`], as the `?` operator would.
+    /// This allows typed errors returned as [`anyhow::Error`]s by lower layers to be mapped
+    /// to their status codes at the API boundary.
+    pub fn from_anyhow_typed<E>(source: anyhow::Error) -> Self
+    where
+        E: IntoStatusCode + std::error::Error + Send + Sync + 'static,
+    {
+        match source.downcast::<E>() {
+            Ok(source) => Error::from_typed(source),
+            Err(source) => Error::from(source),
+        }
+    }
+
     /// Bridge an [`anyhow::Error`] to create responses with a custom status code.
     pub fn with_status<E>(status: StatusCode, source: E) -> Self
     where
         E: Into<anyhow::Error>,
     {
         Self {
+            code: None,
+            context: Default::default(),
             source: source.into(),
             status,
             response_strategy: ResponseStrategy::Json,
         }
     }
 
+    /// Include a stable, machine-readable error code in JSON error responses.
+    ///
+    /// Unlike error messages, codes are not expected to change with wording
+    /// so clients can rely on them to tell errors apart.
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    /// Include the public values of a [`Context`] in JSON error responses.
+    ///
+    /// Only values explicitly marked as public are included.
+    /// Refer to [`ContextBuilder::public_log_value`] for details.
+    ///
+    /// [`Context`]: crate::context::Context
+    /// [`ContextBuilder::public_log_value`]: crate::context::ContextBuilder::public_log_value
+    #[cfg(feature = "context")]
+    pub fn with_context(mut self, context: &crate::context::Context) -> Self {
+        self.context = context.public_values().clone();
+        self
+    }
+
     /// Update the response rendering strategy for the error.
     pub fn use_strategy<S>(mut self, strategy: S) -> Self
     where
@@ -58,7 +118,7 @@ impl std::fmt::Display for Error {
 
 impl ResponseError for Error {
     fn status_code(&self) -> StatusCode {
-        self.status
+        self.response_strategy.status(self)
     }
 
     fn error_response(&self) -> HttpResponse<BoxBody> {
@@ -69,12 +129,16 @@ impl ResponseError for Error {
 impl From<anyhow::Error> for Error {
     fn from(source: anyhow::Error) -> Self {
         // Start with defaults in case there is no response data to propagate.
+        let mut code = None;
+        let mut context = BTreeMap::new();
         let mut status = StatusCode::INTERNAL_SERVER_ERROR;
         let mut response_strategy = ResponseStrategy::Json;
 
         // Look for the latest `Error` instance to propagate error response data.
         for nested in source.chain() {
             if let Some(nested) = nested.downcast_ref::<Error>() {
+                code = nested.code;
+                context = nested.context.clone();
                 status = nested.status;
                 response_strategy = nested.response_strategy.clone();
                 break;
@@ -83,6 +147,8 @@ impl From<anyhow::Error> for Error {
 
         // Wrap the error while propagating response data.
         Error {
+            code,
+            context,
             source,
             status,
             response_strategy,
@@ -90,6 +156,48 @@ impl From<anyhow::Error> for Error {
     }
 }
 
+/// Report values missing from request contexts with a `500 Internal Server Error` response.
+#[cfg(feature = "context")]
+impl From<crate::context::ContextMissing> for Error {
+    fn from(source: crate::context::ContextMissing) -> Self {
+        Error::from_typed(source)
+    }
+}
+
+/// Reject requests that fail validation with a `400 Bad Request` response.
+///
+/// JSON responses list each validation issue under `error_details`.
+#[cfg(feature = "utils-validate")]
+impl From<crate::utils::validate::ValidationErrors> for Error {
+    fn from(source: crate::utils::validate::ValidationErrors) -> Self {
+        Error::from_typed(source)
+    }
+}
+
+/// Map error types to the HTTP status code of their error responses.
+///
+/// Errors implementing this trait can be converted with [`Error::from_typed`],
+/// or [`Error::from_anyhow_typed`] once wrapped in an [`anyhow::Error`],
+/// instead of annotating every handler with [`Error::with_status`].
+pub trait IntoStatusCode {
+    /// The HTTP status code to respond with for this error.
+    fn status_code(&self) -> StatusCode;
+}
+
+#[cfg(feature = "context")]
+impl IntoStatusCode for crate::context::ContextMissing {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+#[cfg(feature = "utils-validate")]
+impl IntoStatusCode for crate::utils::validate::ValidationErrors {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
 /// Strategies to render [`Error`] HTTP responses.
 #[derive(Clone)]
 pub enum ResponseStrategy {
@@ -102,8 +210,34 @@ pub enum ResponseStrategy {
     /// Render a JSON object with the provided body.
     JsonWithBody(serde_json::Value),
 
+    /// Render a JSON object with error information and a status code chosen by a custom function.
+    ///
+    /// The function receives the [`Error`]'s status code and source error and returns
+    /// the status code to use for the response, which is also reported by
+    /// [`ResponseError::status_code`].
+    JsonWithStatus(StatusMapFn),
+
     /// Render a JSON object with error information, including a backtrace if available.
     JsonWithTrace,
+
+    /// Render the messages in the error chain as plain text, one per line.
+    ///
+    /// Useful for clients, such as scripts, that can't parse JSON responses.
+    PlainText,
+
+    /// Render an [RFC 7807](https://www.rfc-editor.org/rfc/rfc7807) problem details object.
+    ///
+    /// Responses use the `application/problem+json` content type and include:
+    ///
+    /// - `type`: always `about:blank`, as problems have no type specific documentation.
+    /// - `title`: the canonical reason of the response status code.
+    /// - `status`: the response status code.
+    /// - `detail`: the messages in the error chain, separated by `: `.
+    ///
+    /// The error code, public context values and validation issues are included
+    /// as the `code`, `context` and `errors` extension members respectively.
+    /// The `instance` member is omitted as errors do not know which request they respond to.
+    ProblemJson,
 }
 
 impl std::fmt::Debug for ResponseStrategy {
@@ -115,19 +249,47 @@ impl std::fmt::Debug for ResponseStrategy {
                 .finish(),
             Self::Json => write!(f, "Json"),
             Self::JsonWithBody(body) => f.debug_tuple("JsonWithBody").field(body).finish(),
+            Self::JsonWithStatus(_) => f
+                .debug_tuple("JsonWithStatus")
+                .field(&"<Fn(StatusCode, &anyhow::Error) -> StatusCode>")
+                .finish(),
             Self::JsonWithTrace => write!(f, "JsonWithTrace"),
+            Self::PlainText => write!(f, "PlainText"),
+            Self::ProblemJson => write!(f, "ProblemJson"),
         }
     }
 }
 
 impl ResponseStrategy {
+    /// Render JSON error responses with the status code returned by the `status` function.
+    ///
+    /// This allows APIs to map errors to status codes based on the error itself,
+    /// for example by downcasting the source error to a known type.
+    pub fn json_with_status<F>(status: F) -> ResponseStrategy
+    where
+        F: Fn(StatusCode, &anyhow::Error) -> StatusCode + Send + Sync + 'static,
+    {
+        ResponseStrategy::JsonWithStatus(Arc::new(status))
+    }
+
     /// Render an HTTP error response based on the [`Error`]'s strategy,
     fn render(&self, error: &Error) -> HttpResponse<BoxBody> {
         match self {
             Self::Custom(strategy) => self.render_custom(strategy, error),
             Self::Json => self.render_json(error, false),
             Self::JsonWithBody(body) => self.render_json_body(error, body),
+            Self::JsonWithStatus(_) => self.render_json(error, false),
             Self::JsonWithTrace => self.render_json(error, true),
+            Self::PlainText => self.render_text(error),
+            Self::ProblemJson => self.render_problem(error),
+        }
+    }
+
+    /// Status code of the HTTP error response for the [`Error`].
+    fn status(&self, error: &Error) -> StatusCode {
+        match self {
+            Self::JsonWithStatus(status) => status(error.status, &error.source),
+            _ => error.status,
         }
     }
 
@@ -137,11 +299,15 @@ impl ResponseStrategy {
 
     /// Render a JSON object with error information.
     ///
+    /// The error code, if one is set, is included under `error_code`.
+    /// Public context values attached to the error are included under `error_context`.
+    /// Validation issues found in the error chain are included under `error_details`.
+    ///
     /// In extended mode include:
     ///
     /// - A backtrace, if one is available,
     fn render_json(&self, error: &Error, extended: bool) -> HttpResponse<BoxBody> {
-        let status = error.status_code();
+        let status = self.status(error);
         let error_cause = error.source.root_cause().to_string();
         let error_msg = error.source.to_string();
         let error_trail: Vec<String> = error.source.chain().map(ToString::to_string).collect();
@@ -151,6 +317,21 @@ impl ResponseStrategy {
         if error_msg != error_cause {
             payload.insert("error_cause".into(), error_cause.into());
         }
+        if let Some(code) = error.code {
+            payload.insert("error_code".into(), code.into());
+        }
+        if !error.context.is_empty() {
+            let context: serde_json::Map<String, serde_json::Value> = error
+                .context
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone().into()))
+                .collect();
+            payload.insert("error_context".into(), context.into());
+        }
+        #[cfg(feature = "utils-validate")]
+        if let Some(details) = validation_details(&error.source) {
+            payload.insert("error_details".into(), details);
+        }
         payload.insert("error_msg".into(), error_msg.into());
         if error_trail.len() > 2 {
             payload.insert("error_trail".into(), error_trail.into());
@@ -174,6 +355,82 @@ impl ResponseStrategy {
         response.insert_header((actix_web::http::header::CONTENT_TYPE, "application/json"));
         response.json(body)
     }
+
+    /// Render an RFC 7807 problem details object.
+    fn render_problem(&self, error: &Error) -> HttpResponse<BoxBody> {
+        let status = self.status(error);
+        let detail = error_messages(&error.source);
+
+        let mut payload = serde_json::Map::new();
+        payload.insert("type".into(), "about:blank".into());
+        if let Some(title) = status.canonical_reason() {
+            payload.insert("title".into(), title.into());
+        }
+        payload.insert("status".into(), status.as_u16().into());
+        payload.insert("detail".into(), detail.join(": ").into());
+        if let Some(code) = error.code {
+            payload.insert("code".into(), code.into());
+        }
+        if !error.context.is_empty() {
+            let context: serde_json::Map<String, serde_json::Value> = error
+                .context
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone().into()))
+                .collect();
+            payload.insert("context".into(), context.into());
+        }
+        #[cfg(feature = "utils-validate")]
+        if let Some(details) = validation_details(&error.source) {
+            payload.insert("errors".into(), details);
+        }
+
+        let body = serde_json::Value::from(payload).to_string();
+        HttpResponse::build(status)
+            .insert_header((
+                actix_web::http::header::CONTENT_TYPE,
+                "application/problem+json",
+            ))
+            .body(body)
+    }
+
+    /// Render the messages in the error chain as plain text, one per line.
+    fn render_text(&self, error: &Error) -> HttpResponse<BoxBody> {
+        let status = self.status(error);
+        let body = error_messages(&error.source).join("\n");
+        HttpResponse::build(status)
+            .insert_header((
+                actix_web::http::header::CONTENT_TYPE,
+                "text/plain; charset=utf-8",
+            ))
+            .body(body)
+    }
+}
+
+/// Messages in the error chain, from the outermost error to the root cause.
+///
+/// [`Error`] wrappers in the chain are skipped as they repeat the message of their source.
+fn error_messages(error: &anyhow::Error) -> Vec<String> {
+    error
+        .chain()
+        .filter(|nested| nested.downcast_ref::<Error>().is_none())
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// Encode validation issues from the first [`ValidationErrors`] in the error chain.
+///
+/// [`ValidationErrors`]: crate::utils::validate::ValidationErrors
+#[cfg(feature = "utils-validate")]
+fn validation_details(error: &anyhow::Error) -> Option<serde_json::Value> {
+    let errors = error
+        .chain()
+        .find_map(|nested| nested.downcast_ref::<crate::utils::validate::ValidationErrors>())?;
+    let details = errors
+        .errors()
+        .iter()
+        .map(|error| serde_json::json!({"message": error.message, "path": error.path}))
+        .collect();
+    Some(serde_json::Value::Array(details))
 }
 
 impl From<serde_json::Value> for ResponseStrategy {
@@ -200,6 +457,7 @@ mod tests {
     use actix_web::ResponseError;
 
     use super::Error;
+    use super::ResponseStrategy;
 
     #[derive(Debug, thiserror::Error)]
     #[error("intermediate error to wrap other errors")]
@@ -274,6 +532,191 @@ mod tests {
         assert_eq!(body, "error from custom strategy: 500 - test error");
     }
 
+    #[actix_web::test]
+    async fn use_plain_text_strategy() {
+        let error = anyhow::anyhow!("test error").context("outer error");
+        let error = Error::with_status(StatusCode::SERVICE_UNAVAILABLE, error)
+            .use_strategy(ResponseStrategy::PlainText);
+        assert_eq!(error.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let response = error.error_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            response
+                .headers()
+                .get(actix_web::http::header::CONTENT_TYPE)
+                .unwrap(),
+            "text/plain; charset=utf-8"
+        );
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, "outer error\ntest error");
+    }
+
+    #[actix_web::test]
+    async fn use_json_with_status_strategy() {
+        let strategy = ResponseStrategy::json_with_status(|status, source| {
+            match source.downcast_ref::<MidErr>() {
+                Some(_) => StatusCode::IM_A_TEAPOT,
+                None => status,
+            }
+        });
+        let cause = Error::with_status(StatusCode::NOT_FOUND, anyhow::anyhow!("root error"));
+        let error = anyhow::anyhow!(MidErr::from(cause));
+        let error = Error::from(error).use_strategy(strategy.clone());
+        assert_eq!(error.status_code(), StatusCode::IM_A_TEAPOT);
+
+        let response = error.error_response();
+        assert_eq!(response.status(), StatusCode::IM_A_TEAPOT);
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error_msg"], "intermediate error to wrap other errors");
+
+        // Errors the function does not map keep their status code.
+        let error = anyhow::anyhow!("test error");
+        let error = Error::with_status(StatusCode::CONFLICT, error).use_strategy(strategy);
+        assert_eq!(error.status_code(), StatusCode::CONFLICT);
+        assert_eq!(error.error_response().status(), StatusCode::CONFLICT);
+    }
+
+    #[actix_web::test]
+    async fn use_problem_json_strategy() {
+        let cause = anyhow::anyhow!("root error");
+        let cause = Error::with_status(StatusCode::NOT_FOUND, cause);
+        let error = anyhow::anyhow!(cause).context("test error");
+        let error = Error::from(error).use_strategy(ResponseStrategy::ProblemJson);
+        assert_eq!(error.status_code(), StatusCode::NOT_FOUND);
+
+        let response = error.error_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let content_type = response
+            .headers()
+            .get(actix_web::http::header::CONTENT_TYPE)
+            .unwrap();
+        assert_eq!(content_type, "application/problem+json");
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "detail": "test error: root error",
+                "status": 404,
+                "title": "Not Found",
+                "type": "about:blank",
+            }),
+        );
+    }
+
+    #[cfg(feature = "context")]
+    #[actix_web::test]
+    async fn use_problem_json_strategy_with_context() {
+        let context = crate::context::Context::fixture()
+            .derive()
+            .public_log_value("request_id", "abc")
+            .build();
+        let error = anyhow::anyhow!("test error");
+        let error = Error::with_status(StatusCode::BAD_REQUEST, error)
+            .with_context(&context)
+            .use_strategy(ResponseStrategy::ProblemJson);
+
+        let body = actix_web::body::to_bytes(error.error_response().into_body())
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["context"], serde_json::json!({"request_id": "abc"}));
+        assert_eq!(body["detail"], "test error");
+        assert_eq!(body["title"], "Bad Request");
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("typed test error")]
+    struct TypedErr;
+
+    impl super::IntoStatusCode for TypedErr {
+        fn status_code(&self) -> StatusCode {
+            StatusCode::CONFLICT
+        }
+    }
+
+    #[actix_web::test]
+    async fn from_typed() {
+        let error = Error::from_typed(TypedErr);
+        assert_eq!(error.status_code(), StatusCode::CONFLICT);
+
+        let body = actix_web::body::to_bytes(error.error_response().into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, "{\"error\":true,\"error_msg\":\"typed test error\"}");
+    }
+
+    #[actix_web::test]
+    async fn from_anyhow_typed() {
+        let error = anyhow::Error::from(TypedErr);
+        let error = Error::from_anyhow_typed::<TypedErr>(error);
+        assert_eq!(error.status_code(), StatusCode::CONFLICT);
+
+        let error = anyhow::anyhow!("untyped error");
+        let error = Error::from_anyhow_typed::<TypedErr>(error);
+        assert_eq!(error.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[actix_web::test]
+    async fn from_typed_in_anyhow_chain() {
+        let error = Error::from_typed(TypedErr);
+        let error = anyhow::Error::from(error).context("wrapping context");
+        let error = Error::from(error);
+        assert_eq!(error.status_code(), StatusCode::CONFLICT);
+    }
+
+    #[actix_web::test]
+    async fn with_code() {
+        let error = anyhow::anyhow!("test error");
+        let error = Error::with_status(StatusCode::CONFLICT, error).with_code("TEST_CODE");
+
+        let body = actix_web::body::to_bytes(error.error_response().into_body())
+            .await
+            .unwrap();
+        assert_eq!(
+            body,
+            "{\"error\":true,\"error_code\":\"TEST_CODE\",\"error_msg\":\"test error\"}"
+        );
+    }
+
+    #[actix_web::test]
+    async fn with_code_propagates() {
+        let error = anyhow::anyhow!("test error");
+        let error = Error::with_status(StatusCode::CONFLICT, error).with_code("TEST_CODE");
+        let error = anyhow::Error::from(MidErr::from(error)).context("outer error");
+        let error = Error::from(error);
+        assert_eq!(error.status_code(), StatusCode::CONFLICT);
+
+        let body = actix_web::body::to_bytes(error.error_response().into_body())
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error_code"], "TEST_CODE");
+        assert_eq!(body["error_msg"], "outer error");
+    }
+
+    #[actix_web::test]
+    async fn with_code_problem_json() {
+        let error = anyhow::anyhow!("test error");
+        let error = Error::with_status(StatusCode::CONFLICT, error)
+            .with_code("TEST_CODE")
+            .use_strategy(ResponseStrategy::ProblemJson);
+
+        let body = actix_web::body::to_bytes(error.error_response().into_body())
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "TEST_CODE");
+    }
+
     #[actix_web::test]
     async fn with_status() {
         let error = anyhow::anyhow!("test error");
@@ -285,4 +728,88 @@ mod tests {
             .unwrap();
         assert_eq!(body, "{\"error\":true,\"error_msg\":\"test error\"}");
     }
+
+    #[cfg(feature = "utils-validate")]
+    #[actix_web::test]
+    async fn from_validation_errors() {
+        let mut errors = crate::utils::validate::ValidationErrors::new();
+        errors.add("id", "value must not be empty");
+        errors.add(
+            "nodes.default.desired_count",
+            "value must be greater than zero",
+        );
+        let error = anyhow::anyhow!(Error::from(errors)).context("invalid request");
+        let error = Error::from(error);
+        assert_eq!(error.status_code(), StatusCode::BAD_REQUEST);
+
+        let body = actix_web::body::to_bytes(error.error_response().into_body())
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body["error_details"],
+            serde_json::json!([
+                {"message": "value must not be empty", "path": "id"},
+                {"message": "value must be greater than zero", "path": "nodes.default.desired_count"},
+            ]),
+        );
+    }
+
+    #[cfg(feature = "context")]
+    #[actix_web::test]
+    async fn from_context_missing() {
+        let context = crate::context::Context::fixture();
+        let error = Error::from(context.try_require::<u64>().unwrap_err());
+        assert_eq!(error.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let body = actix_web::body::to_bytes(error.error_response().into_body())
+            .await
+            .unwrap();
+        assert_eq!(
+            body,
+            "{\"error\":true,\"error_msg\":\"context does not hold a value for the required type u64\"}",
+        );
+    }
+
+    #[cfg(feature = "context")]
+    #[actix_web::test]
+    async fn with_context_public_values() {
+        let context = crate::context::Context::fixture()
+            .derive()
+            .public_log_value("request_id", "abc")
+            .log_values(slog::o!("password" => "secret"))
+            .build();
+        let error = anyhow::anyhow!("test error");
+        let error = Error::with_status(StatusCode::BAD_REQUEST, error).with_context(&context);
+
+        let body = actix_web::body::to_bytes(error.error_response().into_body())
+            .await
+            .unwrap();
+        assert_eq!(
+            body,
+            "{\"error\":true,\"error_context\":{\"request_id\":\"abc\"},\"error_msg\":\"test error\"}"
+        );
+    }
+
+    #[cfg(feature = "context")]
+    #[actix_web::test]
+    async fn with_context_propagates() {
+        let context = crate::context::Context::fixture()
+            .derive()
+            .public_log_value("request_id", "abc")
+            .build();
+        let cause = anyhow::anyhow!("root error");
+        let cause = Error::with_status(StatusCode::NOT_FOUND, cause).with_context(&context);
+        let error = anyhow::anyhow!(cause).context("test error");
+        let error = Error::from(error);
+
+        let body = actix_web::body::to_bytes(error.error_response().into_body())
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body["error_context"],
+            serde_json::json!({"request_id": "abc"})
+        );
+    }
 }
@@ -1,24 +1,39 @@
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::future::ready;
 use std::future::Ready;
+use std::sync::Arc;
+use std::sync::Mutex;
 use std::time::Instant;
 
+use actix_web::body::BodySize;
+use actix_web::body::MessageBody;
 use actix_web::dev::forward_ready;
 use actix_web::dev::Service;
 use actix_web::dev::ServiceRequest;
 use actix_web::dev::ServiceResponse;
 use actix_web::dev::Transform;
+use actix_web::http::header::CONTENT_LENGTH;
 use actix_web::Error;
 use futures_util::future::LocalBoxFuture;
 use prometheus::core::Collector;
 use prometheus::CounterVec;
 use prometheus::HistogramOpts;
 use prometheus::HistogramVec;
+use prometheus::IntGauge;
+use prometheus::IntGaugeVec;
 use prometheus::Opts;
 use prometheus::Registry;
+use slog::Logger;
 
 const DEFAULT_METRIC_DURATIONS_DESC: &str = "Duration of handled requests";
 const DEFAULT_METRIC_ERRORS_DESC: &str = "Number of requests failed with unhandled errors";
+const DEFAULT_METRIC_IN_FLIGHT_DESC: &str = "Number of requests currently being handled";
+const DEFAULT_METRIC_REQUEST_SIZE_DESC: &str = "Size in bytes of request bodies";
+const DEFAULT_METRIC_RESPONSE_SIZE_DESC: &str = "Size in bytes of response bodies";
+
+/// Path label for requests that did not match any path pattern, when normalised.
+const UNMATCHED_PATH: &str = "<unmatched>";
 
 /// An [`actix_web`] middleware to collect request metrics.
 ///
@@ -26,10 +41,43 @@ const DEFAULT_METRIC_ERRORS_DESC: &str = "Number of requests failed with unhandl
 ///
 /// - Histogram of request durations, by method, path and response status.
 /// - Number of requests that failed with unhandled errors, by method and path.
+/// - Optionally, number of requests currently being handled, by path.
+/// - Optionally, histograms of request and response body sizes, by method and path.
+///   Bodies of unknown size, such as streamed responses, are not observed.
+///
+/// ## Excluded paths
+///
+/// Requests for paths excluded with [`MetricsCollectorBuilder::exclude_path`],
+/// such as the metrics endpoint itself or health probes, are not observed at all.
+/// Paths are matched against the request path pattern, or the path itself
+/// for requests that did not match any pattern.
+///
+/// ## Unmatched paths
+///
+/// Requests that do not match any path pattern, such as requests for unknown paths,
+/// are labelled with the request path by default.
+/// Because request paths are client controlled this can create an unbounded number of labels,
+/// so [`MetricsCollectorBuilder::normalise_unmatched`] can be used to label all such requests
+/// with the `<unmatched>` path instead.
+///
+/// ## Duplicate path patterns
+///
+/// Metrics are labelled by the matched path pattern, so distinct routes that resolve to
+/// the same pattern are aggregated together.
+/// In debug builds, and when a logger is provided with [`MetricsCollectorBuilder::diagnostics`],
+/// the middleware warns (once per pattern) when it observes the same pattern served by
+/// resources with different names.
+/// Resources without a name, or sharing the same name, can't be told apart.
 #[derive(Clone)]
 pub struct MetricsCollector {
+    diagnostics: Option<PatternDiagnostics>,
     durations: HistogramVec,
     errors: CounterVec,
+    excluded: Arc<HashSet<&'static str>>,
+    in_flight: Option<IntGaugeVec>,
+    normalise_unmatched: bool,
+    request_size: Option<HistogramVec>,
+    response_size: Option<HistogramVec>,
 }
 
 impl MetricsCollector {
@@ -43,7 +91,7 @@ impl<S, B> Transform<S, ServiceRequest> for MetricsCollector
 where
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
     S::Future: 'static,
-    B: 'static,
+    B: MessageBody + 'static,
 {
     type Response = ServiceResponse<B>;
     type Error = Error;
@@ -60,13 +108,36 @@ where
 
 /// Builds a [`MetricsCollector`].
 pub struct MetricsCollectorBuilder {
+    buckets: Option<Vec<f64>>,
+    diagnostics: Option<Logger>,
     durations: Option<HistogramVec>,
     errors: Option<CounterVec>,
+    excluded: HashSet<&'static str>,
+    in_flight: Option<IntGaugeVec>,
+    normalise_unmatched: bool,
     prefix: &'static str,
     registry: Option<Registry>,
+    request_size: Option<HistogramVec>,
+    response_size: Option<HistogramVec>,
 }
 
 impl MetricsCollectorBuilder {
+    /// Set the buckets, in seconds, of the durations histogram in case it is generated.
+    ///
+    /// Histograms provided with [`MetricsCollectorBuilder::durations`] keep their own buckets.
+    pub fn buckets(mut self, buckets: Vec<f64>) -> Self {
+        self.buckets = Some(buckets);
+        self
+    }
+
+    /// Log diagnostics about distinct routes that share the same path pattern.
+    ///
+    /// Diagnostics are only collected in debug builds and are ignored otherwise.
+    pub fn diagnostics(mut self, logger: Logger) -> Self {
+        self.diagnostics = Some(logger);
+        self
+    }
+
     /// Use the provided histogram to track request durations.
     pub fn durations(mut self, histogram: HistogramVec) -> Self {
         let desc = histogram.desc();
@@ -107,6 +178,21 @@ impl MetricsCollectorBuilder {
         self
     }
 
+    /// Skip metrics collection for requests to the given path pattern.
+    pub fn exclude_path(mut self, path: &'static str) -> Self {
+        self.excluded.insert(path);
+        self
+    }
+
+    /// Skip metrics collection for requests to any of the given path patterns.
+    pub fn exclude_paths<I>(mut self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = &'static str>,
+    {
+        self.excluded.extend(paths);
+        self
+    }
+
     /// Finalise a `MetricsCollector` build.
     ///
     /// # Panics
@@ -114,12 +200,20 @@ impl MetricsCollectorBuilder {
     /// If some metrics are not provided the builder will initialise default metrics.
     /// This method panics in case default metrics are initialised by no [`Registry`] is given.
     ///
-    /// This method also panics if registration of the default metrics fails.
+    /// The optional in-flight requests gauge and body size histograms are only
+    /// initialised by default when a [`Registry`] is given.
+    ///
+    /// This method also panics if registration of the default metrics fails
+    /// or if the buckets set with [`MetricsCollectorBuilder::buckets`] are invalid.
     pub fn finish(self) -> MetricsCollector {
         let durations = self.durations.unwrap_or_else(|| {
             let name = format!("{}_request_durations", self.prefix);
-            let opts = HistogramOpts::new(name, DEFAULT_METRIC_DURATIONS_DESC);
-            let vec = HistogramVec::new(opts, &["method", "path", "status"]).unwrap();
+            let mut opts = HistogramOpts::new(name, DEFAULT_METRIC_DURATIONS_DESC);
+            if let Some(buckets) = self.buckets {
+                opts = opts.buckets(buckets);
+            }
+            let vec = HistogramVec::new(opts, &["method", "path", "status"])
+                .expect("invalid buckets for the durations histogram");
             self.registry
                 .as_ref()
                 .expect("a registry must be provided for metrics to be auto-created")
@@ -138,7 +232,68 @@ impl MetricsCollectorBuilder {
                 .expect("could not register auto-created durations metric");
             vec
         });
-        MetricsCollector { durations, errors }
+        let in_flight = self.in_flight.or_else(|| {
+            let registry = self.registry.as_ref()?;
+            let name = format!("{}_requests_in_flight", self.prefix);
+            let opts = Opts::new(name, DEFAULT_METRIC_IN_FLIGHT_DESC);
+            let vec = IntGaugeVec::new(opts, &["path"]).unwrap();
+            registry
+                .register(Box::new(vec.clone()))
+                .expect("could not register auto-created in-flight metric");
+            Some(vec)
+        });
+        let request_size = self.request_size.or_else(|| {
+            let name = format!("{}_request_sizes", self.prefix);
+            let opts = HistogramOpts::new(name, DEFAULT_METRIC_REQUEST_SIZE_DESC);
+            let registry = self.registry.as_ref()?;
+            Some(sizes_histogram(registry, opts))
+        });
+        let response_size = self.response_size.or_else(|| {
+            let name = format!("{}_response_sizes", self.prefix);
+            let opts = HistogramOpts::new(name, DEFAULT_METRIC_RESPONSE_SIZE_DESC);
+            let registry = self.registry.as_ref()?;
+            Some(sizes_histogram(registry, opts))
+        });
+        let diagnostics = self
+            .diagnostics
+            .filter(|_| cfg!(debug_assertions))
+            .map(PatternDiagnostics::new);
+        MetricsCollector {
+            diagnostics,
+            durations,
+            errors,
+            excluded: Arc::new(self.excluded),
+            in_flight,
+            normalise_unmatched: self.normalise_unmatched,
+            request_size,
+            response_size,
+        }
+    }
+
+    /// Use the provided gauge to track the number of requests currently being handled.
+    pub fn in_flight(mut self, gauge: IntGaugeVec) -> Self {
+        let desc = gauge.desc();
+        let mut descriptions = desc.iter();
+        let in_flight = match descriptions.next() {
+            None => panic!("in-flight gauge has no metrics defined"),
+            Some(in_flight) => in_flight,
+        };
+        if in_flight.variable_labels != ["path"] {
+            panic!(
+                "invalid labels defined for the in-flight gauge: found {:?}",
+                in_flight.variable_labels
+            );
+        }
+        self.in_flight = Some(gauge);
+        self
+    }
+
+    /// Label requests that did not match any path pattern with a single `<unmatched>` path.
+    ///
+    /// When disabled, the default, unmatched requests are labelled with the raw request path.
+    pub fn normalise_unmatched(mut self, normalise: bool) -> Self {
+        self.normalise_unmatched = normalise;
+        self
     }
 
     /// Set the prefix for default metrics names in case they are generated.
@@ -152,15 +307,66 @@ impl MetricsCollectorBuilder {
         self.registry = Some(registry);
         self
     }
+
+    /// Use the provided histogram to track request body sizes.
+    pub fn request_size(mut self, histogram: HistogramVec) -> Self {
+        check_sizes_labels(&histogram, "request");
+        self.request_size = Some(histogram);
+        self
+    }
+
+    /// Use the provided histogram to track response body sizes.
+    pub fn response_size(mut self, histogram: HistogramVec) -> Self {
+        check_sizes_labels(&histogram, "response");
+        self.response_size = Some(histogram);
+        self
+    }
+}
+
+/// Ensure body size histograms have the expected labels.
+fn check_sizes_labels(histogram: &HistogramVec, kind: &str) {
+    let desc = histogram.desc();
+    let mut descriptions = desc.iter();
+    let sizes = match descriptions.next() {
+        None => panic!("{} sizes histogram has no metrics defined", kind),
+        Some(sizes) => sizes,
+    };
+    let mut labels = sizes.variable_labels.clone();
+    labels.sort();
+    if labels != ["method", "path"] {
+        panic!(
+            "invalid labels defined for the {} sizes histogram: found {:?}",
+            kind, labels
+        );
+    }
+}
+
+/// Create and register a default body sizes histogram.
+fn sizes_histogram(registry: &Registry, opts: HistogramOpts) -> HistogramVec {
+    // Buckets from 64 bytes to 16 MiB.
+    let buckets = prometheus::exponential_buckets(64.0, 4.0, 10).unwrap();
+    let opts = opts.buckets(buckets);
+    let vec = HistogramVec::new(opts, &["method", "path"]).unwrap();
+    registry
+        .register(Box::new(vec.clone()))
+        .expect("could not register auto-created body sizes metric");
+    vec
 }
 
 impl Default for MetricsCollectorBuilder {
     fn default() -> Self {
         MetricsCollectorBuilder {
+            buckets: None,
+            diagnostics: None,
             durations: None,
             errors: None,
+            excluded: Default::default(),
+            in_flight: None,
+            normalise_unmatched: false,
             prefix: "replisdk",
             registry: None,
+            request_size: None,
+            response_size: None,
         }
     }
 }
@@ -175,7 +381,7 @@ impl<S, B> Service<ServiceRequest> for MetricsCollectorMiddleware<S>
 where
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
     S::Future: 'static,
-    B: 'static,
+    B: MessageBody + 'static,
 {
     type Response = ServiceResponse<B>;
     type Error = Error;
@@ -186,18 +392,41 @@ where
     fn call(&self, request: ServiceRequest) -> Self::Future {
         let collector = self.collector.clone();
         let method = request.method().as_str().to_owned();
-        let path = request
-            .match_pattern()
-            .unwrap_or_else(|| request.path().to_owned());
+        let pattern = request.match_pattern();
+        let path = pattern.clone().unwrap_or_else(|| request.path().to_owned());
+        if collector.excluded.contains(path.as_str()) {
+            return Box::pin(self.service.call(request));
+        }
+        let path = match pattern {
+            None if collector.normalise_unmatched => UNMATCHED_PATH.to_owned(),
+            _ => path,
+        };
+        let request_size = request
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|length| length.to_str().ok())
+            .and_then(|length| length.parse::<u64>().ok());
+        if let (Some(histogram), Some(size)) = (&collector.request_size, request_size) {
+            let labels = HashMap::from([("method", method.as_str()), ("path", path.as_str())]);
+            histogram.with(&labels).observe(size as f64);
+        }
         let timer = Instant::now();
+        let in_flight = collector
+            .in_flight
+            .as_ref()
+            .map(|in_flight| InFlightGuard::new(in_flight.with_label_values(&[&path])));
 
         let next = self.service.call(request);
         Box::pin(async move {
             let response = next.await;
             let duration = timer.elapsed().as_secs_f64();
+            drop(in_flight);
 
             match &response {
                 Ok(response) => {
+                    if let Some(diagnostics) = &collector.diagnostics {
+                        diagnostics.observe(response.request());
+                    }
                     let status = response.response().status();
                     let labels = HashMap::from([
                         ("method", method.as_str()),
@@ -205,6 +434,17 @@ where
                         ("status", status.as_str()),
                     ]);
                     collector.durations.with(&labels).observe(duration);
+                    let response_size = match response.response().body().size() {
+                        BodySize::None => Some(0),
+                        BodySize::Sized(size) => Some(size),
+                        BodySize::Stream => None,
+                    };
+                    if let (Some(histogram), Some(size)) = (&collector.response_size, response_size)
+                    {
+                        let labels =
+                            HashMap::from([("method", method.as_str()), ("path", path.as_str())]);
+                        histogram.with(&labels).observe(size as f64);
+                    }
                 }
                 Err(_) => {
                     let labels =
@@ -217,17 +457,149 @@ where
     }
 }
 
+/// Track a request as in-flight until the guard is dropped.
+///
+/// Using a guard ensures requests are no longer tracked even if their futures are dropped.
+struct InFlightGuard(IntGauge);
+
+impl InFlightGuard {
+    fn new(gauge: IntGauge) -> InFlightGuard {
+        gauge.inc();
+        InFlightGuard(gauge)
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+/// Detect distinct routes resolving to the same path pattern.
+#[derive(Clone)]
+struct PatternDiagnostics {
+    logger: Logger,
+    state: Arc<Mutex<PatternDiagnosticsState>>,
+}
+
+/// Patterns observed by [`PatternDiagnostics`] so far.
+#[derive(Default)]
+struct PatternDiagnosticsState {
+    /// Name of the first resource definition observed serving each pattern.
+    ///
+    /// Actix does not expose matched resource definitions beyond their pattern and name
+    /// so unnamed resources, or resources with the same name, sharing a pattern
+    /// can't be told apart.
+    routes: HashMap<String, Option<String>>,
+
+    /// Patterns a warning was already emitted for.
+    warned: HashSet<String>,
+}
+
+impl PatternDiagnostics {
+    fn new(logger: Logger) -> PatternDiagnostics {
+        PatternDiagnostics {
+            logger,
+            state: Default::default(),
+        }
+    }
+
+    /// Record the route that handled a request and warn about conflicting patterns.
+    fn observe(&self, request: &actix_web::HttpRequest) {
+        let pattern = match request.match_pattern() {
+            None => return,
+            Some(pattern) => pattern,
+        };
+        let route = request.match_name().map(String::from);
+        let mut state = self
+            .state
+            .lock()
+            .expect("PatternDiagnostics state mutex poisoned");
+        let first = state
+            .routes
+            .entry(pattern.clone())
+            .or_insert_with(|| route.clone())
+            .clone();
+        if first == route || state.warned.contains(&pattern) {
+            return;
+        }
+        slog::warn!(
+            self.logger,
+            "Distinct routes share a path pattern and their request metrics are aggregated";
+            "pattern" => &pattern,
+            "first_route" => first.as_deref().unwrap_or("<unnamed>"),
+            "other_route" => route.as_deref().unwrap_or("<unnamed>"),
+        );
+        state.warned.insert(pattern);
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
     use actix_web::web::Bytes;
     use actix_web::App;
+    use prometheus::core::Collector;
     use prometheus::CounterVec;
     use prometheus::HistogramOpts;
     use prometheus::HistogramVec;
+    use prometheus::IntGaugeVec;
     use prometheus::Opts;
     use prometheus::Registry;
 
     use super::MetricsCollector;
+    use crate::testing::CaptureDrain;
+
+    #[test]
+    fn custom_buckets() {
+        let registry = Registry::new();
+        let middleware = MetricsCollector::build()
+            .buckets(vec![0.001, 0.01, 0.1])
+            .registry(registry.clone())
+            .finish();
+        middleware
+            .durations
+            .with_label_values(&["GET", "/", "200"])
+            .observe(0.005);
+
+        let families = registry.gather();
+        let durations = families
+            .iter()
+            .find(|family| family.get_name() == "replisdk_request_durations")
+            .unwrap();
+        let buckets: Vec<f64> = durations.get_metric()[0]
+            .get_histogram()
+            .get_bucket()
+            .iter()
+            .map(|bucket| bucket.get_upper_bound())
+            .collect();
+        assert_eq!(buckets, [0.001, 0.01, 0.1]);
+    }
+
+    #[test]
+    fn custom_buckets_ignored_for_provided_durations() {
+        let histogram = HistogramVec::new(
+            HistogramOpts::new("test", "test").buckets(vec![1.0, 2.0]),
+            &["method", "path", "status"],
+        )
+        .unwrap();
+        let middleware = MetricsCollector::build()
+            .buckets(vec![0.001, 0.01, 0.1])
+            .durations(histogram)
+            .registry(Registry::new())
+            .finish();
+        middleware
+            .durations
+            .with_label_values(&["GET", "/", "200"])
+            .observe(0.005);
+        let families = middleware.durations.collect();
+        let buckets = families[0].get_metric()[0]
+            .get_histogram()
+            .get_bucket()
+            .len();
+        assert_eq!(buckets, 2);
+    }
 
     #[actix_web::test]
     async fn collect_metrics() {
@@ -249,6 +621,190 @@ mod tests {
         assert_eq!(duration.get_sample_count(), 1);
     }
 
+    #[actix_web::test]
+    async fn in_flight_requests_tracked() {
+        let registry = Registry::new();
+        let middleware = MetricsCollector::build().registry(registry).finish();
+        let in_flight = middleware.in_flight.clone().unwrap();
+        let gauge = in_flight.clone();
+        let app = App::new().wrap(middleware).route(
+            "/{name}",
+            actix_web::web::get().to(move || {
+                let current = gauge.with_label_values(&["/{name}"]).get();
+                async move { current.to_string() }
+            }),
+        );
+
+        // The request is counted while it is handled and no longer once it completes.
+        let app = actix_web::test::init_service(app).await;
+        let request = actix_web::test::TestRequest::get().uri("/ada").to_request();
+        let result = actix_web::test::call_and_read_body(&app, request).await;
+        assert_eq!(result, Bytes::from_static(b"1"));
+        assert_eq!(in_flight.with_label_values(&["/{name}"]).get(), 0);
+    }
+
+    #[actix_web::test]
+    async fn body_sizes_observed() {
+        let registry = Registry::new();
+        let middleware = MetricsCollector::build().registry(registry).finish();
+        let app = App::new()
+            .wrap(middleware.clone())
+            .route("/", actix_web::web::post().to(|| async { "Test Response" }))
+            .route(
+                "/stream",
+                actix_web::web::get().to(|| async {
+                    let chunks = futures_util::stream::once(async {
+                        Ok::<_, actix_web::Error>(Bytes::from_static(b"chunk"))
+                    });
+                    actix_web::HttpResponse::Ok().streaming(chunks)
+                }),
+            );
+
+        let app = actix_web::test::init_service(app).await;
+        let request = actix_web::test::TestRequest::post()
+            .uri("/")
+            .set_payload("request body")
+            .to_request();
+        actix_web::test::call_and_read_body(&app, request).await;
+        let request = actix_web::test::TestRequest::get()
+            .uri("/stream")
+            .to_request();
+        actix_web::test::call_and_read_body(&app, request).await;
+
+        // Known sizes are observed.
+        let request_size = middleware.request_size.as_ref().unwrap();
+        let request_size = request_size.with_label_values(&["POST", "/"]);
+        assert_eq!(request_size.get_sample_count(), 1);
+        assert_eq!(request_size.get_sample_sum(), 12.0);
+        let response_size = middleware.response_size.as_ref().unwrap();
+        let response_size = response_size.with_label_values(&["POST", "/"]);
+        assert_eq!(response_size.get_sample_count(), 1);
+        assert_eq!(response_size.get_sample_sum(), 13.0);
+
+        // Unknown sizes are skipped.
+        let request_size = middleware.request_size.as_ref().unwrap();
+        let request_size = request_size.with_label_values(&["GET", "/stream"]);
+        assert_eq!(request_size.get_sample_count(), 0);
+        let response_size = middleware.response_size.as_ref().unwrap();
+        let response_size = response_size.with_label_values(&["GET", "/stream"]);
+        assert_eq!(response_size.get_sample_count(), 0);
+    }
+
+    #[actix_web::test]
+    async fn body_sizes_labels_by_name() {
+        let durations = HistogramVec::new(
+            HistogramOpts::new("test", "test"),
+            &["method", "path", "status"],
+        )
+        .unwrap();
+        let errors = CounterVec::new(Opts::new("test", "test"), &["method", "path"]).unwrap();
+        let sizes = HistogramVec::new(HistogramOpts::new("test", "test"), &["path", "method"]);
+        let sizes = sizes.unwrap();
+        let middleware = MetricsCollector::build()
+            .durations(durations)
+            .errors(errors)
+            .request_size(sizes.clone())
+            .finish();
+        let app = App::new()
+            .wrap(middleware)
+            .route("/", actix_web::web::post().to(|| async { "Test Response" }));
+
+        let app = actix_web::test::init_service(app).await;
+        let request = actix_web::test::TestRequest::post()
+            .uri("/")
+            .set_payload("request body")
+            .to_request();
+        actix_web::test::call_and_read_body(&app, request).await;
+
+        let labels = HashMap::from([("method", "POST"), ("path", "/")]);
+        assert_eq!(sizes.with(&labels).get_sample_count(), 1);
+    }
+
+    #[test]
+    fn in_flight_requires_registry() {
+        let durations = HistogramVec::new(
+            HistogramOpts::new("test", "test"),
+            &["method", "path", "status"],
+        )
+        .unwrap();
+        let errors = CounterVec::new(Opts::new("test", "test"), &["method", "path"]).unwrap();
+        let middleware = MetricsCollector::build()
+            .durations(durations)
+            .errors(errors)
+            .finish();
+        assert!(middleware.in_flight.is_none());
+        assert!(middleware.request_size.is_none());
+        assert!(middleware.response_size.is_none());
+    }
+
+    #[actix_web::test]
+    async fn excluded_paths_skipped() {
+        let registry = Registry::new();
+        let middleware = MetricsCollector::build()
+            .exclude_path("/metrics")
+            .exclude_paths(["/healthz", "/missing"])
+            .registry(registry)
+            .finish();
+        let app = App::new()
+            .wrap(middleware.clone())
+            .route("/", actix_web::web::get().to(|| async { "Test Response" }))
+            .route("/healthz", actix_web::web::get().to(|| async { "OK" }))
+            .route("/metrics", actix_web::web::get().to(|| async { "Metrics" }));
+
+        let app = actix_web::test::init_service(app).await;
+        for uri in ["/", "/healthz", "/metrics", "/missing"] {
+            let request = actix_web::test::TestRequest::get().uri(uri).to_request();
+            actix_web::test::call_and_read_body(&app, request).await;
+        }
+
+        let duration = middleware.durations.with_label_values(&["GET", "/", "200"]);
+        assert_eq!(duration.get_sample_count(), 1);
+        for (path, status) in [
+            ("/healthz", "200"),
+            ("/metrics", "200"),
+            ("/missing", "404"),
+        ] {
+            let duration = middleware
+                .durations
+                .with_label_values(&["GET", path, status]);
+            assert_eq!(duration.get_sample_count(), 0);
+        }
+    }
+
+    #[rstest::rstest]
+    #[case(false, ["/missing/1", "/missing/2"])]
+    #[case(true, ["<unmatched>", "<unmatched>"])]
+    #[actix_web::test]
+    async fn unmatched_paths(#[case] normalise: bool, #[case] expected: [&str; 2]) {
+        let registry = Registry::new();
+        let middleware = MetricsCollector::build()
+            .normalise_unmatched(normalise)
+            .registry(registry)
+            .finish();
+        let app = App::new()
+            .wrap(middleware.clone())
+            .route("/", actix_web::web::get().to(|| async { "Test Response" }));
+
+        let app = actix_web::test::init_service(app).await;
+        for uri in ["/", "/missing/1", "/missing/2"] {
+            let request = actix_web::test::TestRequest::get().uri(uri).to_request();
+            actix_web::test::call_and_read_body(&app, request).await;
+        }
+
+        let duration = middleware.durations.with_label_values(&["GET", "/", "200"]);
+        assert_eq!(duration.get_sample_count(), 1);
+        let mut counts = HashMap::new();
+        for path in expected {
+            *counts.entry(path).or_insert(0) += 1;
+        }
+        for (path, count) in counts {
+            let duration = middleware
+                .durations
+                .with_label_values(&["GET", path, "404"]);
+            assert_eq!(duration.get_sample_count(), count);
+        }
+    }
+
     #[actix_web::test]
     async fn paths_use_placeholders() {
         // Create App with middleware.
@@ -275,6 +831,115 @@ mod tests {
         assert_eq!(duration.get_sample_count(), 2);
     }
 
+    #[cfg(debug_assertions)]
+    #[actix_web::test]
+    async fn duplicate_patterns_warn_once() {
+        // Create App with middleware and routes sharing the same pattern.
+        let drain = CaptureDrain::default();
+        let logger = slog::Logger::root(drain.clone(), slog::o!());
+        let registry = Registry::new();
+        let middleware = MetricsCollector::build()
+            .diagnostics(logger)
+            .registry(registry)
+            .finish();
+        let app = App::new()
+            .wrap(middleware)
+            .service(
+                actix_web::web::resource("/test")
+                    .name("admin")
+                    .guard(actix_web::guard::Header("x-admin", "true"))
+                    .route(actix_web::web::get().to(|| async { "Admin Response" })),
+            )
+            .service(
+                actix_web::web::resource("/test")
+                    .name("public")
+                    .route(actix_web::web::get().to(|| async { "Public Response" })),
+            );
+
+        // Send requests to both routes, more than once.
+        let app = actix_web::test::init_service(app).await;
+        for _ in 0..2 {
+            let request = actix_web::test::TestRequest::get()
+                .uri("/test")
+                .insert_header(("x-admin", "true"))
+                .to_request();
+            let result = actix_web::test::call_and_read_body(&app, request).await;
+            assert_eq!(result, Bytes::from_static(b"Admin Response"));
+            let request = actix_web::test::TestRequest::get()
+                .uri("/test")
+                .to_request();
+            let result = actix_web::test::call_and_read_body(&app, request).await;
+            assert_eq!(result, Bytes::from_static(b"Public Response"));
+        }
+
+        // Inspect captured logs for the warning.
+        let logs = drain.messages();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(
+            logs[0],
+            "Distinct routes share a path pattern and their request metrics are aggregated"
+        );
+    }
+
+    #[actix_web::test]
+    async fn duplicate_patterns_with_same_name_not_detected() {
+        let drain = CaptureDrain::default();
+        let logger = slog::Logger::root(drain.clone(), slog::o!());
+        let registry = Registry::new();
+        let middleware = MetricsCollector::build()
+            .diagnostics(logger)
+            .registry(registry)
+            .finish();
+        let app = App::new()
+            .wrap(middleware)
+            .service(
+                actix_web::web::resource("/test")
+                    .name("test")
+                    .guard(actix_web::guard::Header("x-admin", "true"))
+                    .route(actix_web::web::get().to(|| async { "Admin Response" })),
+            )
+            .service(
+                actix_web::web::resource("/test")
+                    .name("test")
+                    .route(actix_web::web::get().to(|| async { "Public Response" })),
+            );
+
+        let app = actix_web::test::init_service(app).await;
+        let request = actix_web::test::TestRequest::get()
+            .uri("/test")
+            .insert_header(("x-admin", "true"))
+            .to_request();
+        actix_web::test::call_and_read_body(&app, request).await;
+        let request = actix_web::test::TestRequest::get()
+            .uri("/test")
+            .to_request();
+        let result = actix_web::test::call_and_read_body(&app, request).await;
+        assert_eq!(result, Bytes::from_static(b"Public Response"));
+        assert!(drain.messages().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn same_route_does_not_warn() {
+        let drain = CaptureDrain::default();
+        let logger = slog::Logger::root(drain.clone(), slog::o!());
+        let registry = Registry::new();
+        let middleware = MetricsCollector::build()
+            .diagnostics(logger)
+            .registry(registry)
+            .finish();
+        let app = App::new().wrap(middleware).route(
+            "/{name}",
+            actix_web::web::get().to(|| async { "Test Response" }),
+        );
+
+        let app = actix_web::test::init_service(app).await;
+        for uri in ["/ada", "/grace"] {
+            let request = actix_web::test::TestRequest::get().uri(uri).to_request();
+            actix_web::test::call_and_read_body(&app, request).await;
+        }
+        assert!(drain.messages().is_empty());
+    }
+
     #[test]
     #[should_panic(
         expected = "invalid labels defined for the durations histogram: found [\"only\", \"two\"]"
@@ -301,4 +966,45 @@ mod tests {
             .registry(registry)
             .finish();
     }
+
+    #[test]
+    #[should_panic(expected = "invalid labels defined for the in-flight gauge: found [\"method\"]")]
+    fn metrics_labels_checked_for_in_flight() {
+        let registry = Registry::new();
+        let gauge = IntGaugeVec::new(Opts::new("test", "test"), &["method"]).unwrap();
+        MetricsCollector::build()
+            .in_flight(gauge)
+            .registry(registry)
+            .finish();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "invalid labels defined for the request sizes histogram: found [\"path\"]"
+    )]
+    fn metrics_labels_checked_for_request_size() {
+        let registry = Registry::new();
+        let histogram = HistogramVec::new(HistogramOpts::new("test", "test"), &["path"]).unwrap();
+        MetricsCollector::build()
+            .request_size(histogram)
+            .registry(registry)
+            .finish();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "invalid labels defined for the response sizes histogram: found [\"method\", \"path\", \"status\"]"
+    )]
+    fn metrics_labels_checked_for_response_size() {
+        let registry = Registry::new();
+        let histogram = HistogramVec::new(
+            HistogramOpts::new("test", "test"),
+            &["method", "path", "status"],
+        )
+        .unwrap();
+        MetricsCollector::build()
+            .response_size(histogram)
+            .registry(registry)
+            .finish();
+    }
 }
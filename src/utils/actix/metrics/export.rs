@@ -1,16 +1,35 @@
 use std::future::Ready;
+use std::io::Write;
 
+use actix_web::http::header::HeaderName;
+use actix_web::http::header::ACCEPT;
+use actix_web::http::header::ACCEPT_ENCODING;
+use actix_web::http::header::CONTENT_ENCODING;
 use actix_web::http::header::CONTENT_TYPE;
+use actix_web::http::header::VARY;
 use actix_web::web;
 use actix_web::Handler;
+use actix_web::HttpRequest;
 use actix_web::HttpResponse;
 use actix_web::Resource;
 use actix_web::ResponseError;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use prometheus::Encoder;
 use prometheus::Registry;
 use prometheus::TextEncoder;
 
+use super::openmetrics::OpenMetricsEncoder;
+use super::openmetrics::OPENMETRICS_MEDIA_TYPE;
+
 /// ActixWeb [`Handler`] to export metrics from a [`Registry`].
+///
+/// Metrics are encoded in the [OpenMetrics](https://openmetrics.io/) text format
+/// when clients accept it, and in the Prometheus text format otherwise.
+///
+/// Metrics are gzip compressed for clients that accept gzip encoded responses.
+/// Compressed responses set the `Content-Encoding` header so they are not compressed
+/// again by the [`Compress`](actix_web::middleware::Compress) middleware.
 #[derive(Clone, Debug)]
 pub struct MetricsExporter {
     registry: Registry,
@@ -31,29 +50,81 @@ impl MetricsExporter {
     }
 }
 
-impl Handler<()> for MetricsExporter {
-    type Output = HttpResponse;
-    type Future = Ready<Self::Output>;
-
-    fn call(&self, _: ()) -> Self::Future {
+impl MetricsExporter {
+    /// Encode the metrics in the registry into a response with the given encoder.
+    fn encode<E: Encoder>(&self, encoder: E, gzip: bool) -> HttpResponse {
         let metrics = self.registry.gather();
-        let encoder = TextEncoder::new();
         let mut buffer = Vec::new();
-        let response = match encoder.encode(&metrics, &mut buffer) {
-            Ok(()) => HttpResponse::Ok()
-                .append_header((CONTENT_TYPE, encoder.format_type()))
-                .body(buffer),
+        let encoded = encoder
+            .encode(&metrics, &mut buffer)
+            .map_err(anyhow::Error::from);
+        let body = encoded.and_then(|_| match gzip {
+            false => Ok(buffer),
+            true => compress(&buffer),
+        });
+        match body {
+            Ok(buffer) => {
+                let mut response = HttpResponse::Ok();
+                response.append_header((CONTENT_TYPE, encoder.format_type()));
+                response.append_header((VARY, "accept-encoding"));
+                if gzip {
+                    response.append_header((CONTENT_ENCODING, "gzip"));
+                }
+                response.body(buffer)
+            }
             Err(error) => {
-                let error = anyhow::anyhow!(error);
                 let error = error.context(anyhow::anyhow!("unable to encode metrics"));
                 let error = crate::utils::actix::error::Error::from(error);
                 error.error_response()
             }
+        }
+    }
+}
+
+impl Handler<(HttpRequest,)> for MetricsExporter {
+    type Output = HttpResponse;
+    type Future = Ready<Self::Output>;
+
+    fn call(&self, (request,): (HttpRequest,)) -> Self::Future {
+        let gzip = accepts(&request, ACCEPT_ENCODING, "gzip");
+        let response = if accepts(&request, ACCEPT, OPENMETRICS_MEDIA_TYPE) {
+            self.encode(OpenMetricsEncoder::new(), gzip)
+        } else {
+            self.encode(TextEncoder::new(), gzip)
         };
         std::future::ready(response)
     }
 }
 
+/// Check if the client accepts a value (such as a media type) in the given `Accept*` header.
+fn accepts(request: &HttpRequest, header: HeaderName, value: &str) -> bool {
+    request
+        .headers()
+        .get_all(header)
+        .filter_map(|accept| accept.to_str().ok())
+        .flat_map(|accept| accept.split(','))
+        .any(|entry| {
+            let mut params = entry.split(';').map(str::trim);
+            let accepted = params.next().unwrap_or_default();
+            let refused = params.any(|param| {
+                param
+                    .strip_prefix("q=")
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .map(|q| q <= 0.0)
+                    .unwrap_or(false)
+            });
+            accepted.eq_ignore_ascii_case(value) && !refused
+        })
+}
+
+/// Compress encoded metrics with gzip.
+fn compress(buffer: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(buffer)?;
+    let compressed = encoder.finish()?;
+    Ok(compressed)
+}
+
 #[cfg(test)]
 mod tests {
     use actix_web::test::TestRequest;
@@ -61,6 +132,8 @@ mod tests {
     use actix_web::web::Bytes;
     use actix_web::App;
     use prometheus::Counter;
+    use prometheus::HistogramOpts;
+    use prometheus::HistogramVec;
     use prometheus::Registry;
 
     use super::MetricsExporter;
@@ -106,4 +179,129 @@ mod tests {
         let result = actix_web::test::call_and_read_body(&app, request).await;
         assert_eq!(result, Bytes::from_static(EXPECTED_METRICS));
     }
+
+    #[rstest::rstest]
+    #[case("application/openmetrics-text; version=1.0.0,text/plain;version=0.0.4;q=0.5")]
+    #[case("text/plain;q=0.5, Application/OpenMetrics-Text")]
+    #[actix_web::test]
+    async fn metrics_exporter_openmetrics(#[case] accept: &str) {
+        let registry = make_registry();
+        let resource = MetricsExporter::simple(registry);
+        let app = App::new().service(resource);
+        let app = actix_web::test::init_service(app).await;
+        let request = make_request()
+            .insert_header((actix_web::http::header::ACCEPT, accept))
+            .to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        let content_type = response
+            .headers()
+            .get(actix_web::http::header::CONTENT_TYPE)
+            .unwrap();
+        assert_eq!(
+            content_type,
+            "application/openmetrics-text; version=1.0.0; charset=utf-8"
+        );
+        let result = actix_web::test::read_body(response).await;
+        assert_eq!(
+            result,
+            Bytes::from_static(
+                b"# TYPE metric counter\n# HELP metric test metric to encode\nmetric_total 0\n# EOF\n"
+            )
+        );
+    }
+
+    #[rstest::rstest]
+    #[case("text/plain")]
+    #[case("application/openmetrics-text;q=0")]
+    #[actix_web::test]
+    async fn metrics_exporter_text_fallback(#[case] accept: &str) {
+        let registry = make_registry();
+        let resource = MetricsExporter::simple(registry);
+        let app = App::new().service(resource);
+        let app = actix_web::test::init_service(app).await;
+        let request = make_request()
+            .insert_header((actix_web::http::header::ACCEPT, accept))
+            .to_request();
+        let result = actix_web::test::call_and_read_body(&app, request).await;
+        assert_eq!(result, Bytes::from_static(EXPECTED_METRICS));
+    }
+
+    #[actix_web::test]
+    async fn metrics_exporter_openmetrics_histogram() {
+        let registry = Registry::new();
+        let opts = HistogramOpts::new("latency", "test histogram").buckets(vec![0.5, 1.0]);
+        let histogram = HistogramVec::new(opts, &["path"]).unwrap();
+        registry.register(Box::new(histogram.clone())).unwrap();
+        histogram.with_label_values(&["/\"quoted\""]).observe(0.7);
+
+        let app = App::new().service(MetricsExporter::simple(registry));
+        let app = actix_web::test::init_service(app).await;
+        let request = make_request()
+            .insert_header((
+                actix_web::http::header::ACCEPT,
+                "application/openmetrics-text",
+            ))
+            .to_request();
+        let result = actix_web::test::call_and_read_body(&app, request).await;
+        let expected = concat!(
+            "# TYPE latency histogram\n",
+            "# HELP latency test histogram\n",
+            "latency_bucket{path=\"/\\\"quoted\\\"\",le=\"0.5\"} 0\n",
+            "latency_bucket{path=\"/\\\"quoted\\\"\",le=\"1\"} 1\n",
+            "latency_bucket{path=\"/\\\"quoted\\\"\",le=\"+Inf\"} 1\n",
+            "latency_sum{path=\"/\\\"quoted\\\"\"} 0.7\n",
+            "latency_count{path=\"/\\\"quoted\\\"\"} 1\n",
+            "# EOF\n",
+        );
+        assert_eq!(result, Bytes::from_static(expected.as_bytes()));
+    }
+
+    #[rstest::rstest]
+    #[case("gzip, deflate", true)]
+    #[case("br;q=1.0, gzip;q=0.8", true)]
+    #[case("gzip;q=0", false)]
+    #[case("identity", false)]
+    #[actix_web::test]
+    async fn metrics_exporter_gzip(#[case] accept: &str, #[case] gzip: bool) {
+        let registry = make_registry();
+        let app = App::new().service(MetricsExporter::simple(registry));
+        let app = actix_web::test::init_service(app).await;
+        let request = make_request()
+            .insert_header((actix_web::http::header::ACCEPT_ENCODING, accept))
+            .to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        let encoding = response
+            .headers()
+            .get(actix_web::http::header::CONTENT_ENCODING)
+            .cloned();
+        let result = actix_web::test::read_body(response).await;
+        if !gzip {
+            assert!(encoding.is_none());
+            assert_eq!(result, Bytes::from_static(EXPECTED_METRICS));
+            return;
+        }
+
+        assert_eq!(encoding.unwrap(), "gzip");
+        let mut decoder = flate2::read::GzDecoder::new(result.as_ref());
+        let mut decoded = Vec::new();
+        std::io::Read::read_to_end(&mut decoder, &mut decoded).unwrap();
+        assert_eq!(decoded, EXPECTED_METRICS);
+    }
+
+    #[actix_web::test]
+    async fn metrics_exporter_gzip_not_compressed_again() {
+        let registry = make_registry();
+        let app = App::new()
+            .wrap(actix_web::middleware::Compress::default())
+            .service(MetricsExporter::simple(registry));
+        let app = actix_web::test::init_service(app).await;
+        let request = make_request()
+            .insert_header((actix_web::http::header::ACCEPT_ENCODING, "gzip"))
+            .to_request();
+        let result = actix_web::test::call_and_read_body(&app, request).await;
+        let mut decoder = flate2::read::GzDecoder::new(result.as_ref());
+        let mut decoded = Vec::new();
+        std::io::Read::read_to_end(&mut decoder, &mut decoded).unwrap();
+        assert_eq!(decoded, EXPECTED_METRICS);
+    }
 }
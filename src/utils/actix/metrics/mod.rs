@@ -8,10 +8,12 @@
 //! ## Metrics Exporter
 //!
 //! The [`MetricsExporter`] is an [`actix_web::Handler`] object that responds to all requests
-//! with the current state of metrics in a [`Registry`](prometheus::Registry),
-//! encoded with the [`TextEncoder`](prometheus::TextEncoder).
+//! with the current state of metrics in a [`Registry`](prometheus::Registry).
+//! Metrics are encoded in the OpenMetrics text format for clients that accept it
+//! and with the [`TextEncoder`](prometheus::TextEncoder) otherwise.
 mod collect;
 mod export;
+mod openmetrics;
 
 pub use self::collect::MetricsCollector;
 pub use self::collect::MetricsCollectorBuilder;
@@ -0,0 +1,191 @@
+//! Encode metrics in the [OpenMetrics](https://openmetrics.io/) text format.
+use std::io::Write;
+
+use prometheus::proto::LabelPair;
+use prometheus::proto::Metric;
+use prometheus::proto::MetricFamily;
+use prometheus::proto::MetricType;
+use prometheus::Encoder;
+use prometheus::Result;
+
+/// Media type of the OpenMetrics text format.
+pub const OPENMETRICS_MEDIA_TYPE: &str = "application/openmetrics-text";
+
+/// Content type of responses encoded in the OpenMetrics text format.
+const OPENMETRICS_FORMAT: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// An [`Encoder`] that converts [`MetricFamily`] protos into the OpenMetrics text format.
+#[derive(Debug, Default)]
+pub struct OpenMetricsEncoder;
+
+impl OpenMetricsEncoder {
+    /// Create a new OpenMetrics encoder.
+    pub fn new() -> OpenMetricsEncoder {
+        OpenMetricsEncoder
+    }
+}
+
+impl Encoder for OpenMetricsEncoder {
+    fn encode<W: Write>(&self, families: &[MetricFamily], writer: &mut W) -> Result<()> {
+        for family in families {
+            if family.get_metric().is_empty() {
+                continue;
+            }
+
+            // OpenMetrics counter families are named without the `_total` suffix of samples.
+            let metric_type = family.get_field_type();
+            let name = match metric_type {
+                MetricType::COUNTER => family
+                    .get_name()
+                    .strip_suffix("_total")
+                    .unwrap_or_else(|| family.get_name()),
+                _ => family.get_name(),
+            };
+            let type_name = match metric_type {
+                MetricType::COUNTER => "counter",
+                MetricType::GAUGE => "gauge",
+                MetricType::HISTOGRAM => "histogram",
+                MetricType::SUMMARY => "summary",
+                MetricType::UNTYPED => "unknown",
+            };
+            writeln!(writer, "# TYPE {} {}", name, type_name)?;
+            if !family.get_help().is_empty() {
+                writeln!(writer, "# HELP {} {}", name, escape(family.get_help()))?;
+            }
+
+            for metric in family.get_metric() {
+                match metric_type {
+                    MetricType::COUNTER => {
+                        let value = metric.get_counter().get_value();
+                        write_sample(writer, name, "_total", metric, None, value)?;
+                    }
+                    MetricType::GAUGE => {
+                        let value = metric.get_gauge().get_value();
+                        write_sample(writer, name, "", metric, None, value)?;
+                    }
+                    MetricType::HISTOGRAM => {
+                        let histogram = metric.get_histogram();
+                        let mut inf_seen = false;
+                        for bucket in histogram.get_bucket() {
+                            let bound = bucket.get_upper_bound();
+                            inf_seen |= bound.is_infinite() && bound.is_sign_positive();
+                            let count = bucket.get_cumulative_count() as f64;
+                            let le = format_value(bound);
+                            write_sample(
+                                writer,
+                                name,
+                                "_bucket",
+                                metric,
+                                Some(("le", &le)),
+                                count,
+                            )?;
+                        }
+                        let count = histogram.get_sample_count() as f64;
+                        if !inf_seen {
+                            write_sample(
+                                writer,
+                                name,
+                                "_bucket",
+                                metric,
+                                Some(("le", "+Inf")),
+                                count,
+                            )?;
+                        }
+                        let sum = histogram.get_sample_sum();
+                        write_sample(writer, name, "_sum", metric, None, sum)?;
+                        write_sample(writer, name, "_count", metric, None, count)?;
+                    }
+                    MetricType::SUMMARY => {
+                        let summary = metric.get_summary();
+                        for quantile in summary.get_quantile() {
+                            let label = format_value(quantile.get_quantile());
+                            let label = Some(("quantile", label.as_str()));
+                            write_sample(writer, name, "", metric, label, quantile.get_value())?;
+                        }
+                        let sum = summary.get_sample_sum();
+                        let count = summary.get_sample_count() as f64;
+                        write_sample(writer, name, "_sum", metric, None, sum)?;
+                        write_sample(writer, name, "_count", metric, None, count)?;
+                    }
+                    MetricType::UNTYPED => {
+                        let value = metric.get_untyped().get_value();
+                        write_sample(writer, name, "", metric, None, value)?;
+                    }
+                }
+            }
+        }
+        writer.write_all(b"# EOF\n")?;
+        Ok(())
+    }
+
+    fn format_type(&self) -> &str {
+        OPENMETRICS_FORMAT
+    }
+}
+
+/// Escape backslashes, double quotes and new lines in help text and label values.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('"', "\\\"")
+}
+
+/// Format a sample value or bound following OpenMetrics conventions.
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        return "NaN".into();
+    }
+    if value.is_infinite() {
+        return if value.is_sign_positive() {
+            "+Inf".into()
+        } else {
+            "-Inf".into()
+        };
+    }
+    value.to_string()
+}
+
+/// Write the labels of a sample, including an optional additional label.
+fn write_labels<W: Write>(
+    writer: &mut W,
+    labels: &[LabelPair],
+    additional: Option<(&str, &str)>,
+) -> Result<()> {
+    if labels.is_empty() && additional.is_none() {
+        return Ok(());
+    }
+    let pairs = labels
+        .iter()
+        .map(|pair| (pair.get_name(), pair.get_value()))
+        .chain(additional);
+    let mut separator = "{";
+    for (name, value) in pairs {
+        write!(writer, "{}{}=\"{}\"", separator, name, escape(value))?;
+        separator = ",";
+    }
+    writer.write_all(b"}")?;
+    Ok(())
+}
+
+/// Write a single sample line.
+fn write_sample<W: Write>(
+    writer: &mut W,
+    name: &str,
+    suffix: &str,
+    metric: &Metric,
+    additional: Option<(&str, &str)>,
+    value: f64,
+) -> Result<()> {
+    write!(writer, "{}{}", name, suffix)?;
+    write_labels(writer, metric.get_label(), additional)?;
+    write!(writer, " {}", format_value(value))?;
+
+    // OpenMetrics timestamps are in seconds rather than milliseconds.
+    let timestamp = metric.get_timestamp_ms();
+    if timestamp != 0 {
+        write!(writer, " {}", timestamp as f64 / 1000.0)?;
+    }
+    writer.write_all(b"\n")?;
+    Ok(())
+}
@@ -0,0 +1,504 @@
+//! Human friendly types for configuration options, such as durations and sizes.
+//!
+//! These types deserialize from strings such as `"30s"` or `"256KiB"` as well as from
+//! plain integers so configuration options can switch to them without breaking
+//! existing configuration files.
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::de::Error;
+use serde::de::Unexpected;
+use serde::de::Visitor;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+
+/// Units supported by [`HumanDuration`] with their length in milliseconds.
+const DURATION_UNITS: [(&str, u64); 4] = [
+    ("h", 60 * 60 * 1000),
+    ("m", 60 * 1000),
+    ("s", 1000),
+    ("ms", 1),
+];
+
+/// Units supported by [`ByteSize`], in order of preference when formatting.
+const SIZE_UNITS: [(&str, u64); 9] = [
+    ("TiB", 1 << 40),
+    ("GiB", 1 << 30),
+    ("MiB", 1 << 20),
+    ("KiB", 1 << 10),
+    ("TB", 1_000_000_000_000),
+    ("GB", 1_000_000_000),
+    ("MB", 1_000_000),
+    ("KB", 1_000),
+    ("B", 1),
+];
+
+/// A [`Duration`] that can be expressed in configuration files in a human friendly format.
+///
+/// Durations can be given as:
+///
+/// - A plain integer number of seconds (for example `30`).
+/// - A string with a number followed by a unit: `ms`, `s`, `m` or `h` (for example `"2m"`).
+/// - A string with several of the above, from largest to smallest (for example `"1h30m"`).
+///   Each unit can only appear once and every amount must have a unit.
+///
+/// Durations are always serialized as strings in seconds (for example `"120s"`),
+/// unless they include fractions of a second in which case they are serialized in milliseconds.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct HumanDuration(Duration);
+
+impl HumanDuration {
+    /// Create a [`HumanDuration`] from the given number of seconds.
+    pub const fn from_secs(secs: u64) -> HumanDuration {
+        HumanDuration(Duration::from_secs(secs))
+    }
+
+    /// Create a [`HumanDuration`] from the given number of milliseconds.
+    pub const fn from_millis(millis: u64) -> HumanDuration {
+        HumanDuration(Duration::from_millis(millis))
+    }
+
+    /// Access the [`Duration`] value.
+    pub fn duration(&self) -> Duration {
+        self.0
+    }
+
+    /// Access the duration as a whole number of seconds.
+    ///
+    /// Intended for options applied with a precision of seconds, which should reject
+    /// durations with fractions of a second instead of silently truncating them.
+    pub fn whole_secs(&self) -> Result<u64, HumanDurationSecondsError> {
+        if self.0.subsec_nanos() != 0 {
+            return Err(HumanDurationSecondsError(self.to_string()));
+        }
+        Ok(self.0.as_secs())
+    }
+}
+
+impl Display for HumanDuration {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.0.subsec_nanos() == 0 {
+            write!(f, "{}s", self.0.as_secs())
+        } else {
+            write!(f, "{}ms", self.0.as_millis())
+        }
+    }
+}
+
+impl From<Duration> for HumanDuration {
+    fn from(value: Duration) -> Self {
+        HumanDuration(value)
+    }
+}
+
+impl From<HumanDuration> for Duration {
+    fn from(value: HumanDuration) -> Self {
+        value.0
+    }
+}
+
+impl FromStr for HumanDuration {
+    type Err = HumanDurationError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let error = || HumanDurationError(value.trim().to_string());
+        let mut millis: u64 = 0;
+        let mut smallest = None;
+        for (amount, unit) in split_amounts(value).ok_or_else(error)? {
+            let unit = if unit.is_empty() { "s" } else { unit };
+            let (index, (_, length)) = DURATION_UNITS
+                .iter()
+                .enumerate()
+                .find(|(_, (name, _))| *name == unit)
+                .ok_or_else(error)?;
+
+            // Units must go from largest to smallest, without repeating any of them.
+            if matches!(smallest, Some(smallest) if index <= smallest) {
+                return Err(error());
+            }
+            smallest = Some(index);
+            millis = amount
+                .checked_mul(*length)
+                .and_then(|amount| millis.checked_add(amount))
+                .ok_or_else(error)?;
+        }
+        Ok(HumanDuration::from_millis(millis))
+    }
+}
+
+impl<'de> Deserialize<'de> for HumanDuration {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(HumanVisitor {
+            expecting: "a number of seconds or a duration such as \"30s\" or \"2m\"",
+            from_int: HumanDuration::from_secs,
+        })
+    }
+}
+
+impl Serialize for HumanDuration {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+/// The value is not a valid duration.
+#[derive(Debug, thiserror::Error)]
+#[error("invalid duration '{0}', expected a number optionally followed by ms, s, m or h")]
+pub struct HumanDurationError(String);
+
+/// The duration includes fractions of a second where a whole number of seconds is required.
+#[derive(Debug, thiserror::Error)]
+#[error("invalid duration '{0}', expected a whole number of seconds")]
+pub struct HumanDurationSecondsError(String);
+
+/// A size in bytes that can be expressed in configuration files in a human friendly format.
+///
+/// Sizes can be given as:
+///
+/// - A plain integer number of bytes (for example `4096`).
+/// - A string with a number followed by a unit (for example `"256KiB"`).
+///   Supported units are `B`, the decimal units `KB`, `MB`, `GB` and `TB`,
+///   and the binary units `KiB`, `MiB`, `GiB` and `TiB`.
+///
+/// Sizes are serialized as strings using the largest unit that represents them exactly,
+/// preferring binary units over decimal ones (for example `"256KiB"` or `"10MB"`).
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ByteSize(u64);
+
+impl ByteSize {
+    /// Create a [`ByteSize`] from the given number of bytes.
+    pub const fn from_bytes(bytes: u64) -> ByteSize {
+        ByteSize(bytes)
+    }
+
+    /// Access the size in bytes.
+    pub fn bytes(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Display for ByteSize {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (unit, length) = SIZE_UNITS
+            .iter()
+            .find(|(_, length)| self.0 >= *length && self.0 % length == 0)
+            .copied()
+            .unwrap_or(("B", 1));
+        write!(f, "{}{}", self.0 / length, unit)
+    }
+}
+
+impl From<u64> for ByteSize {
+    fn from(value: u64) -> Self {
+        ByteSize(value)
+    }
+}
+
+impl From<ByteSize> for u64 {
+    fn from(value: ByteSize) -> Self {
+        value.0
+    }
+}
+
+impl FromStr for ByteSize {
+    type Err = ByteSizeError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let error = || ByteSizeError(value.trim().to_string());
+        let amounts = split_amounts(value).ok_or_else(error)?;
+        let (amount, unit) = match amounts.as_slice() {
+            [amount] => *amount,
+            _ => return Err(error()),
+        };
+        let unit = if unit.is_empty() { "B" } else { unit };
+        let (_, length) = SIZE_UNITS
+            .iter()
+            .find(|(name, _)| *name == unit)
+            .ok_or_else(error)?;
+        let bytes = amount.checked_mul(*length).ok_or_else(error)?;
+        Ok(ByteSize(bytes))
+    }
+}
+
+impl<'de> Deserialize<'de> for ByteSize {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(HumanVisitor {
+            expecting: "a number of bytes or a size such as \"256KiB\" or \"10MB\"",
+            from_int: ByteSize::from_bytes,
+        })
+    }
+}
+
+impl Serialize for ByteSize {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+/// The value is not a valid size.
+#[derive(Debug, thiserror::Error)]
+#[error("invalid size '{0}', expected a number optionally followed by B, KB, MB, GB, TB, KiB, MiB, GiB or TiB")]
+pub struct ByteSizeError(String);
+
+/// Process plain integers or human friendly strings from serde.
+struct HumanVisitor<T> {
+    expecting: &'static str,
+    from_int: fn(u64) -> T,
+}
+
+impl<'de, T> Visitor<'de> for HumanVisitor<T>
+where
+    T: FromStr,
+{
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        formatter.write_str(self.expecting)
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        match u64::try_from(v) {
+            Ok(value) => Ok((self.from_int)(value)),
+            Err(_) => Err(Error::invalid_value(Unexpected::Signed(v), &self)),
+        }
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        Ok((self.from_int)(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        v.parse()
+            .map_err(|_| Error::invalid_value(Unexpected::Str(v), &self))
+    }
+}
+
+/// Split a value into amounts, each followed by an optional unit.
+///
+/// Units can only be omitted when the value is a single amount.
+/// Returns `None` if the value is empty, an amount is missing
+/// or a unit is missing from a value with several amounts.
+fn split_amounts(value: &str) -> Option<Vec<(u64, &str)>> {
+    let mut rest = value.trim();
+    if rest.is_empty() {
+        return None;
+    }
+
+    let mut amounts = Vec::new();
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let amount = rest[..digits].parse().ok()?;
+        rest = rest[digits..].trim_start();
+
+        let letters = rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        amounts.push((amount, &rest[..letters]));
+        rest = rest[letters..].trim_start();
+    }
+    if amounts.len() > 1 && amounts.iter().any(|(_, unit)| unit.is_empty()) {
+        return None;
+    }
+    Some(amounts)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_test::assert_de_tokens;
+    use serde_test::assert_de_tokens_error;
+    use serde_test::assert_tokens;
+    use serde_test::Token;
+
+    use super::ByteSize;
+    use super::HumanDuration;
+
+    #[rstest::rstest]
+    #[case("30", Duration::from_secs(30))]
+    #[case("30s", Duration::from_secs(30))]
+    #[case("2m", Duration::from_secs(120))]
+    #[case("1h", Duration::from_secs(3600))]
+    #[case("250ms", Duration::from_millis(250))]
+    #[case(" 5 s ", Duration::from_secs(5))]
+    fn parse_durations(#[case] value: &str, #[case] expected: Duration) {
+        let duration: HumanDuration = value.parse().unwrap();
+        assert_eq!(duration.duration(), expected);
+    }
+
+    #[rstest::rstest]
+    #[case("")]
+    #[case("s")]
+    #[case("-5s")]
+    #[case("2d")]
+    #[case("1.5m")]
+    fn parse_invalid_durations(#[case] value: &str) {
+        let duration = value.parse::<HumanDuration>();
+        assert!(duration.is_err());
+    }
+
+    #[test]
+    fn deserialize_durations() {
+        let expected = HumanDuration::from_secs(90);
+        assert_de_tokens(&expected, &[Token::U64(90)]);
+        assert_de_tokens(&expected, &[Token::I64(90)]);
+        assert_de_tokens(&expected, &[Token::Str("90s")]);
+        assert_de_tokens_error::<HumanDuration>(
+            &[Token::Str("soon")],
+            "invalid value: string \"soon\", expected a number of seconds or a duration such as \"30s\" or \"2m\"",
+        );
+    }
+
+    #[test]
+    fn serialize_durations() {
+        assert_tokens(&HumanDuration::from_secs(120), &[Token::Str("120s")]);
+        let duration = HumanDuration::from(Duration::from_millis(1500));
+        assert_tokens(&duration, &[Token::Str("1500ms")]);
+    }
+
+    #[rstest::rstest]
+    #[case("1h30m", Duration::from_secs(90 * 60))]
+    #[case("1m 30s", Duration::from_secs(90))]
+    #[case("2s500ms", Duration::from_millis(2500))]
+    fn parse_compound_durations(#[case] value: &str, #[case] expected: Duration) {
+        let duration: HumanDuration = value.parse().unwrap();
+        assert_eq!(duration.duration(), expected);
+    }
+
+    #[rstest::rstest]
+    #[case("10 20")]
+    #[case("1m30")]
+    #[case("30m1h")]
+    #[case("1m1m")]
+    #[case("1s 1h")]
+    fn parse_invalid_compound_durations(#[case] value: &str) {
+        let duration = value.parse::<HumanDuration>();
+        assert!(duration.is_err());
+    }
+
+    #[rstest::rstest]
+    #[case("2m", Ok(120))]
+    #[case("0s", Ok(0))]
+    #[case("500ms", Err(()))]
+    #[case("1s500ms", Err(()))]
+    #[case("2000ms", Ok(2))]
+    fn whole_seconds(#[case] value: &str, #[case] expected: Result<u64, ()>) {
+        let duration: HumanDuration = value.parse().unwrap();
+        let secs = duration.whole_secs().map_err(|_| ());
+        assert_eq!(secs, expected);
+    }
+
+    #[rstest::rstest]
+    #[case(HumanDuration::from_secs(0), "0s")]
+    #[case(HumanDuration::from_secs(90 * 60), "5400s")]
+    #[case(HumanDuration::from_millis(2500), "2500ms")]
+    fn display_durations_round_trip(#[case] duration: HumanDuration, #[case] expected: &str) {
+        assert_eq!(duration.to_string(), expected);
+        assert_eq!(expected.parse::<HumanDuration>().unwrap(), duration);
+    }
+
+    #[rstest::rstest]
+    #[case("4096", 4096)]
+    #[case("512B", 512)]
+    #[case("256KiB", 256 * 1024)]
+    #[case(" 256 KiB ", 256 * 1024)]
+    #[case("10MB", 10_000_000)]
+    #[case("2GiB", 2 << 30)]
+    #[case("1TB", 1_000_000_000_000)]
+    fn parse_sizes(#[case] value: &str, #[case] expected: u64) {
+        let size: ByteSize = value.parse().unwrap();
+        assert_eq!(size.bytes(), expected);
+    }
+
+    #[rstest::rstest]
+    #[case("")]
+    #[case("KiB")]
+    #[case("-1KiB")]
+    #[case("1.5MiB")]
+    #[case("10kib")]
+    #[case("1MiB512KiB")]
+    #[case("20000000TiB")]
+    fn parse_invalid_sizes(#[case] value: &str) {
+        let size = value.parse::<ByteSize>();
+        assert!(size.is_err());
+    }
+
+    #[rstest::rstest]
+    #[case(0, "0B")]
+    #[case(1000, "1KB")]
+    #[case(1024, "1KiB")]
+    #[case(1500, "1500B")]
+    #[case(3 << 20, "3MiB")]
+    #[case(5_000_000_000, "5GB")]
+    fn display_sizes_round_trip(#[case] bytes: u64, #[case] expected: &str) {
+        let size = ByteSize::from_bytes(bytes);
+        assert_eq!(size.to_string(), expected);
+        assert_eq!(expected.parse::<ByteSize>().unwrap(), size);
+    }
+
+    #[test]
+    fn deserialize_sizes() {
+        let expected = ByteSize::from_bytes(256 * 1024);
+        assert_de_tokens(&expected, &[Token::U64(262144)]);
+        assert_de_tokens(&expected, &[Token::I64(262144)]);
+        assert_de_tokens(&expected, &[Token::Str("256KiB")]);
+        assert_de_tokens_error::<ByteSize>(
+            &[Token::Str("large")],
+            "invalid value: string \"large\", expected a number of bytes or a size such as \"256KiB\" or \"10MB\"",
+        );
+        assert_de_tokens_error::<ByteSize>(
+            &[Token::I64(-1)],
+            "invalid value: integer `-1`, expected a number of bytes or a size such as \"256KiB\" or \"10MB\"",
+        );
+    }
+
+    #[test]
+    fn serialize_sizes() {
+        assert_tokens(&ByteSize::from_bytes(256 * 1024), &[Token::Str("256KiB")]);
+        assert_tokens(&ByteSize::from_bytes(1500), &[Token::Str("1500B")]);
+    }
+
+    #[test]
+    fn yaml_round_trip() {
+        #[derive(Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+        struct Conf {
+            interval: HumanDuration,
+            size: ByteSize,
+        }
+        let conf: Conf = serde_yaml::from_str("interval: 1h30m\nsize: 64MiB\n").unwrap();
+        assert_eq!(conf.interval, HumanDuration::from_secs(90 * 60));
+        assert_eq!(conf.size, ByteSize::from_bytes(64 << 20));
+
+        let encoded = serde_yaml::to_string(&conf).unwrap();
+        assert_eq!(encoded, "interval: 5400s\nsize: 64MiB\n");
+        let decoded: Conf = serde_yaml::from_str(&encoded).unwrap();
+        assert_eq!(decoded, conf);
+    }
+}
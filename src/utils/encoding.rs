@@ -8,9 +8,52 @@ use time::OffsetDateTime;
 /// Convert nanoseconds from/to u32 to the fractal portion of f64.
 const NANO_SEC_UNIT: f64 = 1_000_000_000.0;
 
+/// Binary formats to encode structured data with.
+///
+/// Binary encoded data starts with a tag byte identifying the format used
+/// so the format can be changed without breaking decoding of existing data.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum BinaryFormat {
+    /// Encode structured data with [MessagePack](https://msgpack.org/).
+    MessagePack,
+}
+
+impl BinaryFormat {
+    /// Identify the [`BinaryFormat`] from the tag byte of encoded data.
+    fn from_tag(tag: u8) -> Option<BinaryFormat> {
+        match tag {
+            0x01 => Some(BinaryFormat::MessagePack),
+            _ => None,
+        }
+    }
+
+    /// Tag byte identifying data encoded in this [`BinaryFormat`].
+    fn tag(&self) -> u8 {
+        match self {
+            BinaryFormat::MessagePack => 0x01,
+        }
+    }
+}
+
 /// Errors when encoding or decoding data.
 #[derive(Debug, thiserror::Error)]
 pub enum EncodeError {
+    /// Unable to encode structured data in a binary format.
+    #[error("unable to encode structured data in a binary format")]
+    AsBinary,
+
+    /// Binary encoded data is missing its format tag.
+    #[error("binary encoded data is missing its format tag")]
+    BinaryFormatMissing,
+
+    /// Binary encoded data is tagged with an unknown format.
+    #[error("binary encoded data is tagged with unknown format {0:#04x}")]
+    BinaryFormatUnknown(u8),
+
+    /// Unable to decode structured data from a binary format.
+    #[error("unable to decode structured data from a binary format")]
+    FromBinary,
+
     /// Unable to encode structured data as a JSON string.
     #[error("unable to encode structured data as a JSON string")]
     AsJson,
@@ -28,6 +71,22 @@ pub enum EncodeError {
     TimeDecode,
 }
 
+/// Decode a [`serde`] deserializable type from tagged binary data.
+///
+/// The binary format is detected from the tag added by [`encode_serde_binary`].
+pub fn decode_serde_binary<V>(value: &[u8]) -> Result<V>
+where
+    V: DeserializeOwned,
+{
+    let (tag, data) = value
+        .split_first()
+        .ok_or(EncodeError::BinaryFormatMissing)?;
+    let format = BinaryFormat::from_tag(*tag).ok_or(EncodeError::BinaryFormatUnknown(*tag))?;
+    match format {
+        BinaryFormat::MessagePack => rmp_serde::from_slice(data).context(EncodeError::FromBinary),
+    }
+}
+
 /// Decode a [`serde`] deserializable type from a string.
 pub fn decode_serde<V>(value: &str) -> Result<V>
 where
@@ -82,6 +141,22 @@ pub fn decode_time_option_f64(value: Option<f64>) -> Result<Option<OffsetDateTim
     decode_time_f64(value).map(Some)
 }
 
+/// Encode a [`serde`] serialisable type into tagged binary data.
+///
+/// The encoded data is prefixed with a tag byte identifying the [`BinaryFormat`].
+pub fn encode_serde_binary<V>(value: &V, format: BinaryFormat) -> Result<Vec<u8>>
+where
+    V: Serialize,
+{
+    let mut data = vec![format.tag()];
+    match format {
+        BinaryFormat::MessagePack => {
+            rmp_serde::encode::write_named(&mut data, value).context(EncodeError::AsBinary)?
+        }
+    }
+    Ok(data)
+}
+
 /// Encode a [`serde`] serialisable type into a string.
 pub fn encode_serde<V>(value: &V) -> Result<String>
 where
@@ -134,6 +209,26 @@ pub fn encode_time_option_f64(value: Option<OffsetDateTime>) -> Result<Option<f6
 
 #[cfg(test)]
 mod tests {
+    use super::BinaryFormat;
+
+    #[test]
+    fn binary_round_trip() {
+        let value = serde_json::json!({"key": "value", "list": [1, 2.5, null, true]});
+        let data = super::encode_serde_binary(&value, BinaryFormat::MessagePack).unwrap();
+        assert_eq!(data[0], 0x01);
+        let decoded: serde_json::Value = super::decode_serde_binary(&data).unwrap();
+        assert_eq!(decoded, value);
+    }
+
+    #[test]
+    fn binary_unknown_format() {
+        let error = super::decode_serde_binary::<serde_json::Value>(&[0xff, 0x00]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "binary encoded data is tagged with unknown format 0xff",
+        );
+    }
+
     #[test]
     fn decode_time_f64() {
         let time = 1680670808.12345;
@@ -4,6 +4,11 @@
 pub mod slog;
 
 /// Utility function to encode an error into a JSON object.
+///
+/// Structured details attached to the error with a [`DetailedError`], or decoded
+/// with a [`RemoteError`], are preserved under `error_details`.
+/// The backtrace of a [`RemoteError`] in the chain is used instead of the local one
+/// so re-encoded errors still point to where the remote failure originated.
 #[cfg(feature = "utils-error_json")]
 pub fn into_json(error: anyhow::Error) -> serde_json::Value {
     let mut document = serde_json::Map::default();
@@ -13,6 +18,9 @@ pub fn into_json(error: anyhow::Error) -> serde_json::Value {
     if error_msg != error_cause {
         document.insert("error_cause".into(), error_cause.into());
     }
+    if let Some(details) = error.chain().find_map(error_details) {
+        document.insert("error_details".into(), details.clone());
+    }
     document.insert("error_msg".into(), error_msg.into());
 
     // Emit the full error trail where intermediate messages are present.
@@ -22,11 +30,165 @@ pub fn into_json(error: anyhow::Error) -> serde_json::Value {
         document.insert("error_trail".into(), error_trail.into());
     }
 
-    // Attach a backtrace if available.
-    let backtrace = error.backtrace().to_string();
+    // Attach a backtrace if available, preferring where remote errors originated.
+    let remote = error
+        .chain()
+        .filter_map(|nested| nested.downcast_ref::<RemoteError>())
+        .find_map(|nested| nested.error_backtrace.clone());
+    let backtrace = remote.unwrap_or_else(|| error.backtrace().to_string());
     if !backtrace.is_empty() && backtrace != crate::utils::BACKTRACE_DISABLED {
         document.insert("error_backtrace".into(), backtrace.into());
     }
 
     serde_json::Value::Object(document)
 }
+
+/// Decode a JSON object created by [`into_json`] back into an error.
+///
+/// The backtrace of the original error, if one was encoded, is kept in
+/// [`RemoteError::error_backtrace`].
+#[cfg(feature = "utils-error_json")]
+pub fn from_json(document: serde_json::Value) -> Result<RemoteError, serde_json::Error> {
+    serde_json::from_value(document)
+}
+
+/// Structured details of the first error in the chain that carries any.
+#[cfg(feature = "utils-error_json")]
+fn error_details<'a>(
+    error: &'a (dyn std::error::Error + 'static),
+) -> Option<&'a serde_json::Value> {
+    if let Some(error) = error.downcast_ref::<DetailedError>() {
+        return Some(&error.details);
+    }
+    error
+        .downcast_ref::<RemoteError>()
+        .and_then(|error| error.error_details.as_ref())
+}
+
+/// Attach structured details to an error so they are preserved by [`into_json`].
+///
+/// The wrapper is transparent: it displays as, and has the same source as, the wrapped error.
+#[cfg(feature = "utils-error_json")]
+#[derive(Debug)]
+pub struct DetailedError {
+    details: serde_json::Value,
+    source: anyhow::Error,
+}
+
+#[cfg(feature = "utils-error_json")]
+impl DetailedError {
+    /// Attach structured `details` to the `source` error.
+    pub fn new<E>(source: E, details: serde_json::Value) -> DetailedError
+    where
+        E: Into<anyhow::Error>,
+    {
+        DetailedError {
+            details,
+            source: source.into(),
+        }
+    }
+
+    /// Structured details attached to the error.
+    pub fn details(&self) -> &serde_json::Value {
+        &self.details
+    }
+}
+
+#[cfg(feature = "utils-error_json")]
+impl std::fmt::Display for DetailedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.source, f)
+    }
+}
+
+#[cfg(feature = "utils-error_json")]
+impl std::error::Error for DetailedError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source.chain().nth(1)
+    }
+}
+
+/// Error decoded from a JSON object created by [`into_json`].
+///
+/// The error displays as the original error message and carries the remaining
+/// information, including any structured details, as is.
+#[cfg(feature = "utils-error_json")]
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize, thiserror::Error)]
+#[error("{error_msg}")]
+pub struct RemoteError {
+    /// Backtrace of the original error, if one was captured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_backtrace: Option<String>,
+
+    /// Root cause of the original error, if different from the error message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_cause: Option<String>,
+
+    /// Structured details attached to the original error.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_details: Option<serde_json::Value>,
+
+    /// Message of the original error.
+    pub error_msg: String,
+
+    /// Full trail of error messages, if intermediate messages were present.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_trail: Option<String>,
+}
+
+#[cfg(all(test, feature = "utils-error_json"))]
+mod tests {
+    use super::from_json;
+    use super::into_json;
+    use super::DetailedError;
+
+    #[test]
+    fn round_trip() {
+        let error = anyhow::anyhow!("root cause").context("outer error");
+        let document = into_json(error);
+        let error = from_json(document).unwrap();
+        assert_eq!(error.error_cause.as_deref(), Some("root cause"));
+        assert_eq!(error.error_details, None);
+        assert_eq!(error.error_msg, "outer error");
+        assert_eq!(error.to_string(), "outer error");
+    }
+
+    #[test]
+    fn round_trip_details() {
+        let details = serde_json::json!({"defined_node_groups": ["a", "b"]});
+        let error = anyhow::anyhow!("root cause");
+        let error = DetailedError::new(error, details.clone());
+        let error = anyhow::Error::from(error).context("outer error");
+        let document = into_json(error);
+        assert_eq!(document["error_details"], details);
+        assert_eq!(document.get("error_trail"), None);
+
+        // Details are preserved when remote errors are encoded again.
+        let error = from_json(document).unwrap();
+        assert_eq!(error.error_details.as_ref(), Some(&details));
+        let error = anyhow::Error::from(error).context("forwarded error");
+        let document = into_json(error);
+        assert_eq!(document["error_details"], details);
+        assert_eq!(document["error_msg"], "forwarded error");
+    }
+
+    #[test]
+    fn round_trip_backtrace() {
+        let document = serde_json::json!({
+            "error_backtrace": "0: remote::frame",
+            "error_msg": "remote error",
+        });
+        let error = from_json(document).unwrap();
+        assert_eq!(error.error_backtrace.as_deref(), Some("0: remote::frame"));
+
+        let error = anyhow::Error::from(error).context("forwarded error");
+        let document = into_json(error);
+        assert_eq!(document["error_backtrace"], "0: remote::frame");
+    }
+
+    #[test]
+    fn from_json_invalid() {
+        let document = serde_json::json!({"error_cause": "missing message"});
+        assert!(from_json(document).is_err());
+    }
+}
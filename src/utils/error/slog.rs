@@ -5,10 +5,22 @@ use slog::Serializer;
 use slog::KV;
 
 /// Borrow an [`Error`] to attach structured information to [`slog`] events.
+///
+/// A backtrace is included under `error_backtrace` when one was captured,
+/// unless disabled with [`ErrorAttributes::with_backtrace`].
 pub struct ErrorAttributes<'a> {
+    backtrace: bool,
     error: &'a Error,
 }
 
+impl<'a> ErrorAttributes<'a> {
+    /// Set whether the error backtrace, when captured, is included in events.
+    pub fn with_backtrace(mut self, backtrace: bool) -> Self {
+        self.backtrace = backtrace;
+        self
+    }
+}
+
 impl<'a> KV for ErrorAttributes<'a> {
     fn serialize(&self, _: &Record, serializer: &mut dyn Serializer) -> slog::Result {
         // Essential error information.
@@ -26,7 +38,10 @@ impl<'a> KV for ErrorAttributes<'a> {
             serializer.emit_str("error_trail", &error_trail)?;
         }
 
-        // Attach a backtrace if available.
+        // Attach a backtrace if available and requested.
+        if !self.backtrace {
+            return Ok(());
+        }
         let backtrace = self.error.backtrace().to_string();
         if !backtrace.is_empty() && backtrace != crate::utils::BACKTRACE_DISABLED {
             serializer.emit_str("error_backtrace", &backtrace)?;
@@ -37,6 +52,67 @@ impl<'a> KV for ErrorAttributes<'a> {
 
 impl<'a> From<&'a Error> for ErrorAttributes<'a> {
     fn from(error: &'a Error) -> Self {
-        ErrorAttributes { error }
+        ErrorAttributes {
+            backtrace: true,
+            error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::backtrace::BacktraceStatus;
+    use std::collections::BTreeMap;
+
+    use slog::Serializer;
+    use slog::KV;
+
+    use super::ErrorAttributes;
+
+    /// Capture key/value pairs emitted by the attributes under test.
+    #[derive(Default)]
+    struct CaptureSerializer(BTreeMap<String, String>);
+
+    impl Serializer for CaptureSerializer {
+        fn emit_arguments(&mut self, key: slog::Key, value: &std::fmt::Arguments) -> slog::Result {
+            self.0.insert(key.to_string(), value.to_string());
+            Ok(())
+        }
+    }
+
+    /// Serialize the attributes into a map of emitted keys and values.
+    fn emitted(attributes: ErrorAttributes) -> BTreeMap<String, String> {
+        let mut serializer = CaptureSerializer::default();
+        let record_static = slog::record_static!(slog::Level::Error, "");
+        let result = attributes.serialize(
+            &slog::Record::new(&record_static, &format_args!("test"), slog::b!()),
+            &mut serializer,
+        );
+        result.unwrap();
+        serializer.0
+    }
+
+    #[test]
+    fn backtrace_emitted_by_default() {
+        let error = anyhow::anyhow!("test error");
+        let emitted = emitted(ErrorAttributes::from(&error));
+        assert_eq!(emitted["error_msg"], "test error");
+
+        // Backtraces are only captured when enabled for the process (RUST_LIB_BACKTRACE).
+        let backtrace = error.backtrace();
+        match backtrace.status() {
+            BacktraceStatus::Captured => {
+                assert_eq!(emitted["error_backtrace"], backtrace.to_string())
+            }
+            _ => assert!(!emitted.contains_key("error_backtrace")),
+        }
+    }
+
+    #[test]
+    fn backtrace_omitted_when_disabled() {
+        let error = anyhow::anyhow!("test error");
+        let emitted = emitted(ErrorAttributes::from(&error).with_backtrace(false));
+        assert_eq!(emitted["error_msg"], "test error");
+        assert!(!emitted.contains_key("error_backtrace"));
     }
 }
@@ -1,6 +1,8 @@
 //! Collection of various utilities and code for common tasks.
 #[cfg(any(feature = "utils-actix_error", feature = "utils-actix_metrics"))]
 pub mod actix;
+#[cfg(feature = "utils-config")]
+pub mod config;
 #[cfg(feature = "utils-encoding")]
 pub mod encoding;
 #[cfg(any(feature = "utils-error_json", feature = "utils-error_slog"))]
@@ -9,6 +11,8 @@ pub mod error;
 pub mod metrics;
 #[cfg(feature = "utils-trace")]
 pub mod trace;
+#[cfg(feature = "utils-validate")]
+pub mod validate;
 
 /// Special marker used by anyhow to indicate the backtrace is not available.
 #[cfg(any(
@@ -1,4 +1,13 @@
 //! Decorate [`Result`]s and [`Future`]s to trace occurred errors.
+//!
+//! Errors are recorded as `exception` events with `exception.type` and `exception.message`
+//! attributes, following the OpenTelemetry semantic conventions for exceptions.
+//! The `*_with_status` variants also set the span status to error and the `error.type`
+//! span attribute so failed spans can be filtered on in tracing backends.
+//!
+//! The concrete type of errors wrapped in an [`anyhow::Error`] is not known
+//! so their `exception.type` and `error.type` attributes are omitted.
+use std::any::type_name;
 use std::future::Future;
 use std::pin::Pin;
 use std::result::Result;
@@ -6,6 +15,7 @@ use std::task::Context as TaskContext;
 use std::task::Poll;
 
 use anyhow::Error;
+use opentelemetry_api::trace::SpanRef;
 use opentelemetry_api::trace::Status;
 use opentelemetry_api::trace::TraceContextExt;
 use opentelemetry_api::Context;
@@ -87,14 +97,23 @@ macro_rules! impl_poll {
     }};
 }
 
-/// Reusable block to record anyhow errors onto a span.
-macro_rules! impl_record_anyhow {
-    ($span:expr, $error:expr) => {{
-        if $span.is_recording() {
-            let attributes = vec![KeyValue::new("exception.message", $error.to_string())];
-            $span.add_event("exception", attributes);
+/// Record an exception event onto a span following OpenTelemetry semantic conventions.
+fn record_exception(span: &SpanRef, error_type: Option<&'static str>, message: String) {
+    if span.is_recording() {
+        let mut attributes = vec![KeyValue::new("exception.message", message)];
+        if let Some(error_type) = error_type {
+            attributes.push(KeyValue::new("exception.type", error_type));
         }
-    }};
+        span.add_event("exception", attributes);
+    }
+}
+
+/// Mark a span as failed and record the type of error that caused the failure, if known.
+fn record_status(span: &SpanRef, error_type: Option<&'static str>, message: String) {
+    if let Some(error_type) = error_type {
+        span.set_attribute(KeyValue::new("error.type", error_type));
+    }
+    span.set_status(Status::error(message));
 }
 
 // --- Trait Implementations for sync errors --- //
@@ -106,7 +125,7 @@ impl<T> TraceErrExt for Result<T, Error> {
         // Trace the error event and return the original error.
         let context = Context::current();
         let span = context.span();
-        impl_record_anyhow!(span, error);
+        record_exception(&span, None, error.to_string());
         Err(error)
     }
 
@@ -117,8 +136,8 @@ impl<T> TraceErrExt for Result<T, Error> {
         // Trace the error event and return the original error.
         let context = Context::current();
         let span = context.span();
-        impl_record_anyhow!(span, error);
-        span.set_status(Status::error(error.to_string()));
+        record_exception(&span, None, error.to_string());
+        record_status(&span, None, error.to_string());
         Err(error)
     }
 }
@@ -134,7 +153,7 @@ where
         // Trace the error event and return the original error.
         let context = Context::current();
         let span = context.span();
-        span.record_error(&error);
+        record_exception(&span, Some(type_name::<E>()), error.to_string());
         Err(error)
     }
 
@@ -145,8 +164,8 @@ where
         // Trace the error event and return the original error.
         let context = Context::current();
         let span = context.span();
-        span.record_error(&error);
-        span.set_status(Status::error(error.to_string()));
+        record_exception(&span, Some(type_name::<E>()), error.to_string());
+        record_status(&span, Some(type_name::<E>()), error.to_string());
         Err(error)
     }
 }
@@ -240,9 +259,21 @@ where
 
 #[cfg(test)]
 mod tests {
+    use std::borrow::Cow;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use std::time::SystemTime;
+
     use opentelemetry_api::trace::FutureExt;
+    use opentelemetry_api::trace::Span;
+    use opentelemetry_api::trace::SpanContext;
+    use opentelemetry_api::trace::Status;
     use opentelemetry_api::trace::TraceContextExt;
     use opentelemetry_api::trace::Tracer;
+    use opentelemetry_api::Context;
+    use opentelemetry_api::Key;
+    use opentelemetry_api::KeyValue;
+    use opentelemetry_api::Value;
 
     use super::TraceErrExt;
     use super::TraceFutureErrExt;
@@ -253,6 +284,147 @@ mod tests {
     #[error("test")]
     pub struct TestStdError;
 
+    /// Data recorded by a [`RecordingSpan`].
+    #[derive(Debug, Default)]
+    struct Recorded {
+        attributes: Vec<KeyValue>,
+        events: Vec<(String, Vec<KeyValue>)>,
+        status: Status,
+    }
+
+    impl Recorded {
+        fn attribute(attributes: &[KeyValue], key: &'static str) -> Option<Value> {
+            attributes
+                .iter()
+                .find(|kv| kv.key == Key::from_static_str(key))
+                .map(|kv| kv.value.clone())
+        }
+    }
+
+    /// Test span that records the data reported to it for later inspection.
+    #[derive(Debug)]
+    struct RecordingSpan {
+        context: SpanContext,
+        recorded: Arc<Mutex<Recorded>>,
+    }
+
+    impl RecordingSpan {
+        fn start() -> (Context, Arc<Mutex<Recorded>>) {
+            let recorded = Arc::new(Mutex::new(Recorded::default()));
+            let span = RecordingSpan {
+                context: SpanContext::empty_context(),
+                recorded: Arc::clone(&recorded),
+            };
+            (Context::current_with_span(span), recorded)
+        }
+    }
+
+    impl Span for RecordingSpan {
+        fn add_event_with_timestamp<T>(
+            &mut self,
+            name: T,
+            _timestamp: SystemTime,
+            attributes: Vec<KeyValue>,
+        ) where
+            T: Into<Cow<'static, str>>,
+        {
+            let name = name.into().into_owned();
+            self.recorded
+                .lock()
+                .unwrap()
+                .events
+                .push((name, attributes));
+        }
+
+        fn span_context(&self) -> &SpanContext {
+            &self.context
+        }
+
+        fn is_recording(&self) -> bool {
+            true
+        }
+
+        fn set_attribute(&mut self, attribute: KeyValue) {
+            self.recorded.lock().unwrap().attributes.push(attribute);
+        }
+
+        fn set_status(&mut self, status: Status) {
+            self.recorded.lock().unwrap().status = status;
+        }
+
+        fn update_name<T>(&mut self, _new_name: T)
+        where
+            T: Into<Cow<'static, str>>,
+        {
+        }
+
+        fn end_with_timestamp(&mut self, _timestamp: SystemTime) {}
+    }
+
+    #[test]
+    fn record_error_attributes() {
+        let (context, recorded) = RecordingSpan::start();
+        let _guard = context.attach();
+
+        let error: std::result::Result<(), TestStdError> = Err(TestStdError);
+        let _ = error.trace_on_err();
+
+        let recorded = recorded.lock().unwrap();
+        assert_eq!(recorded.status, Status::Unset);
+        assert!(recorded.attributes.is_empty());
+        let (name, attributes) = &recorded.events[0];
+        assert_eq!(name, "exception");
+        assert_eq!(
+            Recorded::attribute(attributes, "exception.type"),
+            Some(Value::from(std::any::type_name::<TestStdError>())),
+        );
+        assert_eq!(
+            Recorded::attribute(attributes, "exception.message"),
+            Some(Value::from("test")),
+        );
+    }
+
+    #[test]
+    fn record_error_with_status() {
+        let (context, recorded) = RecordingSpan::start();
+        let _guard = context.attach();
+
+        let error: anyhow::Result<()> = Err(anyhow::anyhow!("test"));
+        let _ = error.trace_on_err_with_status();
+
+        let recorded = recorded.lock().unwrap();
+        assert_eq!(recorded.status, Status::error("test"));
+        assert_eq!(
+            Recorded::attribute(&recorded.attributes, "error.type"),
+            None,
+        );
+        let (name, attributes) = &recorded.events[0];
+        assert_eq!(name, "exception");
+        assert_eq!(Recorded::attribute(attributes, "exception.type"), None);
+        assert_eq!(
+            Recorded::attribute(attributes, "exception.message"),
+            Some(Value::from("test")),
+        );
+    }
+
+    #[tokio::test]
+    async fn record_future_error_with_status() {
+        let (context, recorded) = RecordingSpan::start();
+        let error = async {
+            let error: std::result::Result<(), TestStdError> = Err(TestStdError);
+            error
+        };
+        let _ = error.trace_on_err_with_status().with_context(context).await;
+
+        let recorded = recorded.lock().unwrap();
+        assert_eq!(recorded.status, Status::error("test"));
+        assert_eq!(
+            Recorded::attribute(&recorded.attributes, "error.type"),
+            Some(Value::from(std::any::type_name::<TestStdError>())),
+        );
+        assert_eq!(recorded.events.len(), 1);
+    }
+
     #[test]
     fn trace_error() {
         let tracer = opentelemetry_api::global::tracer("test");
@@ -0,0 +1,41 @@
+//! Process identity attributes automatically attached to root spans.
+use std::sync::OnceLock;
+
+use opentelemetry_api::KeyValue;
+
+/// Process-wide identity attributes, set once during process initialisation.
+static IDENTITY: OnceLock<Vec<KeyValue>> = OnceLock::new();
+
+/// The process identity attributes can only be set once and were already set to different values.
+#[derive(Debug, thiserror::Error)]
+#[error("process identity attributes for spans have already been set to different values")]
+pub struct SpanIdentityAlreadySet;
+
+/// Set the process identity attributes attached to all root spans.
+///
+/// Identity attributes (such as node ID, store ID, platform name) are set once
+/// during process initialisation and are attached to all root spans created with [`root`].
+///
+/// Setting the same attributes again, for example when telemetry is initialised more
+/// than once, is allowed. Setting an empty identity is a no-op.
+///
+/// [`root`]: super::root
+pub fn set_span_identity<I>(attributes: I) -> Result<(), SpanIdentityAlreadySet>
+where
+    I: IntoIterator<Item = KeyValue>,
+{
+    let attributes: Vec<KeyValue> = attributes.into_iter().collect();
+    if attributes.is_empty() {
+        return Ok(());
+    }
+    match IDENTITY.set(attributes) {
+        Ok(()) => Ok(()),
+        Err(attributes) if IDENTITY.get() == Some(&attributes) => Ok(()),
+        Err(_) => Err(SpanIdentityAlreadySet),
+    }
+}
+
+/// Process identity attributes attached to root spans, if any were set.
+pub fn span_identity() -> &'static [KeyValue] {
+    IDENTITY.get().map(Vec::as_slice).unwrap_or_default()
+}
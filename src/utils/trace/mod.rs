@@ -5,13 +5,19 @@ use opentelemetry_api::trace::TraceContextExt;
 use opentelemetry_api::Context;
 
 mod error;
+mod identity;
 
 pub use self::error::TraceErrExt;
 pub use self::error::TraceFutureErrExt;
 pub use self::error::TraceFutureStdErrExt;
 pub use self::error::TraceStdErrExt;
+pub use self::identity::set_span_identity;
+pub use self::identity::span_identity;
+pub use self::identity::SpanIdentityAlreadySet;
 
 /// Create a root span and context.
+///
+/// Root spans carry the process identity attributes set with [`set_span_identity`].
 pub fn root<N, T>(tracer: &T, name: N) -> Context
 where
     N: Into<Cow<'static, str>>,
@@ -19,6 +25,11 @@ where
     T::Span: Send + Sync + 'static,
 {
     let empty = Context::new();
-    let root = tracer.start_with_context(name, &empty);
+    let mut builder = tracer.span_builder(name);
+    let identity = span_identity();
+    if !identity.is_empty() {
+        builder = builder.with_attributes(identity.iter().cloned());
+    }
+    let root = tracer.build_with_context(builder, &empty);
     empty.with_span(root)
 }
@@ -0,0 +1,199 @@
+//! Validation of (request) models beyond what is enforced by their types.
+//!
+//! Models implement the [`Validate`] trait to check their fields for consistency.
+//! Validation does not stop at the first problem: all issues are collected into
+//! [`ValidationErrors`] so clients can fix all of them at once.
+//!
+//! Each issue is reported with the path of the field it applies to, using `.` to
+//! separate nested fields (for example `cluster.nodes.default.node_class`).
+//! An empty path indicates the issue applies to the validated value as a whole.
+
+/// Check a model for consistency and report all issues found.
+pub trait Validate {
+    /// Validate the model, returning all issues found if any.
+    fn validate(&self) -> Result<(), ValidationErrors>;
+}
+
+/// An individual issue found while validating a model.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ValidationError {
+    /// Description of the issue.
+    pub message: String,
+
+    /// Path of the field the issue applies to.
+    pub path: String,
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.path.is_empty() {
+            return write!(f, "{}", self.message);
+        }
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Collection of all issues found while validating a model.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ValidationErrors {
+    errors: Vec<ValidationError>,
+}
+
+impl ValidationErrors {
+    /// Initialise an empty collection of validation issues.
+    pub fn new() -> ValidationErrors {
+        ValidationErrors::default()
+    }
+
+    /// Record an issue for the field at the given path.
+    pub fn add<P, M>(&mut self, path: P, message: M)
+    where
+        P: Into<String>,
+        M: Into<String>,
+    {
+        self.errors.push(ValidationError {
+            message: message.into(),
+            path: path.into(),
+        });
+    }
+
+    /// Iterate over the recorded issues, in the order they were found.
+    pub fn errors(&self) -> &[ValidationError] {
+        &self.errors
+    }
+
+    /// Convert the collection into a [`Result`] that fails only if issues were recorded.
+    pub fn into_result(self) -> Result<(), ValidationErrors> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        Err(self)
+    }
+
+    /// Check if no issues were recorded.
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Validate a nested value and record its issues under the given path prefix.
+    pub fn nested<V>(&mut self, prefix: &str, value: &V)
+    where
+        V: Validate + ?Sized,
+    {
+        let nested = match value.validate() {
+            Ok(()) => return,
+            Err(nested) => nested,
+        };
+        for error in nested.errors {
+            let path = match error.path.is_empty() {
+                true => prefix.to_string(),
+                false => format!("{}.{}", prefix, error.path),
+            };
+            self.add(path, error.message);
+        }
+    }
+
+    /// Record an issue for the field at the given path if its value is empty.
+    pub fn require_not_empty(&mut self, path: &str, value: &str) {
+        if value.trim().is_empty() {
+            self.add(path, "value must not be empty");
+        }
+    }
+}
+
+impl std::fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "validation failed")?;
+        let mut separator = ": ";
+        for error in &self.errors {
+            write!(f, "{}{}", separator, error)?;
+            separator = "; ";
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+#[cfg(test)]
+mod tests {
+    use super::Validate;
+    use super::ValidationError;
+    use super::ValidationErrors;
+
+    struct Group {
+        name: String,
+        size: u32,
+    }
+
+    impl Validate for Group {
+        fn validate(&self) -> Result<(), ValidationErrors> {
+            let mut errors = ValidationErrors::new();
+            errors.require_not_empty("name", &self.name);
+            if self.size == 0 {
+                errors.add("size", "value must be greater than zero");
+            }
+            errors.into_result()
+        }
+    }
+
+    struct Request {
+        group: Group,
+        id: String,
+    }
+
+    impl Validate for Request {
+        fn validate(&self) -> Result<(), ValidationErrors> {
+            let mut errors = ValidationErrors::new();
+            errors.nested("group", &self.group);
+            errors.require_not_empty("id", &self.id);
+            errors.into_result()
+        }
+    }
+
+    #[test]
+    fn validate_passes() {
+        let request = Request {
+            group: Group {
+                name: "default".into(),
+                size: 3,
+            },
+            id: "request-1".into(),
+        };
+        assert_eq!(request.validate(), Ok(()));
+    }
+
+    #[test]
+    fn validate_fails_with_all_errors() {
+        let request = Request {
+            group: Group {
+                name: " ".into(),
+                size: 0,
+            },
+            id: "".into(),
+        };
+        let errors = request.validate().unwrap_err();
+        assert_eq!(
+            errors.errors(),
+            [
+                ValidationError {
+                    message: "value must not be empty".into(),
+                    path: "group.name".into(),
+                },
+                ValidationError {
+                    message: "value must be greater than zero".into(),
+                    path: "group.size".into(),
+                },
+                ValidationError {
+                    message: "value must not be empty".into(),
+                    path: "id".into(),
+                },
+            ],
+        );
+        assert_eq!(
+            errors.to_string(),
+            "validation failed: group.name: value must not be empty; \
+             group.size: value must be greater than zero; id: value must not be empty",
+        );
+    }
+}